@@ -0,0 +1,330 @@
+//! # The IPv6-Address Module
+//!
+//! An IPv6 Address identifies a single host just as an IPv4 address does, but with 128 bits
+//! rather than 32. There are so many that the notation had to change: eight groups of hex digits
+//! separated by colons, where the longest run of zero groups may be left out entirely, as in
+//! `2001:db8::1`.
+use super::{
+    addr::{AddrParseError, AddrParseErrorKind},
+    bits::AddressBits,
+    mac::MacAddress,
+    multicast6::{Multicast, Scope},
+    net6::Ipv6Network,
+    special6::{self, SpecialPurpose},
+};
+use std::{fmt, net::Ipv6Addr, str};
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ipv6Address(u128);
+
+impl From<u128> for Ipv6Address {
+    fn from(value: u128) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Ipv6Address> for u128 {
+    fn from(addr: Ipv6Address) -> Self {
+        addr.0
+    }
+}
+
+impl From<[u8; 16]> for Ipv6Address {
+    fn from(octets: [u8; 16]) -> Self {
+        Self::from(u128::from_be_bytes(octets))
+    }
+}
+
+impl From<[u16; 8]> for Ipv6Address {
+    fn from(segments: [u16; 8]) -> Self {
+        Self::from(Ipv6Addr::from(segments))
+    }
+}
+
+impl From<Ipv6Addr> for Ipv6Address {
+    fn from(addr: Ipv6Addr) -> Self {
+        Self::from(addr.octets())
+    }
+}
+
+impl From<Ipv6Address> for Ipv6Addr {
+    fn from(addr: Ipv6Address) -> Self {
+        Self::from(addr.octets())
+    }
+}
+
+impl Ipv6Address {
+    /// Creates a new IPv6 Address with the specified binary representation.
+    pub fn new(value: u128) -> Self {
+        Self(value)
+    }
+
+    /// The binary representation of this address.
+    pub fn value(&self) -> u128 {
+        self.0
+    }
+
+    /// The address the specified number of addresses after this one, unless that would run past
+    /// the last address.
+    pub fn checked_add(&self, n: u128) -> Option<Self> {
+        self.0.checked_add(n).map(Self)
+    }
+
+    /// The address the specified number of addresses before this one, unless that would run past
+    /// `::`.
+    pub fn checked_sub(&self, n: u128) -> Option<Self> {
+        self.0.checked_sub(n).map(Self)
+    }
+
+    /// Gets an array of bytes representing this address.
+    pub fn octets(&self) -> [u8; 16] {
+        self.0.to_be_bytes()
+    }
+
+    /// Gets the eight 16-bit groups of this address, as they are written.
+    pub fn segments(&self) -> [u16; 8] {
+        Ipv6Addr::from(*self).segments()
+    }
+
+    /// Splits this address into the network of its first bits, and the interface ID of the rest.
+    ///
+    /// The prefix length may not be longer than 128.
+    pub fn split(&self, prefix_len: u8) -> Option<(Ipv6Network, u128)> {
+        let prefix = Ipv6Address::from(self.0 & u128::mask(prefix_len.min(128)));
+        let prefix = Ipv6Network::new(prefix, prefix_len)?;
+        Some((prefix, self.0 & u128::wildcard(prefix_len)))
+    }
+
+    /// Splits this address into its `/64` prefix and its 64-bit interface ID, as nearly every
+    /// unicast address is laid out.
+    pub fn split_64(&self) -> (Ipv6Network, u64) {
+        let (prefix, interface_id) = self.split(64).unwrap();
+        (prefix, interface_id as u64)
+    }
+
+    /// Recombines a network prefix and an interface ID into an address; the reverse of
+    /// [`split`](Self::split).
+    ///
+    /// The interface ID **must** fit within the host bits of the network.
+    pub fn from_parts(prefix: Ipv6Network, interface_id: u128) -> Option<Self> {
+        if interface_id & prefix.get_mask().value() != 0 {
+            return None;
+        }
+        Some(Self(prefix.network_address().value() | interface_id))
+    }
+
+    /// Recovers the MAC address of the interface, if the interface ID of this address is in the
+    /// modified EUI-64 format which SLAAC builds out of one.
+    ///
+    /// Privacy extensions and stable opaque identifiers are random instead, so they hide the MAC.
+    pub fn embedded_mac(&self) -> Option<MacAddress> {
+        MacAddress::from_modified_eui64(self.split_64().1)
+    }
+
+    /// Moves this address into another network, keeping the bits after the network's prefix, as
+    /// when renumbering a host into a new prefix.
+    pub fn with_prefix(&self, prefix: Ipv6Network) -> Self {
+        Self(prefix.network_address().value() | self.0 & prefix.get_wildcard().value())
+    }
+
+    /// The most specific entry of the special-purpose registry which covers this address.
+    pub fn special_purpose(&self) -> Option<&'static SpecialPurpose> {
+        special6::lookup(*self)
+    }
+
+    /// Whether this is the unspecified address, `::`.
+    pub fn is_unspecified(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether this is the loopback address, `::1`.
+    pub fn is_loopback(&self) -> bool {
+        self.0 == 1
+    }
+
+    /// Whether this address is an IPv4 address mapped into IPv6, within `::ffff:0:0/96`.
+    pub fn is_ipv4_mapped(&self) -> bool {
+        self.0 >> 32 == 0xffff
+    }
+
+    /// Whether this address is reserved for documentation, within `2001:db8::/32` or
+    /// `3fff::/20`.
+    pub fn is_documentation(&self) -> bool {
+        self.0 >> 96 == 0x2001_0db8 || self.0 >> 108 == 0x3fff0
+    }
+
+    /// Whether this address is reserved for benchmarking, within `2001:2::/48`.
+    pub fn is_benchmarking(&self) -> bool {
+        self.0 >> 80 == 0x2001_0002_0000
+    }
+
+    /// Whether this is a unique local address, within `fc00::/7`.
+    pub fn is_unique_local(&self) -> bool {
+        self.0 >> 121 == 0xfc >> 1
+    }
+
+    /// Whether this is a link-local unicast address, within `fe80::/10`.
+    pub fn is_unicast_link_local(&self) -> bool {
+        self.0 >> 118 == 0xfe80 >> 6
+    }
+
+    /// Whether this is a multicast address, within `ff00::/8`.
+    pub fn is_multicast(&self) -> bool {
+        self.0 >> 120 == 0xff
+    }
+
+    /// Breaks this address down into its flags, scope, and group ID, if it is multicast.
+    pub fn multicast(&self) -> Option<Multicast> {
+        Multicast::parse(*self)
+    }
+
+    /// Whether this address is reachable across the Internet.
+    ///
+    /// A multicast address is global only if its scope is. Any other address is global unless the
+    /// special-purpose registry says it is not.
+    pub fn is_global(&self) -> bool {
+        if let Some(multicast) = self.multicast() {
+            return multicast.scope == Scope::Global;
+        }
+        special6::is_globally_reachable(*self)
+    }
+}
+
+impl str::FromStr for Ipv6Address {
+    type Err = AddrParseError;
+
+    /// Parses an address from its colon-hex form, such as `2001:db8::1`, which may end in a
+    /// dotted-quad IPv4 address, as in `::ffff:192.0.2.1`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Ipv6Addr>()
+            .map(Self::from)
+            .map_err(|_| AddrParseError(AddrParseErrorKind::Syntax))
+    }
+}
+
+/// Formats the address in the canonical form of RFC 5952, as in `2001:db8::1`.
+impl fmt::Display for Ipv6Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&Ipv6Addr::from(*self), f)
+    }
+}
+
+/// Shows the address in colon-hex, as in `Ipv6Address(2001:db8::1)`, or its binary
+/// representation under `{:#?}`.
+impl fmt::Debug for Ipv6Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.debug_tuple("Ipv6Address").field(&self.0).finish()
+        } else {
+            write!(f, "Ipv6Address({})", self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init() {
+        let addr = Ipv6Address::from([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(Ipv6Address::from(0x2001_0db8_u128 << 96 | 1), addr);
+        assert_eq!(
+            [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+            addr.octets()
+        );
+        assert_eq!([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1], addr.segments());
+        assert_eq!(addr, Ipv6Address::from(addr.octets()));
+        assert_eq!(addr, Ipv6Address::from(Ipv6Addr::from(addr)));
+        assert_eq!(addr.value(), u128::from(addr));
+        assert_eq!(
+            Some(Ipv6Address::from(2)),
+            Ipv6Address::from(1).checked_add(1)
+        );
+        assert_eq!(None, Ipv6Address::from(u128::MAX).checked_add(1));
+        assert_eq!(None, Ipv6Address::from(0).checked_sub(1));
+    }
+
+    #[test]
+    fn split() {
+        let addr: Ipv6Address = "2001:db8:1:2:a:b:c:d".parse().unwrap();
+        let (prefix, interface_id) = addr.split_64();
+        assert_eq!("2001:db8:1:2::/64", prefix.to_string());
+        assert_eq!(0x000a_000b_000c_000d, interface_id);
+        assert_eq!(
+            Some(addr),
+            Ipv6Address::from_parts(prefix, u128::from(interface_id))
+        );
+
+        let (prefix, interface_id) = addr.split(48).unwrap();
+        assert_eq!("2001:db8:1::/48", prefix.to_string());
+        assert_eq!(0x0002_000a_000b_000c_000d, interface_id);
+        assert_eq!(Some(addr), Ipv6Address::from_parts(prefix, interface_id));
+        assert_eq!(0, addr.split(128).unwrap().1);
+        assert_eq!(addr.value(), addr.split(0).unwrap().1);
+        assert!(addr.split(129).is_none());
+        assert!(Ipv6Address::from_parts(prefix, 1 << 80).is_none());
+
+        let slaac: Ipv6Address = "fe80::21a:2bff:fe3c:4d5e".parse().unwrap();
+        assert_eq!(
+            Some("00:1a:2b:3c:4d:5e".parse().unwrap()),
+            slaac.embedded_mac()
+        );
+        assert_eq!(None, addr.embedded_mac());
+
+        let renumbered = addr.with_prefix("2001:db8:ffff:ff00::/56".parse().unwrap());
+        assert_eq!("2001:db8:ffff:ff02:a:b:c:d", renumbered.to_string());
+    }
+
+    #[test]
+    fn special_ranges() {
+        let addr = |s: &str| s.parse::<Ipv6Address>().unwrap();
+        assert!(addr("::").is_unspecified());
+        assert!(addr("::1").is_loopback());
+        assert!(addr("::ffff:192.0.2.1").is_ipv4_mapped());
+        assert!(!addr("::fffe:192.0.2.1").is_ipv4_mapped());
+        assert!(addr("2001:db8::1").is_documentation());
+        assert!(addr("3fff:fff::1").is_documentation());
+        assert!(!addr("3fff:1000::1").is_documentation());
+        assert!(addr("2001:2::1").is_benchmarking());
+        assert!(!addr("2001:2:1::1").is_benchmarking());
+        assert!(addr("fd00::1").is_unique_local());
+        assert!(addr("fc00::1").is_unique_local());
+        assert!(!addr("fe00::1").is_unique_local());
+        assert!(addr("febf::1").is_unicast_link_local());
+        assert!(!addr("fec0::1").is_unicast_link_local());
+        assert!(addr("ff02::1").is_multicast());
+
+        assert!(addr("2606:4700::1111").is_global());
+        assert!(addr("ff0e::1").is_global());
+        assert!(!addr("ff02::1").is_global());
+        assert!(!addr("fd00::1").is_global());
+        assert!(!addr("::ffff:8.8.8.8").is_global());
+        assert_eq!(
+            Some("Documentation"),
+            addr("2001:db8::1")
+                .special_purpose()
+                .map(|entry| entry.name)
+        );
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for &(input, canonical) in &[
+            ("2001:db8::1", "2001:db8::1"),
+            ("2001:0DB8:0000:0000:0000:0000:0000:0001", "2001:db8::1"),
+            ("::", "::"),
+            ("::1", "::1"),
+            ("2001:db8:0:0:1:0:0:1", "2001:db8::1:0:0:1"),
+            ("::ffff:192.0.2.1", "::ffff:192.0.2.1"),
+        ] {
+            let addr: Ipv6Address = input.parse().unwrap();
+            assert_eq!(canonical, addr.to_string());
+        }
+        for bad in &["", ":::", "2001:db8::1::1", "12345::", "192.0.2.1", "[::1]"] {
+            assert!(bad.parse::<Ipv6Address>().is_err(), "{}", bad);
+        }
+        let addr: Ipv6Address = "2001:db8::1".parse().unwrap();
+        assert_eq!("Ipv6Address(2001:db8::1)", format!("{:?}", addr));
+    }
+}
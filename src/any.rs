@@ -0,0 +1,439 @@
+//! # The Any-Family Module
+//!
+//! Input from the outside world rarely says in advance which family its addresses belong to.
+//! This module has an address and a network which may be of either family, decided as they are
+//! parsed, along with a trait over networks of every kind, so that an algorithm which only needs
+//! prefixes, containment, and the addresses within can be written once, for IPv4 and IPv6 alike.
+use super::{
+    addr::{AddrParseError, IpAddress},
+    addr6::Ipv6Address,
+    net::{IpNetwork, NetParseError},
+    net6::Ipv6Network,
+};
+use std::{convert::TryFrom, fmt, net::IpAddr, str};
+
+/// An address of either family.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AnyIpAddress {
+    V4(IpAddress),
+    V6(Ipv6Address),
+}
+
+impl AnyIpAddress {
+    /// Whether this is an IPv4 address.
+    pub fn is_ipv4(&self) -> bool {
+        matches!(self, Self::V4(_))
+    }
+
+    /// Whether this is an IPv6 address.
+    pub fn is_ipv6(&self) -> bool {
+        matches!(self, Self::V6(_))
+    }
+}
+
+impl From<IpAddress> for AnyIpAddress {
+    fn from(addr: IpAddress) -> Self {
+        Self::V4(addr)
+    }
+}
+
+impl From<Ipv6Address> for AnyIpAddress {
+    fn from(addr: Ipv6Address) -> Self {
+        Self::V6(addr)
+    }
+}
+
+impl From<IpAddr> for AnyIpAddress {
+    fn from(addr: IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(addr) => Self::V4(addr.into()),
+            IpAddr::V6(addr) => Self::V6(addr.into()),
+        }
+    }
+}
+
+impl From<AnyIpAddress> for IpAddr {
+    fn from(addr: AnyIpAddress) -> Self {
+        match addr {
+            AnyIpAddress::V4(addr) => IpAddr::V4(addr.into()),
+            AnyIpAddress::V6(addr) => IpAddr::V6(addr.into()),
+        }
+    }
+}
+
+impl str::FromStr for AnyIpAddress {
+    type Err = AddrParseError;
+
+    /// Parses an address of either family; any address with a colon is taken to be IPv6.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(':') {
+            s.parse().map(Self::V6)
+        } else {
+            s.parse().map(Self::V4)
+        }
+    }
+}
+
+impl fmt::Display for AnyIpAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V4(addr) => fmt::Display::fmt(addr, f),
+            Self::V6(addr) => fmt::Display::fmt(addr, f),
+        }
+    }
+}
+
+/// A network of either family.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AnyIpNetwork {
+    V4(IpNetwork),
+    V6(Ipv6Network),
+}
+
+impl AnyIpNetwork {
+    /// Whether this is an IPv4 network.
+    pub fn is_ipv4(&self) -> bool {
+        matches!(self, Self::V4(_))
+    }
+
+    /// Whether this is an IPv6 network.
+    pub fn is_ipv6(&self) -> bool {
+        matches!(self, Self::V6(_))
+    }
+}
+
+impl From<IpNetwork> for AnyIpNetwork {
+    fn from(net: IpNetwork) -> Self {
+        Self::V4(net)
+    }
+}
+
+impl From<Ipv6Network> for AnyIpNetwork {
+    fn from(net: Ipv6Network) -> Self {
+        Self::V6(net)
+    }
+}
+
+impl str::FromStr for AnyIpNetwork {
+    type Err = NetParseError;
+
+    /// Parses a network of either family from CIDR notation.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(':') {
+            s.parse().map(Self::V6)
+        } else {
+            s.parse().map(Self::V4)
+        }
+    }
+}
+
+impl fmt::Display for AnyIpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V4(net) => fmt::Display::fmt(net, f),
+            Self::V6(net) => fmt::Display::fmt(net, f),
+        }
+    }
+}
+
+/// A network of any family: a prefix, and every address which shares it.
+pub trait Prefix: Copy + Eq {
+    type Address: Copy + Eq;
+
+    /// The number of bits in an address, and so the longest prefix there can be.
+    fn max_prefix_len(&self) -> u8;
+
+    /// The number of bits in the prefix.
+    fn prefix_len(&self) -> u8;
+
+    /// The first address within this network.
+    fn network_address(&self) -> Self::Address;
+
+    /// The last address within this network.
+    fn last_address(&self) -> Self::Address;
+
+    /// The address at the offset from the start of this network, unless it runs past the end.
+    fn nth(&self, n: u128) -> Option<Self::Address>;
+
+    /// Whether the address lies within this network.
+    fn contains(&self, addr: Self::Address) -> bool;
+
+    /// Whether every address of this network lies within the other.
+    fn is_subnet_of(&self, other: &Self) -> bool;
+
+    /// The network one bit shorter, which contains this one, unless this is already the widest.
+    fn supernet(self) -> Option<Self>;
+
+    /// The two halves of this network, unless it is a single address.
+    fn subnets(self) -> Option<(Self, Self)>;
+
+    /// The number of bits after the prefix.
+    fn num_host_bits(&self) -> u8 {
+        self.max_prefix_len() - self.prefix_len()
+    }
+
+    /// Whether every address of the other network lies within this one.
+    fn is_supernet_of(&self, other: &Self) -> bool {
+        other.is_subnet_of(self)
+    }
+
+    /// Every address within this network, in ascending order.
+    fn addresses(&self) -> Addresses<Self> {
+        Addresses {
+            net: *self,
+            next: Some(0),
+        }
+    }
+}
+
+impl Prefix for IpNetwork {
+    type Address = IpAddress;
+
+    fn max_prefix_len(&self) -> u8 {
+        32
+    }
+
+    fn prefix_len(&self) -> u8 {
+        self.num_network_bits()
+    }
+
+    fn network_address(&self) -> IpAddress {
+        IpNetwork::network_address(self)
+    }
+
+    fn last_address(&self) -> IpAddress {
+        self.broadcast_address()
+    }
+
+    fn nth(&self, n: u128) -> Option<IpAddress> {
+        IpNetwork::nth(self, u64::try_from(n).ok()?)
+    }
+
+    fn contains(&self, addr: IpAddress) -> bool {
+        IpNetwork::contains(self, addr)
+    }
+
+    fn is_subnet_of(&self, other: &Self) -> bool {
+        IpNetwork::is_subnet_of(self, other)
+    }
+
+    fn supernet(self) -> Option<Self> {
+        IpNetwork::supernet(self)
+    }
+
+    fn subnets(self) -> Option<(Self, Self)> {
+        IpNetwork::subnets(self)
+    }
+}
+
+impl Prefix for Ipv6Network {
+    type Address = Ipv6Address;
+
+    fn max_prefix_len(&self) -> u8 {
+        128
+    }
+
+    fn prefix_len(&self) -> u8 {
+        self.num_network_bits()
+    }
+
+    fn network_address(&self) -> Ipv6Address {
+        Ipv6Network::network_address(self)
+    }
+
+    fn last_address(&self) -> Ipv6Address {
+        Ipv6Network::last_address(self)
+    }
+
+    fn nth(&self, n: u128) -> Option<Ipv6Address> {
+        Ipv6Network::nth(self, n)
+    }
+
+    fn contains(&self, addr: Ipv6Address) -> bool {
+        Ipv6Network::contains(self, addr)
+    }
+
+    fn is_subnet_of(&self, other: &Self) -> bool {
+        Ipv6Network::is_subnet_of(self, other)
+    }
+
+    fn supernet(self) -> Option<Self> {
+        Ipv6Network::supernet(self)
+    }
+
+    fn subnets(self) -> Option<(Self, Self)> {
+        Ipv6Network::subnets(self)
+    }
+}
+
+/// Networks of different families never contain one another, nor each other's addresses.
+impl Prefix for AnyIpNetwork {
+    type Address = AnyIpAddress;
+
+    fn max_prefix_len(&self) -> u8 {
+        match self {
+            Self::V4(net) => Prefix::max_prefix_len(net),
+            Self::V6(net) => Prefix::max_prefix_len(net),
+        }
+    }
+
+    fn prefix_len(&self) -> u8 {
+        match self {
+            Self::V4(net) => net.num_network_bits(),
+            Self::V6(net) => net.num_network_bits(),
+        }
+    }
+
+    fn network_address(&self) -> AnyIpAddress {
+        match self {
+            Self::V4(net) => net.network_address().into(),
+            Self::V6(net) => net.network_address().into(),
+        }
+    }
+
+    fn last_address(&self) -> AnyIpAddress {
+        match self {
+            Self::V4(net) => net.broadcast_address().into(),
+            Self::V6(net) => net.last_address().into(),
+        }
+    }
+
+    fn nth(&self, n: u128) -> Option<AnyIpAddress> {
+        match self {
+            Self::V4(net) => Prefix::nth(net, n).map(AnyIpAddress::V4),
+            Self::V6(net) => net.nth(n).map(AnyIpAddress::V6),
+        }
+    }
+
+    fn contains(&self, addr: AnyIpAddress) -> bool {
+        match (self, addr) {
+            (Self::V4(net), AnyIpAddress::V4(addr)) => net.contains(addr),
+            (Self::V6(net), AnyIpAddress::V6(addr)) => net.contains(addr),
+            _ => false,
+        }
+    }
+
+    fn is_subnet_of(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::V4(net), Self::V4(other)) => net.is_subnet_of(other),
+            (Self::V6(net), Self::V6(other)) => net.is_subnet_of(other),
+            _ => false,
+        }
+    }
+
+    fn supernet(self) -> Option<Self> {
+        match self {
+            Self::V4(net) => net.supernet().map(Self::V4),
+            Self::V6(net) => net.supernet().map(Self::V6),
+        }
+    }
+
+    fn subnets(self) -> Option<(Self, Self)> {
+        match self {
+            Self::V4(net) => net.subnets().map(|(a, b)| (Self::V4(a), Self::V4(b))),
+            Self::V6(net) => net.subnets().map(|(a, b)| (Self::V6(a), Self::V6(b))),
+        }
+    }
+}
+
+/// An iterator over every address of a network. See [`Prefix::addresses`].
+#[derive(Debug, Clone)]
+pub struct Addresses<P> {
+    net: P,
+    /// The offset of the next address, or `None` once the last has been yielded.
+    next: Option<u128>,
+}
+
+impl<P: Prefix> Iterator for Addresses<P> {
+    type Item = P::Address;
+
+    fn next(&mut self) -> Option<P::Address> {
+        let n = self.next?;
+        let addr = self.net.nth(n);
+        self.next = addr.and(n.checked_add(1));
+        addr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Written once, for networks of every family.
+    fn covered<P: Prefix>(net: P, addrs: &[P::Address]) -> usize {
+        addrs.iter().filter(|&&addr| net.contains(addr)).count()
+    }
+
+    #[test]
+    fn parse() {
+        for s in &["192.0.2.1", "2001:db8::1", "::ffff:192.0.2.1"] {
+            let addr: AnyIpAddress = s.parse().unwrap();
+            assert_eq!(*s, addr.to_string());
+            assert_eq!(addr, AnyIpAddress::from(IpAddr::from(addr)));
+        }
+        assert!("192.0.2.1".parse::<AnyIpAddress>().unwrap().is_ipv4());
+        assert!("2001:db8::1".parse::<AnyIpAddress>().unwrap().is_ipv6());
+        assert!("192.0.2".parse::<AnyIpAddress>().is_err());
+        assert!("2001:db8:::1".parse::<AnyIpAddress>().is_err());
+
+        let net: AnyIpNetwork = "2001:db8::/32".parse().unwrap();
+        assert!(net.is_ipv6());
+        assert_eq!("2001:db8::/32", net.to_string());
+        assert_eq!(
+            "10.0.0.0/8",
+            "10.0.0.0/8".parse::<AnyIpNetwork>().unwrap().to_string()
+        );
+        assert!("10.0.0.0/33".parse::<AnyIpNetwork>().is_err());
+    }
+
+    #[test]
+    fn prefix() {
+        let v4: IpNetwork = "192.0.2.0/30".parse().unwrap();
+        let v6: Ipv6Network = "2001:db8::/126".parse().unwrap();
+        let addrs4: Vec<_> = v4.addresses().collect();
+        assert_eq!(4, addrs4.len());
+        assert_eq!(v4.last_address(), addrs4[3]);
+        assert_eq!(4, v6.addresses().count());
+        assert_eq!(2, covered(v4, &addrs4[1..3]));
+        assert_eq!(1, covered(v6, &[Prefix::network_address(&v6)]));
+        assert_eq!(2, Prefix::num_host_bits(&v6));
+
+        let any4 = AnyIpNetwork::from(v4);
+        let any6 = AnyIpNetwork::from(v6);
+        let mixed: Vec<AnyIpAddress> = vec![addrs4[0].into(), Prefix::last_address(&v6).into()];
+        assert_eq!(1, covered(any4, &mixed));
+        assert_eq!(1, covered(any6, &mixed));
+        assert!(!any4.is_subnet_of(&any6));
+        assert!(any4.supernet().unwrap().is_supernet_of(&any4));
+        let (a, b) = any6.subnets().unwrap();
+        assert!(a.is_subnet_of(&any6) && b.is_subnet_of(&any6));
+        assert_eq!(128, any6.max_prefix_len());
+
+        // Host bits never leak into the halves, whichever family the network is.
+        for (s, upper, lower) in &[
+            (
+                "2001:db8::ffff/126",
+                "2001:db8::fffe/127",
+                "2001:db8::fffc/127",
+            ),
+            ("2001:db8::3/127", "2001:db8::3/128", "2001:db8::2/128"),
+            ("10.0.0.7/30", "10.0.0.6/31", "10.0.0.4/31"),
+        ] {
+            let net: AnyIpNetwork = s.parse().unwrap();
+            let (a, b) = net.subnets().unwrap();
+            assert_ne!(a, b, "{}", s);
+            assert_eq!(
+                (upper.to_string(), lower.to_string()),
+                (a.to_string(), b.to_string())
+            );
+        }
+
+        // The last address of the whole space ends the walk, rather than overflowing.
+        let top: Ipv6Network = "ffff:ffff:ffff:ffff:ffff:ffff:ffff:fffe/127"
+            .parse()
+            .unwrap();
+        assert_eq!(2, top.addresses().count());
+        let top4: IpNetwork = "255.255.255.255/32".parse().unwrap();
+        assert_eq!(1, top4.addresses().count());
+    }
+}
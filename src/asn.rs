@@ -0,0 +1,257 @@
+//! # The ASN Module
+//!
+//! Every routed address is announced by some autonomous system. Knowing which one is the first
+//! step of abuse triage and peering analysis alike, so this module loads the freely published
+//! IP-to-ASN datasets into a table which answers that question for any address, and groups
+//! addresses by the AS they belong to.
+//!
+//! Each line of a dataset gives a range of addresses, then its ASN, then optionally a country
+//! code and the name of the AS. The range may be written as a network, as `first-last`, or as
+//! separate first and last fields. Fields are separated by tabs, or by whitespace if there are no
+//! tabs; `#` begins a comment. AS 0 marks addresses which are not routed, and is skipped.
+use super::{addr::IpAddress, net::IpNetwork, range::IpRange, range_map::IpRangeMap, set::IpSet};
+use std::{collections::BTreeMap, error, fmt};
+
+/// A table of which AS announces each range of addresses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AsnTable {
+    ranges: IpRangeMap<u32>,
+    names: BTreeMap<u32, String>,
+}
+
+impl AsnTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a dataset. Where ranges overlap, the later line wins.
+    pub fn load(text: &str) -> Result<Self, AsnError> {
+        let mut table = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            let error = |kind| AsnError { line: i + 1, kind };
+            let line = line.split('#').next().unwrap_or_default();
+            let fields: Vec<&str> = if line.contains('\t') {
+                line.split('\t').map(str::trim).collect()
+            } else {
+                line.split_whitespace().collect()
+            };
+            if fields.iter().all(|f| f.is_empty()) {
+                continue;
+            }
+
+            let (range, rest) = parse_range(&fields).map_err(error)?;
+            let (asn, rest) = match rest {
+                [asn, rest @ ..] => (parse_asn(asn).map_err(error)?, rest),
+                [] => return Err(error(ErrorKind::MissingAsn)),
+            };
+            if asn == 0 {
+                continue;
+            }
+            table.ranges.insert(range, asn);
+            // The name comes last, after any country code.
+            let name = match rest {
+                [country, name @ ..] if !name.is_empty() && is_country(country) => name,
+                name => name,
+            };
+            if !name.is_empty() {
+                table.names.insert(asn, name.join(" "));
+            }
+        }
+        Ok(table)
+    }
+
+    /// Records that the AS announces every address of the range.
+    pub fn insert(&mut self, range: IpRange, asn: u32) {
+        self.ranges.insert(range, asn);
+    }
+
+    /// Records the name of the AS.
+    pub fn insert_name(&mut self, asn: u32, name: &str) {
+        self.names.insert(asn, name.to_string());
+    }
+
+    /// Finds the AS which announces the address.
+    pub fn asn_of(&self, addr: IpAddress) -> Option<u32> {
+        self.ranges.lookup(addr).copied()
+    }
+
+    /// Finds the name of the AS, if the dataset gave one.
+    pub fn name_of(&self, asn: u32) -> Option<&str> {
+        self.names.get(&asn).map(String::as_str)
+    }
+
+    /// Gets every address which the AS announces.
+    pub fn prefixes_of(&self, asn: u32) -> IpSet {
+        let mut set = IpSet::new();
+        for (range, _) in self.ranges.iter().filter(|(_, &a)| a == asn) {
+            set.insert_range(range);
+        }
+        set
+    }
+
+    /// Groups the addresses by the AS which announces each, in ascending order of ASN. Addresses
+    /// which no AS announces are grouped under `None`, which comes first.
+    pub fn group_by_asn<I>(&self, addrs: I) -> BTreeMap<Option<u32>, Vec<IpAddress>>
+    where
+        I: IntoIterator<Item = IpAddress>,
+    {
+        let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for addr in addrs {
+            groups.entry(self.asn_of(addr)).or_default().push(addr);
+        }
+        groups
+    }
+}
+
+/// Parses the range at the start of a line, returning the fields which follow it.
+fn parse_range<'a, 'b>(fields: &'a [&'b str]) -> Result<(IpRange, &'a [&'b str]), ErrorKind> {
+    let invalid = |s: &str| ErrorKind::InvalidRange(s.to_string());
+    let address = |s: &str| s.parse::<IpAddress>().map_err(|_| invalid(s));
+    match fields {
+        [net, rest @ ..] if net.contains('/') => {
+            let net: IpNetwork = net.parse().map_err(|_| invalid(net))?;
+            Ok((IpRange::from(net), rest))
+        }
+        [range, rest @ ..] if range.contains('-') => {
+            let (start, end) = range.split_once('-').unwrap();
+            let range =
+                IpRange::new(address(start)?, address(end)?).ok_or_else(|| invalid(range))?;
+            Ok((range, rest))
+        }
+        [start, end, rest @ ..] => {
+            let range = IpRange::new(address(start)?, address(end)?)
+                .ok_or_else(|| invalid(&format!("{}-{}", start, end)))?;
+            Ok((range, rest))
+        }
+        _ => Err(invalid(fields[0])),
+    }
+}
+
+/// Whether the field is a country code, or the placeholder datasets use for the lack of one.
+fn is_country(field: &str) -> bool {
+    field == "None" || (field.len() == 2 && field.bytes().all(|b| b.is_ascii_uppercase()))
+}
+
+/// Parses an ASN, with or without an `AS` prefix.
+fn parse_asn(s: &str) -> Result<u32, ErrorKind> {
+    let digits = s
+        .strip_prefix("AS")
+        .or_else(|| s.strip_prefix("as"))
+        .unwrap_or(s);
+    digits
+        .parse()
+        .map_err(|_| ErrorKind::InvalidAsn(s.to_string()))
+}
+
+/// The error returned when an IP-to-ASN dataset could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsnError {
+    line: usize,
+    kind: ErrorKind,
+}
+
+/// What was wrong with a line of an IP-to-ASN dataset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The range is not a network, nor a pair of addresses in order.
+    InvalidRange(String),
+    /// There is nothing after the range.
+    MissingAsn,
+    /// The ASN is not a number.
+    InvalidAsn(String),
+}
+
+impl AsnError {
+    /// The line on which the error was found, counting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// What was wrong with the line.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for AsnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::InvalidRange(s) => write!(f, "invalid range `{}`", s),
+            ErrorKind::MissingAsn => f.write_str("missing ASN"),
+            ErrorKind::InvalidAsn(s) => write!(f, "invalid ASN `{}`", s),
+        }
+    }
+}
+
+impl error::Error for AsnError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    const DATASET: &str = "\
+# range_start\trange_end\tAS_number\tcountry_code\tAS_description
+1.0.0.0\t1.0.0.255\t13335\tUS\tCLOUDFLARENET
+1.0.1.0\t1.0.3.255\t0\tNone\tNot routed
+8.8.8.0/24\tAS15169\tGOOGLE
+9.9.9.0-9.9.9.255\t19281\tCH\tQUAD9-AS-1, Quad9
+1.1.1.0 1.1.1.255 13335 US CLOUDFLARENET
+";
+
+    #[test]
+    fn lookup() {
+        let table = AsnTable::load(DATASET).unwrap();
+        assert_eq!(Some(13335), table.asn_of(addr("1.0.0.1")));
+        assert_eq!(Some(13335), table.asn_of(addr("1.1.1.1")));
+        assert_eq!(None, table.asn_of(addr("1.0.2.0")));
+        assert_eq!(Some(15169), table.asn_of(addr("8.8.8.8")));
+        assert_eq!(Some(19281), table.asn_of(addr("9.9.9.9")));
+        assert_eq!(Some("CLOUDFLARENET"), table.name_of(13335));
+        assert_eq!(Some("GOOGLE"), table.name_of(15169));
+        assert_eq!(Some("QUAD9-AS-1, Quad9"), table.name_of(19281));
+        assert_eq!(None, table.name_of(0));
+    }
+
+    #[test]
+    fn grouping() {
+        let table = AsnTable::load(DATASET).unwrap();
+        let groups = table.group_by_asn(
+            ["1.0.0.1", "8.8.4.4", "1.1.1.1", "8.8.8.8"]
+                .iter()
+                .map(|s| addr(s)),
+        );
+        let expected: BTreeMap<_, _> = vec![
+            (None, vec![addr("8.8.4.4")]),
+            (Some(13335), vec![addr("1.0.0.1"), addr("1.1.1.1")]),
+            (Some(15169), vec![addr("8.8.8.8")]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(expected, groups);
+
+        let prefixes: Vec<String> = table
+            .prefixes_of(13335)
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        assert_eq!(vec!["1.0.0.0/24", "1.1.1.0/24"], prefixes);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |text: &str| AsnTable::load(text).unwrap_err().to_string();
+        assert_eq!("line 1: invalid range `1.0.0.0/33`", err("1.0.0.0/33\t1"));
+        assert_eq!("line 2: missing ASN", err("\n1.0.0.0/24"));
+        assert_eq!("line 1: invalid ASN `ASX`", err("1.0.0.0/24 ASX"));
+        assert_eq!(
+            "line 1: invalid range `1.0.0.9-1.0.0.0`",
+            err("1.0.0.9\t1.0.0.0\t1")
+        );
+    }
+}
@@ -0,0 +1,26 @@
+//! The `aggregate` subcommand, which collapses lists of networks into the fewest equivalent
+//! networks.
+use crate::{args::Args, input, output::Table, Result};
+use ip_utils::set;
+
+pub fn run(mut args: Args) -> Result<()> {
+    let max_prefix: Option<u8> = args.parsed("--max-prefix")?;
+    let strip_shadowed = args.flag("--strip-shadowed");
+    let format = args.format()?;
+    let mut nets = input::read_networks(&args.positional()?)?;
+
+    if let Some(max_prefix) = max_prefix {
+        nets.retain(|net| net.num_network_bits() <= max_prefix);
+    }
+    let nets = if strip_shadowed {
+        set::remove_shadowed(nets)
+    } else {
+        set::aggregate(nets)
+    };
+    let mut table = Table::new(&["network"]);
+    for net in nets {
+        table.push(vec![net.to_string().into()]);
+    }
+    print!("{}", table.render(format));
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! A deliberately small command-line argument parser.
+//!
+//! Options are pulled out of the argument list by name as each subcommand asks for them; once a
+//! subcommand has taken everything it understands, whatever is left over must be positional.
+use crate::{output::Format, Error, Result};
+use std::str::FromStr;
+
+pub struct Args(Vec<String>);
+
+impl Args {
+    pub fn new(args: impl IntoIterator<Item = String>) -> Self {
+        Self(args.into_iter().collect())
+    }
+
+    /// Removes a boolean `--name` switch, returning whether it was present.
+    pub fn flag(&mut self, name: &str) -> bool {
+        let len = self.0.len();
+        self.0.retain(|arg| arg != name);
+        self.0.len() != len
+    }
+
+    /// Removes an option which takes a value, given either as `--name value` or `--name=value`.
+    /// If the option was repeated, the last occurrence wins.
+    pub fn value(&mut self, name: &str) -> Result<Option<String>> {
+        let mut found = None;
+        let mut i = 0;
+        while i < self.0.len() {
+            if self.0[i] == name {
+                if i + 1 == self.0.len() {
+                    return Err(Error::Usage(format!("`{}` requires a value", name)));
+                }
+                found = Some(self.0.remove(i + 1));
+                self.0.remove(i);
+            } else if let Some(value) = self.0[i]
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('='))
+            {
+                found = Some(value.to_string());
+                self.0.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Removes an option which takes a value, and parses that value.
+    pub fn parsed<T: FromStr>(&mut self, name: &str) -> Result<Option<T>> {
+        match self.value(name)? {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| Error::Usage(format!("invalid value `{}` for `{}`", value, name))),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the `--output` option shared by every subcommand, defaulting to plain output.
+    pub fn format(&mut self) -> Result<Format> {
+        Ok(self.parsed("--output")?.unwrap_or(Format::Plain))
+    }
+
+    /// Takes the remaining positional arguments. Anything that still looks like an option at
+    /// this point was not understood by the subcommand.
+    pub fn positional(self) -> Result<Vec<String>> {
+        match self.0.iter().find(|arg| arg.starts_with("--")) {
+            Some(arg) => Err(Error::Usage(format!("unknown option `{}`", arg))),
+            None => Ok(self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Args {
+        Args::new(list.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn options() {
+        let mut a = args(&["10.0.0.0/8", "--prefix", "24", "--json", "--count=3"]);
+        assert!(a.flag("--json"));
+        assert!(!a.flag("--json"));
+        assert_eq!(Some(24), a.parsed::<u8>("--prefix").unwrap());
+        assert_eq!(Some("3".to_string()), a.value("--count").unwrap());
+        assert_eq!(None, a.value("--missing").unwrap());
+        assert_eq!(vec!["10.0.0.0/8".to_string()], a.positional().unwrap());
+
+        assert!(args(&["--prefix"]).value("--prefix").is_err());
+        assert!(args(&["--prefix", "abc"]).parsed::<u8>("--prefix").is_err());
+    }
+
+    #[test]
+    fn positional() {
+        assert_eq!(
+            vec!["10.0.0.0/8".to_string(), "-".to_string()],
+            args(&["10.0.0.0/8", "-"]).positional().unwrap()
+        );
+        assert!(args(&["x", "--bogus"]).positional().is_err());
+    }
+}
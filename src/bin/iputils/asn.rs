@@ -0,0 +1,57 @@
+//! The `asn` subcommand, which finds the autonomous system announcing each address.
+use crate::{args::Args, input, output::Table, Error, Result};
+use ip_utils::{asn::AsnTable, IpAddress};
+use std::fs;
+
+pub fn run(mut args: Args) -> Result<()> {
+    let path = args
+        .value("--table")?
+        .ok_or_else(|| Error::Usage("`asn` requires `--table <file>`".into()))?;
+    let summary = args.flag("--summary");
+    let format = args.format()?;
+    let positional = args.positional()?;
+
+    let text =
+        fs::read_to_string(&path).map_err(|err| Error::Runtime(format!("{}: {}", path, err)))?;
+    let table =
+        AsnTable::load(&text).map_err(|err| Error::Runtime(format!("{}: {}", path, err)))?;
+    let mut addrs = Vec::new();
+    let mut parse = |entry: &str| {
+        let addr: IpAddress = entry
+            .parse()
+            .map_err(|_| Error::Runtime(format!("invalid address `{}`", entry)))?;
+        addrs.push(addr);
+        Ok(())
+    };
+    if positional.is_empty() {
+        input::for_each_entry(&[], parse)?;
+    } else {
+        positional.iter().try_for_each(|addr| parse(addr))?;
+    }
+
+    let name = |asn: Option<u32>| asn.and_then(|asn| table.name_of(asn)).map(str::to_string);
+    let output = if summary {
+        let mut output = Table::new(&["asn", "name", "addresses"]);
+        for (asn, group) in table.group_by_asn(addrs) {
+            output.push(vec![
+                asn.map(u64::from).into(),
+                name(asn).into(),
+                (group.len() as u64).into(),
+            ]);
+        }
+        output
+    } else {
+        let mut output = Table::new(&["address", "asn", "name"]);
+        for addr in addrs {
+            let asn = table.asn_of(addr);
+            output.push(vec![
+                addr.to_string().into(),
+                asn.map(u64::from).into(),
+                name(asn).into(),
+            ]);
+        }
+        output
+    };
+    print!("{}", output.render(format));
+    Ok(())
+}
@@ -0,0 +1,292 @@
+//! The `eval` subcommand, which evaluates expressions over sets of addresses.
+//!
+//! ```text
+//! expr := term (('+' | '|' | '-') term)*
+//! term := atom ('&' atom)*
+//! atom := '(' expr ')' | '@' file | name | network | address
+//! ```
+//!
+//! `+` and `|` are both union, `-` is difference, and `&` is intersection. Intersection binds
+//! tighter than union and difference, which are evaluated from left to right. A file reference
+//! stands for the union of every network listed in the file, and a name stands for a set which
+//! was bound earlier (within the `repl`).
+use crate::{args::Args, input, output::Table, Error, Result};
+use ip_utils::IpSet;
+use std::collections::HashMap;
+
+/// The sets which have been bound to names.
+pub type Scope = HashMap<String, IpSet>;
+
+pub fn run(mut args: Args) -> Result<()> {
+    let format = args.format()?;
+    let expr = args.positional()?.join(" ");
+    if expr.trim().is_empty() {
+        return Err(Error::Usage("`eval` requires an expression".into()));
+    }
+    let set = evaluate(&expr, &Scope::new())?;
+    let mut table = Table::new(&["network"]);
+    for net in set.iter() {
+        table.push(vec![net.to_string().into()]);
+    }
+    print!("{}", table.render(format));
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A network or address, written out in full.
+    Literal(String),
+    /// A reference to a file of networks.
+    File(String),
+    /// A reference to a set bound to a name.
+    Name(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '+' | '|' | '-' | '&' => {
+                chars.next();
+                tokens.push(Token::Op(c));
+            }
+            '@' => {
+                chars.next();
+                let mut path = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '+' | '|' | '&') {
+                        break;
+                    }
+                    path.push(c);
+                    chars.next();
+                }
+                if path.is_empty() {
+                    return Err(Error::Runtime("`@` must be followed by a file name".into()));
+                }
+                tokens.push(Token::File(path));
+            }
+            c if c.is_ascii_digit() => {
+                let mut literal = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.' || c == '/') {
+                        break;
+                    }
+                    literal.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Literal(literal));
+            }
+            c if is_name_start(c) => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !is_name_continue(c) {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Name(name));
+            }
+            c => return Err(Error::Runtime(format!("unexpected character `{}`", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Whether the character may begin a name.
+pub fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+/// Whether the character may appear within a name, after the first.
+pub fn is_name_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// A recursive-descent parser which evaluates as it goes.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    scope: &'a Scope,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expr(&mut self) -> Result<IpSet> {
+        let mut set = self.term()?;
+        while let Some(&Token::Op(op @ ('+' | '|' | '-'))) = self.peek() {
+            self.next();
+            let rhs = self.term()?;
+            set = match op {
+                '-' => set.difference(&rhs),
+                _ => set.union(&rhs),
+            };
+        }
+        Ok(set)
+    }
+
+    fn term(&mut self) -> Result<IpSet> {
+        let mut set = self.atom()?;
+        while let Some(Token::Op('&')) = self.peek() {
+            self.next();
+            set = set.intersection(&self.atom()?);
+        }
+        Ok(set)
+    }
+
+    fn atom(&mut self) -> Result<IpSet> {
+        match self.next() {
+            Some(Token::Open) => {
+                let set = self.expr()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(set),
+                    _ => Err(Error::Runtime("expected `)`".into())),
+                }
+            }
+            Some(Token::Literal(literal)) => Ok(IpSet::from(input::parse_network(&literal)?)),
+            Some(Token::File(path)) => Ok(input::read_networks(&[path])?.into_iter().collect()),
+            Some(Token::Name(name)) => self
+                .scope
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| Error::Runtime(format!("`{}` is not bound", name))),
+            Some(Token::Op(op)) => Err(Error::Runtime(format!("unexpected `{}`", op))),
+            Some(Token::Close) => Err(Error::Runtime("unexpected `)`".into())),
+            None => Err(Error::Runtime("unexpected end of expression".into())),
+        }
+    }
+}
+
+/// Evaluates the expression into the set it describes, looking up any names within the scope.
+pub fn evaluate(expr: &str, scope: &Scope) -> Result<IpSet> {
+    let mut parser = Parser {
+        tokens: tokenize(expr)?,
+        pos: 0,
+        scope,
+    };
+    let set = parser.expr()?;
+    match parser.peek() {
+        None => Ok(set),
+        Some(token) => Err(Error::Runtime(format!("unexpected {:?}", token))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expr: &str) -> Vec<String> {
+        evaluate(expr, &Scope::new())
+            .unwrap()
+            .iter()
+            .map(|net| net.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(vec!["10.0.0.0/8"], eval("10.0.0.0/8"));
+        assert_eq!(vec!["10.0.0.1/32"], eval("10.0.0.1"));
+        assert_eq!(vec!["10.0.0.0/23"], eval("10.0.0.0/24 + 10.0.1.0/24"));
+        assert_eq!(vec!["10.0.0.0/23"], eval("10.0.0.0/24|10.0.1.0/24"));
+        assert_eq!(vec!["10.0.1.0/24"], eval("10.0.0.0/23 - 10.0.0.0/24"));
+        assert_eq!(vec!["10.0.1.0/24"], eval("10.0.0.0/23 & 10.0.1.0/24"));
+        assert!(eval("10.0.0.0/24 & 10.0.1.0/24").is_empty());
+    }
+
+    #[test]
+    fn precedence() {
+        // Left to right for difference and union.
+        assert_eq!(
+            vec!["10.0.0.0/24"],
+            eval("10.0.0.0/23 - 10.0.1.0/24 + 10.0.0.0/24")
+        );
+        assert_eq!(
+            vec!["10.0.0.0/24", "10.0.2.0/24"],
+            eval("10.0.0.0/22 - 10.0.1.0/24 - 10.0.3.0/24")
+        );
+        // Intersection first.
+        assert_eq!(
+            vec!["10.0.0.0/24", "10.0.2.0/24"],
+            eval("10.0.0.0/24 + 10.0.2.0/23 & 10.0.2.0/24")
+        );
+        assert_eq!(
+            vec![
+                "10.0.0.0/16",
+                "10.1.1.0/24",
+                "10.1.2.0/23",
+                "10.1.4.0/22",
+                "10.1.8.0/21",
+                "10.1.16.0/20",
+                "10.1.32.0/19",
+                "10.1.64.0/18",
+                "10.1.128.0/17",
+                "10.2.0.0/23",
+                "10.2.2.0/24",
+                "10.2.4.0/22",
+                "10.2.8.0/21",
+                "10.2.16.0/20",
+                "10.2.32.0/19",
+                "10.2.64.0/18",
+                "10.2.128.0/17",
+                "10.3.0.0/16",
+                "10.4.0.0/14",
+                "10.8.0.0/13",
+                "10.16.0.0/12",
+                "10.32.0.0/11",
+                "10.64.0.0/10",
+                "10.128.0.0/9"
+            ],
+            eval("10.0.0.0/8 - (10.1.0.0/24 + 10.2.3.0/24 + 10.1.0.0/16 & 10.1.0.0/24)")
+        );
+    }
+
+    #[test]
+    fn errors() {
+        for bad in &[
+            "",
+            "(10.0.0.0/8",
+            "10.0.0.0/8)",
+            "10.0.0.0/8 +",
+            "- 10.0.0.0/8",
+            "10.0.0.0/33",
+            "10.0.0.0/8 10.0.0.0/8",
+            "x",
+            "@",
+        ] {
+            assert!(evaluate(bad, &Scope::new()).is_err(), "{}", bad);
+        }
+        assert!(evaluate("@/nonexistent/file", &Scope::new()).is_err());
+    }
+
+    #[test]
+    fn names() {
+        let mut scope = Scope::new();
+        scope.insert("ours".into(), evaluate("10.1.0.0/16", &scope).unwrap());
+        assert_eq!(
+            evaluate("10.0.0.0/8 - 10.1.0.0/16", &scope).unwrap(),
+            evaluate("10.0.0.0/8 - ours", &scope).unwrap()
+        );
+        assert!(evaluate("10.0.0.0/8 - theirs", &scope).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+//! The `exclude` subcommand, which finds what is left of a network once others are taken out.
+use crate::{
+    args::Args,
+    input,
+    output::{Format, Table},
+    Error, Result,
+};
+use ip_utils::wireguard::AllowedIps;
+
+pub fn run(mut args: Args) -> Result<()> {
+    let minus = args
+        .value("--minus")?
+        .ok_or_else(|| Error::Usage("`exclude` requires `--minus <file>`".into()))?;
+    let wireguard = args.flag("--wireguard");
+    let format = args.format()?;
+    if wireguard && format != Format::Plain {
+        return Err(Error::Usage(
+            "`--wireguard` cannot be combined with `--output`".into(),
+        ));
+    }
+    let positional = args.positional()?;
+    let supernet = match positional.as_slice() {
+        [net] => input::parse_network(net)?,
+        _ => return Err(Error::Usage("`exclude` takes exactly one network".into())),
+    };
+
+    let allowed = input::read_networks(&[minus])?
+        .into_iter()
+        .fold(AllowedIps::new().include(supernet), AllowedIps::exclude);
+    if wireguard {
+        println!("{}", allowed.config_line());
+        return Ok(());
+    }
+    let mut table = Table::new(&["network"]);
+    for net in allowed.networks() {
+        table.push(vec![net.to_string().into()]);
+    }
+    print!("{}", table.render(format));
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! The `firewall` subcommand, which renders lists of networks as sets for the kernel firewall.
+use crate::{args::Args, input, output::Format, Error, Result};
+use ip_utils::{
+    firewall::{Ipset, IpsetType, NftFamily, NftSet},
+    IpSet,
+};
+
+pub fn run(mut args: Args) -> Result<()> {
+    let nft = args.flag("--nft");
+    let ipset = args.flag("--ipset");
+    let name = args
+        .value("--set")?
+        .ok_or_else(|| Error::Usage("`firewall` requires `--set <name>`".into()))?;
+    let table = args.value("--table")?;
+    let family = args.value("--family")?;
+    let no_interval = args.flag("--no-interval");
+    let hash_ip = args.flag("--hash-ip");
+    let flush = args.flag("--flush");
+    if args.format()? != Format::Plain {
+        return Err(Error::Usage(
+            "`firewall` writes a script, so cannot be combined with `--output`".into(),
+        ));
+    }
+    let set: IpSet = input::read_networks(&args.positional()?)?
+        .into_iter()
+        .collect();
+
+    let script = match (nft, ipset) {
+        (true, false) => {
+            let family = match family.as_deref() {
+                None | Some("inet") => NftFamily::Inet,
+                Some("ip") => NftFamily::Ip,
+                Some("bridge") => NftFamily::Bridge,
+                Some("netdev") => NftFamily::Netdev,
+                Some(other) => {
+                    return Err(Error::Usage(format!("unknown nftables family `{}`", other)))
+                }
+            };
+            NftSet::new(table.as_deref().unwrap_or("filter"), &name)
+                .family(family)
+                .interval(!no_interval)
+                .render(&set)
+                .ok_or_else(|| {
+                    Error::Runtime("a set without `interval` can only hold single addresses".into())
+                })?
+        }
+        (false, true) => {
+            let set_type = if hash_ip {
+                IpsetType::HashIp
+            } else {
+                IpsetType::HashNet
+            };
+            Ipset::new(&name)
+                .set_type(set_type)
+                .flush(flush)
+                .render(&set)
+        }
+        _ => {
+            return Err(Error::Usage(
+                "`firewall` requires exactly one of `--nft` or `--ipset`".into(),
+            ))
+        }
+    };
+    print!("{}", script);
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! The `info` subcommand, which describes a single address or network.
+use crate::{
+    args::Args,
+    input,
+    output::{Format, Table},
+    Error, Result,
+};
+use ip_utils::{IpAddress, IpNetwork};
+
+pub fn run(mut args: Args) -> Result<()> {
+    let format = args.format()?;
+    let positional = args.positional()?;
+    let target = match positional.as_slice() {
+        [target] => target.trim(),
+        _ => {
+            return Err(Error::Usage(
+                "`info` takes exactly one address or network".into(),
+            ))
+        }
+    };
+    let net = input::parse_network(target)?;
+    let addr = net.network_address();
+    let prefix = net.num_network_bits();
+    let host = target.split('/').next().unwrap_or_default();
+
+    if format != Format::Plain {
+        let mut table = Table::new(&[
+            "address",
+            "network",
+            "netmask",
+            "prefix_len",
+            "wildcard",
+            "broadcast",
+            "addresses",
+            "hosts",
+            "class",
+            "flags",
+        ]);
+        table.push(vec![
+            host.to_string().into(),
+            IpNetwork::new(addr, prefix).unwrap().to_string().into(),
+            net.get_mask().to_string().into(),
+            u64::from(prefix).into(),
+            net.get_wildcard().to_string().into(),
+            net.broadcast_address().to_string().into(),
+            net.num_addresses().into(),
+            net.num_usable_hosts().into(),
+            addr.class().to_string().into(),
+            flags(&net).join(",").into(),
+        ]);
+        print!("{}", table.render(format));
+        return Ok(());
+    }
+
+    println!("Address:   {}", host);
+    println!("Network:   {}", IpNetwork::new(addr, prefix).unwrap());
+    println!("Netmask:   {} = {}", net.get_mask(), prefix);
+    println!("Wildcard:  {}", net.get_wildcard());
+    println!("Broadcast: {}", net.broadcast_address());
+    println!("Addresses: {}", net.num_addresses());
+    println!("Hosts:     {}", net.num_usable_hosts());
+    println!("Class:     {}", addr.class());
+    println!("Flags:     {}", flags(&net).join(", "));
+    Ok(())
+}
+
+type Check = fn(&IpAddress) -> bool;
+
+/// Names every special-purpose range which this network lies entirely within.
+fn flags(net: &IpNetwork) -> Vec<&'static str> {
+    let checks: [(&str, Check); 10] = [
+        ("unspecified", IpAddress::is_unspecified),
+        ("loopback", IpAddress::is_loopback),
+        ("private", IpAddress::is_private),
+        ("shared", IpAddress::is_shared),
+        ("link-local", IpAddress::is_link_local),
+        ("documentation", IpAddress::is_documentation),
+        ("benchmarking", IpAddress::is_benchmarking),
+        ("multicast", IpAddress::is_multicast),
+        ("reserved", IpAddress::is_reserved),
+        ("broadcast", IpAddress::is_broadcast),
+    ];
+    let (first, last) = (net.network_address(), net.broadcast_address());
+    let flags: Vec<_> = checks
+        .iter()
+        .filter(|(_, check)| check(&first) && check(&last))
+        .map(|(name, _)| *name)
+        .collect();
+    if flags.is_empty() {
+        vec!["none"]
+    } else {
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_flags() {
+        let flags_of = |s: &str| flags(&input::parse_network(s).unwrap());
+        assert_eq!(vec!["private"], flags_of("192.168.1.5/24"));
+        assert_eq!(vec!["none"], flags_of("8.8.8.8"));
+        assert_eq!(vec!["none"], flags_of("0.0.0.0/0"));
+        assert_eq!(vec!["unspecified"], flags_of("0.0.0.0"));
+        assert_eq!(vec!["none"], flags_of("172.0.0.0/8"));
+        assert_eq!(vec!["multicast"], flags_of("224.0.0.0/4"));
+        assert_eq!(vec!["broadcast"], flags_of("255.255.255.255/32"));
+    }
+}
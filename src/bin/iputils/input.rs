@@ -0,0 +1,63 @@
+//! Helpers for turning user input into the library's types.
+use crate::{Error, Result};
+use ip_utils::{IpAddress, IpNetwork};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
+
+/// Parses either a network in CIDR notation, or a bare address which is taken to be a `/32`.
+pub fn parse_network(s: &str) -> Result<IpNetwork> {
+    let s = s.trim();
+    if s.contains('/') {
+        s.parse()
+            .map_err(|_| Error::Runtime(format!("invalid network `{}`", s)))
+    } else {
+        let addr: IpAddress = s
+            .parse()
+            .map_err(|_| Error::Runtime(format!("invalid address `{}`", s)))?;
+        Ok(IpNetwork::new(addr, 32).unwrap())
+    }
+}
+
+/// Reads every network listed in the files, or on stdin if there are none (or the file is `-`).
+///
+/// Entries are separated by whitespace, and anything following a `#` on a line is a comment.
+pub fn read_networks(paths: &[String]) -> Result<Vec<IpNetwork>> {
+    let mut nets = Vec::new();
+    for_each_entry(paths, |entry| {
+        nets.push(parse_network(entry)?);
+        Ok(())
+    })?;
+    Ok(nets)
+}
+
+/// Calls the function with every whitespace-separated entry in the files (or stdin), tagging any
+/// runtime error with where the offending entry came from.
+pub fn for_each_entry<F>(paths: &[String], mut f: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<()>,
+{
+    let stdin = ["-".to_string()];
+    let paths = if paths.is_empty() { &stdin[..] } else { paths };
+    for path in paths {
+        let reader: Box<dyn BufRead> = if path == "-" {
+            Box::new(BufReader::new(io::stdin()))
+        } else {
+            let file =
+                File::open(path).map_err(|err| Error::Runtime(format!("{}: {}", path, err)))?;
+            Box::new(BufReader::new(file))
+        };
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.split('#').next().unwrap_or_default();
+            for entry in line.split_whitespace() {
+                f(entry).map_err(|err| match err {
+                    Error::Runtime(msg) => Error::Runtime(format!("{}:{}: {}", path, i + 1, msg)),
+                    err => err,
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! The `lookup` subcommand, which checks addresses against a list of networks.
+use crate::{args::Args, input, output::RowWriter, Error, Result};
+use ip_utils::{IpAddress, IpNetwork, IpNetworkMap};
+
+pub fn run(mut args: Args) -> Result<()> {
+    let list = args
+        .value("--list")?
+        .ok_or_else(|| Error::Usage("`lookup` requires `--list <file>`".into()))?;
+    let format = args.format()?;
+    let addrs = args.positional()?;
+
+    let map: IpNetworkMap<()> = input::read_networks(&[list])?
+        .into_iter()
+        .map(|net| (net, ()))
+        .collect();
+
+    // Results are written as each address is read, so the widest possible values set the
+    // alignment.
+    let widest = IpNetwork::new(IpAddress::from(u32::MAX), 32).unwrap();
+    let widths = [
+        widest.network_address().to_string().len(),
+        false.to_string().len(),
+        widest.to_string().len(),
+    ];
+    let mut rows = RowWriter::stdout(&["address", "matched", "network"], format, &widths)?;
+    let mut any_matched = false;
+    let mut check = |entry: &str| {
+        let addr: IpAddress = entry
+            .parse()
+            .map_err(|_| Error::Runtime(format!("invalid address `{}`", entry)))?;
+        let matched = map.longest_match(addr).map(|(net, _)| net);
+        any_matched |= matched.is_some();
+        rows.push(&[
+            addr.to_string().into(),
+            matched.is_some().into(),
+            matched.map(|net| net.to_string()).into(),
+        ])?;
+        Ok(())
+    };
+    if addrs.is_empty() {
+        input::for_each_entry(&[], check)?;
+    } else {
+        addrs.iter().try_for_each(|addr| check(addr))?;
+    }
+    rows.finish()?;
+
+    // Like grep, the exit status tells whether anything matched.
+    if any_matched {
+        Ok(())
+    } else {
+        Err(Error::Exit(1))
+    }
+}
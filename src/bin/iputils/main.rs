@@ -0,0 +1,144 @@
+//! # The `iputils` command-line tool.
+//!
+//! An ipcalc-style front end to the library. Each subcommand lives in its own module, and is
+//! handed the remaining command-line arguments to pick apart as it sees fit.
+mod aggregate;
+mod args;
+mod asn;
+mod eval;
+mod exclude;
+mod firewall;
+mod info;
+mod input;
+mod lookup;
+mod output;
+mod ptr;
+mod random;
+mod repl;
+mod split;
+mod tree;
+
+use std::{env, fmt, io, process};
+
+const USAGE: &str = "\
+usage: iputils <command> [arguments]
+
+commands:
+    info <addr[/prefix]>    describe an address or network
+    aggregate [file...]     merge networks into the fewest equivalent networks
+        --max-prefix <n>        discard entries more specific than /n first
+        --strip-shadowed        only drop entries covered by another entry
+    split <net>             carve a network into equally-sized subnets
+        --prefix <n>            into subnets of length /n
+        --parts <k>             into at least k subnets
+        --hosts <h>             into the smallest subnets holding h usable hosts
+    exclude <net>           list the fewest networks covering what remains of a network
+        --minus <file>          the networks to take out of it
+        --wireguard             as a WireGuard `AllowedIPs` line, rather than with `--output`
+    random                  generate random addresses (or subnets) within a network
+        --net <net>             the network to generate within
+        --count <n>             how many to generate (default 1)
+        --prefix <n>            generate /n subnets rather than addresses
+        --unique                never repeat a result
+        --seed <n>              make the results repeatable
+    lookup [addr...]        find the longest prefix matching each address (or stdin)
+        --list <file>           the networks to match against
+    asn [addr...]           find the autonomous system announcing each address (or stdin)
+        --table <file>          an IP-to-ASN dataset, such as iptoasn.com's ip2asn-v4.tsv
+        --summary               count the addresses of each AS instead
+    eval <expr>             evaluate an expression over sets of networks, such as
+                            `10.0.0.0/8 - (10.1.0.0/16 + @ours.txt)`, where `+` or `|` is
+                            union, `-` is difference, `&` is intersection, and `@file`
+                            stands for every network listed in the file
+    ptr <addr|net>          list the reverse DNS names of every address
+        --zones                 list the reverse zones covering the network instead
+        --bind                  write BIND zone statements and zone files instead, without
+                                `--output`
+        --domain <domain>       the domain to name hosts within, for `--bind`
+    tree [file...]          draw the hierarchy of networks, with how much of each is used
+        --dot                   as a Graphviz DOT graph, rather than with `--output`
+    firewall [file...]      render networks as a set for the kernel firewall, without
+                            `--output`
+        --nft                   as an nftables table block, for `nft -f`
+        --ipset                 as a script for `ipset restore`
+        --set <name>            the name of the set
+        --table <name>          the nftables table holding the set (default filter)
+        --family <family>       the nftables table family (default inet)
+        --no-interval           an nftables set of single addresses only
+        --hash-ip               an ipset of type hash:ip rather than hash:net
+        --flush                 empty the ipset before adding to it
+    repl                    explore networks interactively; type `help` within it
+    help                    show this message
+
+options:
+    --output <format>       print results as plain text (the default), csv, or json
+";
+
+/// Everything that can go wrong while running a subcommand.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line itself was malformed.
+    Usage(String),
+    /// The command line was fine, but the input or environment was not.
+    Runtime(String),
+    /// Nothing went wrong, but the command still wants to exit with this status.
+    Exit(i32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(msg) | Self::Runtime(msg) => f.write_str(msg),
+            Self::Exit(code) => write!(f, "exited with status {}", code),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        // Output piped into something like `head`, which stopped reading, is not a failure.
+        if err.kind() == io::ErrorKind::BrokenPipe {
+            return Self::Exit(0);
+        }
+        Self::Runtime(err.to_string())
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+fn main() {
+    let mut argv = env::args().skip(1);
+    let command = argv.next();
+    let args = args::Args::new(argv);
+    let result = match command.as_deref() {
+        Some("info") => info::run(args),
+        Some("aggregate") => aggregate::run(args),
+        Some("split") => split::run(args),
+        Some("lookup") => lookup::run(args),
+        Some("exclude") => exclude::run(args),
+        Some("firewall") => firewall::run(args),
+        Some("random") => random::run(args),
+        Some("asn") => asn::run(args),
+        Some("eval") => eval::run(args),
+        Some("repl") => repl::run(args),
+        Some("ptr") => ptr::run(args),
+        Some("tree") => tree::run(args),
+        None | Some("help") | Some("-h") | Some("--help") => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        Some(other) => Err(Error::Usage(format!("unknown command `{}`", other))),
+    };
+    match result {
+        Ok(()) => {}
+        Err(Error::Exit(code)) => process::exit(code),
+        Err(err @ Error::Usage(_)) => {
+            eprintln!("iputils: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+        Err(err) => {
+            eprintln!("iputils: {}", err);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! Rendering of tabular results, either for people or for other programs.
+use crate::{Error, Result};
+use std::{fmt::Write, io, str::FromStr};
+
+/// The ways in which results may be printed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    /// Aligned columns, for reading in a terminal.
+    Plain,
+    /// Comma-separated values, with a header row.
+    Csv,
+    /// An array of objects, one per row.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "plain" => Ok(Self::Plain),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(Error::Usage(format!("unknown output format `{}`", s))),
+        }
+    }
+}
+
+/// A single cell of a table. Anything other than text is kept apart so that JSON output does not
+/// quote it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    Number(u64),
+    Bool(bool),
+    /// A missing value, shown as `-` in plain output.
+    Null,
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Self {
+        Self::Number(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl Value {
+    fn plain(&self) -> String {
+        match self {
+            Self::Text(s) => s.clone(),
+            Self::Number(n) => n.to_string(),
+            Self::Bool(b) => b.to_string(),
+            Self::Null => "-".to_string(),
+        }
+    }
+}
+
+/// Rows of results under a fixed set of column names. The column names double as the field names
+/// for structured output, so they must stay stable.
+pub struct Table {
+    columns: &'static [&'static str],
+    rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    pub fn new(columns: &'static [&'static str]) -> Self {
+        Self {
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, row: Vec<Value>) {
+        debug_assert_eq!(self.columns.len(), row.len());
+        self.rows.push(row);
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        let mut widths = vec![0; self.columns.len()];
+        if format == Format::Plain {
+            for row in &self.rows {
+                for (width, value) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(value.plain().len());
+                }
+            }
+        }
+        let mut writer = RowWriter::new(self.columns, format, &widths, Vec::new()).unwrap();
+        for row in &self.rows {
+            writer.push(row).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+}
+
+/// Writes rows out as they are produced, for results too numerous to hold in a [`Table`] first.
+/// Plain output cannot look ahead, so its columns are aligned to widths given up front.
+pub struct RowWriter<W> {
+    columns: &'static [&'static str],
+    format: Format,
+    widths: Vec<usize>,
+    out: W,
+    rows: usize,
+}
+
+impl RowWriter<io::BufWriter<io::StdoutLock<'static>>> {
+    /// Starts writing rows to standard output.
+    pub fn stdout(
+        columns: &'static [&'static str],
+        format: Format,
+        widths: &[usize],
+    ) -> io::Result<Self> {
+        Self::new(
+            columns,
+            format,
+            widths,
+            io::BufWriter::new(io::stdout().lock()),
+        )
+    }
+}
+
+impl<W: io::Write> RowWriter<W> {
+    /// Starts the output, writing any header. Each column is at least as wide as its name.
+    pub fn new(
+        columns: &'static [&'static str],
+        format: Format,
+        widths: &[usize],
+        out: W,
+    ) -> io::Result<Self> {
+        let widths = columns
+            .iter()
+            .zip(widths)
+            .map(|(column, &width)| width.max(column.len()))
+            .collect();
+        let mut writer = Self {
+            columns,
+            format,
+            widths,
+            out,
+            rows: 0,
+        };
+        match format {
+            // Plain output of a single column is really just a list, printed one value per line
+            // so that it can be piped along.
+            Format::Plain if columns.len() > 1 => {
+                let header: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+                writer.write_plain(&header)?;
+            }
+            Format::Plain => {}
+            Format::Csv => writeln!(writer.out, "{}", columns.join(","))?,
+            Format::Json => write!(writer.out, "[")?,
+        }
+        Ok(writer)
+    }
+
+    pub fn push(&mut self, row: &[Value]) -> io::Result<()> {
+        debug_assert_eq!(self.columns.len(), row.len());
+        match self.format {
+            Format::Plain if self.columns.len() > 1 => {
+                let cells: Vec<String> = row.iter().map(Value::plain).collect();
+                self.write_plain(&cells)?;
+            }
+            Format::Plain => writeln!(self.out, "{}", row[0].plain())?,
+            Format::Csv => {
+                let line: Vec<String> = row.iter().map(|v| csv_field(&v.plain())).collect();
+                writeln!(self.out, "{}", line.join(","))?;
+            }
+            Format::Json => {
+                let fields: Vec<String> = self
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| format!("{}:{}", json_string(column), json_value(value)))
+                    .collect();
+                let comma = if self.rows == 0 { "" } else { "," };
+                write!(self.out, "{}{{{}}}", comma, fields.join(","))?;
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Ends the output, writing anything which closes it, and hands back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.format == Format::Json {
+            writeln!(self.out, "]")?;
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_plain(&mut self, cells: &[String]) -> io::Result<()> {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(&self.widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        writeln!(self.out, "{}", line.join("  ").trim_end())
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Text(s) => json_string(s),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => "null".to_string(),
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(&["network", "hosts"]);
+        table.push(vec!["10.0.0.0/24".to_string().into(), 256.into()]);
+        table.push(vec!["a,\"b\"".to_string().into(), 1.into()]);
+        table
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(
+            "network      hosts\n10.0.0.0/24  256\na,\"b\"        1\n",
+            table().render(Format::Plain)
+        );
+    }
+
+    #[test]
+    fn plain_list() {
+        let mut table = Table::new(&["network"]);
+        table.push(vec!["10.0.0.0/8".to_string().into()]);
+        table.push(vec!["11.0.0.0/8".to_string().into()]);
+        assert_eq!("10.0.0.0/8\n11.0.0.0/8\n", table.render(Format::Plain));
+        assert_eq!(
+            "network\n10.0.0.0/8\n11.0.0.0/8\n",
+            table.render(Format::Csv)
+        );
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            "network,hosts\n10.0.0.0/24,256\n\"a,\"\"b\"\"\",1\n",
+            table().render(Format::Csv)
+        );
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            "[{\"network\":\"10.0.0.0/24\",\"hosts\":256},{\"network\":\"a,\\\"b\\\"\",\"hosts\":1}]\n",
+            table().render(Format::Json)
+        );
+        assert_eq!("\"\\u0001\\t\"", json_string("\u{1}\t"));
+
+        let mut table = Table::new(&["matched", "network"]);
+        table.push(vec![false.into(), None::<String>.into()]);
+        assert_eq!(
+            "[{\"matched\":false,\"network\":null}]\n",
+            table.render(Format::Json)
+        );
+        assert_eq!(
+            "matched  network\nfalse    -\n",
+            table.render(Format::Plain)
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! The `ptr` subcommand, which converts addresses and networks to their reverse DNS names.
+//!
+//! A network may hold billions of addresses, so every line is written out as soon as it is made.
+use crate::{
+    args::Args,
+    input,
+    output::{Format, RowWriter, Table},
+    Error, Result,
+};
+use std::io::{self, BufWriter, Write};
+
+pub fn run(mut args: Args) -> Result<()> {
+    let zones = args.flag("--zones");
+    let bind = args.flag("--bind");
+    let domain = args.value("--domain")?;
+    let format = args.format()?;
+    let positional = args.positional()?;
+    let net = match positional.as_slice() {
+        [target] => input::parse_network(target)?,
+        _ => {
+            return Err(Error::Usage(
+                "`ptr` takes exactly one address or network".into(),
+            ))
+        }
+    };
+
+    if bind && format != Format::Plain {
+        return Err(Error::Usage(
+            "`--bind` cannot be combined with `--output`".into(),
+        ));
+    }
+
+    if bind {
+        let domain = domain.ok_or_else(|| {
+            Error::Usage("`--bind` requires `--domain <domain>` to name the hosts".into())
+        })?;
+        let mut out = BufWriter::new(io::stdout().lock());
+        write_bind(net, domain.trim_end_matches('.'), &mut out)?;
+        out.flush()?;
+    } else if zones {
+        let mut table = Table::new(&["zone", "network"]);
+        for zone in net.reverse_zones() {
+            table.push(vec![
+                zone.name().to_string().into(),
+                zone.network().to_string().into(),
+            ]);
+        }
+        print!("{}", table.render(format));
+    } else {
+        // The broadcast address has every octet at its largest, so it is the widest of all.
+        let last = net.broadcast_address();
+        let widths = [last.to_string().len(), last.reverse_pointer().len()];
+        let mut rows = RowWriter::stdout(&["address", "ptr"], format, &widths)?;
+        for addr in net.addresses() {
+            rows.push(&[addr.to_string().into(), addr.reverse_pointer().into()])?;
+        }
+        rows.finish()?;
+    }
+    Ok(())
+}
+
+/// Writes out a BIND zone statement and zone-file body for every reverse zone of the network,
+/// with a `PTR` record for each address naming it after its dashed form within the domain.
+fn write_bind<W: Write>(net: ip_utils::IpNetwork, domain: &str, out: &mut W) -> io::Result<()> {
+    for zone in net.reverse_zones() {
+        let file = format!("db.{}", zone.name().replace('/', "-"));
+        writeln!(out, "zone \"{}\" {{", zone)?;
+        writeln!(out, "    type master;")?;
+        writeln!(out, "    file \"{}\";", file)?;
+        writeln!(out, "}};")?;
+        writeln!(out)?;
+        writeln!(out, "; {}", file)?;
+        writeln!(out, "$ORIGIN {}.", zone)?;
+        writeln!(out, "$TTL 3600")?;
+        writeln!(
+            out,
+            "@ IN SOA ns1.{d}. hostmaster.{d}. ( 1 3600 900 604800 3600 )",
+            d = domain
+        )?;
+        writeln!(out, "@ IN NS ns1.{}.", domain)?;
+        for addr in zone.network().addresses() {
+            let host = addr.to_string().replace('.', "-");
+            writeln!(
+                out,
+                "{} IN PTR {}.{}.",
+                zone.record_label(addr),
+                host,
+                domain
+            )?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind() {
+        let net = "192.0.2.64/31".parse().unwrap();
+        let mut out = Vec::new();
+        write_bind(net, "example.com", &mut out).unwrap();
+        assert_eq!(
+            "zone \"64/31.2.0.192.in-addr.arpa\" {
+    type master;
+    file \"db.64-31.2.0.192.in-addr.arpa\";
+};
+
+; db.64-31.2.0.192.in-addr.arpa
+$ORIGIN 64/31.2.0.192.in-addr.arpa.
+$TTL 3600
+@ IN SOA ns1.example.com. hostmaster.example.com. ( 1 3600 900 604800 3600 )
+@ IN NS ns1.example.com.
+64 IN PTR 192-0-2-64.example.com.
+65 IN PTR 192-0-2-65.example.com.
+
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! The `random` subcommand, which generates random addresses or subnets within a network.
+use crate::{args::Args, input, output::RowWriter, Error, Result};
+use ip_utils::{
+    random::{Permutation, Rng},
+    IpAddress, IpNetwork,
+};
+
+pub fn run(mut args: Args) -> Result<()> {
+    let net = match args.value("--net")? {
+        Some(net) => input::parse_network(&net)?,
+        None => return Err(Error::Usage("`random` requires `--net <network>`".into())),
+    };
+    let count: u64 = args.parsed("--count")?.unwrap_or(1);
+    let prefix: u8 = args.parsed("--prefix")?.unwrap_or(32);
+    let unique = args.flag("--unique");
+    let format = args.format()?;
+    let mut rng = match args.parsed("--seed")? {
+        Some(seed) => Rng::seed_from_u64(seed),
+        None => Rng::from_entropy(),
+    };
+    if !args.positional()?.is_empty() {
+        return Err(Error::Usage(
+            "`random` takes no positional arguments".into(),
+        ));
+    }
+
+    let subnets = net
+        .subnets_with_prefix(prefix)
+        .ok_or_else(|| Error::Runtime(format!("{} has no /{} subnets", net, prefix)))?;
+    // Walking a random permutation of the subnets guarantees that none repeat.
+    let available = subnets.len() as u64;
+    if unique && count > available {
+        let what = match prefix {
+            32 => "addresses".to_string(),
+            n => format!("/{} subnets", n),
+        };
+        return Err(Error::Runtime(format!(
+            "{} only has {} unique {}",
+            net, available, what
+        )));
+    }
+
+    // Addresses are shown bare, and anything larger in CIDR notation. Each sample is written as
+    // it is drawn, so the count does not bound memory use.
+    let columns: &[&str] = if prefix == 32 {
+        &["address"]
+    } else {
+        &["network"]
+    };
+    let mut rows = RowWriter::stdout(columns, format, &[0])?;
+    let mut push = |base: u32| {
+        let cell = match prefix {
+            32 => IpAddress::from(base).to_string(),
+            _ => IpNetwork::new(IpAddress::from(base), prefix)
+                .unwrap()
+                .to_string(),
+        };
+        rows.push(&[cell.into()])
+    };
+    if unique {
+        let perm = Permutation::new(prefix - net.num_network_bits(), &mut rng);
+        let base = u64::from(net.network_address().value());
+        for i in 0..count {
+            let offset = perm.apply(i) << (32 - prefix);
+            push((base | offset) as u32)?;
+        }
+    } else {
+        for _ in 0..count {
+            let subnet = net.random_subnet(prefix, &mut rng).unwrap();
+            push(subnet.network_address().value())?;
+        }
+    }
+    rows.finish()?;
+    Ok(())
+}
@@ -0,0 +1,300 @@
+//! The `repl` subcommand, an interactive prompt for exploring an address plan.
+//!
+//! Every line is either a command, a binding of the form `name = expr`, or an expression to show.
+//! Expressions use the same grammar as `eval`, and may refer to anything bound earlier.
+use crate::{
+    args::Args,
+    eval::{self, Scope},
+    Error, Result,
+};
+use ip_utils::IpSet;
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, IsTerminal, Write as _},
+};
+
+const HELP: &str = "\
+    name = <expr>           bind the result of an expression to a name
+    <expr>                  show the networks an expression describes
+    show <expr>             the same, for an expression which starts like a command
+    split <expr> <n>        divide each network of an expression into /n subnets
+    compare <a> <b>         describe how two expressions (without spaces) relate
+    vars                    list every bound name
+    history                 list every line entered so far
+    !!  or  !<n>            repeat the last line, or line n of the history
+    help                    show this message
+    quit                    leave the repl
+";
+
+pub fn run(args: Args) -> Result<()> {
+    if !args.positional()?.is_empty() {
+        return Err(Error::Usage("`repl` takes no arguments".into()));
+    }
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut repl = Repl::default();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            print!("iputils> ");
+            io::stdout().flush()?;
+        }
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        match repl.execute(&line) {
+            Ok(Outcome::Print(out)) => print!("{}", out),
+            Ok(Outcome::Quit) => break,
+            Err(err) => eprintln!("error: {}", err),
+        }
+    }
+    Ok(())
+}
+
+/// What should happen after a line has been executed.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Print(String),
+    Quit,
+}
+
+#[derive(Default)]
+struct Repl {
+    scope: Scope,
+    history: Vec<String>,
+}
+
+impl Repl {
+    fn execute(&mut self, line: &str) -> Result<Outcome> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(Outcome::Print(String::new()));
+        }
+        // A repeated line is echoed, so it is clear what actually ran.
+        let (line, echo) = match self.expand_history(line)? {
+            Some(expanded) => (expanded.clone(), format!("{}\n", expanded)),
+            None => (line.to_string(), String::new()),
+        };
+        self.history.push(line.clone());
+
+        let (command, rest) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (&line[..], ""),
+        };
+        let out = match command {
+            "quit" | "exit" => return Ok(Outcome::Quit),
+            "help" => HELP.to_string(),
+            "history" => self.history(),
+            "vars" => self.vars(),
+            "show" => show(&eval::evaluate(rest, &self.scope)?),
+            "split" => self.split(rest)?,
+            "compare" => self.compare(rest)?,
+            _ => match binding(&line) {
+                Some((name, expr)) => {
+                    let set = eval::evaluate(expr, &self.scope)?;
+                    let out = format!("{} = {}", name, summary(&set));
+                    self.scope.insert(name.to_string(), set);
+                    out + "\n"
+                }
+                None => show(&eval::evaluate(&line, &self.scope)?),
+            },
+        };
+        Ok(Outcome::Print(echo + &out))
+    }
+
+    /// Expands `!!` and `!n` into the line from history which they refer to.
+    fn expand_history(&self, line: &str) -> Result<Option<String>> {
+        let index = match line.strip_prefix('!') {
+            None => return Ok(None),
+            Some("!") => self.history.len(),
+            Some(n) => n
+                .parse()
+                .map_err(|_| Error::Runtime(format!("invalid history reference `{}`", line)))?,
+        };
+        match index.checked_sub(1).and_then(|i| self.history.get(i)) {
+            Some(line) => Ok(Some(line.clone())),
+            None => Err(Error::Runtime(format!("no line {} in history", index))),
+        }
+    }
+
+    fn history(&self) -> String {
+        let mut out = String::new();
+        for (i, line) in self.history.iter().enumerate() {
+            writeln!(out, "{:5}  {}", i + 1, line).unwrap();
+        }
+        out
+    }
+
+    fn vars(&self) -> String {
+        let mut names: Vec<_> = self.scope.keys().collect();
+        names.sort();
+        let mut out = String::new();
+        for name in names {
+            writeln!(out, "{} = {}", name, summary(&self.scope[name])).unwrap();
+        }
+        out
+    }
+
+    fn split(&self, rest: &str) -> Result<String> {
+        let usage = || Error::Runtime("usage: split <expr> <prefix>".into());
+        let i = rest.rfind(char::is_whitespace).ok_or_else(usage)?;
+        let prefix: u8 = rest[i..].trim().parse().map_err(|_| usage())?;
+        let set = eval::evaluate(&rest[..i], &self.scope)?;
+        let mut out = String::new();
+        for net in set.iter() {
+            let subnets = net.subnets_with_prefix(prefix).ok_or_else(|| {
+                Error::Runtime(format!("{} cannot be split into /{} subnets", net, prefix))
+            })?;
+            for subnet in subnets {
+                writeln!(out, "{}", subnet).unwrap();
+            }
+        }
+        Ok(out)
+    }
+
+    fn compare(&self, rest: &str) -> Result<String> {
+        let (a, b) = match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+            [a, b] => (a.to_string(), b.to_string()),
+            _ => return Err(Error::Runtime("usage: compare <a> <b>".into())),
+        };
+        let (x, y) = (
+            eval::evaluate(&a, &self.scope)?,
+            eval::evaluate(&b, &self.scope)?,
+        );
+        let common = x.intersection(&y).num_addresses();
+        let relation = if x == y {
+            format!("{} and {} are equal", a, b)
+        } else if common == x.num_addresses() {
+            format!("{} lies within {}", a, b)
+        } else if common == y.num_addresses() {
+            format!("{} lies within {}", b, a)
+        } else if common == 0 {
+            format!("{} and {} are disjoint", a, b)
+        } else {
+            format!("{} and {} overlap by {} addresses", a, b, common)
+        };
+        Ok(format!(
+            "{}\n{} = {}\n{} = {}\n",
+            relation,
+            a,
+            summary(&x),
+            b,
+            summary(&y)
+        ))
+    }
+}
+
+/// Splits a line of the form `name = expr`, if it is one.
+fn binding(line: &str) -> Option<(&str, &str)> {
+    let (name, expr) = line.split_at(line.find('=')?);
+    let name = name.trim();
+    let mut chars = name.chars();
+    if chars.next().is_some_and(eval::is_name_start) && chars.all(eval::is_name_continue) {
+        Some((name, &expr[1..]))
+    } else {
+        None
+    }
+}
+
+/// Describes a set in a few words.
+fn summary(set: &IpSet) -> String {
+    let nets: Vec<_> = set.iter().collect();
+    match nets.as_slice() {
+        [] => "empty".to_string(),
+        [net] => format!("{} ({} addresses)", net, net.num_addresses()),
+        _ => format!(
+            "{} networks ({} addresses)",
+            nets.len(),
+            set.num_addresses()
+        ),
+    }
+}
+
+fn show(set: &IpSet) -> String {
+    let mut out = String::new();
+    for net in set.iter() {
+        writeln!(out, "{}", net).unwrap();
+    }
+    writeln!(out, "= {}", summary(set)).unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(repl: &mut Repl, line: &str) -> String {
+        match repl.execute(line).unwrap() {
+            Outcome::Print(out) => out,
+            Outcome::Quit => panic!("unexpected quit"),
+        }
+    }
+
+    #[test]
+    fn bindings() {
+        let mut repl = Repl::default();
+        assert_eq!(
+            "plan = 10.0.0.0/16 (65536 addresses)\n",
+            output(&mut repl, "plan = 10.0.0.0/16")
+        );
+        assert_eq!(
+            "used = 2 networks (512 addresses)\n",
+            output(&mut repl, "used = 10.0.0.0/24 + 10.0.5.0/24")
+        );
+        assert_eq!(
+            "10.0.1.0/24\n10.0.2.0/23\n10.0.4.0/24\n= 3 networks (1024 addresses)\n",
+            output(&mut repl, "(plan - used) & 10.0.0.0/21 - 10.0.6.0/23")
+        );
+        assert_eq!(
+            "plan = 10.0.0.0/16 (65536 addresses)\nused = 2 networks (512 addresses)\n",
+            output(&mut repl, "vars")
+        );
+        assert!(repl.execute("= 10.0.0.0/8").is_err());
+        assert!(repl.execute("missing").is_err());
+    }
+
+    #[test]
+    fn commands() {
+        let mut repl = Repl::default();
+        output(&mut repl, "a = 10.0.0.0/23");
+        assert_eq!(
+            "10.0.0.0/24\n10.0.1.0/24\n",
+            output(&mut repl, "split a 24")
+        );
+        assert!(repl.execute("split a 22").is_err());
+        assert!(
+            output(&mut repl, "compare 10.0.1.0/24 a").starts_with("10.0.1.0/24 lies within a\n")
+        );
+        assert!(
+            output(&mut repl, "compare a 10.0.1.0/25").starts_with("10.0.1.0/25 lies within a\n")
+        );
+        assert!(output(&mut repl, "compare a 11.0.0.0/8")
+            .starts_with("a and 11.0.0.0/8 are disjoint\n"));
+        assert!(output(&mut repl, "compare a 10.0.1.0/24+10.0.2.0/24")
+            .starts_with("a and 10.0.1.0/24+10.0.2.0/24 overlap by 256 addresses\n"));
+        assert!(output(&mut repl, "compare a a").starts_with("a and a are equal\n"));
+        assert_eq!(Outcome::Quit, repl.execute("quit").unwrap());
+    }
+
+    #[test]
+    fn history() {
+        let mut repl = Repl::default();
+        output(&mut repl, "10.0.0.0/8");
+        output(&mut repl, "a = 10.0.0.0/24");
+        assert_eq!(
+            "a = 10.0.0.0/24\na = 10.0.0.0/24 (256 addresses)\n",
+            output(&mut repl, "!!")
+        );
+        assert_eq!(
+            "10.0.0.0/8\n10.0.0.0/8\n= 10.0.0.0/8 (16777216 addresses)\n",
+            output(&mut repl, "!1")
+        );
+        assert_eq!(
+            "    1  10.0.0.0/8\n    2  a = 10.0.0.0/24\n    3  a = 10.0.0.0/24\n    4  10.0.0.0/8\n    5  history\n",
+            output(&mut repl, "history")
+        );
+        assert!(repl.execute("!99").is_err());
+        assert!(repl.execute("!x").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+//! The `split` subcommand, which carves a network into equally-sized subnets.
+use crate::{args::Args, input, output::RowWriter, Error, Result};
+use ip_utils::{IpAddress, IpNetwork};
+
+pub fn run(mut args: Args) -> Result<()> {
+    let prefix: Option<u8> = args.parsed("--prefix")?;
+    let parts: Option<u64> = args.parsed("--parts")?;
+    let hosts: Option<u64> = args.parsed("--hosts")?;
+    let format = args.format()?;
+    let positional = args.positional()?;
+    let net = match positional.as_slice() {
+        [net] => input::parse_network(net)?,
+        _ => return Err(Error::Usage("`split` takes exactly one network".into())),
+    };
+
+    let prefix = match (prefix, parts, hosts) {
+        (Some(prefix), None, None) => prefix,
+        (None, Some(parts), None) => prefix_for_parts(net.num_network_bits(), parts)?,
+        (None, None, Some(hosts)) => prefix_for_hosts(hosts)?,
+        _ => {
+            return Err(Error::Usage(
+                "`split` takes exactly one of `--prefix`, `--parts`, or `--hosts`".into(),
+            ))
+        }
+    };
+    let subnets = net.subnets_with_prefix(prefix).ok_or_else(|| {
+        Error::Runtime(format!("{} cannot be split into /{} subnets", net, prefix))
+    })?;
+
+    // A network may split into billions of subnets, so each is written out as soon as it is made.
+    // The last subnet has every octet at its largest, so it is the widest of all.
+    let last = IpNetwork::new(net.broadcast_address(), prefix).unwrap();
+    let last = IpNetwork::new(last.network_address(), prefix).unwrap();
+    let widths = [
+        last.to_string().len(),
+        last.network_address().to_string().len(),
+        last.broadcast_address().to_string().len(),
+        last.num_addresses().to_string().len(),
+        last.num_usable_hosts().to_string().len(),
+    ];
+    let columns = &["network", "first", "last", "addresses", "hosts"];
+    let mut rows = RowWriter::stdout(columns, format, &widths)?;
+    for subnet in subnets {
+        rows.push(&[
+            subnet.to_string().into(),
+            subnet.network_address().to_string().into(),
+            subnet.broadcast_address().to_string().into(),
+            subnet.num_addresses().into(),
+            subnet.num_usable_hosts().into(),
+        ])?;
+    }
+    rows.finish()?;
+    Ok(())
+}
+
+/// The prefix length which divides a network into at least the requested number of parts. Parts
+/// always come in powers of two, so there may be more than were asked for.
+fn prefix_for_parts(prefix: u8, parts: u64) -> Result<u8> {
+    let bits = match parts {
+        0 => return Err(Error::Usage("`--parts` must be at least 1".into())),
+        n => 64 - (n - 1).leading_zeros() as u8,
+    };
+    match prefix.checked_add(bits) {
+        Some(prefix) if prefix <= 32 => Ok(prefix),
+        _ => Err(Error::Runtime(format!(
+            "cannot split a /{} into {} parts",
+            prefix, parts
+        ))),
+    }
+}
+
+/// The longest prefix length whose networks still hold the requested number of usable hosts.
+fn prefix_for_hosts(hosts: u64) -> Result<u8> {
+    (0..=32u8)
+        .rev()
+        .find(|&prefix| {
+            let net = IpNetwork::new(IpAddress::from(0), prefix).unwrap();
+            net.num_usable_hosts() >= hosts
+        })
+        .ok_or_else(|| Error::Runtime(format!("no network holds {} hosts", hosts)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts() {
+        assert_eq!(20, prefix_for_parts(20, 1).unwrap());
+        assert_eq!(21, prefix_for_parts(20, 2).unwrap());
+        assert_eq!(22, prefix_for_parts(20, 3).unwrap());
+        assert_eq!(24, prefix_for_parts(20, 16).unwrap());
+        assert_eq!(25, prefix_for_parts(20, 17).unwrap());
+        assert_eq!(32, prefix_for_parts(0, 1 << 32).unwrap());
+        assert!(prefix_for_parts(20, 0).is_err());
+        assert!(prefix_for_parts(30, 5).is_err());
+    }
+
+    #[test]
+    fn hosts() {
+        assert_eq!(32, prefix_for_hosts(1).unwrap());
+        assert_eq!(31, prefix_for_hosts(2).unwrap());
+        assert_eq!(29, prefix_for_hosts(3).unwrap());
+        assert_eq!(24, prefix_for_hosts(254).unwrap());
+        assert_eq!(23, prefix_for_hosts(255).unwrap());
+        assert!(prefix_for_hosts(1 << 33).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+//! The `tree` subcommand, which draws the containment hierarchy of a list of networks.
+use crate::{
+    args::Args,
+    input,
+    output::{Format, Table},
+    Error, Result,
+};
+use ip_utils::tree::{NetworkTree, Node};
+
+pub fn run(mut args: Args) -> Result<()> {
+    let dot = args.flag("--dot");
+    let format = args.format()?;
+    if dot && format != Format::Plain {
+        return Err(Error::Usage(
+            "`--dot` cannot be combined with `--output`".into(),
+        ));
+    }
+    let tree = NetworkTree::new(input::read_networks(&args.positional()?)?);
+    if dot {
+        print!("{}", tree.to_dot());
+    } else if format == Format::Plain {
+        print!("{}", tree.to_text());
+    } else {
+        print!("{}", table(&tree).render(format));
+    }
+    Ok(())
+}
+
+/// Flattens the hierarchy into one row per network, parents before their children.
+fn table(tree: &NetworkTree) -> Table {
+    fn visit(node: &Node, parent: Option<&Node>, depth: u64, table: &mut Table) {
+        let free = node.free().num_addresses();
+        table.push(vec![
+            node.network().to_string().into(),
+            parent.map(|parent| parent.network().to_string()).into(),
+            depth.into(),
+            (node.children().len() as u64).into(),
+            (node.network().num_addresses() - free).into(),
+            free.into(),
+        ]);
+        for child in node.children() {
+            visit(child, Some(node), depth + 1, table);
+        }
+    }
+    let mut table = Table::new(&[
+        "network",
+        "parent",
+        "depth",
+        "children",
+        "allocated",
+        "free",
+    ]);
+    for root in tree.roots() {
+        visit(root, None, 0, &mut table);
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json() {
+        let tree = NetworkTree::new(vec![
+            "10.0.0.0/23".parse().unwrap(),
+            "10.0.1.0/24".parse().unwrap(),
+        ]);
+        assert_eq!(
+            "[{\"network\":\"10.0.0.0/23\",\"parent\":null,\"depth\":0,\"children\":1,\
+             \"allocated\":256,\"free\":256},\
+             {\"network\":\"10.0.1.0/24\",\"parent\":\"10.0.0.0/23\",\"depth\":1,\"children\":0,\
+             \"allocated\":0,\"free\":256}]\n",
+            table(&tree).render(Format::Json)
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! # The Address-Bits Module
+//!
+//! Underneath the notation, an address is just an unsigned integer, and a prefix is just a count
+//! of its leading bits. The arithmetic of masks, supernets, subnets, and aligned blocks is the same
+//! whatever the width of that integer, so it is written once here, for the `u32` of an IPv4
+//! address and the `u128` of an IPv6 address alike.
+use std::{
+    fmt,
+    hash::Hash,
+    ops::{BitAnd, BitOr, BitXor, Not},
+};
+
+/// The integer representation of an address of some family.
+pub(crate) trait AddressBits:
+    Copy
+    + Ord
+    + Hash
+    + fmt::Debug
+    + Not<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+{
+    /// The number of bits in an address, which is the longest possible prefix.
+    const BITS: u8;
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+
+    fn checked_shl(self, n: u32) -> Option<Self>;
+    fn checked_shr(self, n: u32) -> Option<Self>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn leading_ones(self) -> u32;
+    fn leading_zeros(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+
+    /// The mask with the prefix's bits set, and every other bit clear.
+    fn mask(prefix_len: u8) -> Self {
+        // A shift by the full width overflows, but is exactly the empty mask of a `/0`.
+        Self::MAX
+            .checked_shl(u32::from(Self::BITS - prefix_len))
+            .unwrap_or(Self::ZERO)
+    }
+
+    /// The mask with every bit after the prefix set; the inverse of [`mask`](Self::mask).
+    fn wildcard(prefix_len: u8) -> Self {
+        !Self::mask(prefix_len)
+    }
+
+    /// The single bit which follows the prefix, which is the bit distinguishing its two subnets.
+    /// A full-length prefix has no such bit.
+    fn next_bit(prefix_len: u8) -> Option<Self> {
+        match prefix_len {
+            len if len >= Self::BITS => None,
+            len => Self::ONE.checked_shl(u32::from(Self::BITS - len - 1)),
+        }
+    }
+
+    /// The bit of the address at the specified depth, counting from the most significant bit.
+    fn bit(self, depth: u8) -> bool {
+        self.checked_shr(u32::from(Self::BITS - 1 - depth))
+            .unwrap_or(Self::ZERO)
+            & Self::ONE
+            == Self::ONE
+    }
+
+    /// The prefix length of a mask, if the mask is a contiguous run of set bits followed by a
+    /// contiguous run of clear bits.
+    fn prefix_len_of_mask(self) -> Option<u8> {
+        let len = self.leading_ones() as u8;
+        if self == Self::mask(len) {
+            Some(len)
+        } else {
+            None
+        }
+    }
+
+    /// The length of the prefix which the two addresses have in common.
+    fn common_prefix_len(self, other: Self) -> u8 {
+        (self ^ other).leading_zeros() as u8
+    }
+
+    /// The prefix length of the largest aligned block which begins at the first address and does
+    /// not run past the last address.
+    fn largest_block(start: Self, end: Self) -> u8 {
+        let mut prefix_len = Self::BITS - (start.trailing_zeros() as u8).min(Self::BITS);
+        while start | Self::wildcard(prefix_len) > end {
+            prefix_len += 1;
+        }
+        prefix_len
+    }
+}
+
+macro_rules! address_bits {
+    ($($ty:ty),*) => {$(
+        impl AddressBits for $ty {
+            const BITS: u8 = <$ty>::BITS as u8;
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const MAX: Self = <$ty>::MAX;
+
+            fn checked_shl(self, n: u32) -> Option<Self> {
+                <$ty>::checked_shl(self, n)
+            }
+
+            fn checked_shr(self, n: u32) -> Option<Self> {
+                <$ty>::checked_shr(self, n)
+            }
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$ty>::checked_add(self, other)
+            }
+
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$ty>::checked_sub(self, other)
+            }
+
+            fn leading_ones(self) -> u32 {
+                <$ty>::leading_ones(self)
+            }
+
+            fn leading_zeros(self) -> u32 {
+                <$ty>::leading_zeros(self)
+            }
+
+            fn trailing_zeros(self) -> u32 {
+                <$ty>::trailing_zeros(self)
+            }
+        }
+    )*};
+}
+
+address_bits!(u32, u128);
+
+/// An iterator over the fewest aligned blocks which exactly cover a range, in ascending order, as
+/// the first address and prefix length of each.
+#[derive(Debug, Clone)]
+pub(crate) struct Blocks<B> {
+    remaining: Option<(B, B)>,
+}
+
+/// Breaks the range from the first address to the last, inclusive, into aligned blocks.
+pub(crate) fn blocks<B: AddressBits>(start: B, end: B) -> Blocks<B> {
+    Blocks {
+        remaining: Some((start, end)).filter(|_| start <= end),
+    }
+}
+
+impl<B: AddressBits> Iterator for Blocks<B> {
+    type Item = (B, u8);
+
+    fn next(&mut self) -> Option<(B, u8)> {
+        let (start, end) = self.remaining?;
+        let prefix_len = B::largest_block(start, end);
+        let last = start | B::wildcard(prefix_len);
+        self.remaining = if last < end {
+            Some((last.checked_add(B::ONE)?, end))
+        } else {
+            None
+        };
+        Some((start, prefix_len))
+    }
+}
+
+/// Sorts ranges, and merges every range which overlaps or touches another, so that they are
+/// disjoint and separated by at least one address.
+pub(crate) fn merge_ranges<B: AddressBits>(ranges: &mut Vec<(B, B)>) {
+    ranges.sort_unstable();
+    let mut merged: Vec<(B, B)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges.drain(..) {
+        match merged.last_mut() {
+            // A range ending at the very last address touches everything after it.
+            Some(last) if last.1.checked_add(B::ONE).is_none_or(|next| start <= next) => {
+                last.1 = last.1.max(end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+    *ranges = merged;
+}
+
+/// Keeps only the addresses which are within both lists of ranges. Both must already be merged,
+/// as by [`merge_ranges`], and so is the result.
+pub(crate) fn intersect_ranges<B: AddressBits>(a: &[(B, B)], b: &[(B, B)]) -> Vec<(B, B)> {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while let (Some(&(a_start, a_end)), Some(&(b_start, b_end))) = (a.get(i), b.get(j)) {
+        let (start, end) = (a_start.max(b_start), a_end.min(b_end));
+        if start <= end {
+            result.push((start, end));
+        }
+        // Whichever range ends first cannot overlap anything further along the other list.
+        if a_end < b_end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+/// Removes the addresses of the holes from the ranges. Both must already be merged, as by
+/// [`merge_ranges`], and so is the result.
+pub(crate) fn subtract_ranges<B: AddressBits>(ranges: &[(B, B)], holes: &[(B, B)]) -> Vec<(B, B)> {
+    let mut result = Vec::with_capacity(ranges.len());
+    // Every hole which ends before the current range begins can be skipped for good, since the
+    // ranges are sorted too.
+    let mut skip = 0;
+    for &(start, end) in ranges {
+        while holes.get(skip).is_some_and(|hole| hole.1 < start) {
+            skip += 1;
+        }
+        let mut start = Some(start);
+        let mut holes = holes[skip..].iter();
+        while let Some(first) = start {
+            match holes.next() {
+                Some(&(hole_start, hole_end)) if hole_start <= end => {
+                    if hole_start > first {
+                        result.push((first, hole_start.checked_sub(B::ONE).unwrap()));
+                    }
+                    start = hole_end.checked_add(B::ONE).filter(|&next| next <= end);
+                }
+                _ => {
+                    result.push((first, end));
+                    break;
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Drops every prefix which lies within another of the prefixes, each given as its network
+/// address and length. The survivors are left sorted by address, then by length.
+pub(crate) fn remove_shadowed<B: AddressBits>(prefixes: &mut Vec<(B, u8)>) {
+    for prefix in prefixes.iter_mut() {
+        prefix.0 = prefix.0 & B::mask(prefix.1);
+    }
+    // Any prefix which contains another is sorted before it, and everything between the two is
+    // also contained by it; so only the most recently kept prefix needs to be checked.
+    prefixes.sort_unstable();
+    let mut kept: Vec<(B, u8)> = Vec::with_capacity(prefixes.len());
+    for (base, prefix_len) in prefixes.drain(..) {
+        match kept.last() {
+            Some(&(last, last_len))
+                if prefix_len >= last_len && base & B::mask(last_len) == last => {}
+            _ => kept.push((base, prefix_len)),
+        }
+    }
+    *prefixes = kept;
+}
+
+/// The longest prefix which contains every one of the prefixes, if there are any.
+pub(crate) fn spanning<B: AddressBits, I>(prefixes: I) -> Option<(B, u8)>
+where
+    I: IntoIterator<Item = (B, u8)>,
+{
+    prefixes
+        .into_iter()
+        .map(|(base, prefix_len)| (base & B::mask(prefix_len), prefix_len))
+        .reduce(|(a, a_len), (b, b_len)| {
+            let prefix_len = a.common_prefix_len(b).min(a_len).min(b_len);
+            (a & B::mask(prefix_len), prefix_len)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks() {
+        assert_eq!(0, u32::mask(0));
+        assert_eq!(0xff00_0000, u32::mask(8));
+        assert_eq!(u32::MAX, u32::mask(32));
+        assert_eq!(0x00ff_ffff, u32::wildcard(8));
+        assert_eq!(u128::MAX << 64, u128::mask(64));
+        assert_eq!(u128::MAX, u128::mask(128));
+        assert_eq!(u128::MAX, u128::wildcard(0));
+
+        assert_eq!(Some(0x0080_0000), u32::next_bit(8));
+        assert_eq!(Some(1), u32::next_bit(31));
+        assert_eq!(None, u32::next_bit(32));
+        assert_eq!(Some(1 << 127), u128::next_bit(0));
+        assert_eq!(None, u128::next_bit(128));
+
+        assert_eq!(Some(24), 0xffff_ff00u32.prefix_len_of_mask());
+        assert_eq!(Some(0), 0u32.prefix_len_of_mask());
+        assert_eq!(None, 0xff00_ff00u32.prefix_len_of_mask());
+        assert_eq!(Some(128), u128::MAX.prefix_len_of_mask());
+    }
+
+    #[test]
+    fn bits() {
+        assert!(0x8000_0000u32.bit(0));
+        assert!(!0x8000_0000u32.bit(1));
+        assert!(1u32.bit(31));
+        assert!(1u128.bit(127));
+        assert_eq!(32, 7u32.common_prefix_len(7));
+        assert_eq!(29, 7u32.common_prefix_len(0));
+        assert_eq!(0, 0u128.common_prefix_len(u128::MAX));
+    }
+
+    #[test]
+    fn ranges() {
+        let all: Vec<_> = blocks(0u128, u128::MAX).collect();
+        assert_eq!(vec![(0, 0)], all);
+        let blocks: Vec<_> = blocks(5u32, 10).collect();
+        assert_eq!(vec![(5, 32), (6, 31), (8, 31), (10, 32)], blocks);
+
+        let mut ranges = vec![
+            (10u32, 20),
+            (0, 4),
+            (21, 30),
+            (3, 7),
+            (40, u32::MAX),
+            (9, 9),
+        ];
+        merge_ranges(&mut ranges);
+        assert_eq!(vec![(0, 7), (9, 30), (40, u32::MAX)], ranges);
+        assert_eq!(
+            vec![(0, 1), (4, 7), (9, 30), (40, 49)],
+            subtract_ranges(&ranges, &[(2, 3), (8, 8), (50, u32::MAX)])
+        );
+        assert_eq!(
+            Vec::<(u128, u128)>::new(),
+            subtract_ranges(&[(0, u128::MAX)], &[(0, u128::MAX)])
+        );
+        assert_eq!(
+            vec![(3, 4), (9, 9), (40, 40)],
+            intersect_ranges(&ranges, &[(3, 4), (8, 9), (31, 40)])
+        );
+    }
+
+    #[test]
+    fn prefixes() {
+        let mut prefixes = vec![
+            (0x0a00_0005u32, 24),
+            (0x0a00_0000, 8),
+            (0x0b00_0000, 8),
+            (0, 32),
+        ];
+        remove_shadowed(&mut prefixes);
+        assert_eq!(vec![(0, 32), (0x0a00_0000, 8), (0x0b00_0000, 8)], prefixes);
+        let mut prefixes = vec![(u128::MAX, 0), (1, 128)];
+        remove_shadowed(&mut prefixes);
+        assert_eq!(vec![(0, 0)], prefixes);
+
+        assert_eq!(None, spanning(Vec::<(u32, u8)>::new()));
+        assert_eq!(
+            Some((0x0a00_0000u32, 24)),
+            spanning(vec![(0x0a00_00ff, 24)])
+        );
+        assert_eq!(
+            Some((0x0a00_0000u32, 22)),
+            spanning(vec![(0x0a00_0000, 24), (0x0a00_0300, 24)])
+        );
+        assert_eq!(Some((0, 0)), spanning(vec![(0u128, 1), (u128::MAX, 128)]));
+    }
+
+    #[test]
+    fn largest_block() {
+        assert_eq!(0, u32::largest_block(0, u32::MAX));
+        assert_eq!(32, u32::largest_block(5, 1000));
+        assert_eq!(31, u32::largest_block(6, 1000));
+        assert_eq!(30, u32::largest_block(8, 11));
+        assert_eq!(32, u32::largest_block(u32::MAX, u32::MAX));
+        assert_eq!(0, u128::largest_block(0, u128::MAX));
+        assert_eq!(65, u128::largest_block(0, u128::MAX >> 1 >> 64));
+    }
+}
@@ -0,0 +1,351 @@
+//! # The Blocklist Module
+//!
+//! Threat feeds are published in a handful of loosely specified text formats, and every consumer
+//! ends up writing its own slightly different parser for them. This module reads the common ones
+//! into [`IpSet`]s, keeping track of which feed listed which addresses so that a match can always
+//! be traced back to its source.
+//!
+//! Feeds are messy, so entries which cannot be understood are skipped and reported rather than
+//! failing the whole feed.
+use super::{addr::IpAddress, net::IpNetwork, range::IpRange, set::IpSet};
+use std::{error, fmt};
+
+/// The formats in which a feed may be published.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BlocklistFormat {
+    /// One address, network, or `first-last` range per line, as in Spamhaus DROP. Anything after
+    /// the entry, and any line starting with `#`, `;`, or `//`, is a comment.
+    Plain,
+    /// The TCP wrappers `hosts.deny` format, as in `sshd: 192.0.2.1, 10.0.0.0/255.0.0.0`. Only
+    /// address patterns are read; host names, keywords, and the like are skipped quietly, and any
+    /// patterns following `EXCEPT` are taken out of the list.
+    HostsDeny,
+    /// FireHOL `.netset` and `.ipset` files: one address or network per line, with `#` comments.
+    FireHol,
+}
+
+/// A single feed, along with everything read from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    name: String,
+    format: BlocklistFormat,
+    set: IpSet,
+    errors: Vec<BlocklistError>,
+}
+
+impl Source {
+    /// The name the feed was loaded under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The format the feed was read as.
+    pub fn format(&self) -> BlocklistFormat {
+        self.format
+    }
+
+    /// Every address the feed listed.
+    pub fn set(&self) -> &IpSet {
+        &self.set
+    }
+
+    /// The entries of the feed which could not be understood, and were skipped.
+    pub fn errors(&self) -> &[BlocklistError] {
+        &self.errors
+    }
+}
+
+/// A collection of feeds, each kept apart so that every listed address can be traced back to the
+/// feeds which listed it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Blocklist {
+    sources: Vec<Source>,
+}
+
+impl Blocklist {
+    /// Creates a new, empty blocklist.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a feed, adding it under the specified name.
+    pub fn load(&mut self, name: &str, text: &str, format: BlocklistFormat) -> &Source {
+        let (set, errors) = parse(text, format);
+        self.sources.push(Source {
+            name: name.to_string(),
+            format,
+            set,
+            errors,
+        });
+        self.sources.last().unwrap()
+    }
+
+    /// The feeds, in the order they were loaded.
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    /// Every address listed by any feed.
+    pub fn to_set(&self) -> IpSet {
+        self.sources
+            .iter()
+            .fold(IpSet::new(), |set, source| set.union(&source.set))
+    }
+
+    /// The names of the feeds which list the address.
+    pub fn sources_of(&self, addr: IpAddress) -> Vec<&str> {
+        self.sources
+            .iter()
+            .filter(|source| source.set.contains(addr))
+            .map(|source| source.name.as_str())
+            .collect()
+    }
+}
+
+/// Reads a single feed into a set, along with the entries which could not be understood.
+pub fn parse(text: &str, format: BlocklistFormat) -> (IpSet, Vec<BlocklistError>) {
+    let mut set = IpSet::new();
+    let mut errors = Vec::new();
+    let mut error = |line: usize, entry: &str| {
+        errors.push(BlocklistError {
+            line,
+            entry: entry.to_string(),
+        })
+    };
+    match format {
+        BlocklistFormat::Plain | BlocklistFormat::FireHol => {
+            for (i, line) in text.lines().enumerate() {
+                let line = line.trim();
+                let comment = match format {
+                    BlocklistFormat::Plain => ["#", ";", "//"].iter().any(|c| line.starts_with(c)),
+                    _ => line.starts_with('#'),
+                };
+                let uncommented = line.split(['#', ';']).next().unwrap_or_default();
+                let entry = match uncommented.split_whitespace().next() {
+                    Some(entry) if !comment => entry,
+                    _ => continue,
+                };
+                let range = match format {
+                    BlocklistFormat::Plain => parse_range(entry),
+                    _ => parse_network(entry).map(IpRange::from),
+                };
+                match range {
+                    Some(range) => set.insert_range(range),
+                    None => error(i + 1, entry),
+                }
+            }
+        }
+        BlocklistFormat::HostsDeny => {
+            // A backslash at the end of a line continues the rule onto the next.
+            let mut rule = String::new();
+            let mut first_line = 1;
+            for (i, line) in text.lines().enumerate() {
+                if rule.is_empty() {
+                    first_line = i + 1;
+                }
+                let line = line.split('#').next().unwrap();
+                match line.strip_suffix('\\') {
+                    Some(line) => {
+                        rule += line;
+                        rule.push(' ');
+                        continue;
+                    }
+                    None => rule += line,
+                }
+                let mut parts = rule.splitn(3, ':');
+                if let (Some(_), Some(clients)) = (parts.next(), parts.next()) {
+                    let (mut listed, mut excepted) = (IpSet::new(), IpSet::new());
+                    let mut except = false;
+                    for pattern in clients.split([',', ' ', '\t']).filter(|p| !p.is_empty()) {
+                        if pattern == "EXCEPT" {
+                            except = true;
+                        } else if pattern.starts_with(|c: char| c.is_ascii_digit()) {
+                            match parse_hosts_pattern(pattern) {
+                                Some(range) if except => excepted.insert_range(range),
+                                Some(range) => listed.insert_range(range),
+                                None => error(first_line, pattern),
+                            }
+                        }
+                    }
+                    set = set.union(&listed.difference(&excepted));
+                } else if !rule.trim().is_empty() {
+                    error(first_line, rule.trim());
+                }
+                rule.clear();
+            }
+        }
+    }
+    (set, errors)
+}
+
+/// Parses a network, or a bare address which is taken to be a `/32`.
+fn parse_network(s: &str) -> Option<IpNetwork> {
+    if s.contains('/') {
+        s.parse().ok()
+    } else {
+        IpNetwork::new(s.parse().ok()?, 32)
+    }
+}
+
+/// Parses a network, a bare address, or a `first-last` range.
+fn parse_range(s: &str) -> Option<IpRange> {
+    match s.split_once('-') {
+        Some((start, end)) => IpRange::new(start.trim().parse().ok()?, end.trim().parse().ok()?),
+        None => parse_network(s).map(IpRange::from),
+    }
+}
+
+/// Parses a `hosts.deny` address pattern: an address, a network with either a prefix length or
+/// a netmask, or the leading octets of an address followed by a dot.
+fn parse_hosts_pattern(s: &str) -> Option<IpRange> {
+    if let Some((addr, mask)) = s.split_once('/') {
+        if let Ok(mask) = mask.parse::<IpAddress>() {
+            let net = IpNetwork::with_netmask(addr.parse().ok()?, mask).ok()?;
+            return Some(IpRange::from(net));
+        }
+    }
+    if let Some(prefix) = s.strip_suffix('.') {
+        let octets: Vec<&str> = prefix.split('.').collect();
+        if octets.len() > 3 {
+            return None;
+        }
+        let mut padded = octets.clone();
+        padded.resize(4, "0");
+        let net = IpNetwork::new(padded.join(".").parse().ok()?, 8 * octets.len() as u8)?;
+        return Some(IpRange::from(net));
+    }
+    parse_network(s).map(IpRange::from)
+}
+
+/// An entry of a feed which could not be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlocklistError {
+    line: usize,
+    entry: String,
+}
+
+impl BlocklistError {
+    /// The line on which the entry was found, counting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The entry itself.
+    pub fn entry(&self) -> &str {
+        &self.entry
+    }
+}
+
+impl fmt::Display for BlocklistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: unrecognised entry `{}`", self.line, self.entry)
+    }
+}
+
+impl error::Error for BlocklistError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn networks(set: &IpSet) -> Vec<String> {
+        set.iter().map(|net| net.to_string()).collect()
+    }
+
+    #[test]
+    fn plain() {
+        let text = "\
+; Spamhaus DROP List
+1.10.16.0/20 ; SBL256894
+# a comment
+// another
+192.0.2.1
+198.51.100.0-198.51.100.127
+example.com
+10.0.0.0/33
+";
+        let (set, errors) = parse(text, BlocklistFormat::Plain);
+        assert_eq!(
+            vec!["1.10.16.0/20", "192.0.2.1/32", "198.51.100.0/25"],
+            networks(&set)
+        );
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "line 7: unrecognised entry `example.com`",
+                "line 8: unrecognised entry `10.0.0.0/33`",
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn firehol() {
+        let text = "#\n# firehol_level1\n#\n0.0.0.0/8\n1.19.0.0/16\n5.134.128.7\n1.2.3.4-1.2.3.5\n";
+        let (set, errors) = parse(text, BlocklistFormat::FireHol);
+        assert_eq!(
+            vec!["0.0.0.0/8", "1.19.0.0/16", "5.134.128.7/32"],
+            networks(&set)
+        );
+        assert_eq!("1.2.3.4-1.2.3.5", errors[0].entry());
+    }
+
+    #[test]
+    fn hosts_deny() {
+        let text = "\
+# /etc/hosts.deny
+ALL: 192.168.0.0/23 EXCEPT 192.168.1.
+ALL: 172.16.
+sshd: 203.0.113.7, 10.0.0.0/255.255.0.0 \\
+    198.51.100.0/24 .example.com
+in.telnetd : ALL : spawn echo
+portmap: 10.2.0.0/255.0.255.0
+garbage
+";
+        let (set, errors) = parse(text, BlocklistFormat::HostsDeny);
+        assert_eq!(
+            vec![
+                "10.0.0.0/16",
+                "172.16.0.0/16",
+                "192.168.0.0/24",
+                "198.51.100.0/24",
+                "203.0.113.7/32",
+            ],
+            networks(&set)
+        );
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "line 7: unrecognised entry `10.2.0.0/255.0.255.0`",
+                "line 8: unrecognised entry `garbage`",
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn provenance() {
+        let mut blocklist = Blocklist::new();
+        blocklist.load("drop", "10.0.0.0/8 ; SBL1\n", BlocklistFormat::Plain);
+        let source = blocklist.load(
+            "level1",
+            "10.1.0.0/16\n192.0.2.1\n",
+            BlocklistFormat::FireHol,
+        );
+        assert_eq!("level1", source.name());
+        assert_eq!(2, blocklist.sources().len());
+        assert_eq!(
+            vec!["drop", "level1"],
+            blocklist.sources_of("10.1.2.3".parse().unwrap())
+        );
+        assert_eq!(
+            vec!["drop"],
+            blocklist.sources_of("10.2.0.0".parse().unwrap())
+        );
+        assert!(blocklist.sources_of("8.8.8.8".parse().unwrap()).is_empty());
+        assert_eq!(
+            vec!["10.0.0.0/8", "192.0.2.1/32"],
+            networks(&blocklist.to_set())
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! # The Bloom-Filter Module
+//!
+//! An exact set of addresses can grow large, and most lookups against it, on a busy edge node, are
+//! for addresses it does not contain. A Bloom filter answers "definitely not" or "maybe" in a
+//! fraction of the memory, so the exact structure is only consulted for the few addresses which
+//! might be in it. The filter stores the networks of a set, rather than every address, so that a
+//! `/8` costs no more than a `/32`.
+use super::{addr::IpAddress, bits::AddressBits, net::IpNetwork, set::IpSet};
+
+/// A compact, approximate copy of an [`IpSet`], which may wrongly claim to contain an address, but
+/// never wrongly denies one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IpBloomFilter {
+    bits: Vec<u64>,
+    num_hashes: u32,
+    /// Every prefix length among the stored networks, as a bit per length.
+    prefix_lens: u64,
+}
+
+impl IpBloomFilter {
+    /// Builds a filter of the networks within the set, sized so that an address outside the set is
+    /// claimed to be inside it at most at the specified rate, which **must** be between `0` and
+    /// `1`.
+    pub fn new(set: &IpSet, false_positive_rate: f64) -> Option<Self> {
+        if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+            return None;
+        }
+        let nets: Vec<IpNetwork> = set.iter().collect();
+        let prefix_lens = nets
+            .iter()
+            .fold(0u64, |lens, net| lens | 1 << net.num_network_bits());
+        // An address is looked up once per stored prefix length, and each lookup may be a false
+        // positive, so each must be correspondingly rarer.
+        let rate = false_positive_rate / f64::from(prefix_lens.count_ones().max(1));
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-(nets.len() as f64) * rate.ln() / (ln2 * ln2)).ceil() as usize;
+        let words = num_bits.max(64).div_ceil(64);
+        let num_hashes = (-rate.log2()).round().max(1.0) as u32;
+        let mut filter = Self {
+            bits: vec![0; words],
+            num_hashes,
+            prefix_lens,
+        };
+        for net in nets {
+            let key = key(net.network_address().value(), net.num_network_bits());
+            for i in filter.indices(key) {
+                filter.bits[i / 64] |= 1 << (i % 64);
+            }
+        }
+        Some(filter)
+    }
+
+    /// Whether the address might lie within the set. Where this is `false`, it certainly does not.
+    pub fn maybe_contains(&self, addr: IpAddress) -> bool {
+        (0..=32u8)
+            .filter(|len| self.prefix_lens & 1 << len != 0)
+            .any(|len| {
+                let key = key(addr.value() & u32::mask(len), len);
+                self.indices(key)
+                    .all(|i| self.bits[i / 64] & 1 << (i % 64) != 0)
+            })
+    }
+
+    /// The memory taken up by the filter's bits, in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// The bits which a key sets, by double hashing.
+    fn indices(&self, key: u64) -> impl Iterator<Item = usize> {
+        let num_bits = self.bits.len() as u64 * 64;
+        let first = mix(key);
+        let step = mix(first) | 1;
+        (0..u64::from(self.num_hashes))
+            .map(move |i| (first.wrapping_add(i.wrapping_mul(step)) % num_bits) as usize)
+    }
+}
+
+/// Packs a network into a single number, which is unique for every network.
+fn key(bits: u32, prefix_len: u8) -> u64 {
+    u64::from(bits) << 6 | u64::from(prefix_len)
+}
+
+/// The SplitMix64 finalizer, which spreads every bit of its input across its output.
+pub(crate) fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+
+    #[test]
+    fn no_false_negatives() {
+        let mut rng = Rng::seed_from_u64(7);
+        let mut set = IpSet::new();
+        for _ in 0..2000 {
+            let len = 8 + rng.below(25) as u8;
+            let base = IpAddress::from(rng.next_u64() as u32);
+            set.insert(IpNetwork::new(base, len).unwrap());
+        }
+        let filter = IpBloomFilter::new(&set, 0.01).unwrap();
+        for net in set.iter() {
+            assert!(filter.maybe_contains(net.network_address()), "{}", net);
+            assert!(filter.maybe_contains(net.broadcast_address()), "{}", net);
+            assert!(
+                filter.maybe_contains(net.random_address(&mut rng)),
+                "{}",
+                net
+            );
+        }
+
+        let mut false_positives = 0;
+        let mut outside = 0;
+        while outside < 20_000 {
+            let addr = IpAddress::from(rng.next_u64() as u32);
+            if !set.contains(addr) {
+                outside += 1;
+                false_positives += filter.maybe_contains(addr) as u32;
+            }
+        }
+        assert!(false_positives < 400, "{}", false_positives);
+    }
+
+    #[test]
+    fn edges() {
+        let empty = IpBloomFilter::new(&IpSet::new(), 0.01).unwrap();
+        assert!(!empty.maybe_contains(IpAddress::from(0)));
+        assert_eq!(8, empty.size_in_bytes());
+
+        let mut set = IpSet::new();
+        set.insert("0.0.0.0/0".parse().unwrap());
+        let everything = IpBloomFilter::new(&set, 0.5).unwrap();
+        assert!(everything.maybe_contains(IpAddress::from(u32::MAX)));
+
+        assert!(IpBloomFilter::new(&set, 0.0).is_none());
+        assert!(IpBloomFilter::new(&set, 1.0).is_none());
+        assert!(IpBloomFilter::new(&set, f64::NAN).is_none());
+    }
+}
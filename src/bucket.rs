@@ -0,0 +1,224 @@
+//! # The Bucket Module
+//!
+//! Sharding by client address, whether across rate-limiter shards or backends, is easy with
+//! `hash(addr) % n`, until `n` changes and nearly every client moves. Consistent hashing keeps
+//! moves to a minimum: growing from `n` to `n + 1` buckets moves only the clients which the new
+//! bucket takes, and removing a backend moves only the clients which it held. Two schemes are
+//! provided: jump hashing, for buckets numbered `0..n`, and rendezvous hashing, for named
+//! backends which come and go in any order.
+//!
+//! Both are seeded by a key, so that different deployments shard differently, and both give the
+//! same answers on every machine and every run.
+use super::{addr::IpAddress, addr6::Ipv6Address, bloom::mix};
+use std::hash::{Hash, Hasher};
+
+fn hash(addr: IpAddress, key: u64) -> u64 {
+    mix(key ^ mix(u64::from(addr.value())))
+}
+
+fn hash6(addr: Ipv6Address, key: u64) -> u64 {
+    let value = addr.value();
+    mix(key ^ mix(mix((value >> 64) as u64) ^ value as u64))
+}
+
+/// The jump consistent hash of Lamping and Veach, which picks a bucket in `0..buckets`.
+fn jump(mut hash: u64, buckets: u32) -> u32 {
+    let (mut bucket, mut next) = (0, 0);
+    while next < u64::from(buckets) {
+        bucket = next;
+        hash = hash.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((hash >> 33) + 1) as f64)) as u64;
+    }
+    bucket as u32
+}
+
+/// The bucket, out of the specified number, which the address falls into, if there are any
+/// buckets.
+///
+/// Going from `n` buckets to `n + 1` moves about one address in `n + 1`, every one of them into
+/// the new bucket.
+pub fn jump_bucket(addr: IpAddress, key: u64, buckets: u32) -> Option<u32> {
+    if buckets == 0 {
+        return None;
+    }
+    Some(jump(hash(addr, key), buckets))
+}
+
+/// The bucket, out of the specified number, which the IPv6 address falls into, if there are any
+/// buckets.
+pub fn jump_bucket6(addr: Ipv6Address, key: u64, buckets: u32) -> Option<u32> {
+    if buckets == 0 {
+        return None;
+    }
+    Some(jump(hash6(addr, key), buckets))
+}
+
+/// A hasher which gives the same results everywhere, unlike the standard library's.
+struct StableHasher(u64);
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.0 = mix(self.0 ^ u64::from_le_bytes(word));
+        }
+    }
+
+    // Integers are hashed in little-endian, and `usize` as 64 bits, whatever the platform.
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Rendezvous (highest random weight) hashing over a set of nodes, such as backends.
+///
+/// Each address goes to the node which scores highest for it, so removing a node moves only the
+/// addresses it held, and adding one takes addresses only from the others, evenly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rendezvous<T> {
+    key: u64,
+    /// Each node, along with its hash.
+    nodes: Vec<(T, u64)>,
+}
+
+impl<T: Hash + Eq> Rendezvous<T> {
+    /// Creates a new hash with the key, without any nodes.
+    pub fn new(key: u64) -> Self {
+        Self {
+            key,
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Adds a node, returning whether it was not already present.
+    pub fn insert(&mut self, node: T) -> bool {
+        if self.nodes.iter().any(|(other, _)| *other == node) {
+            return false;
+        }
+        let mut hasher = StableHasher(self.key);
+        node.hash(&mut hasher);
+        let hash = hasher.finish();
+        self.nodes.push((node, hash));
+        true
+    }
+
+    /// Removes a node, returning whether it was present.
+    pub fn remove(&mut self, node: &T) -> bool {
+        let len = self.nodes.len();
+        self.nodes.retain(|(other, _)| other != node);
+        self.nodes.len() != len
+    }
+
+    /// The nodes, in the order they were added.
+    pub fn nodes(&self) -> impl Iterator<Item = &T> + '_ {
+        self.nodes.iter().map(|(node, _)| node)
+    }
+
+    /// The node which the address goes to, if there are any nodes.
+    pub fn pick(&self, addr: IpAddress) -> Option<&T> {
+        self.pick_hash(hash(addr, self.key))
+    }
+
+    /// The node which the IPv6 address goes to, if there are any nodes.
+    pub fn pick6(&self, addr: Ipv6Address) -> Option<&T> {
+        self.pick_hash(hash6(addr, self.key))
+    }
+
+    fn pick_hash(&self, hash: u64) -> Option<&T> {
+        self.nodes
+            .iter()
+            .max_by_key(|(_, node)| mix(node ^ hash))
+            .map(|(node, _)| node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs() -> impl Iterator<Item = IpAddress> {
+        (0..10_000u32).map(|i| IpAddress::from(0x0a00_0000 + i * 7919))
+    }
+
+    #[test]
+    fn jump() {
+        let mut counts = [0; 10];
+        for addr in addrs() {
+            counts[jump_bucket(addr, 42, 10).unwrap() as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&count| count > 850 && count < 1150),
+            "{:?}",
+            counts
+        );
+
+        let mut moved = 0;
+        for addr in addrs() {
+            let before = jump_bucket(addr, 42, 10).unwrap();
+            let after = jump_bucket(addr, 42, 11).unwrap();
+            if before != after {
+                assert_eq!(10, after);
+                moved += 1;
+            }
+        }
+        assert!(moved > 750 && moved < 1050, "{}", moved);
+
+        let addr = "192.0.2.1".parse().unwrap();
+        assert_eq!(None, jump_bucket(addr, 42, 0));
+        assert_eq!(Some(0), jump_bucket(addr, 42, 1));
+        let addr6 = "2001:db8::1".parse().unwrap();
+        assert!(jump_bucket6(addr6, 42, 5).unwrap() < 5);
+    }
+
+    #[test]
+    fn rendezvous() {
+        let mut backends = Rendezvous::new(7);
+        for name in &["a", "b", "c", "d"] {
+            assert!(backends.insert(name.to_string()));
+        }
+        assert!(!backends.insert("a".to_string()));
+        let before: Vec<_> = addrs()
+            .map(|addr| backends.pick(addr).unwrap().clone())
+            .collect();
+        assert!(before.iter().filter(|node| *node == "c").count() > 2000);
+
+        assert!(backends.remove(&"c".to_string()));
+        assert!(!backends.remove(&"c".to_string()));
+        for (addr, node) in addrs().zip(&before) {
+            let after = backends.pick(addr).unwrap();
+            if node != "c" {
+                assert_eq!(node, after);
+            }
+        }
+        assert_eq!(
+            vec!["a", "b", "d"],
+            backends.nodes().map(String::as_str).collect::<Vec<_>>()
+        );
+        assert!(backends.pick6("2001:db8::1".parse().unwrap()).is_some());
+        assert_eq!(
+            None,
+            Rendezvous::<String>::new(7).pick(addrs().next().unwrap())
+        );
+    }
+}
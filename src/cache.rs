@@ -0,0 +1,238 @@
+//! # The Lookup-Cache Module
+//!
+//! Real traffic is heavily skewed: most packets come from, or go to, a small number of addresses.
+//! A longest-prefix match walks the whole depth of the trie every time, even for the address it
+//! matched a moment ago. This module wraps an [`IpNetworkMap`] with a small cache of the results
+//! of its most recent lookups, so repeated addresses skip the walk.
+use super::{addr::IpAddress, map::IpNetworkMap, net::IpNetwork};
+use std::collections::HashMap;
+
+/// Marks the absence of a neighbouring entry in the recency list.
+const NIL: usize = usize::MAX;
+
+/// The result of a lookup, linked to the entries used just before and just after it.
+#[derive(Debug, Clone)]
+struct Entry<V> {
+    addr: IpAddress,
+    result: Option<(IpNetwork, V)>,
+    prev: usize,
+    next: usize,
+}
+
+/// An [`IpNetworkMap`] which remembers the longest match of the addresses it was most recently
+/// asked about, evicting whichever was used longest ago once it is full.
+///
+/// Every change to the map through this wrapper empties the cache, so a lookup never returns a
+/// stale result.
+#[derive(Debug, Clone)]
+pub struct CachedIpNetworkMap<V> {
+    map: IpNetworkMap<V>,
+    capacity: usize,
+    entries: Vec<Entry<V>>,
+    index: HashMap<IpAddress, usize>,
+    /// The most recently used entry.
+    head: usize,
+    /// The least recently used entry, which is evicted next.
+    tail: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl<V: Clone> CachedIpNetworkMap<V> {
+    /// Wraps a map with a cache of the specified number of lookups, which **must** be at least
+    /// one.
+    pub fn new(map: IpNetworkMap<V>, capacity: usize) -> Option<Self> {
+        if capacity == 0 {
+            return None;
+        }
+        Some(Self {
+            map,
+            capacity,
+            entries: Vec::new(),
+            index: HashMap::new(),
+            head: NIL,
+            tail: NIL,
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /// The wrapped map.
+    pub fn map(&self) -> &IpNetworkMap<V> {
+        &self.map
+    }
+
+    /// Unwraps the map, discarding the cache.
+    pub fn into_inner(self) -> IpNetworkMap<V> {
+        self.map
+    }
+
+    /// The number of lookups the cache can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// The number of lookups which had to walk the map.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Finds the most specific network which contains the address, along with its value, as
+    /// [`IpNetworkMap::longest_match`] does.
+    pub fn longest_match(&mut self, addr: IpAddress) -> Option<(IpNetwork, &V)> {
+        let i = match self.index.get(&addr) {
+            Some(&i) => {
+                self.hits += 1;
+                self.unlink(i);
+                i
+            }
+            None => {
+                self.misses += 1;
+                self.fill(addr)
+            }
+        };
+        self.push_front(i);
+        self.entries[i]
+            .result
+            .as_ref()
+            .map(|(net, value)| (*net, value))
+    }
+
+    /// Associates the value with the network, returning the value previously associated with it.
+    pub fn insert(&mut self, net: IpNetwork, value: V) -> Option<V> {
+        self.clear();
+        self.map.insert(net, value)
+    }
+
+    /// Removes the network from the map, returning the value that was associated with it.
+    pub fn remove(&mut self, net: &IpNetwork) -> Option<V> {
+        self.clear();
+        self.map.remove(net)
+    }
+
+    /// Gets a mutable reference to the value associated with exactly this network.
+    pub fn get_mut(&mut self, net: &IpNetwork) -> Option<&mut V> {
+        self.clear();
+        self.map.get_mut(net)
+    }
+
+    /// Empties the cache, leaving the map as it is.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    /// Looks up an address which is not cached, storing the result in a free entry, or else in
+    /// place of the least recently used one, which is left unlinked.
+    fn fill(&mut self, addr: IpAddress) -> usize {
+        let entry = Entry {
+            addr,
+            result: self
+                .map
+                .longest_match(addr)
+                .map(|(net, value)| (net, value.clone())),
+            prev: NIL,
+            next: NIL,
+        };
+        let i = if self.entries.len() < self.capacity {
+            self.entries.push(entry);
+            self.entries.len() - 1
+        } else {
+            let i = self.tail;
+            self.unlink(i);
+            self.index.remove(&self.entries[i].addr);
+            self.entries[i] = entry;
+            i
+        };
+        self.index.insert(addr, i);
+        i
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let Entry { prev, next, .. } = self.entries[i];
+        match prev {
+            NIL => self.head = next,
+            prev => self.entries[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.entries[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, i: usize) {
+        self.entries[i].prev = NIL;
+        self.entries[i].next = self.head;
+        match self.head {
+            NIL => self.tail = i,
+            head => self.entries[head].prev = i,
+        }
+        self.head = i;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    fn lookup(cache: &mut CachedIpNetworkMap<&'static str>, s: &str) -> Option<&'static str> {
+        cache.longest_match(addr(s)).map(|(_, v)| *v)
+    }
+
+    #[test]
+    fn eviction() {
+        let map = vec![(net("10.0.0.0/8"), "a"), (net("10.1.0.0/16"), "b")]
+            .into_iter()
+            .collect();
+        let mut cache = CachedIpNetworkMap::new(map, 2).unwrap();
+        assert_eq!(Some("a"), lookup(&mut cache, "10.0.0.1"));
+        assert_eq!(Some("b"), lookup(&mut cache, "10.1.0.1"));
+        assert_eq!(Some("a"), lookup(&mut cache, "10.0.0.1"));
+        assert_eq!((1, 2), (cache.hits(), cache.misses()));
+        // 10.1.0.1 was used longest ago, so it makes way for 11.0.0.1.
+        assert_eq!(None, lookup(&mut cache, "11.0.0.1"));
+        assert_eq!(Some("a"), lookup(&mut cache, "10.0.0.1"));
+        assert_eq!((2, 3), (cache.hits(), cache.misses()));
+        assert_eq!(Some("b"), lookup(&mut cache, "10.1.0.1"));
+        assert_eq!(None, lookup(&mut cache, "11.0.0.1"));
+        assert_eq!((2, 5), (cache.hits(), cache.misses()));
+
+        let mut single = CachedIpNetworkMap::new(cache.into_inner(), 1).unwrap();
+        assert_eq!(Some("a"), lookup(&mut single, "10.0.0.1"));
+        assert_eq!(Some("a"), lookup(&mut single, "10.0.0.1"));
+        assert_eq!(Some("b"), lookup(&mut single, "10.1.0.1"));
+        assert_eq!((1, 2), (single.hits(), single.misses()));
+        assert!(CachedIpNetworkMap::new(IpNetworkMap::<()>::new(), 0).is_none());
+    }
+
+    #[test]
+    fn invalidation() {
+        let map = vec![(net("10.0.0.0/8"), "a")].into_iter().collect();
+        let mut cache = CachedIpNetworkMap::new(map, 8).unwrap();
+        assert_eq!(Some("a"), lookup(&mut cache, "10.1.0.1"));
+        cache.insert(net("10.1.0.0/16"), "b");
+        assert_eq!(Some("b"), lookup(&mut cache, "10.1.0.1"));
+        *cache.get_mut(&net("10.1.0.0/16")).unwrap() = "bb";
+        assert_eq!(Some("bb"), lookup(&mut cache, "10.1.0.1"));
+        cache.remove(&net("10.1.0.0/16"));
+        assert_eq!(Some("a"), lookup(&mut cache, "10.1.0.1"));
+        assert_eq!(Some("a"), lookup(&mut cache, "10.1.0.1"));
+        assert_eq!((1, 4), (cache.hits(), cache.misses()));
+        assert_eq!(1, cache.map().len());
+    }
+}
@@ -0,0 +1,183 @@
+//! # The Prefix-Counter Module
+//!
+//! The first question asked of a log full of addresses is usually "which networks are hammering
+//! us?", and answering it means counting hits per `/24`, or some other prefix, rather than per
+//! address. A prefix counter does that counting as the addresses stream past, and can be split
+//! across threads, each counting its own share, and merged at the end.
+use super::{addr::IpAddress, bits::AddressBits, net::IpNetwork};
+use std::{cmp::Reverse, collections::HashMap, iter::FromIterator};
+
+/// Hit counts for the networks of some prefix length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixCounter {
+    prefix_len: u8,
+    /// The count for each network, keyed by its network address.
+    counts: HashMap<u32, u64>,
+    total: u64,
+}
+
+impl PrefixCounter {
+    /// Creates a counter of the networks of the specified prefix length, which may be at most
+    /// 32.
+    pub fn new(prefix_len: u8) -> Option<Self> {
+        if prefix_len > 32 {
+            return None;
+        }
+        Some(Self {
+            prefix_len,
+            counts: HashMap::new(),
+            total: 0,
+        })
+    }
+
+    /// The prefix length of the networks being counted.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Counts a hit on the network containing the address.
+    pub fn add(&mut self, addr: IpAddress) {
+        self.add_n(addr, 1);
+    }
+
+    /// Counts a number of hits on the network containing the address at once.
+    pub fn add_n(&mut self, addr: IpAddress, hits: u64) {
+        let key = addr.value() & u32::mask(self.prefix_len);
+        *self.counts.entry(key).or_insert(0) += hits;
+        self.total += hits;
+    }
+
+    /// The number of hits on the network containing the address.
+    pub fn get(&self, addr: IpAddress) -> u64 {
+        let key = addr.value() & u32::mask(self.prefix_len);
+        self.counts.get(&key).copied().unwrap_or(0)
+    }
+
+    /// The number of hits counted across every network.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The number of networks with at least one hit.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Whether no hits have been counted at all.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The networks with the most hits, along with their counts, from the most hits down, and
+    /// then in ascending order.
+    pub fn top(&self, n: usize) -> Vec<(IpNetwork, u64)> {
+        let mut counts = self.iter().collect::<Vec<_>>();
+        counts.sort_by_key(|&(net, count)| (Reverse(count), net.network_address()));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Every network with at least one hit, along with its count, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (IpNetwork, u64)> + '_ {
+        self.counts.iter().map(move |(&key, &count)| {
+            (
+                IpNetwork::new(IpAddress::from(key), self.prefix_len).unwrap(),
+                count,
+            )
+        })
+    }
+
+    /// Adds the hits of another counter to this one, returning whether it could. The other
+    /// counter's prefix length **must** be at least as long as this one's, so that each of its
+    /// networks lies within one of these.
+    pub fn merge(&mut self, other: &Self) -> bool {
+        if other.prefix_len < self.prefix_len {
+            return false;
+        }
+        for (&key, &count) in &other.counts {
+            self.add_n(IpAddress::from(key), count);
+        }
+        true
+    }
+}
+
+impl Extend<IpAddress> for PrefixCounter {
+    fn extend<I: IntoIterator<Item = IpAddress>>(&mut self, iter: I) {
+        for addr in iter {
+            self.add(addr);
+        }
+    }
+}
+
+/// Counts hits per `/24`, the most common granularity.
+impl FromIterator<IpAddress> for PrefixCounter {
+    fn from_iter<I: IntoIterator<Item = IpAddress>>(iter: I) -> Self {
+        let mut counter = Self::new(24).unwrap();
+        counter.extend(iter);
+        counter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn top() {
+        let log = [
+            "10.0.0.1",
+            "10.0.0.2",
+            "10.0.1.1",
+            "10.0.0.3",
+            "192.0.2.9",
+            "10.0.1.7",
+        ];
+        let counter: PrefixCounter = log.iter().map(|s| addr(s)).collect();
+        assert_eq!(3, counter.len());
+        assert_eq!(6, counter.total());
+        assert_eq!(3, counter.get(addr("10.0.0.255")));
+        assert_eq!(0, counter.get(addr("10.0.2.0")));
+        let top: Vec<_> = counter
+            .top(2)
+            .into_iter()
+            .map(|(net, count)| format!("{} {}", net, count))
+            .collect();
+        assert_eq!(vec!["10.0.0.0/24 3", "10.0.1.0/24 2"], top);
+        assert_eq!(3, counter.top(10).len());
+
+        let mut per_8 = PrefixCounter::new(8).unwrap();
+        per_8.extend(log.iter().map(|s| addr(s)));
+        assert_eq!(vec![("10.0.0.0/8".parse().unwrap(), 5)], per_8.top(1));
+        assert!(PrefixCounter::new(33).is_none());
+    }
+
+    #[test]
+    fn merge() {
+        let handles: Vec<_> = (0..4u32)
+            .map(|i| {
+                thread::spawn(move || {
+                    let mut counter = PrefixCounter::new(24).unwrap();
+                    for host in 0..100 {
+                        counter.add(IpAddress::from(0x0a00_0000 | i << 8 | host));
+                    }
+                    counter
+                })
+            })
+            .collect();
+        let mut total = PrefixCounter::new(16).unwrap();
+        for handle in handles {
+            assert!(total.merge(&handle.join().unwrap()));
+        }
+        assert_eq!(400, total.get(addr("10.0.0.0")));
+        assert_eq!(1, total.len());
+
+        let mut finer = PrefixCounter::new(24).unwrap();
+        assert!(!finer.merge(&total));
+        assert!(finer.is_empty());
+    }
+}
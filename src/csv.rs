@@ -0,0 +1,83 @@
+//! # The CSV Module
+//!
+//! Reading and writing of delimited text, shared by the modules which load tables of networks.
+//!
+//! This follows RFC 4180, except that any delimiter may be used, and that lines may end with
+//! either `\n` or `\r\n`.
+
+/// Quotes a field if it would otherwise be misread.
+pub(crate) fn quote(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// An iterator over the rows of a table, each with the line on which it began. Quoted fields may
+/// contain delimiters, doubled quotes, and line breaks; blank lines are skipped. A quoted field
+/// which is never closed is an error, holding the line on which its row began.
+pub(crate) struct Rows<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    delimiter: char,
+    line: usize,
+}
+
+impl<'a> Rows<'a> {
+    pub(crate) fn new(text: &'a str, delimiter: char) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            delimiter,
+            line: 1,
+        }
+    }
+}
+
+impl Iterator for Rows<'_> {
+    type Item = Result<(usize, Vec<String>), usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some('\n') | Some('\r') = self.chars.peek() {
+            if self.chars.next() == Some('\n') {
+                self.line += 1;
+            }
+        }
+        self.chars.peek()?;
+
+        let start = self.line;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        while let Some(c) = self.chars.next() {
+            match c {
+                '"' if quoted => {
+                    if self.chars.peek() == Some(&'"') {
+                        field.push(self.chars.next().unwrap());
+                    } else {
+                        quoted = false;
+                    }
+                }
+                '"' if field.trim().is_empty() => {
+                    field.clear();
+                    quoted = true;
+                }
+                '\n' if quoted => {
+                    self.line += 1;
+                    field.push(c);
+                }
+                '\n' => {
+                    self.line += 1;
+                    break;
+                }
+                '\r' if !quoted => {}
+                c if c == self.delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+                c => field.push(c),
+            }
+        }
+        if quoted {
+            return Some(Err(start));
+        }
+        fields.push(field);
+        Some(Ok((start, fields)))
+    }
+}
@@ -0,0 +1,163 @@
+//! # The DNS Module
+//!
+//! Reverse DNS maps addresses back to names, through specially-constructed names under
+//! `in-addr.arpa`; the octets of the address are written out backwards, so that the DNS hierarchy
+//! lines up with the address hierarchy. Zones can only be delegated on label (octet) boundaries,
+//! so networks longer than `/24` are delegated with the classless scheme of RFC 2317.
+use super::{addr::IpAddress, net::IpNetwork};
+use std::fmt;
+
+/// The domain under which every reverse IPv4 name lives.
+const SUFFIX: &str = "in-addr.arpa";
+
+impl IpAddress {
+    /// Gets the name under which this address's `PTR` record is found, such as
+    /// `5.1.168.192.in-addr.arpa`.
+    pub fn reverse_pointer(&self) -> String {
+        let [a, b, c, d] = self.octets();
+        format!("{}.{}.{}.{}.{}", d, c, b, a, SUFFIX)
+    }
+}
+
+/// A reverse zone, along with the part of a network that it holds records for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReverseZone {
+    name: String,
+    network: IpNetwork,
+}
+
+impl ReverseZone {
+    /// The fully-qualified name of the zone (without the trailing dot).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The addresses which this zone holds records for.
+    pub fn network(&self) -> IpNetwork {
+        self.network
+    }
+
+    /// Whether this is a classless zone, delegated as described by RFC 2317.
+    pub fn is_classless(&self) -> bool {
+        self.network.num_network_bits() > 24
+    }
+
+    /// Gets the name of the address's `PTR` record, relative to this zone. The address should be
+    /// within the zone's network.
+    pub fn record_label(&self, addr: IpAddress) -> String {
+        let octets = addr.octets();
+        let zone_octets = if self.is_classless() {
+            3
+        } else {
+            usize::from(self.network.num_network_bits() / 8)
+        };
+        let labels: Vec<String> = octets[zone_octets..]
+            .iter()
+            .rev()
+            .map(|octet| octet.to_string())
+            .collect();
+        labels.join(".")
+    }
+}
+
+impl fmt::Display for ReverseZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl IpNetwork {
+    /// Gets the reverse zones which together hold the records for every address in this network.
+    ///
+    /// A network which ends on an octet boundary is exactly one zone. Otherwise, shorter networks
+    /// are spread over every zone at the next octet boundary, and networks longer than `/24` get
+    /// a single classless zone named for their first address and prefix, such as
+    /// `0/26.2.0.192.in-addr.arpa`.
+    pub fn reverse_zones(&self) -> Vec<ReverseZone> {
+        let prefix = self.num_network_bits();
+        if prefix > 24 {
+            let [a, b, c, d] = self.network_address().octets();
+            let name = format!("{}/{}.{}.{}.{}.{}", d, prefix, c, b, a, SUFFIX);
+            let network = IpNetwork::new(self.network_address(), prefix).unwrap();
+            return vec![ReverseZone { name, network }];
+        }
+        let zone_prefix = prefix.div_ceil(8) * 8;
+        self.subnets_with_prefix(zone_prefix)
+            .unwrap()
+            .map(|network| {
+                let octets = network.network_address().octets();
+                let mut labels: Vec<String> = octets[..usize::from(zone_prefix / 8)]
+                    .iter()
+                    .rev()
+                    .map(|octet| octet.to_string())
+                    .collect();
+                labels.push(SUFFIX.to_string());
+                ReverseZone {
+                    name: labels.join("."),
+                    network,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zones(s: &str) -> Vec<String> {
+        s.parse::<IpNetwork>()
+            .unwrap()
+            .reverse_zones()
+            .iter()
+            .map(|zone| zone.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn reverse_pointer() {
+        assert_eq!(
+            "5.1.168.192.in-addr.arpa",
+            IpAddress::from([192, 168, 1, 5]).reverse_pointer()
+        );
+        assert_eq!("0.0.0.0.in-addr.arpa", IpAddress::from(0).reverse_pointer());
+    }
+
+    #[test]
+    fn octet_zones() {
+        assert_eq!(vec!["in-addr.arpa"], zones("0.0.0.0/0"));
+        assert_eq!(vec!["10.in-addr.arpa"], zones("10.0.0.0/8"));
+        assert_eq!(vec!["168.192.in-addr.arpa"], zones("192.168.0.0/16"));
+        assert_eq!(vec!["2.0.192.in-addr.arpa"], zones("192.0.2.77/24"));
+        assert_eq!(
+            vec![
+                "0.0.10.in-addr.arpa",
+                "1.0.10.in-addr.arpa",
+                "2.0.10.in-addr.arpa",
+                "3.0.10.in-addr.arpa"
+            ],
+            zones("10.0.0.0/22")
+        );
+        assert_eq!(2, zones("10.0.0.0/7").len());
+        assert_eq!(128, zones("10.0.0.0/17").len());
+    }
+
+    #[test]
+    fn classless_zones() {
+        assert_eq!(vec!["0/26.2.0.192.in-addr.arpa"], zones("192.0.2.0/26"));
+        assert_eq!(vec!["64/26.2.0.192.in-addr.arpa"], zones("192.0.2.100/26"));
+        assert_eq!(vec!["9/32.2.0.192.in-addr.arpa"], zones("192.0.2.9/32"));
+    }
+
+    #[test]
+    fn record_labels() {
+        let addr = IpAddress::from([192, 0, 2, 9]);
+        let label =
+            |net: &str| net.parse::<IpNetwork>().unwrap().reverse_zones()[0].record_label(addr);
+        assert_eq!("9.2.0.192", label("0.0.0.0/0"));
+        assert_eq!("9.2.0", label("192.0.0.0/8"));
+        assert_eq!("9.2", label("192.0.0.0/16"));
+        assert_eq!("9", label("192.0.2.0/24"));
+        assert_eq!("9", label("192.0.2.0/28"));
+    }
+}
@@ -0,0 +1,213 @@
+//! # The Dual-Stack Module
+//!
+//! Networks which run both IPv4 and IPv6 are easiest to operate when the IPv6 plan mirrors the
+//! IPv4 one, so that `10.1.23.0/24` becomes something like `2001:db8:1:23::/64`. This module
+//! derives such IPv6 subnets from IPv4 networks, by a rule which embeds chosen octets of the IPv4
+//! network into chosen nibbles of the IPv6 subnet, and checks that no two networks of a plan are
+//! given the same subnet.
+use super::{addr6::Ipv6Address, net::IpNetwork, net6::Ipv6Network};
+use std::{error, fmt};
+
+/// How an IPv4 octet is written into the nibbles of an IPv6 subnet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// As two hex digits, so that `23` becomes `17`.
+    Hex,
+    /// As three decimal digits, so that `23` reads as `023`.
+    Decimal,
+}
+
+impl Encoding {
+    /// The number of nibbles an octet takes up.
+    fn width(&self) -> u8 {
+        match self {
+            Encoding::Hex => 2,
+            Encoding::Decimal => 3,
+        }
+    }
+
+    fn encode(&self, octet: u8) -> u128 {
+        match self {
+            Encoding::Hex => u128::from(octet),
+            Encoding::Decimal => {
+                let (hundreds, tens, ones) = (octet / 100, octet / 10 % 10, octet % 10);
+                u128::from(hundreds) << 8 | u128::from(tens) << 4 | u128::from(ones)
+            }
+        }
+    }
+}
+
+/// An octet of the IPv4 network, and where it goes within the IPv6 subnet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct Field {
+    octet: u8,
+    nibble: u8,
+    encoding: Encoding,
+}
+
+/// A rule deriving IPv6 subnets from the IPv4 networks they are paired with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pairing {
+    parent: Ipv6Network,
+    prefix_len: u8,
+    fields: Vec<Field>,
+}
+
+impl Pairing {
+    /// Creates a rule which derives subnets of the specified prefix length from within the parent
+    /// prefix, but embeds nothing yet, so every network is paired with the first subnet.
+    pub fn new(parent: Ipv6Network, prefix_len: u8) -> Option<Self> {
+        if prefix_len < parent.num_network_bits() || prefix_len > 128 {
+            return None;
+        }
+        Some(Self {
+            parent,
+            prefix_len,
+            fields: Vec::new(),
+        })
+    }
+
+    /// Embeds an octet of the IPv4 network, counting from `0`, starting at a nibble of the IPv6
+    /// subnet, also counting from `0`.
+    ///
+    /// The octet **must** be written after the parent prefix but within the subnet prefix, and
+    /// **must not** overlap another embedded octet.
+    pub fn embed(mut self, octet: u8, nibble: u8, encoding: Encoding) -> Option<Self> {
+        let field = Field {
+            octet,
+            nibble,
+            encoding,
+        };
+        let (start, end) = field.bits();
+        let overlaps = self.fields.iter().any(|other| {
+            let (other_start, other_end) = other.bits();
+            start < other_end && other_start < end
+        });
+        if octet > 3 || start < u32::from(self.parent.num_network_bits()) {
+            return None;
+        }
+        if end > u32::from(self.prefix_len) || overlaps {
+            return None;
+        }
+        self.fields.push(field);
+        Some(self)
+    }
+
+    /// Derives the IPv6 subnet paired with an IPv4 network.
+    pub fn pair(&self, net: IpNetwork) -> Ipv6Network {
+        let octets = net.network_address().octets();
+        let base = self
+            .fields
+            .iter()
+            .fold(self.parent.network_address().value(), |base, field| {
+                let shift = 128 - field.bits().1;
+                base | field.encoding.encode(octets[usize::from(field.octet)]) << shift
+            });
+        Ipv6Network::new(Ipv6Address::from(base), self.prefix_len).unwrap()
+    }
+
+    /// Pairs every network of an IPv4 plan with an IPv6 subnet, in the same order, making sure
+    /// that no two networks are given the same subnet, so that the pairing can be reversed.
+    pub fn pair_all<I>(&self, plan: I) -> Result<Vec<(IpNetwork, Ipv6Network)>, PairingError>
+    where
+        I: IntoIterator<Item = IpNetwork>,
+    {
+        let pairs: Vec<_> = plan.into_iter().map(|net| (net, self.pair(net))).collect();
+        let mut sorted: Vec<_> = pairs.iter().map(|&(net, subnet)| (subnet, net)).collect();
+        sorted.sort_by_key(|&(subnet, _)| subnet.network_address());
+        for pair in sorted.windows(2) {
+            let ((subnet, first), (other, second)) = (pair[0], pair[1]);
+            if subnet.network_address() == other.network_address() {
+                return Err(PairingError {
+                    first,
+                    second,
+                    subnet,
+                });
+            }
+        }
+        Ok(pairs)
+    }
+}
+
+impl Field {
+    /// The bits of the IPv6 address which the field takes up, from the first to just past the
+    /// last.
+    fn bits(&self) -> (u32, u32) {
+        let start = u32::from(self.nibble) * 4;
+        (start, start + u32::from(self.encoding.width()) * 4)
+    }
+}
+
+/// The error returned when two networks of a plan would be paired with the same subnet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairingError {
+    pub first: IpNetwork,
+    pub second: IpNetwork,
+    pub subnet: Ipv6Network,
+}
+
+impl fmt::Display for PairingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} and {} would both be paired with {}",
+            self.first, self.second, self.subnet
+        )
+    }
+}
+
+impl error::Error for PairingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNetwork> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn pair() {
+        let parent: Ipv6Network = "2001:db8::/32".parse().unwrap();
+        let pairing = Pairing::new(parent, 64)
+            .unwrap()
+            .embed(1, 9, Encoding::Decimal)
+            .unwrap()
+            .embed(2, 13, Encoding::Decimal)
+            .unwrap();
+        let pairs = pairing
+            .pair_all(nets(&["10.1.23.0/24", "10.200.5.0/24"]))
+            .unwrap();
+        let subnets: Vec<_> = pairs.iter().map(|(_, s)| s.to_string()).collect();
+        assert_eq!(vec!["2001:db8:1:23::/64", "2001:db8:200:5::/64"], subnets);
+
+        let hex = Pairing::new(parent, 56)
+            .unwrap()
+            .embed(3, 10, Encoding::Hex)
+            .unwrap();
+        assert_eq!(
+            "2001:db8:ff::/56",
+            hex.pair("192.0.2.255/32".parse().unwrap()).to_string()
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let parent: Ipv6Network = "2001:db8::/48".parse().unwrap();
+        let pairing = Pairing::new(parent, 64).unwrap();
+        // Inside the parent prefix, past the subnet prefix, or not an octet at all.
+        assert!(pairing.clone().embed(2, 11, Encoding::Hex).is_none());
+        assert!(pairing.clone().embed(2, 15, Encoding::Hex).is_none());
+        assert!(pairing.clone().embed(4, 12, Encoding::Hex).is_none());
+        let pairing = pairing.embed(2, 12, Encoding::Decimal).unwrap();
+        assert!(pairing.clone().embed(3, 14, Encoding::Hex).is_none());
+        assert!(Pairing::new(parent, 47).is_none());
+
+        let err = pairing
+            .pair_all(nets(&["10.0.5.0/24", "10.1.6.0/24", "10.2.5.0/24"]))
+            .unwrap_err();
+        assert_eq!("10.0.5.0/24", err.first.to_string());
+        assert_eq!("10.2.5.0/24", err.second.to_string());
+        assert_eq!("2001:db8:0:50::/64", err.subnet.to_string());
+    }
+}
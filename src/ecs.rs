@@ -0,0 +1,156 @@
+//! # The EDNS Client Subnet Module
+//!
+//! EDNS Client Subnet (RFC 7871) lets a resolver pass along part of its client's address, so
+//! that an authoritative server can answer with something near that client. The resolver sends
+//! the address cut down to a *source* prefix, the server replies with the *scope* prefix the
+//! answer is good for, and the resolver may then reuse the answer for any other client within
+//! that scope. Each step is simple, but easy to get subtly wrong; the helpers here cover them for
+//! IPv4 and, with a `6` suffix, for IPv6.
+use super::{
+    addr::IpAddress,
+    addr6::Ipv6Address,
+    net::IpNetwork,
+    net6::{self, Ipv6Network},
+    set,
+};
+
+/// The source prefix length RFC 7871 recommends a resolver send for an IPv4 client.
+pub const DEFAULT_SOURCE_PREFIX_LEN: u8 = 24;
+
+/// The source prefix length RFC 7871 recommends a resolver send for an IPv6 client.
+pub const DEFAULT_SOURCE_PREFIX_LEN6: u8 = 56;
+
+/// Cuts an address down to the network of the specified prefix length, clearing every bit past
+/// it, as the address of a client subnet option must be.
+pub fn truncate(addr: IpAddress, prefix_len: u8) -> Option<IpNetwork> {
+    let net = IpNetwork::new(addr, prefix_len)?;
+    IpNetwork::new(net.network_address(), prefix_len)
+}
+
+/// Cuts an IPv6 address down to the network of the specified prefix length, clearing every bit
+/// past it.
+pub fn truncate6(addr: Ipv6Address, prefix_len: u8) -> Option<Ipv6Network> {
+    let net = Ipv6Network::new(addr, prefix_len)?;
+    Ipv6Network::new(net.network_address(), prefix_len)
+}
+
+/// The network an answer may be cached for, given the source network of the query and the scope
+/// prefix length of the reply.
+///
+/// A scope longer than the source cannot be honoured, since the bits past the source were never
+/// sent, so the answer is cached for the source network instead.
+pub fn cache_scope(source: IpNetwork, scope_prefix_len: u8) -> IpNetwork {
+    let prefix_len = scope_prefix_len.min(source.num_network_bits());
+    truncate(source.network_address(), prefix_len).unwrap()
+}
+
+/// The network an IPv6 answer may be cached for, given the source network of the query and the
+/// scope prefix length of the reply.
+pub fn cache_scope6(source: Ipv6Network, scope_prefix_len: u8) -> Ipv6Network {
+    let prefix_len = scope_prefix_len.min(source.num_network_bits());
+    truncate6(source.network_address(), prefix_len).unwrap()
+}
+
+/// Whether an answer cached for a scope may be reused for a query from the client network.
+///
+/// The client must lie within the scope, and must have revealed at least as many bits as the
+/// scope needs: a query from a `/16` cannot be answered from a cache entry for a `/24`, even one
+/// inside it. A bare client address is a `/32`.
+pub fn covers(scope: IpNetwork, client: IpNetwork) -> bool {
+    client.is_subnet_of(&scope)
+}
+
+/// Whether an IPv6 answer cached for a scope may be reused for a query from the client network.
+pub fn covers6(scope: Ipv6Network, client: Ipv6Network) -> bool {
+    client.is_subnet_of(&scope)
+}
+
+/// The longest scope which covers every one of the clients, so that a single answer can be
+/// shared between them, if there are any clients.
+pub fn minimal_scope<I: IntoIterator<Item = IpAddress>>(clients: I) -> Option<IpNetwork> {
+    let span = set::spanning(
+        clients
+            .into_iter()
+            .map(|addr| IpNetwork::new(addr, 32).unwrap()),
+    )?;
+    truncate(span.network_address(), span.num_network_bits())
+}
+
+/// The longest scope which covers every one of the IPv6 clients, if there are any.
+pub fn minimal_scope6<I: IntoIterator<Item = Ipv6Address>>(clients: I) -> Option<Ipv6Network> {
+    let span = net6::spanning(
+        clients
+            .into_iter()
+            .map(|addr| Ipv6Network::new(addr, 128).unwrap()),
+    )?;
+    truncate6(span.network_address(), span.num_network_bits())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn truncate_and_cache() {
+        let source = truncate(addr("192.0.2.77"), DEFAULT_SOURCE_PREFIX_LEN).unwrap();
+        assert_eq!(net("192.0.2.0/24"), source);
+        assert_eq!(addr("192.0.2.0"), source.network_address());
+        assert!(truncate(addr("192.0.2.77"), 33).is_none());
+
+        assert_eq!(net("192.0.0.0/20"), cache_scope(source, 20));
+        assert_eq!(source, cache_scope(source, 28));
+        assert_eq!(net("0.0.0.0/0"), cache_scope(source, 0));
+
+        let addr6: Ipv6Address = "2001:db8:1234:5678::1".parse().unwrap();
+        let source6 = truncate6(addr6, DEFAULT_SOURCE_PREFIX_LEN6).unwrap();
+        assert_eq!("2001:db8:1234:5600::/56", source6.to_string());
+        assert_eq!(source6, cache_scope6(source6, 64));
+        assert_eq!("2001:db8::/32", cache_scope6(source6, 32).to_string());
+    }
+
+    #[test]
+    fn covers() {
+        let scope = net("192.0.2.0/24");
+        assert!(super::covers(scope, net("192.0.2.200/32")));
+        assert!(super::covers(scope, net("192.0.2.128/25")));
+        assert!(super::covers(scope, scope));
+        assert!(!super::covers(scope, net("192.0.3.1/32")));
+        // The client revealed too little to tell whether it lies within the scope.
+        assert!(!super::covers(scope, net("192.0.0.0/16")));
+
+        let scope6: Ipv6Network = "2001:db8::/48".parse().unwrap();
+        assert!(covers6(scope6, "2001:db8:0:ff00::/56".parse().unwrap()));
+        assert!(!covers6(scope6, "2001:db8:1::/56".parse().unwrap()));
+    }
+
+    #[test]
+    fn minimal_scope() {
+        let clients = ["198.51.100.7", "198.51.100.200", "198.51.101.1"];
+        assert_eq!(
+            Some(net("198.51.100.0/23")),
+            super::minimal_scope(clients.iter().map(|s| addr(s)))
+        );
+        assert_eq!(
+            Some(net("198.51.100.7/32")),
+            super::minimal_scope(vec![addr("198.51.100.7")])
+        );
+        assert_eq!(None, super::minimal_scope(vec![]));
+
+        let clients6: Vec<Ipv6Address> = vec![
+            "2001:db8:0:1::1".parse().unwrap(),
+            "2001:db8:0:2::1".parse().unwrap(),
+        ];
+        assert_eq!(
+            "2001:db8::/62",
+            minimal_scope6(clients6).unwrap().to_string()
+        );
+    }
+}
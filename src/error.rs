@@ -0,0 +1,182 @@
+//! # The Error Module
+//!
+//! Each module reports its own errors with its own type, which says exactly what went wrong. An
+//! application which uses several modules usually wants a single error type instead, which it can
+//! still branch on. Every error of the crate converts into the [`Error`] here, which remembers
+//! what kind of error it was, and keeps the original as its source.
+use super::{
+    addr::AddrParseError,
+    asn::AsnError,
+    blocklist::BlocklistError,
+    dualstack::PairingError,
+    feed::FeedError,
+    geoip::GeoIpError,
+    inventory::InventoryError,
+    mac::MacParseError,
+    net::{BuildError, NetParseError},
+    pattern::PatternError,
+    prefix_list::PrefixListError,
+    shortcode::ShortCodeError,
+    snapshot::SnapshotError,
+    socket::SocketAddrParseError,
+    targets::{ScanCursorError, TargetError},
+    vpc::VpcError,
+};
+use std::{error, fmt};
+
+/// Any error of the crate.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    source: Box<dyn error::Error + Send + Sync + 'static>,
+}
+
+/// Which of the crate's errors an [`Error`] is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An [`AddrParseError`].
+    AddrParse,
+    /// A [`NetParseError`].
+    NetParse,
+    /// A [`SocketAddrParseError`].
+    SocketAddrParse,
+    /// A [`MacParseError`].
+    MacParse,
+    /// A [`BuildError`].
+    Build,
+    /// A [`PairingError`].
+    Pairing,
+    /// An [`AsnError`].
+    Asn,
+    /// A [`BlocklistError`].
+    Blocklist,
+    /// A [`GeoIpError`].
+    GeoIp,
+    /// An [`InventoryError`].
+    Inventory,
+    /// A [`PrefixListError`].
+    PrefixList,
+    /// A [`SnapshotError`].
+    Snapshot,
+    /// A [`ShortCodeError`].
+    ShortCode,
+    /// A [`VpcError`].
+    Vpc,
+    /// A [`FeedError`].
+    Feed,
+    /// A [`PatternError`].
+    Pattern,
+    /// A [`TargetError`].
+    Target,
+    /// A [`ScanCursorError`].
+    ScanCursor,
+    /// An [`MrtError`](crate::mrt::MrtError).
+    #[cfg(feature = "mrt")]
+    Mrt,
+    /// A [`PcapError`](crate::packet::PcapError).
+    #[cfg(feature = "packet")]
+    Pcap,
+    /// An [`RdapError`](crate::rdap::RdapError).
+    #[cfg(feature = "rdap")]
+    Rdap,
+    /// A [`ResolveError`](crate::resolve::ResolveError).
+    #[cfg(feature = "resolve")]
+    Resolve,
+}
+
+impl Error {
+    /// Which of the crate's errors this is.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The original error, which can be downcast to the type named by its kind.
+    pub fn get_ref(&self) -> &(dyn error::Error + Send + Sync + 'static) {
+        &*self.source
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.source, f)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+macro_rules! from_errors {
+    ($($(#[$cfg:meta])* $ty:ty => $kind:ident),* $(,)?) => {$(
+        $(#[$cfg])*
+        impl From<$ty> for Error {
+            fn from(err: $ty) -> Self {
+                Self {
+                    kind: ErrorKind::$kind,
+                    source: Box::new(err),
+                }
+            }
+        }
+    )*};
+}
+
+from_errors! {
+    AddrParseError => AddrParse,
+    NetParseError => NetParse,
+    SocketAddrParseError => SocketAddrParse,
+    MacParseError => MacParse,
+    BuildError => Build,
+    PairingError => Pairing,
+    AsnError => Asn,
+    BlocklistError => Blocklist,
+    GeoIpError => GeoIp,
+    InventoryError => Inventory,
+    PrefixListError => PrefixList,
+    SnapshotError => Snapshot,
+    ShortCodeError => ShortCode,
+    VpcError => Vpc,
+    FeedError => Feed,
+    PatternError => Pattern,
+    TargetError => Target,
+    ScanCursorError => ScanCursor,
+    #[cfg(feature = "mrt")]
+    crate::mrt::MrtError => Mrt,
+    #[cfg(feature = "packet")]
+    crate::packet::PcapError => Pcap,
+    #[cfg(feature = "rdap")]
+    crate::rdap::RdapError => Rdap,
+    #[cfg(feature = "resolve")]
+    crate::resolve::ResolveError => Resolve,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IpAddress, IpNetwork};
+    use std::error::Error as _;
+
+    fn parse_both(addr: &str, net: &str) -> Result<(IpAddress, IpNetwork), Error> {
+        Ok((addr.parse()?, net.parse()?))
+    }
+
+    #[test]
+    fn convert() {
+        assert!(parse_both("10.0.0.1", "10.0.0.0/8").is_ok());
+        let err = parse_both("10.0.0", "10.0.0.0/8").unwrap_err();
+        assert_eq!(ErrorKind::AddrParse, err.kind());
+        assert_eq!("too few octets in IP address", err.to_string());
+        let err = parse_both("10.0.0.1", "10.0.0.0/33").unwrap_err();
+        assert_eq!(ErrorKind::NetParse, err.kind());
+        assert!(err.source().unwrap().is::<NetParseError>());
+
+        let err = Error::from(BuildError::MissingBase);
+        assert_eq!(ErrorKind::Build, err.kind());
+        assert_eq!(
+            Some(&BuildError::MissingBase),
+            err.get_ref().downcast_ref::<BuildError>()
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! # The Explain Module
+//!
+//! Subnetting is taught, and debugged, by writing addresses out in binary and lining the bits up.
+//! The functions here answer the same questions as the rest of the crate, but also return the
+//! steps taken to reach the answer: each intermediate value, in binary, with the network bits set
+//! apart from the host bits. A teaching tool can print the steps as they are; a debugging UI can
+//! lay them out however it likes.
+use super::{addr::IpAddress, bits::AddressBits, net::IpNetwork};
+use std::fmt;
+
+/// The value a step arrives at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    /// An address or mask, along with the number of leading bits which belong to the network.
+    Bits { value: IpAddress, prefix_len: u8 },
+    /// A number of addresses, subnets, or bits.
+    Count(u64),
+    /// The outcome of a comparison.
+    Verdict(bool),
+}
+
+/// Shows bits in binary octets, with a `|` between the network bits and the host bits, and the
+/// dotted-decimal form after them, as in `11000000.10101000.00000001|00000101 (192.168.1.5)`.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Value::Bits { value, prefix_len } => {
+                let mut binary = String::with_capacity(35);
+                for depth in 0..32 {
+                    if depth > 0 && depth == prefix_len {
+                        binary.push('|');
+                    } else if depth > 0 && depth % 8 == 0 {
+                        binary.push('.');
+                    }
+                    binary.push(if value.value().bit(depth) { '1' } else { '0' });
+                }
+                write!(f, "{} ({})", binary, value)
+            }
+            Value::Count(count) => write!(f, "{}", count),
+            Value::Verdict(verdict) => write!(f, "{}", if verdict { "yes" } else { "no" }),
+        }
+    }
+}
+
+/// One step of a calculation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Step {
+    /// What the step does, in words.
+    pub description: String,
+    pub value: Value,
+}
+
+impl Step {
+    fn new(description: impl Into<String>, value: Value) -> Self {
+        Self {
+            description: description.into(),
+            value,
+        }
+    }
+
+    fn bits(description: impl Into<String>, value: IpAddress, prefix_len: u8) -> Self {
+        Self::new(description, Value::Bits { value, prefix_len })
+    }
+}
+
+/// The answer to a calculation, along with the steps taken to reach it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Explanation<T> {
+    pub answer: T,
+    pub steps: Vec<Step>,
+}
+
+/// Shows each step on its own line, numbered from `1`.
+impl<T> fmt::Display for Explanation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{}. {}: {}", i + 1, step.description, step.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Derives the netmask of a prefix length, which may be at most 32.
+pub fn mask(prefix_len: u8) -> Option<Explanation<IpAddress>> {
+    if prefix_len > 32 {
+        return None;
+    }
+    let mask = IpAddress::from(u32::mask(prefix_len));
+    let steps = vec![
+        Step::bits(
+            format!(
+                "set the first {} bits, which belong to the network",
+                prefix_len
+            ),
+            mask,
+            prefix_len,
+        ),
+        Step::bits("invert the mask for the wildcard", !mask, prefix_len),
+    ];
+    Some(Explanation {
+        answer: mask,
+        steps,
+    })
+}
+
+/// Derives the network an address belongs to, and its bounds, for a prefix length which may be
+/// at most 32.
+pub fn network(addr: IpAddress, prefix_len: u8) -> Option<Explanation<IpNetwork>> {
+    let net = IpNetwork::new(addr, prefix_len)?;
+    let mask = net.get_mask();
+    let steps = vec![
+        Step::bits("take the address", addr, prefix_len),
+        Step::bits(
+            format!("take the mask of /{}", prefix_len),
+            mask,
+            prefix_len,
+        ),
+        Step::bits(
+            "AND them together for the network address",
+            net.network_address(),
+            prefix_len,
+        ),
+        Step::bits("invert the mask for the wildcard", !mask, prefix_len),
+        Step::bits(
+            "OR the network address with the wildcard for the broadcast address",
+            net.broadcast_address(),
+            prefix_len,
+        ),
+        Step::new(
+            format!("count 2^{} addresses", 32 - prefix_len),
+            Value::Count(net.num_addresses()),
+        ),
+    ];
+    let answer = IpNetwork::new(net.network_address(), prefix_len).unwrap();
+    Some(Explanation { answer, steps })
+}
+
+/// Checks whether the network contains the address, by comparing their network bits.
+pub fn contains(net: IpNetwork, addr: IpAddress) -> Explanation<bool> {
+    let prefix_len = net.num_network_bits();
+    let masked = addr & net.get_mask();
+    let common = net
+        .network_address()
+        .value()
+        .common_prefix_len(addr.value());
+    let answer = common >= prefix_len;
+    let comparison = if answer {
+        format!("compare the first {} bits, which all match", prefix_len)
+    } else {
+        format!(
+            "compare the first {} bits, which differ from bit {}",
+            prefix_len,
+            common + 1
+        )
+    };
+    let steps = vec![
+        Step::bits(
+            "take the network address",
+            net.network_address(),
+            prefix_len,
+        ),
+        Step::bits("take the address", addr, prefix_len),
+        Step::bits(
+            format!("AND the address with the mask of /{}", prefix_len),
+            masked,
+            prefix_len,
+        ),
+        Step::new(comparison, Value::Verdict(answer)),
+    ];
+    Explanation { answer, steps }
+}
+
+/// Counts the subnets of a longer prefix length within the network, showing the first and last.
+pub fn subnets(net: IpNetwork, prefix_len: u8) -> Option<Explanation<u64>> {
+    let borrowed = prefix_len.checked_sub(net.num_network_bits())?;
+    let first = IpNetwork::new(net.network_address(), prefix_len)?;
+    let last = IpNetwork::new(net.broadcast_address(), prefix_len)?;
+    let answer = 1u64 << borrowed;
+    let steps = vec![
+        Step::new(
+            format!(
+                "borrow host bits to go from /{} to /{}",
+                net.num_network_bits(),
+                prefix_len
+            ),
+            Value::Count(u64::from(borrowed)),
+        ),
+        Step::new(
+            format!("count 2^{} subnets", borrowed),
+            Value::Count(answer),
+        ),
+        Step::new(
+            format!("count 2^{} addresses in each", 32 - prefix_len),
+            Value::Count(first.num_addresses()),
+        ),
+        Step::bits("the first subnet", first.network_address(), prefix_len),
+        Step::bits("the last subnet", last.network_address(), prefix_len),
+    ];
+    Some(Explanation { answer, steps })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn binary() {
+        let bits = |s, prefix_len| Value::Bits {
+            value: addr(s),
+            prefix_len,
+        };
+        assert_eq!(
+            "11000000.10101000.00000001|00000101 (192.168.1.5)",
+            bits("192.168.1.5", 24).to_string()
+        );
+        assert_eq!(
+            "11111111.1111|0000.00000000.00000000 (255.240.0.0)",
+            bits("255.240.0.0", 12).to_string()
+        );
+        assert_eq!(
+            "00000000.00000000.00000000.00000001 (0.0.0.1)",
+            bits("0.0.0.1", 0).to_string()
+        );
+        assert_eq!("no", Value::Verdict(false).to_string());
+    }
+
+    #[test]
+    fn mask_and_network() {
+        let mask = mask(20).unwrap();
+        assert_eq!(addr("255.255.240.0"), mask.answer);
+        assert_eq!(
+            Value::Bits {
+                value: addr("0.0.15.255"),
+                prefix_len: 20
+            },
+            mask.steps[1].value
+        );
+        assert!(super::mask(33).is_none());
+
+        let network = network(addr("172.16.77.9"), 20).unwrap();
+        assert_eq!("172.16.64.0/20", network.answer.to_string());
+        assert_eq!(
+            "1. take the address: 10101100.00010000.0100|1101.00001001 (172.16.77.9)\n\
+             2. take the mask of /20: 11111111.11111111.1111|0000.00000000 (255.255.240.0)\n\
+             3. AND them together for the network address: \
+             10101100.00010000.0100|0000.00000000 (172.16.64.0)\n\
+             4. invert the mask for the wildcard: 00000000.00000000.0000|1111.11111111 (0.0.15.255)\n\
+             5. OR the network address with the wildcard for the broadcast address: \
+             10101100.00010000.0100|1111.11111111 (172.16.79.255)\n\
+             6. count 2^12 addresses: 4096\n",
+            network.to_string()
+        );
+    }
+
+    #[test]
+    fn contains() {
+        let net: IpNetwork = "10.0.0.0/9".parse().unwrap();
+        let inside = super::contains(net, addr("10.127.0.1"));
+        assert!(inside.answer);
+        assert_eq!(Value::Verdict(true), inside.steps[3].value);
+        let outside = super::contains(net, addr("10.128.0.1"));
+        assert!(!outside.answer);
+        assert_eq!(
+            "compare the first 9 bits, which differ from bit 9",
+            outside.steps[3].description
+        );
+    }
+
+    #[test]
+    fn subnets() {
+        let net: IpNetwork = "192.168.0.0/16".parse().unwrap();
+        let subnets = super::subnets(net, 20).unwrap();
+        assert_eq!(16, subnets.answer);
+        let values: Vec<_> = subnets.steps.iter().map(|s| s.value.to_string()).collect();
+        assert_eq!(
+            vec![
+                "4",
+                "16",
+                "4096",
+                "11000000.10101000.0000|0000.00000000 (192.168.0.0)",
+                "11000000.10101000.1111|0000.00000000 (192.168.240.0)"
+            ],
+            values
+        );
+        assert_eq!(1, super::subnets(net, 16).unwrap().answer);
+        assert!(super::subnets(net, 15).is_none());
+        assert!(super::subnets(net, 33).is_none());
+    }
+}
@@ -0,0 +1,308 @@
+//! # The Feed Module
+//!
+//! A security pipeline rarely has just one list of addresses. There are threat feeds to block,
+//! partners and monitoring services to allow no matter what the feeds say, and each of them is
+//! refreshed on its own schedule. A feed manager keeps track of every source by name, decides
+//! between them by precedence, so that an allowlist can override a blocklist, and says exactly
+//! which networks each reload added or removed, so that downstream systems can be updated
+//! incrementally and every change can be logged against its source.
+use super::{
+    addr::IpAddress,
+    blocklist::{self, BlocklistError, BlocklistFormat},
+    filter::{Action, IpFilter},
+    net::IpNetwork,
+    set::IpSet,
+};
+use std::{error, fmt, io};
+
+/// A named source of networks, along with what is done with them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedSource {
+    name: String,
+    format: BlocklistFormat,
+    action: Action,
+    precedence: i32,
+    set: IpSet,
+    errors: Vec<BlocklistError>,
+}
+
+impl FeedSource {
+    /// The name the source was added under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The format the source is read as.
+    pub fn format(&self) -> BlocklistFormat {
+        self.format
+    }
+
+    /// What is done with the addresses the source lists.
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    /// The precedence of the source; where sources overlap, the highest decides.
+    pub fn precedence(&self) -> i32 {
+        self.precedence
+    }
+
+    /// Every address listed as of the last load.
+    pub fn set(&self) -> &IpSet {
+        &self.set
+    }
+
+    /// The entries of the last load which could not be understood, and were skipped.
+    pub fn errors(&self) -> &[BlocklistError] {
+        &self.errors
+    }
+}
+
+/// Whether a network was added to a source or removed from it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Change {
+    Added,
+    Removed,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+        })
+    }
+}
+
+/// A network which a reload added to, or removed from, a source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FeedEvent {
+    pub source: String,
+    pub change: Change,
+    pub net: IpNetwork,
+}
+
+/// Formats the event as a log line, as in `10.0.0.0/8 added by spamhaus`.
+impl fmt::Display for FeedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let by = match self.change {
+            Change::Added => "by",
+            Change::Removed => "from",
+        };
+        write!(f, "{} {} {} {}", self.net, self.change, by, self.source)
+    }
+}
+
+/// Several named sources of networks, merged by precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedManager {
+    /// The sources, from the highest precedence to the lowest, and in the order they were added
+    /// where precedences are equal.
+    sources: Vec<FeedSource>,
+}
+
+impl FeedManager {
+    /// Creates a new manager, without any sources.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an empty source, returning whether it could; names **must** be unique.
+    pub fn add_source(
+        &mut self,
+        name: &str,
+        format: BlocklistFormat,
+        action: Action,
+        precedence: i32,
+    ) -> bool {
+        if self.source(name).is_some() {
+            return false;
+        }
+        let i = self
+            .sources
+            .partition_point(|source| source.precedence >= precedence);
+        self.sources.insert(
+            i,
+            FeedSource {
+                name: name.to_string(),
+                format,
+                action,
+                precedence,
+                set: IpSet::new(),
+                errors: Vec::new(),
+            },
+        );
+        true
+    }
+
+    /// The source with the name.
+    pub fn source(&self, name: &str) -> Option<&FeedSource> {
+        self.sources.iter().find(|source| source.name == name)
+    }
+
+    /// The sources, from the highest precedence to the lowest.
+    pub fn sources(&self) -> &[FeedSource] {
+        &self.sources
+    }
+
+    /// Replaces everything a source lists with the text, returning the networks which were added
+    /// and removed, in ascending order.
+    pub fn reload(&mut self, name: &str, text: &str) -> Result<Vec<FeedEvent>, FeedError> {
+        let source = self
+            .sources
+            .iter_mut()
+            .find(|source| source.name == name)
+            .ok_or_else(|| FeedError::UnknownSource(name.to_string()))?;
+        let (set, errors) = blocklist::parse(text, source.format);
+        let event = |change, net| FeedEvent {
+            source: source.name.clone(),
+            change,
+            net,
+        };
+        let mut events: Vec<_> = set
+            .difference(&source.set)
+            .iter()
+            .map(|net| event(Change::Added, net))
+            .chain(
+                source
+                    .set
+                    .difference(&set)
+                    .iter()
+                    .map(|net| event(Change::Removed, net)),
+            )
+            .collect();
+        events.sort_by_key(|event| event.net);
+        source.set = set;
+        source.errors = errors;
+        Ok(events)
+    }
+
+    /// Replaces everything a source lists with what is read from the reader.
+    pub fn reload_from<R: io::Read>(
+        &mut self,
+        name: &str,
+        mut reader: R,
+    ) -> Result<Vec<FeedEvent>, FeedError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        self.reload(name, &text)
+    }
+
+    /// The source which decides what is done with the address, if any lists it.
+    pub fn decide(&self, addr: IpAddress) -> Option<&FeedSource> {
+        self.sources.iter().find(|source| source.set.contains(addr))
+    }
+
+    /// A filter equivalent to the sources, with a rule for each, from the highest precedence to
+    /// the lowest, and the default action for addresses which none lists.
+    pub fn to_filter(&self, default: Action) -> IpFilter {
+        let mut filter = IpFilter::new(default);
+        for source in &self.sources {
+            filter.push(source.action, source.set.clone());
+        }
+        filter
+    }
+}
+
+/// The error returned when a source could not be reloaded.
+#[derive(Debug)]
+pub enum FeedError {
+    /// No source has the name.
+    UnknownSource(String),
+    /// The underlying reader failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownSource(name) => write!(f, "unknown feed source {:?}", name),
+            Self::Io(err) => write!(f, "failed to read feed: {}", err),
+        }
+    }
+}
+
+impl error::Error for FeedError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FeedError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    fn manager() -> FeedManager {
+        let mut feeds = FeedManager::new();
+        assert!(feeds.add_source("drop", BlocklistFormat::Plain, Action::Deny, 0));
+        assert!(feeds.add_source("partners", BlocklistFormat::FireHol, Action::Allow, 10));
+        assert!(!feeds.add_source("drop", BlocklistFormat::Plain, Action::Deny, 5));
+        feeds
+    }
+
+    #[test]
+    fn precedence() {
+        let mut feeds = manager();
+        feeds
+            .reload("drop", "192.0.2.0/24 ; bad\n198.51.100.0/24\n")
+            .unwrap();
+        feeds.reload("partners", "# trusted\n192.0.2.10\n").unwrap();
+
+        let names: Vec<_> = feeds.sources().iter().map(FeedSource::name).collect();
+        assert_eq!(vec!["partners", "drop"], names);
+        assert_eq!("partners", feeds.decide(addr("192.0.2.10")).unwrap().name());
+        assert_eq!("drop", feeds.decide(addr("192.0.2.11")).unwrap().name());
+        assert!(feeds.decide(addr("203.0.113.1")).is_none());
+
+        let filter = feeds.to_filter(Action::Allow);
+        assert!(filter.is_allowed(addr("192.0.2.10")));
+        assert!(!filter.is_allowed(addr("198.51.100.7")));
+        assert!(filter.is_allowed(addr("203.0.113.1")));
+    }
+
+    #[test]
+    fn reload_events() {
+        let mut feeds = manager();
+        let events = feeds.reload("drop", "10.0.0.0/8\n192.0.2.0/24\n").unwrap();
+        assert_eq!(2, events.len());
+        assert!(events.iter().all(|event| event.change == Change::Added));
+
+        let events = feeds
+            .reload_from(
+                "drop",
+                "10.0.0.0/9\n192.0.2.0/24\n203.0.113.0/24\nnot-an-ip\n".as_bytes(),
+            )
+            .unwrap();
+        let lines: Vec<_> = events.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "10.128.0.0/9 removed from drop",
+                "203.0.113.0/24 added by drop"
+            ],
+            lines
+        );
+        assert_eq!(1, feeds.source("drop").unwrap().errors().len());
+        assert!(feeds
+            .reload("drop", "10.0.0.0/9\n192.0.2.0/24\n203.0.113.0/24\n")
+            .unwrap()
+            .is_empty());
+
+        match feeds.reload("missing", "") {
+            Err(FeedError::UnknownSource(name)) => assert_eq!("missing", name),
+            other => panic!("{:?}", other),
+        }
+    }
+}
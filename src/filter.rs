@@ -0,0 +1,368 @@
+//! # The Filter Module
+//!
+//! Access lists, firewalls, and rate limiters all come down to the same question: given an
+//! ordered list of allow and deny rules, what happens to this address? This module answers it the
+//! way they all do, with the first rule to match deciding, and a default for addresses which
+//! match nothing.
+use super::{addr::IpAddress, net::IpNetwork, set::IpSet};
+use std::fmt;
+
+/// What a filter does with an address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+        })
+    }
+}
+
+/// A rule of a filter, which applies its action to every address of its set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    action: Action,
+    set: IpSet,
+}
+
+impl Rule {
+    /// What the rule does with the addresses it matches.
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    /// The addresses the rule matches.
+    pub fn set(&self) -> &IpSet {
+        &self.set
+    }
+}
+
+/// The outcome of filtering an address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    pub action: Action,
+    /// The index of the rule which matched, or `None` if no rule did and the default applied.
+    pub rule: Option<usize>,
+}
+
+/// An ordered list of allow and deny rules, of which the first to match an address decides what
+/// happens to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpFilter {
+    rules: Vec<Rule>,
+    default: Action,
+}
+
+impl Default for IpFilter {
+    fn default() -> Self {
+        Self::new(Action::Deny)
+    }
+}
+
+impl IpFilter {
+    /// Creates a filter without any rules, which does the default action with every address.
+    pub fn new(default: Action) -> Self {
+        Self {
+            rules: Vec::new(),
+            default,
+        }
+    }
+
+    /// Adds a rule allowing the addresses, after every existing rule.
+    pub fn allow<S: Into<IpSet>>(mut self, addrs: S) -> Self {
+        self.push(Action::Allow, addrs);
+        self
+    }
+
+    /// Adds a rule denying the addresses, after every existing rule.
+    pub fn deny<S: Into<IpSet>>(mut self, addrs: S) -> Self {
+        self.push(Action::Deny, addrs);
+        self
+    }
+
+    /// Adds a rule after every existing rule, returning its index.
+    pub fn push<S: Into<IpSet>>(&mut self, action: Action, addrs: S) -> usize {
+        self.rules.push(Rule {
+            action,
+            set: addrs.into(),
+        });
+        self.rules.len() - 1
+    }
+
+    /// The rules, in the order they are evaluated.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// What happens to addresses which no rule matches.
+    pub fn default_action(&self) -> Action {
+        self.default
+    }
+
+    /// Sets what happens to addresses which no rule matches.
+    pub fn set_default_action(&mut self, action: Action) {
+        self.default = action;
+    }
+
+    /// Decides what happens to the address, and which rule decided it.
+    pub fn evaluate(&self, addr: IpAddress) -> Verdict {
+        match self.rules.iter().position(|rule| rule.set.contains(addr)) {
+            Some(i) => Verdict {
+                action: self.rules[i].action,
+                rule: Some(i),
+            },
+            None => Verdict {
+                action: self.default,
+                rule: None,
+            },
+        }
+    }
+
+    /// Whether the address is allowed.
+    pub fn is_allowed(&self, addr: IpAddress) -> bool {
+        self.evaluate(addr).action == Action::Allow
+    }
+
+    /// Every address the filter allows.
+    pub fn allowed(&self) -> IpSet {
+        let (mut allowed, mut decided) = (IpSet::new(), IpSet::new());
+        for rule in &self.rules {
+            if rule.action == Action::Allow {
+                allowed = allowed.union(&rule.set.difference(&decided));
+            }
+            decided = decided.union(&rule.set);
+        }
+        match self.default {
+            Action::Allow => {
+                let everything = IpNetwork::new(IpAddress::from(0), 0).unwrap();
+                allowed.union(&IpSet::from(everything).difference(&decided))
+            }
+            Action::Deny => allowed,
+        }
+    }
+}
+
+/// A filter equivalent to another, with fewer rules, and which of the original rules it dropped.
+///
+/// See [`IpFilter::optimize`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Optimization {
+    pub filter: IpFilter,
+    /// The indices of the original rules which could never match, in ascending order.
+    pub shadowed: Vec<usize>,
+    /// The indices of the original rules which could match, but only ever did what would have
+    /// been done anyway, in ascending order.
+    pub redundant: Vec<usize>,
+}
+
+impl IpFilter {
+    /// The indices of the rules which can never match, because every address of theirs is
+    /// matched by an earlier rule first.
+    pub fn shadowed(&self) -> Vec<usize> {
+        let mut decided = IpSet::new();
+        let mut shadowed = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if rule.set.difference(&decided).is_empty() {
+                shadowed.push(i);
+            }
+            decided = decided.union(&rule.set);
+        }
+        shadowed
+    }
+
+    /// Whether the two filters do the same thing with every address, whatever their rules.
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        self.allowed() == other.allowed()
+    }
+
+    /// Finds an equivalent filter with fewer rules: rules which can never match are dropped, as
+    /// are rules which only ever do what a later rule, or the default, would have done anyway, and
+    /// then adjacent rules with the same action are merged into one.
+    ///
+    /// The result is checked to be equivalent before it is returned.
+    pub fn optimize(&self) -> Optimization {
+        let shadowed = self.shadowed();
+        let mut rules: Vec<(usize, &Rule)> = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| shadowed.binary_search(i).is_err())
+            .collect();
+        let mut redundant = Vec::new();
+        // Dropping a rule changes what the rules before it fall through to, so the rules are
+        // checked from the last to the first.
+        for k in (0..rules.len()).rev() {
+            let decided = rules[..k]
+                .iter()
+                .fold(IpSet::new(), |decided, (_, rule)| decided.union(&rule.set));
+            let (i, rule) = rules[k];
+            if self.falls_through_to(rule.action, rule.set.difference(&decided), &rules[k + 1..]) {
+                redundant.push(i);
+                rules.remove(k);
+            }
+        }
+        redundant.reverse();
+
+        let mut filter = Self::new(self.default);
+        for (_, rule) in rules {
+            match filter.rules.last_mut() {
+                Some(last) if last.action == rule.action => last.set = last.set.union(&rule.set),
+                _ => {
+                    filter.push(rule.action, rule.set.clone());
+                }
+            }
+        }
+        assert!(
+            self.is_equivalent(&filter),
+            "optimized filter is not equivalent"
+        );
+        Optimization {
+            filter,
+            shadowed,
+            redundant,
+        }
+    }
+
+    /// Whether every address of the set would be given the action anyway by the later rules, or
+    /// by the default.
+    fn falls_through_to(
+        &self,
+        action: Action,
+        mut remaining: IpSet,
+        later: &[(usize, &Rule)],
+    ) -> bool {
+        for (_, rule) in later {
+            if rule.action != action && !remaining.intersection(&rule.set).is_empty() {
+                return false;
+            }
+            remaining = remaining.difference(&rule.set);
+        }
+        remaining.is_empty() || self.default == action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn first_match() {
+        let filter = IpFilter::new(Action::Deny)
+            .deny(net("10.0.66.0/24"))
+            .allow(net("10.0.0.0/8"))
+            .allow(IpSet::from(net("192.168.0.0/16")).union(&net("172.16.0.0/12").into()))
+            .deny(net("0.0.0.0/0"));
+        assert_eq!(
+            Verdict {
+                action: Action::Deny,
+                rule: Some(0)
+            },
+            filter.evaluate(addr("10.0.66.1"))
+        );
+        assert_eq!(
+            Verdict {
+                action: Action::Allow,
+                rule: Some(1)
+            },
+            filter.evaluate(addr("10.0.67.1"))
+        );
+        assert_eq!(Some(2), filter.evaluate(addr("172.20.0.1")).rule);
+        assert_eq!(Some(3), filter.evaluate(addr("8.8.8.8")).rule);
+        assert!(filter.is_allowed(addr("192.168.1.1")));
+        assert!(!filter.is_allowed(addr("8.8.8.8")));
+        assert_eq!(4, filter.rules().len());
+    }
+
+    #[test]
+    fn default_action() {
+        let mut filter = IpFilter::default().deny(net("192.0.2.0/24"));
+        assert_eq!(
+            Verdict {
+                action: Action::Deny,
+                rule: None
+            },
+            filter.evaluate(addr("8.8.8.8"))
+        );
+        filter.set_default_action(Action::Allow);
+        assert!(filter.is_allowed(addr("8.8.8.8")));
+        assert!(!filter.is_allowed(addr("192.0.2.1")));
+        assert_eq!(Action::Allow, filter.default_action());
+    }
+
+    #[test]
+    fn allowed() {
+        let filter = IpFilter::new(Action::Deny)
+            .deny(net("10.0.66.0/24"))
+            .allow(net("10.0.0.0/16"));
+        let allowed: Vec<String> = filter.allowed().iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            vec![
+                "10.0.0.0/18",
+                "10.0.64.0/23",
+                "10.0.67.0/24",
+                "10.0.68.0/22",
+                "10.0.72.0/21",
+                "10.0.80.0/20",
+                "10.0.96.0/19",
+                "10.0.128.0/17",
+            ],
+            allowed
+        );
+
+        let filter = IpFilter::new(Action::Allow).deny(net("128.0.0.0/1"));
+        let allowed: Vec<String> = filter.allowed().iter().map(|n| n.to_string()).collect();
+        assert_eq!(vec!["0.0.0.0/1"], allowed);
+    }
+
+    #[test]
+    fn optimize() {
+        let filter = IpFilter::new(Action::Deny)
+            .deny(net("10.0.66.0/24"))
+            .allow(net("10.0.0.0/16"))
+            // Never matches: already denied, or already allowed.
+            .deny(net("10.0.66.128/25"))
+            .allow(net("10.0.1.0/24"))
+            // Matches, but only denies what the default would deny anyway.
+            .deny(net("192.0.2.0/24"))
+            .allow(net("10.1.0.0/16"))
+            .allow(net("10.2.0.0/16"))
+            .deny(net("0.0.0.0/0"));
+        assert_eq!(vec![2, 3], filter.shadowed());
+        let optimization = filter.optimize();
+        assert_eq!(vec![2, 3], optimization.shadowed);
+        assert_eq!(vec![4, 7], optimization.redundant);
+        let rules: Vec<_> = optimization
+            .filter
+            .rules()
+            .iter()
+            .map(|rule| {
+                let nets: Vec<_> = rule.set().iter().map(|n| n.to_string()).collect();
+                format!("{} {}", rule.action(), nets.join(" "))
+            })
+            .collect();
+        assert_eq!(
+            vec!["deny 10.0.66.0/24", "allow 10.0.0.0/15 10.2.0.0/16"],
+            rules
+        );
+        assert!(filter.is_equivalent(&optimization.filter));
+        assert!(!filter.is_equivalent(&IpFilter::new(Action::Deny)));
+
+        let everything = IpFilter::new(Action::Allow).allow(net("0.0.0.0/0"));
+        let optimization = everything.optimize();
+        assert!(optimization.filter.rules().is_empty());
+        assert_eq!(vec![0], optimization.redundant);
+    }
+}
@@ -0,0 +1,237 @@
+//! # The Firewall Module
+//!
+//! Most blocklists and allowlists end their life in the kernel's firewall. This module renders an
+//! [`IpSet`] in the exact syntax expected by the two common ways of loading one: an `nft` set
+//! block, or a script for `ipset restore`.
+use super::set::IpSet;
+use std::fmt;
+
+/// The nftables address families which may hold a table of IPv4 sets.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NftFamily {
+    Ip,
+    Inet,
+    Bridge,
+    Netdev,
+}
+
+impl fmt::Display for NftFamily {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ip => "ip",
+            Self::Inet => "inet",
+            Self::Bridge => "bridge",
+            Self::Netdev => "netdev",
+        })
+    }
+}
+
+/// A named set within an nftables table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NftSet {
+    table: String,
+    name: String,
+    family: NftFamily,
+    interval: bool,
+}
+
+impl NftSet {
+    /// Creates a set with the specified name, within the table of the specified name.
+    ///
+    /// By default the table is of the `inet` family, and the set is an interval set, so that it
+    /// may hold whole networks rather than only single addresses.
+    pub fn new(table: &str, name: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            name: name.to_string(),
+            family: NftFamily::Inet,
+            interval: true,
+        }
+    }
+
+    /// Sets the family of the table.
+    pub fn family(mut self, family: NftFamily) -> Self {
+        self.family = family;
+        self
+    }
+
+    /// Sets whether the set has the `interval` flag.
+    pub fn interval(mut self, interval: bool) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Renders a table block defining this set, holding every address of `set`, suitable for
+    /// `nft -f`.
+    ///
+    /// A set without the `interval` flag may only hold single addresses, so this is `None` if it
+    /// is not an interval set but `set` holds any larger network.
+    pub fn render(&self, set: &IpSet) -> Option<String> {
+        let elements = elements(set);
+        if !self.interval && set.iter().any(|net| net.num_host_bits() != 0) {
+            return None;
+        }
+        let mut out = format!("table {} {} {{\n", self.family, self.table);
+        out += &format!("\tset {} {{\n", self.name);
+        out += "\t\ttype ipv4_addr\n";
+        if self.interval {
+            out += "\t\tflags interval\n";
+        }
+        // An empty element list is a syntax error, rather than an empty set.
+        if !elements.is_empty() {
+            out += &format!(
+                "\t\telements = {{\n\t\t\t{}\n\t\t}}\n",
+                elements.join(",\n\t\t\t")
+            );
+        }
+        out += "\t}\n}\n";
+        Some(out)
+    }
+}
+
+/// The types of ipset which may hold IPv4 addresses.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IpsetType {
+    /// Holds networks of any size, as networks.
+    HashNet,
+    /// Holds single addresses; a network is added as every address within it.
+    HashIp,
+}
+
+impl fmt::Display for IpsetType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::HashNet => "hash:net",
+            Self::HashIp => "hash:ip",
+        })
+    }
+}
+
+/// A named set, as created by `ipset`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipset {
+    name: String,
+    set_type: IpsetType,
+    flush: bool,
+}
+
+impl Ipset {
+    /// Creates a `hash:net` set with the specified name.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            set_type: IpsetType::HashNet,
+            flush: false,
+        }
+    }
+
+    /// Sets the type of the set.
+    pub fn set_type(mut self, set_type: IpsetType) -> Self {
+        self.set_type = set_type;
+        self
+    }
+
+    /// Sets whether the script empties the set before adding to it. Together with
+    /// `ipset restore -exist`, this lets the same script be loaded again to replace the set.
+    pub fn flush(mut self, flush: bool) -> Self {
+        self.flush = flush;
+        self
+    }
+
+    /// Renders a script creating this set and adding every address of `set`, suitable for
+    /// `ipset restore`.
+    ///
+    /// The set is made large enough to hold every entry, as the default limit is easily reached
+    /// by real blocklists.
+    pub fn render(&self, set: &IpSet) -> String {
+        let elements = elements(set);
+        let size = match self.set_type {
+            IpsetType::HashNet => elements.len() as u64,
+            IpsetType::HashIp => set.num_addresses(),
+        };
+        let mut out = format!("create {} {} family inet", self.name, self.set_type);
+        if size > DEFAULT_MAXELEM {
+            out += &format!(" maxelem {}", size);
+        }
+        out.push('\n');
+        if self.flush {
+            out += &format!("flush {}\n", self.name);
+        }
+        for element in elements {
+            out += &format!("add {} {}\n", self.name, element);
+        }
+        out
+    }
+}
+
+/// The number of entries an ipset holds unless told otherwise.
+const DEFAULT_MAXELEM: u64 = 65536;
+
+/// The fewest networks covering the set, with single addresses written without a prefix length.
+fn elements(set: &IpSet) -> Vec<String> {
+    set.iter()
+        .map(|net| match net.num_host_bits() {
+            0 => net.network_address().to_string(),
+            _ => net.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(nets: &[&str]) -> IpSet {
+        nets.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn nft() {
+        let blocklist = set(&["10.0.0.0/25", "10.0.0.128/25", "192.0.2.7/32"]);
+        assert_eq!(
+            Some(
+                "table inet filter {
+\tset blocklist {
+\t\ttype ipv4_addr
+\t\tflags interval
+\t\telements = {
+\t\t\t10.0.0.0/24,
+\t\t\t192.0.2.7
+\t\t}
+\t}
+}
+"
+                .to_string()
+            ),
+            NftSet::new("filter", "blocklist").render(&blocklist)
+        );
+        let hosts = NftSet::new("fw", "hosts")
+            .family(NftFamily::Ip)
+            .interval(false);
+        assert_eq!(None, hosts.render(&blocklist));
+        assert_eq!(
+            Some("table ip fw {\n\tset hosts {\n\t\ttype ipv4_addr\n\t}\n}\n".to_string()),
+            hosts.render(&IpSet::new())
+        );
+    }
+
+    #[test]
+    fn ipset() {
+        let blocklist = set(&["10.0.0.0/24", "192.0.2.7/32"]);
+        assert_eq!(
+            "create bad hash:net family inet\nadd bad 10.0.0.0/24\nadd bad 192.0.2.7\n",
+            Ipset::new("bad").render(&blocklist)
+        );
+        assert_eq!(
+            "create bad hash:ip family inet\nflush bad\nadd bad 10.0.0.0/24\nadd bad 192.0.2.7\n",
+            Ipset::new("bad")
+                .set_type(IpsetType::HashIp)
+                .flush(true)
+                .render(&blocklist)
+        );
+        let large = Ipset::new("big")
+            .set_type(IpsetType::HashIp)
+            .render(&set(&["10.0.0.0/8"]));
+        assert!(large.starts_with("create big hash:ip family inet maxelem 16777216\n"));
+    }
+}
@@ -0,0 +1,266 @@
+//! # The GeoIP Module
+//!
+//! Enriching logs with the country of each address is the most common use of a range map. This
+//! module loads the CSV range databases published by GeoIP vendors into an [`IpRangeMap`] of
+//! records.
+//!
+//! Each row gives either a network, or the first and last addresses of a range, followed by
+//! metadata such as a country code. Addresses may be written either in dotted-quad form or as
+//! decimal integers, as some vendors do. The file may or may not begin with a header row; without
+//! one, the columns are taken to be the range (or network), then the country, then anything else.
+use super::{addr::IpAddress, csv::Rows, net::IpNetwork, range::IpRange, range_map::IpRangeMap};
+use std::{collections::BTreeMap, error, fmt};
+
+/// The metadata associated with a range of addresses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GeoRecord {
+    /// The country code, if the database has a column for one.
+    pub country: Option<String>,
+    /// The values of every other column, by header; or by column number, counting from zero, if
+    /// the database has no header.
+    pub extra: BTreeMap<String, String>,
+}
+
+/// The columns which locate a row's range, or give its country.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Column {
+    Network,
+    Start,
+    End,
+    Country,
+}
+
+impl Column {
+    fn from_header(header: &str) -> Option<Self> {
+        match header.trim().to_ascii_lowercase().as_str() {
+            "network" | "cidr" | "prefix" => Some(Self::Network),
+            "start" | "start_ip" | "ip_start" | "ip_from" | "range_start" | "first"
+            | "network_start_ip" => Some(Self::Start),
+            "end" | "end_ip" | "ip_end" | "ip_to" | "range_end" | "last" | "network_last_ip" => {
+                Some(Self::End)
+            }
+            "country" | "country_code" | "country_iso_code" | "countrycode" | "cc" => {
+                Some(Self::Country)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parses an address in either dotted-quad or decimal form.
+fn parse_address(s: &str) -> Option<IpAddress> {
+    s.parse()
+        .ok()
+        .or_else(|| s.parse::<u32>().ok().map(IpAddress::from))
+}
+
+/// Loads a CSV range database. Where ranges overlap, the later row wins.
+pub fn load(text: &str) -> Result<IpRangeMap<GeoRecord>, GeoIpError> {
+    let mut rows = Rows::new(text, ',').peekable();
+    let unterminated = |line| GeoIpError {
+        line,
+        kind: ErrorKind::UnterminatedQuote,
+    };
+
+    // A header is recognised by its first field not being an address or network.
+    let (columns, names, headerless) = match rows.peek() {
+        None => return Ok(IpRangeMap::new()),
+        Some(Err(line)) => return Err(unterminated(*line)),
+        Some(Ok((_, first))) => {
+            let field = first[0].trim();
+            if parse_address(field).is_some() || field.parse::<IpNetwork>().is_ok() {
+                let mut columns = if field.contains('/') {
+                    vec![Some(Column::Network)]
+                } else {
+                    vec![Some(Column::Start), Some(Column::End)]
+                };
+                columns.push(Some(Column::Country));
+                (columns, Vec::new(), true)
+            } else {
+                let (_, names) = rows.next().unwrap().unwrap();
+                let columns: Vec<_> = names.iter().map(|n| Column::from_header(n)).collect();
+                let has = |c| columns.contains(&Some(c));
+                let locatable = has(Column::Network) || (has(Column::Start) && has(Column::End));
+                if !locatable {
+                    return Err(GeoIpError {
+                        line: 1,
+                        kind: ErrorKind::MissingRangeColumns,
+                    });
+                }
+                (columns, names, false)
+            }
+        }
+    };
+
+    let mut map = IpRangeMap::new();
+    for row in rows {
+        let (line, fields) = row.map_err(unterminated)?;
+        let error = |kind| GeoIpError { line, kind };
+        if !headerless && fields.len() > columns.len() {
+            return Err(error(ErrorKind::FieldCount {
+                expected: columns.len(),
+                found: fields.len(),
+            }));
+        }
+        let (mut network, mut start, mut end) = (None, None, None);
+        let mut record = GeoRecord::default();
+        for (i, field) in fields.iter().enumerate() {
+            let field = field.trim();
+            let address = || {
+                parse_address(field).ok_or_else(|| error(ErrorKind::InvalidAddress(field.into())))
+            };
+            match columns.get(i).copied().flatten() {
+                Some(Column::Network) => {
+                    let net: IpNetwork = field
+                        .parse()
+                        .map_err(|_| error(ErrorKind::InvalidNetwork(field.into())))?;
+                    network = Some(IpRange::from(net));
+                }
+                Some(Column::Start) => start = Some(address()?),
+                Some(Column::End) => end = Some(address()?),
+                Some(Column::Country) if !field.is_empty() => record.country = Some(field.into()),
+                None if !field.is_empty() => {
+                    let name = names
+                        .get(i)
+                        .map_or_else(|| i.to_string(), |n| n.trim().into());
+                    record.extra.insert(name, field.into());
+                }
+                _ => {}
+            }
+        }
+        let range = match (network, start, end) {
+            (Some(range), ..) => range,
+            (None, Some(start), Some(end)) => IpRange::new(start, end)
+                .ok_or_else(|| error(ErrorKind::InvalidRange(format!("{}-{}", start, end))))?,
+            _ => {
+                return Err(error(ErrorKind::FieldCount {
+                    expected: columns.len(),
+                    found: fields.len(),
+                }))
+            }
+        };
+        map.insert(range, record);
+    }
+    Ok(map)
+}
+
+/// The error returned when a range database could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeoIpError {
+    line: usize,
+    kind: ErrorKind,
+}
+
+/// What was wrong with a row of a range database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The header has neither a network column, nor both a start and an end column.
+    MissingRangeColumns,
+    /// The row has more fields than the header, or too few to locate its range.
+    FieldCount { expected: usize, found: usize },
+    /// An address is neither in dotted-quad nor decimal form.
+    InvalidAddress(String),
+    /// A network is not in CIDR notation.
+    InvalidNetwork(String),
+    /// A range ends before it starts.
+    InvalidRange(String),
+    /// A quoted field is never closed.
+    UnterminatedQuote,
+}
+
+impl GeoIpError {
+    /// The line on which the offending row began, counting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// What was wrong with the row.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for GeoIpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::MissingRangeColumns => {
+                f.write_str("no network, or start and end, columns in header")
+            }
+            ErrorKind::FieldCount { expected, found } => {
+                write!(f, "expected {} fields, found {}", expected, found)
+            }
+            ErrorKind::InvalidAddress(s) => write!(f, "invalid address `{}`", s),
+            ErrorKind::InvalidNetwork(s) => write!(f, "invalid network `{}`", s),
+            ErrorKind::InvalidRange(s) => write!(f, "invalid range `{}`", s),
+            ErrorKind::UnterminatedQuote => f.write_str("unterminated quoted field"),
+        }
+    }
+}
+
+impl error::Error for GeoIpError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn country(map: &IpRangeMap<GeoRecord>, addr: &str) -> Option<String> {
+        map.lookup(addr.parse().unwrap())?.country.clone()
+    }
+
+    #[test]
+    fn headerless_ranges() {
+        let map =
+            load("1.0.0.0,1.0.0.255,AU\n\"16777472\",\"16778239\",\"CN\",\"China\"\n").unwrap();
+        assert_eq!(2, map.len());
+        assert_eq!(Some("AU".into()), country(&map, "1.0.0.7"));
+        let record = map.lookup("1.0.2.0".parse().unwrap()).unwrap();
+        assert_eq!(Some("CN".into()), record.country);
+        assert_eq!(Some("China"), record.extra.get("3").map(String::as_str));
+        assert_eq!(None, country(&map, "1.0.4.0"));
+    }
+
+    #[test]
+    fn header() {
+        let text = "\
+network,geoname_id,country_iso_code,is_anonymous_proxy
+1.0.0.0/24,2077456,AU,0
+1.0.1.0/24,1814991,,0
+";
+        let map = load(text).unwrap();
+        assert_eq!(Some("AU".into()), country(&map, "1.0.0.1"));
+        let record = map.lookup("1.0.1.1".parse().unwrap()).unwrap();
+        assert_eq!(None, record.country);
+        assert_eq!(
+            Some("1814991"),
+            record.extra.get("geoname_id").map(String::as_str)
+        );
+
+        let text = "ip_from,ip_to,country_code\n0,16777215,-\n16777216,16777471,US\n";
+        let map = load(text).unwrap();
+        assert_eq!(Some("US".into()), country(&map, "1.0.0.255"));
+        assert_eq!(Some("-".into()), country(&map, "0.1.2.3"));
+    }
+
+    #[test]
+    fn errors() {
+        let err = |text: &str| load(text).unwrap_err().to_string();
+        assert_eq!(
+            "line 1: no network, or start and end, columns in header",
+            err("start,country\n")
+        );
+        assert_eq!(
+            "line 2: invalid address `1.0.0.256`",
+            err("1.0.0.0,1.0.0.255,AU\n1.0.0.0,1.0.0.256,AU\n")
+        );
+        assert_eq!(
+            "line 1: invalid range `1.0.0.255-1.0.0.0`",
+            err("1.0.0.255,1.0.0.0,AU\n")
+        );
+        assert_eq!(
+            "line 2: expected 3 fields, found 1",
+            err("start,end,cc\n1.0.0.0\n")
+        );
+        assert_eq!(Ok(IpRangeMap::new()), load(""));
+    }
+}
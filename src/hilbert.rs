@@ -0,0 +1,210 @@
+//! # The Hilbert-Curve Module
+//!
+//! The Hilbert curve folds a line into a square while keeping points which were close on the
+//! line close in the square. Laying the address space along it gives the well-known "map of the
+//! internet", where every CIDR block is a compact square or rectangle rather than a long strip.
+//!
+//! A curve of order `n` is a grid of `2^n` by `2^n` cells, so each cell covers `2^(32 - 2n)`
+//! addresses; at order 16, every cell is a `/32`, and at order 12 every cell is a `/24`.
+use super::{addr::IpAddress, set::IpSet};
+
+/// The highest order of curve which still has no more cells than there are addresses.
+const MAX_ORDER: u8 = 16;
+
+/// Rotates (and flips) a quadrant so that the sub-curve within it is oriented correctly.
+fn rotate(side: u32, x: &mut u32, y: &mut u32, rx: u32, ry: u32) {
+    if ry == 0 {
+        if rx == 1 {
+            *x = side - 1 - *x;
+            *y = side - 1 - *y;
+        }
+        std::mem::swap(x, y);
+    }
+}
+
+/// Converts a distance along a curve with the specified side length into grid coordinates.
+fn d2xy(side: u32, d: u64) -> (u32, u32) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < side {
+        let rx = (1 & (t / 2)) as u32;
+        let ry = (1 & (t ^ u64::from(rx))) as u32;
+        rotate(s, &mut x, &mut y, rx, ry);
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+/// Converts grid coordinates into a distance along a curve with the specified side length.
+fn xy2d(side: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut d = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = u32::from(x & s > 0);
+        let ry = u32::from(y & s > 0);
+        d += u64::from(s) * u64::from(s) * u64::from((3 * rx) ^ ry);
+        rotate(side, &mut x, &mut y, rx, ry);
+        s /= 2;
+    }
+    d
+}
+
+/// Gets the coordinates of the cell containing the address, on a Hilbert curve of the specified
+/// order. The order may be at most 16.
+pub fn to_xy(addr: IpAddress, order: u8) -> Option<(u32, u32)> {
+    if order > MAX_ORDER {
+        return None;
+    }
+    let d = u64::from(addr.value()) >> (32 - 2 * u32::from(order));
+    Some(d2xy(1 << order, d))
+}
+
+/// Gets the first address within the cell at the coordinates, on a Hilbert curve of the specified
+/// order. This is the inverse of [`to_xy`].
+pub fn from_xy(order: u8, x: u32, y: u32) -> Option<IpAddress> {
+    if order > MAX_ORDER || x >> order != 0 || y >> order != 0 {
+        return None;
+    }
+    let d = xy2d(1 << order, x, y) << (32 - 2 * u32::from(order));
+    Some(IpAddress::from(d as u32))
+}
+
+/// A count of how many addresses of a set fall within each cell of a Hilbert curve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DensityGrid {
+    order: u8,
+    /// Indexed by row, and then by column.
+    counts: Vec<u64>,
+}
+
+impl DensityGrid {
+    /// Rasterizes the set onto a Hilbert curve of the specified order, which may be at most 16.
+    ///
+    /// The grid holds one count per cell, so it grows fourfold with each order; an order 12 grid
+    /// is already sixteen million cells.
+    pub fn from_set(set: &IpSet, order: u8) -> Option<Self> {
+        if order > MAX_ORDER {
+            return None;
+        }
+        let side = 1u64 << order;
+        let mut grid = Self {
+            order,
+            counts: vec![0; (side * side) as usize],
+        };
+        let cell_bits = 32 - 2 * u32::from(order);
+        for range in set.ranges() {
+            let (start, end) = (
+                u64::from(range.start().value()),
+                u64::from(range.end().value()),
+            );
+            for d in (start >> cell_bits)..=(end >> cell_bits) {
+                // Only the cells at either end of a range can be partially covered.
+                let cell_start = d << cell_bits;
+                let cell_end = cell_start + (1 << cell_bits) - 1;
+                let count = end.min(cell_end) - start.max(cell_start) + 1;
+                let (x, y) = d2xy(side as u32, d);
+                grid.counts[(u64::from(y) * side + u64::from(x)) as usize] += count;
+            }
+        }
+        Some(grid)
+    }
+
+    /// The order of the curve this grid was rasterized onto.
+    pub fn order(&self) -> u8 {
+        self.order
+    }
+
+    /// The number of cells along each side of the grid.
+    pub fn side(&self) -> u32 {
+        1 << self.order
+    }
+
+    /// The number of addresses which each cell covers.
+    pub fn addresses_per_cell(&self) -> u64 {
+        1 << (32 - 2 * u32::from(self.order))
+    }
+
+    /// The number of addresses of the set within the cell at the coordinates.
+    pub fn count(&self, x: u32, y: u32) -> Option<u64> {
+        if x >= self.side() || y >= self.side() {
+            return None;
+        }
+        Some(self.counts[(y as usize) * (self.side() as usize) + x as usize])
+    }
+
+    /// The fraction of the addresses within the cell at the coordinates which are in the set.
+    pub fn density(&self, x: u32, y: u32) -> Option<f64> {
+        self.count(x, y)
+            .map(|count| count as f64 / self.addresses_per_cell() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{net::IpNetwork, random::Rng};
+
+    #[test]
+    fn quadrants() {
+        let xy = |a: u8| to_xy(IpAddress::from([a, 0, 0, 0]), 1).unwrap();
+        assert_eq!((0, 0), xy(0));
+        assert_eq!((0, 1), xy(64));
+        assert_eq!((1, 1), xy(128));
+        assert_eq!((1, 0), xy(192));
+        assert_eq!(Some((0, 0)), to_xy(IpAddress::from(u32::MAX), 0));
+        assert_eq!(None, to_xy(IpAddress::from(0), 17));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng::seed_from_u64(5);
+        for order in 0..=16 {
+            for _ in 0..50 {
+                let addr = IpAddress::from(rng.next_u64() as u32);
+                let (x, y) = to_xy(addr, order).unwrap();
+                assert!(x < 1 << order && y < 1 << order);
+                let first = from_xy(order, x, y).unwrap();
+                let cell = IpNetwork::new(first, 2 * order).unwrap();
+                assert!(cell.contains(addr), "{} not in {}", addr, cell);
+            }
+        }
+        assert_eq!(None, from_xy(4, 16, 0));
+        assert_eq!(None, from_xy(17, 0, 0));
+    }
+
+    #[test]
+    fn adjacent_cells() {
+        // Consecutive cells along the curve are always neighbours in the grid.
+        let order = 6;
+        let mut last = to_xy(IpAddress::from(0), order).unwrap();
+        for d in 1..(1u32 << (2 * order)) {
+            let (x, y) = to_xy(IpAddress::from(d << (32 - 2 * order)), order).unwrap();
+            assert_eq!(
+                1,
+                (x as i64 - last.0 as i64).abs() + (y as i64 - last.1 as i64).abs()
+            );
+            last = (x, y);
+        }
+    }
+
+    #[test]
+    fn density() {
+        let set: IpSet = ["0.0.0.0/2", "128.0.0.0/3", "192.0.0.0/32"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let grid = DensityGrid::from_set(&set, 1).unwrap();
+        assert_eq!(2, grid.side());
+        assert_eq!(Some(1.0), grid.density(0, 0));
+        assert_eq!(Some(0.0), grid.density(0, 1));
+        assert_eq!(Some(0.5), grid.density(1, 1));
+        assert_eq!(Some(1), grid.count(1, 0));
+        assert_eq!(None, grid.count(2, 0));
+        assert_eq!(set.num_addresses(), grid.counts.iter().sum::<u64>());
+        assert!(DensityGrid::from_set(&set, 17).is_none());
+    }
+}
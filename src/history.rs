@@ -0,0 +1,163 @@
+//! # The Route-History Module
+//!
+//! A routing table only ever says what is routed now. Reconstructing an incident means asking
+//! what it said at some moment in the past, which otherwise takes replaying a full dump and every
+//! update after it. A route history keeps every announcement and withdrawal of every network,
+//! along with when it happened, and answers lookups as of any moment.
+use super::{addr::IpAddress, map::IpNetworkMap, net::IpNetwork};
+
+/// A change to the route for a network: an announcement with its value, or a withdrawal.
+type Event<T, V> = (T, Option<V>);
+
+/// A routing table which remembers every change made to it, and when.
+///
+/// Times may be of any ordered type, such as seconds since the Unix epoch, or a `SystemTime`.
+/// Changes need not be recorded in order; a change recorded at the same time as another takes
+/// effect after it.
+#[derive(Debug, Clone)]
+pub struct RouteHistory<T, V> {
+    map: IpNetworkMap<Vec<Event<T, V>>>,
+}
+
+impl<T, V> Default for RouteHistory<T, V> {
+    fn default() -> Self {
+        Self {
+            map: IpNetworkMap::new(),
+        }
+    }
+}
+
+impl<T: Ord + Copy, V> RouteHistory<T, V> {
+    /// Creates a new, empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the network was announced, with the value, at the specified time.
+    pub fn announce(&mut self, net: IpNetwork, time: T, value: V) {
+        self.record(net, time, Some(value));
+    }
+
+    /// Records that the network was withdrawn at the specified time.
+    pub fn withdraw(&mut self, net: IpNetwork, time: T) {
+        self.record(net, time, None);
+    }
+
+    fn record(&mut self, net: IpNetwork, time: T, value: Option<V>) {
+        if self.map.get(&net).is_none() {
+            self.map.insert(net, Vec::new());
+        }
+        let events = self.map.get_mut(&net).unwrap();
+        let i = events.partition_point(|&(other, _)| other <= time);
+        events.insert(i, (time, value));
+    }
+
+    /// Finds the most specific network which contained the address, and was announced, at the
+    /// specified time, along with its value at that time.
+    pub fn lookup_at(&self, addr: IpAddress, time: T) -> Option<(IpNetwork, &V)> {
+        self.map
+            .matches(addr)
+            .filter_map(|(net, events)| Some((net, value_at(events, time)?)))
+            .last()
+    }
+
+    /// Every network which was announced at the specified time, along with its value at that
+    /// time, ordered as [`IpNetworkMap::iter`] orders them.
+    pub fn routes_at(&self, time: T) -> impl Iterator<Item = (IpNetwork, &V)> + '_ {
+        self.map
+            .iter()
+            .filter_map(move |(net, events)| Some((net, value_at(events, time)?)))
+    }
+
+    /// Every change ever recorded for exactly this network, in order of time.
+    pub fn events(&self, net: &IpNetwork) -> impl Iterator<Item = (T, Option<&V>)> + '_ {
+        self.map
+            .get(net)
+            .into_iter()
+            .flatten()
+            .map(|(time, value)| (*time, value.as_ref()))
+    }
+}
+
+/// The value of the latest change at or before the time, if that change was an announcement.
+fn value_at<T: Ord + Copy, V>(events: &[Event<T, V>], time: T) -> Option<&V> {
+    let i = events.partition_point(|&(other, _)| other <= time);
+    events[..i].last()?.1.as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn lookup(history: &RouteHistory<u64, u32>, s: &str, time: u64) -> Option<(String, u32)> {
+        history
+            .lookup_at(s.parse().unwrap(), time)
+            .map(|(net, asn)| (net.to_string(), *asn))
+    }
+
+    #[test]
+    fn lookup_at() {
+        let mut history = RouteHistory::new();
+        history.announce(net("10.0.0.0/8"), 100, 64500);
+        history.announce(net("10.1.0.0/16"), 200, 64501);
+        history.withdraw(net("10.1.0.0/16"), 300);
+        history.announce(net("10.1.0.0/16"), 400, 64502);
+        // Recorded late, but still takes effect in order.
+        history.announce(net("10.1.0.0/16"), 250, 64503);
+
+        assert_eq!(None, lookup(&history, "10.1.2.3", 99));
+        assert_eq!(
+            Some(("10.0.0.0/8".into(), 64500)),
+            lookup(&history, "10.1.2.3", 100)
+        );
+        assert_eq!(
+            Some(("10.1.0.0/16".into(), 64501)),
+            lookup(&history, "10.1.2.3", 249)
+        );
+        assert_eq!(
+            Some(("10.1.0.0/16".into(), 64503)),
+            lookup(&history, "10.1.2.3", 299)
+        );
+        assert_eq!(
+            Some(("10.0.0.0/8".into(), 64500)),
+            lookup(&history, "10.1.2.3", 300)
+        );
+        assert_eq!(
+            Some(("10.1.0.0/16".into(), 64502)),
+            lookup(&history, "10.1.2.3", 400)
+        );
+        assert_eq!(None, lookup(&history, "11.0.0.0", 400));
+
+        let routes: Vec<_> = history.routes_at(350).map(|(net, _)| net).collect();
+        assert_eq!(vec![net("10.0.0.0/8")], routes);
+        assert_eq!(2, history.routes_at(u64::MAX).count());
+        let events: Vec<_> = history.events(&net("10.1.0.0/16")).collect();
+        assert_eq!(
+            vec![
+                (200, Some(&64501)),
+                (250, Some(&64503)),
+                (300, None),
+                (400, Some(&64502))
+            ],
+            events
+        );
+        assert_eq!(0, history.events(&net("10.2.0.0/16")).count());
+    }
+
+    #[test]
+    fn same_time() {
+        let mut history = RouteHistory::new();
+        history.announce(net("10.0.0.0/8"), 5, 1);
+        history.withdraw(net("10.0.0.0/8"), 5);
+        assert_eq!(None, lookup(&history, "10.0.0.1", 5));
+        history.announce(net("10.0.0.0/8"), 5, 2);
+        assert_eq!(
+            Some(("10.0.0.0/8".into(), 2)),
+            lookup(&history, "10.0.0.1", 5)
+        );
+    }
+}
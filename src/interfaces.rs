@@ -0,0 +1,302 @@
+//! # The Interfaces Module
+//!
+//! "What networks am I on?" is the first question most tools built on this crate ask of the host
+//! they run on. This module lists the host's network interfaces, along with their addresses and
+//! flags, as the crate's own types.
+//!
+//! The list comes from `getifaddrs`, so it is only available on Unix-like systems.
+//!
+//! To explain where traffic will go, the module can also predict which interface, and which
+//! source address, the host would use to reach a destination.
+use super::{addr::IpAddress, net::IpNetwork};
+use std::{
+    ffi::CStr,
+    io,
+    net::{SocketAddr, UdpSocket},
+    os::raw::{c_char, c_int, c_uint, c_void},
+    ptr,
+};
+
+/// An entry of the list returned by `getifaddrs`, which has the same layout on every platform.
+#[repr(C)]
+struct IfAddrs {
+    ifa_next: *mut IfAddrs,
+    ifa_name: *const c_char,
+    ifa_flags: c_uint,
+    ifa_addr: *const SockAddrIn,
+    ifa_netmask: *const SockAddrIn,
+    ifa_dstaddr: *const c_void,
+    ifa_data: *mut c_void,
+}
+
+/// An IPv4 socket address. The BSDs lead with its length, leaving one byte for the family.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[repr(C)]
+struct SockAddrIn {
+    sin_family: u16,
+    sin_port: u16,
+    sin_addr: [u8; 4],
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[repr(C)]
+struct SockAddrIn {
+    sin_len: u8,
+    sin_family: u8,
+    sin_port: u16,
+    sin_addr: [u8; 4],
+}
+
+impl SockAddrIn {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn family(&self) -> u16 {
+        self.sin_family
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn family(&self) -> u16 {
+        u16::from(self.sin_family)
+    }
+}
+
+const AF_INET: u16 = 2;
+
+extern "C" {
+    fn getifaddrs(ifap: *mut *mut IfAddrs) -> c_int;
+    fn freeifaddrs(ifa: *mut IfAddrs);
+}
+
+/// The state of an interface, as reported by the operating system.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct InterfaceFlags(u32);
+
+impl InterfaceFlags {
+    const UP: u32 = 0x1;
+    const BROADCAST: u32 = 0x2;
+    const LOOPBACK: u32 = 0x8;
+    const POINT_TO_POINT: u32 = 0x10;
+    const RUNNING: u32 = 0x40;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const MULTICAST: u32 = 0x1000;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const MULTICAST: u32 = 0x8000;
+
+    /// The flags exactly as the operating system gave them.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Whether the interface has been brought up.
+    pub fn is_up(&self) -> bool {
+        self.0 & Self::UP != 0
+    }
+
+    /// Whether the interface is ready to carry traffic.
+    pub fn is_running(&self) -> bool {
+        self.0 & Self::RUNNING != 0
+    }
+
+    /// Whether the interface is a loopback, which only reaches the host itself.
+    pub fn is_loopback(&self) -> bool {
+        self.0 & Self::LOOPBACK != 0
+    }
+
+    /// Whether the interface supports broadcast.
+    pub fn is_broadcast(&self) -> bool {
+        self.0 & Self::BROADCAST != 0
+    }
+
+    /// Whether the interface is a link to a single peer, such as a tunnel.
+    pub fn is_point_to_point(&self) -> bool {
+        self.0 & Self::POINT_TO_POINT != 0
+    }
+
+    /// Whether the interface supports multicast.
+    pub fn is_multicast(&self) -> bool {
+        self.0 & Self::MULTICAST != 0
+    }
+}
+
+/// An address assigned to an interface, along with the length of the prefix it was assigned
+/// with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InterfaceAddress {
+    pub addr: IpAddress,
+    pub prefix_len: u8,
+}
+
+impl InterfaceAddress {
+    /// The network which the address places the interface on.
+    pub fn network(&self) -> IpNetwork {
+        let net = IpNetwork::new(self.addr, self.prefix_len).unwrap();
+        IpNetwork::new(net.network_address(), self.prefix_len).unwrap()
+    }
+}
+
+/// A network interface of the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    name: String,
+    flags: InterfaceFlags,
+    addresses: Vec<InterfaceAddress>,
+}
+
+impl Interface {
+    /// The name of the interface, such as `eth0`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The state of the interface.
+    pub fn flags(&self) -> InterfaceFlags {
+        self.flags
+    }
+
+    /// The addresses assigned to the interface, which may be none at all.
+    pub fn addresses(&self) -> &[InterfaceAddress] {
+        &self.addresses
+    }
+
+    /// The networks which the interface is on.
+    pub fn networks(&self) -> impl Iterator<Item = IpNetwork> + '_ {
+        self.addresses.iter().map(InterfaceAddress::network)
+    }
+}
+
+/// Lists the host's network interfaces, in the order the operating system gives them.
+pub fn interfaces() -> io::Result<Vec<Interface>> {
+    let mut list = ptr::null_mut();
+    if unsafe { getifaddrs(&mut list) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut entry = list;
+    // Safety: every entry, and everything it points to, lives until the list is freed.
+    while let Some(ifa) = unsafe { entry.as_ref() } {
+        entry = ifa.ifa_next;
+        let name = unsafe { CStr::from_ptr(ifa.ifa_name) }
+            .to_string_lossy()
+            .into_owned();
+        // An interface has an entry for every address of every family, so its entries are
+        // gathered under its first.
+        let i = match interfaces.iter().position(|i| i.name == name) {
+            Some(i) => i,
+            None => {
+                interfaces.push(Interface {
+                    name,
+                    flags: InterfaceFlags(ifa.ifa_flags),
+                    addresses: Vec::new(),
+                });
+                interfaces.len() - 1
+            }
+        };
+        let (addr, netmask) = unsafe { (ifa.ifa_addr.as_ref(), ifa.ifa_netmask.as_ref()) };
+        if let Some(addr) = addr.filter(|addr| addr.family() == AF_INET) {
+            let prefix_len = netmask.map_or(32, |mask| {
+                u32::from_be_bytes(mask.sin_addr).leading_ones() as u8
+            });
+            interfaces[i].addresses.push(InterfaceAddress {
+                addr: IpAddress::from(addr.sin_addr),
+                prefix_len,
+            });
+        }
+    }
+    unsafe { freeifaddrs(list) };
+    Ok(interfaces)
+}
+
+/// Predicts the source address the host would use to reach the destination.
+///
+/// This asks the operating system's own routing table, by connecting a UDP socket to the
+/// destination; connecting sends nothing, but picks a route. It is an error for there to be no
+/// route at all.
+pub fn source_address_for(dest: IpAddress) -> io::Result<IpAddress> {
+    let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], 0)))?;
+    socket.connect(SocketAddr::from((dest.octets(), 9)))?;
+    match socket.local_addr()? {
+        SocketAddr::V4(local) => Ok(IpAddress::from(local.ip().octets())),
+        SocketAddr::V6(_) => unreachable!("the socket is bound to an IPv4 address"),
+    }
+}
+
+/// Predicts the interface the host would use to reach the destination.
+///
+/// The interface is the one holding the source address the routing table picks. Failing that,
+/// such as when there is no default route, it is the interface on the most specific network
+/// containing the destination, if any.
+pub fn which_interface_routes(dest: IpAddress) -> io::Result<Option<Interface>> {
+    let interfaces = interfaces()?;
+    let routed = source_address_for(dest).ok().and_then(|source| {
+        interfaces
+            .iter()
+            .find(|i| i.addresses.iter().any(|a| a.addr == source))
+    });
+    Ok(routed.or_else(|| on_link(&interfaces, dest)).cloned())
+}
+
+/// Finds the interface on the most specific network containing the destination.
+fn on_link(interfaces: &[Interface], dest: IpAddress) -> Option<&Interface> {
+    interfaces
+        .iter()
+        .flat_map(|i| i.addresses.iter().map(move |a| (i, a)))
+        .filter(|(_, a)| a.network().contains(dest))
+        .max_by_key(|(_, a)| a.prefix_len)
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network() {
+        let addr = InterfaceAddress {
+            addr: "192.168.1.17".parse().unwrap(),
+            prefix_len: 24,
+        };
+        assert_eq!("192.168.1.0/24", addr.network().to_string());
+    }
+
+    #[test]
+    fn loopback() {
+        let interfaces = interfaces().unwrap();
+        let mut names: Vec<&str> = interfaces.iter().map(Interface::name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(interfaces.len(), names.len());
+        // Every host has a loopback, though a sandbox may not have given it an address.
+        let lo = interfaces.iter().find(|i| i.flags().is_loopback()).unwrap();
+        for addr in lo.addresses() {
+            assert!(addr.addr.is_loopback());
+        }
+    }
+
+    #[test]
+    fn routes() {
+        let interface = |name: &str, addrs: &[(&str, u8)]| Interface {
+            name: name.to_string(),
+            flags: InterfaceFlags(InterfaceFlags::UP),
+            addresses: addrs
+                .iter()
+                .map(|&(addr, prefix_len)| InterfaceAddress {
+                    addr: addr.parse().unwrap(),
+                    prefix_len,
+                })
+                .collect(),
+        };
+        let interfaces = vec![
+            interface("eth0", &[("10.0.0.5", 8)]),
+            interface("eth1", &[("10.1.2.3", 16), ("192.168.0.1", 24)]),
+            interface("eth2", &[]),
+        ];
+        let on_link = |dest: &str| on_link(&interfaces, dest.parse().unwrap()).map(|i| i.name());
+        assert_eq!(Some("eth0"), on_link("10.2.0.0"));
+        assert_eq!(Some("eth1"), on_link("10.1.0.0"));
+        assert_eq!(Some("eth1"), on_link("192.168.0.200"));
+        assert_eq!(None, on_link("8.8.8.8"));
+
+        let loopback: IpAddress = "127.0.0.1".parse().unwrap();
+        assert_eq!(loopback, source_address_for(loopback).unwrap());
+    }
+}
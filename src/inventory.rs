@@ -0,0 +1,379 @@
+//! # The Inventory Module
+//!
+//! Spreadsheets are the de facto address-management tool of many teams. This module reads such
+//! tables, exported as comma- or tab-separated values, into typed records; and writes them back
+//! out again. Besides the well-known columns (network, description, VLAN, and site), any other
+//! columns are kept as key/value pairs so that nothing is lost in a round trip.
+use super::{
+    csv::{quote, Rows},
+    net::IpNetwork,
+};
+use std::{collections::BTreeMap, error, fmt};
+
+/// The character which separates the fields of a row.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    fn as_char(self) -> char {
+        match self {
+            Self::Comma => ',',
+            Self::Tab => '\t',
+        }
+    }
+}
+
+/// A single row of an inventory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryRecord {
+    pub network: IpNetwork,
+    pub description: Option<String>,
+    pub vlan: Option<u16>,
+    pub site: Option<String>,
+    /// The values of any columns which are not otherwise understood, by header.
+    pub extra: BTreeMap<String, String>,
+}
+
+impl InventoryRecord {
+    /// Creates a record for the network, with every other field empty.
+    pub fn new(network: IpNetwork) -> Self {
+        Self {
+            network,
+            description: None,
+            vlan: None,
+            site: None,
+            extra: BTreeMap::new(),
+        }
+    }
+}
+
+/// The well-known columns, and the headers under which each may be found.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Column {
+    Network,
+    Description,
+    Vlan,
+    Site,
+}
+
+impl Column {
+    fn from_header(header: &str) -> Option<Self> {
+        match header.trim().to_ascii_lowercase().as_str() {
+            "network" | "cidr" | "prefix" | "subnet" => Some(Self::Network),
+            "description" | "desc" | "name" => Some(Self::Description),
+            "vlan" | "vlan_id" | "vlan id" => Some(Self::Vlan),
+            "site" | "location" => Some(Self::Site),
+            _ => None,
+        }
+    }
+}
+
+/// A table of network records, along with the names of its extra columns in their original
+/// order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inventory {
+    extra_columns: Vec<String>,
+    records: Vec<InventoryRecord>,
+}
+
+impl Inventory {
+    /// Creates an empty inventory, with no extra columns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a table, failing on the first row which is not valid.
+    pub fn parse(text: &str, delimiter: Delimiter) -> Result<Self, InventoryError> {
+        let (inventory, mut errors) = Self::parse_lossy(text, delimiter);
+        if errors.is_empty() {
+            Ok(inventory)
+        } else {
+            Err(errors.swap_remove(0))
+        }
+    }
+
+    /// Parses a table, skipping any rows which are not valid and reporting each of them.
+    ///
+    /// If the header itself is not valid, then the inventory is empty and that is the only error.
+    pub fn parse_lossy(text: &str, delimiter: Delimiter) -> (Self, Vec<InventoryError>) {
+        let mut inventory = Self::new();
+        let mut errors = Vec::new();
+        let mut rows = Rows::new(text, delimiter.as_char());
+
+        let unterminated = |line| InventoryError {
+            line,
+            kind: ErrorKind::UnterminatedQuote,
+        };
+        let header = match rows.next() {
+            Some(Ok((_, header))) => header,
+            Some(Err(line)) => return (inventory, vec![unterminated(line)]),
+            None => return (inventory, errors),
+        };
+        let mut columns = Vec::with_capacity(header.len());
+        for name in &header {
+            let column = Column::from_header(name);
+            if column.is_some() && columns.contains(&column) {
+                let kind = ErrorKind::DuplicateColumn(name.clone());
+                return (inventory, vec![InventoryError { line: 1, kind }]);
+            }
+            if column.is_none() {
+                inventory.extra_columns.push(name.trim().to_string());
+            }
+            columns.push(column);
+        }
+        let network_index = match columns.iter().position(|&c| c == Some(Column::Network)) {
+            Some(index) => index,
+            None => {
+                let kind = ErrorKind::MissingNetworkColumn;
+                return (inventory, vec![InventoryError { line: 1, kind }]);
+            }
+        };
+
+        for row in rows {
+            let result = row.map_err(unterminated).and_then(|(line, fields)| {
+                let error = |kind| InventoryError { line, kind };
+                if fields.len() != columns.len() {
+                    return Err(error(ErrorKind::FieldCount {
+                        expected: columns.len(),
+                        found: fields.len(),
+                    }));
+                }
+                let network = fields[network_index].trim();
+                let network = network
+                    .parse()
+                    .map_err(|_| error(ErrorKind::InvalidNetwork(network.to_string())))?;
+                let mut record = InventoryRecord::new(network);
+                for ((column, name), field) in columns.iter().zip(&header).zip(fields) {
+                    let value = Some(field.trim().to_string()).filter(|v| !v.is_empty());
+                    match column {
+                        Some(Column::Network) => {}
+                        Some(Column::Description) => record.description = value,
+                        Some(Column::Vlan) => {
+                            record.vlan = value
+                                .map(|v| v.parse().map_err(|_| error(ErrorKind::InvalidVlan(v))))
+                                .transpose()?;
+                        }
+                        Some(Column::Site) => record.site = value,
+                        None => {
+                            if let Some(value) = value {
+                                record.extra.insert(name.trim().to_string(), value);
+                            }
+                        }
+                    }
+                }
+                Ok(record)
+            });
+            match result {
+                Ok(record) => inventory.records.push(record),
+                Err(err) => errors.push(err),
+            }
+        }
+        (inventory, errors)
+    }
+
+    /// Appends a record. Any of its extra fields which are not yet columns become new columns.
+    pub fn push(&mut self, record: InventoryRecord) {
+        for key in record.extra.keys() {
+            if !self.extra_columns.contains(key) {
+                self.extra_columns.push(key.clone());
+            }
+        }
+        self.records.push(record);
+    }
+
+    /// The records, in the order they were read or added.
+    pub fn records(&self) -> &[InventoryRecord] {
+        &self.records
+    }
+
+    /// The names of the columns beyond the well-known ones.
+    pub fn extra_columns(&self) -> &[String] {
+        &self.extra_columns
+    }
+
+    /// Writes the table, with a header row. The well-known columns always come first, followed
+    /// by any extra columns.
+    pub fn to_delimited(&self, delimiter: Delimiter) -> String {
+        let delimiter = delimiter.as_char();
+        let mut out = String::new();
+        let mut write_row = |fields: Vec<&str>| {
+            let fields: Vec<String> = fields.iter().map(|f| quote(f, delimiter)).collect();
+            out.push_str(&fields.join(&delimiter.to_string()));
+            out.push('\n');
+        };
+
+        let mut header = vec!["network", "description", "vlan", "site"];
+        header.extend(self.extra_columns.iter().map(String::as_str));
+        write_row(header);
+        for record in &self.records {
+            let network = record.network.to_string();
+            let vlan = record.vlan.map(|v| v.to_string()).unwrap_or_default();
+            let mut fields = vec![
+                network.as_str(),
+                record.description.as_deref().unwrap_or_default(),
+                vlan.as_str(),
+                record.site.as_deref().unwrap_or_default(),
+            ];
+            fields.extend(self.extra_columns.iter().map(|column| {
+                record
+                    .extra
+                    .get(column)
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            }));
+            write_row(fields);
+        }
+        out
+    }
+}
+
+/// The error returned when a row of an inventory could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryError {
+    line: usize,
+    kind: ErrorKind,
+}
+
+/// What was wrong with a row of an inventory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The header has no column for the network.
+    MissingNetworkColumn,
+    /// The header has more than one column for the same field.
+    DuplicateColumn(String),
+    /// The row has a different number of fields than the header.
+    FieldCount { expected: usize, found: usize },
+    /// The network field is missing, or is not in CIDR notation.
+    InvalidNetwork(String),
+    /// The VLAN field is not a number from 0 to 65535.
+    InvalidVlan(String),
+    /// A quoted field is never closed.
+    UnterminatedQuote,
+}
+
+impl InventoryError {
+    /// The line on which the offending row began, counting the header as line 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// What was wrong with the row.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::MissingNetworkColumn => f.write_str("no network column in header"),
+            ErrorKind::DuplicateColumn(name) => write!(f, "duplicate column `{}`", name),
+            ErrorKind::FieldCount { expected, found } => {
+                write!(f, "expected {} fields, found {}", expected, found)
+            }
+            ErrorKind::InvalidNetwork(s) => write!(f, "invalid network `{}`", s),
+            ErrorKind::InvalidVlan(s) => write!(f, "invalid VLAN `{}`", s),
+            ErrorKind::UnterminatedQuote => f.write_str("unterminated quoted field"),
+        }
+    }
+}
+
+impl error::Error for InventoryError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "\
+Subnet,Description,VLAN,Site,Owner
+10.0.0.0/24,\"Servers, rack 1\",100,ams1,ops
+10.0.1.0/24,,,ams1,
+
+10.0.2.0/24,\"Multi
+line\",200,,\"said \"\"hi\"\"\"
+";
+
+    #[test]
+    fn parse() {
+        let inventory = Inventory::parse(CSV, Delimiter::Comma).unwrap();
+        assert_eq!(&["Owner".to_string()], inventory.extra_columns());
+        let records = inventory.records();
+        assert_eq!(3, records.len());
+        assert_eq!("10.0.0.0/24", records[0].network.to_string());
+        assert_eq!(Some("Servers, rack 1"), records[0].description.as_deref());
+        assert_eq!(Some(100), records[0].vlan);
+        assert_eq!(
+            Some("ops"),
+            records[0].extra.get("Owner").map(String::as_str)
+        );
+        assert_eq!(None, records[1].description);
+        assert_eq!(None, records[1].vlan);
+        assert!(records[1].extra.is_empty());
+        assert_eq!(Some("Multi\nline"), records[2].description.as_deref());
+        assert_eq!(
+            Some("said \"hi\""),
+            records[2].extra.get("Owner").map(String::as_str)
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let inventory = Inventory::parse(CSV, Delimiter::Comma).unwrap();
+        let csv = inventory.to_delimited(Delimiter::Comma);
+        assert!(csv.starts_with("network,description,vlan,site,Owner\n"));
+        assert_eq!(inventory, Inventory::parse(&csv, Delimiter::Comma).unwrap());
+
+        let tsv = inventory.to_delimited(Delimiter::Tab);
+        assert!(tsv.contains("10.0.0.0/24\tServers, rack 1\t100\tams1\tops\n"));
+        assert_eq!(inventory, Inventory::parse(&tsv, Delimiter::Tab).unwrap());
+    }
+
+    #[test]
+    fn row_errors() {
+        let text =
+            "network,vlan\n10.0.0.0/24,1\n10.0.0.0,2\n10.0.1.0/24,x\n10.0.2.0/24\n10.0.3.0/24,3\n";
+        let (inventory, errors) = Inventory::parse_lossy(text, Delimiter::Comma);
+        assert_eq!(2, inventory.records().len());
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "line 3: invalid network `10.0.0.0`",
+                "line 4: invalid VLAN `x`",
+                "line 5: expected 2 fields, found 1",
+            ],
+            errors
+        );
+        assert_eq!(
+            3,
+            Inventory::parse(text, Delimiter::Comma).unwrap_err().line()
+        );
+    }
+
+    #[test]
+    fn header_errors() {
+        let (inventory, errors) = Inventory::parse_lossy("site,vlan\nams1,1\n", Delimiter::Comma);
+        assert!(inventory.records().is_empty());
+        assert_eq!(&ErrorKind::MissingNetworkColumn, errors[0].kind());
+        let err = Inventory::parse("cidr,prefix\n", Delimiter::Comma).unwrap_err();
+        assert_eq!(&ErrorKind::DuplicateColumn("prefix".into()), err.kind());
+        let err = Inventory::parse("network\n\"10.0.0.0/8\n", Delimiter::Comma).unwrap_err();
+        assert_eq!((2, &ErrorKind::UnterminatedQuote), (err.line(), err.kind()));
+        assert_eq!(Ok(Inventory::new()), Inventory::parse("", Delimiter::Tab));
+    }
+
+    #[test]
+    fn push() {
+        let mut inventory = Inventory::new();
+        let mut record = InventoryRecord::new("192.0.2.0/24".parse().unwrap());
+        record.extra.insert("owner".into(), "net".into());
+        inventory.push(record);
+        assert_eq!(
+            "network,description,vlan,site,owner\n192.0.2.0/24,,,,net\n",
+            inventory.to_delimited(Delimiter::Comma)
+        );
+    }
+}
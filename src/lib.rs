@@ -1,2 +1,80 @@
-mod addr;
-mod net;
+pub mod addr;
+pub mod addr6;
+pub mod any;
+pub mod asn;
+mod bits;
+pub mod blocklist;
+pub mod bloom;
+pub mod bucket;
+pub mod cache;
+pub mod counter;
+mod csv;
+pub mod dns;
+pub mod dualstack;
+pub mod ecs;
+pub mod error;
+pub mod explain;
+pub mod feed;
+pub mod filter;
+pub mod firewall;
+pub mod geoip;
+pub mod hilbert;
+pub mod history;
+#[cfg(all(feature = "interfaces", unix))]
+pub mod interfaces;
+pub mod inventory;
+pub mod mac;
+pub mod map;
+pub mod map6;
+pub mod matcher;
+pub mod merge;
+#[cfg(feature = "mrt")]
+pub mod mrt;
+pub mod multicast6;
+pub mod nat;
+pub mod net;
+pub mod net6;
+pub mod options;
+#[cfg(feature = "packet")]
+pub mod packet;
+pub mod pattern;
+pub mod persistent;
+pub mod plan;
+pub mod pool;
+pub mod prefix_list;
+pub mod random;
+pub mod range;
+pub mod range_map;
+#[cfg(feature = "rdap")]
+pub mod rdap;
+#[cfg(feature = "resolve")]
+pub mod resolve;
+pub mod set;
+pub mod set6;
+pub mod shortcode;
+pub mod sixrd;
+pub mod snapshot;
+pub mod socket;
+pub mod special6;
+#[cfg(feature = "sweep")]
+pub mod sweep;
+pub mod targets;
+pub mod tree;
+pub mod vpc;
+pub mod wireguard;
+
+pub use addr::IpAddress;
+pub use addr6::Ipv6Address;
+pub use error::Error;
+pub use filter::IpFilter;
+pub use mac::MacAddress;
+pub use map::IpNetworkMap;
+pub use map6::Ipv6NetworkMap;
+pub use nat::NatMap;
+pub use net::IpNetwork;
+pub use net6::Ipv6Network;
+pub use range::IpRange;
+pub use range_map::IpRangeMap;
+pub use set::IpSet;
+pub use set6::Ipv6Set;
+pub use socket::SocketAddress;
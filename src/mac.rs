@@ -0,0 +1,137 @@
+//! # The MAC-Address Module
+//!
+//! A MAC Address identifies a network interface on its local link, as six bytes written in hex,
+//! such as `00:1a:2b:3c:4d:5e`. Stateless address autoconfiguration (SLAAC) builds an IPv6
+//! interface ID out of one, in the modified EUI-64 format, which is why a MAC can often be read
+//! back out of an IPv6 address.
+use std::{error, fmt, str};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddress([u8; 6]);
+
+impl From<[u8; 6]> for MacAddress {
+    fn from(octets: [u8; 6]) -> Self {
+        Self(octets)
+    }
+}
+
+impl From<MacAddress> for [u8; 6] {
+    fn from(mac: MacAddress) -> Self {
+        mac.0
+    }
+}
+
+impl MacAddress {
+    /// Gets an array of bytes representing this address.
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+
+    /// Whether this address is administered locally, rather than assigned by the manufacturer.
+    pub fn is_local(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+
+    /// Whether this address identifies a group of interfaces, rather than a single interface.
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    /// Builds the modified EUI-64 interface ID of this address, as SLAAC does: `ff:fe` is
+    /// inserted in the middle, and the universal/local bit is flipped.
+    pub fn modified_eui64(&self) -> u64 {
+        let [a, b, c, d, e, f] = self.0;
+        u64::from_be_bytes([a ^ 0x02, b, c, 0xff, 0xfe, d, e, f])
+    }
+
+    /// Recovers the address a modified EUI-64 interface ID was built from, if it was built from
+    /// one at all.
+    pub fn from_modified_eui64(interface_id: u64) -> Option<Self> {
+        match interface_id.to_be_bytes() {
+            [a, b, c, 0xff, 0xfe, d, e, f] => Some(Self([a ^ 0x02, b, c, d, e, f])),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned when a string could not be parsed as a MAC Address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacParseError(pub(crate) ());
+
+impl fmt::Display for MacParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid MAC address syntax")
+    }
+}
+
+impl error::Error for MacParseError {}
+
+impl str::FromStr for MacAddress {
+    type Err = MacParseError;
+
+    /// Parses an address from six pairs of hex digits separated by colons or by hyphens, such as
+    /// `00:1a:2b:3c:4d:5e` or `00-1A-2B-3C-4D-5E`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separator = if s.contains('-') { '-' } else { ':' };
+        let mut octets = [0u8; 6];
+        let mut parts = s.split(separator);
+        for octet in octets.iter_mut() {
+            let part = parts.next().ok_or(MacParseError(()))?;
+            if part.len() != 2 || !part.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(MacParseError(()));
+            }
+            *octet = u8::from_str_radix(part, 16).map_err(|_| MacParseError(()))?;
+        }
+        if parts.next().is_some() {
+            return Err(MacParseError(()));
+        }
+        Ok(Self(octets))
+    }
+}
+
+/// Formats the address as lowercase hex pairs separated by colons, as in `00:1a:2b:3c:4d:5e`.
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            a, b, c, d, e, g
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let mac: MacAddress = "00:1A:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e], mac.octets());
+        assert_eq!("00:1a:2b:3c:4d:5e", mac.to_string());
+        assert_eq!(mac, "00-1a-2b-3c-4d-5e".parse().unwrap());
+        for bad in &[
+            "",
+            "00:1a:2b:3c:4d",
+            "00:1a:2b:3c:4d:5e:6f",
+            "00:1a:2b-3c:4d:5e",
+            "0:1a:2b:3c:4d:5e",
+            "00:1a:2b:3c:4d:5g",
+        ] {
+            assert!(bad.parse::<MacAddress>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn eui64() {
+        let mac = MacAddress::from([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]);
+        assert!(!mac.is_local() && !mac.is_multicast());
+        assert_eq!(0x021a_2bff_fe3c_4d5e, mac.modified_eui64());
+        assert_eq!(
+            Some(mac),
+            MacAddress::from_modified_eui64(mac.modified_eui64())
+        );
+        assert_eq!(None, MacAddress::from_modified_eui64(0x021a_2b00_003c_4d5e));
+    }
+}
@@ -0,0 +1,456 @@
+//! # The IP-Network-Map Module
+//!
+//! An IP Network Map associates values with networks, and answers the question every router has
+//! to: of all the networks which contain some address, which is the most specific? The map is a
+//! binary trie, where each level down the tree fixes one more bit of the prefix.
+use super::{addr::IpAddress, bits::AddressBits, net::IpNetwork};
+use std::iter::FromIterator;
+
+#[derive(Debug, Clone)]
+pub struct IpNetworkMap<V> {
+    trie: Trie<IpNetwork, V>,
+}
+
+/// A network which can key a [`Trie`].
+pub(crate) trait TrieKey: Copy {
+    type Bits: AddressBits;
+
+    /// The network address, as an integer.
+    fn bits(&self) -> Self::Bits;
+
+    fn prefix_len(&self) -> u8;
+
+    /// The same network, without any host bits, as only the prefix is meaningful for a key.
+    fn canonical(self) -> Self;
+}
+
+impl TrieKey for IpNetwork {
+    type Bits = u32;
+
+    fn bits(&self) -> u32 {
+        self.network_address().value()
+    }
+
+    fn prefix_len(&self) -> u8 {
+        self.num_network_bits()
+    }
+
+    fn canonical(self) -> Self {
+        IpNetwork::new(self.network_address(), self.num_network_bits()).unwrap()
+    }
+}
+
+/// The binary trie behind the network maps of both families, where each level down the tree
+/// fixes one more bit of the prefix.
+#[derive(Debug, Clone)]
+pub(crate) struct Trie<K, V> {
+    /// The nodes of the trie; the root is always the first. Nodes are never deallocated on
+    /// removal, only emptied, so indices remain stable.
+    nodes: Vec<Node<K, V>>,
+    len: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node<K, V> {
+    children: [Option<usize>; 2],
+    entry: Option<(K, V)>,
+}
+
+impl<K, V> Node<K, V> {
+    fn new() -> Self {
+        Self {
+            children: [None, None],
+            entry: None,
+        }
+    }
+}
+
+/// Gets the bit of the address at the specified depth of the trie, counting from the most
+/// significant bit.
+fn bit<B: AddressBits>(addr: B, depth: u8) -> usize {
+    usize::from(addr.bit(depth))
+}
+
+impl<K: TrieKey, V> Trie<K, V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            nodes: vec![Node::new()],
+            len: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Finds the index of the node for the network, if the path to it exists.
+    fn find(&self, net: K) -> Option<usize> {
+        let addr = net.bits();
+        (0..net.prefix_len()).try_fold(0, |node, depth| self.nodes[node].children[bit(addr, depth)])
+    }
+
+    pub(crate) fn insert(&mut self, net: K, value: V) -> Option<V> {
+        let net = net.canonical();
+        let addr = net.bits();
+        let mut node = 0;
+        for depth in 0..net.prefix_len() {
+            let b = bit(addr, depth);
+            node = match self.nodes[node].children[b] {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(Node::new());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children[b] = Some(child);
+                    child
+                }
+            };
+        }
+        let old = self.nodes[node].entry.replace((net, value));
+        if old.is_none() {
+            self.len += 1;
+        }
+        old.map(|(_, value)| value)
+    }
+
+    pub(crate) fn remove(&mut self, net: K) -> Option<V> {
+        let node = self.find(net)?;
+        let (_, value) = self.nodes[node].entry.take()?;
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub(crate) fn get(&self, net: K) -> Option<&V> {
+        let node = self.find(net)?;
+        self.nodes[node].entry.as_ref().map(|(_, value)| value)
+    }
+
+    pub(crate) fn get_mut(&mut self, net: K) -> Option<&mut V> {
+        let node = self.find(net)?;
+        self.nodes[node].entry.as_mut().map(|(_, value)| value)
+    }
+
+    /// Finds every network which contains the address, from the least to the most specific.
+    pub(crate) fn matches(&self, addr: K::Bits) -> impl Iterator<Item = (K, &V)> + '_ {
+        let mut node = Some(0);
+        let mut depth = 0;
+        std::iter::from_fn(move || {
+            while let Some(current) = node {
+                node = if depth < K::Bits::BITS {
+                    self.nodes[current].children[bit(addr, depth)]
+                } else {
+                    None
+                };
+                depth += 1;
+                if let Some((net, value)) = &self.nodes[current].entry {
+                    return Some((*net, value));
+                }
+            }
+            None
+        })
+    }
+
+    /// Iterates over every network, ordered by address and then by prefix length.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (K, &V)> + '_ {
+        let mut stack = vec![0];
+        std::iter::from_fn(move || {
+            while let Some(current) = stack.pop() {
+                let node = &self.nodes[current];
+                stack.extend(node.children.iter().rev().flatten());
+                if let Some((net, value)) = &node.entry {
+                    return Some((*net, value));
+                }
+            }
+            None
+        })
+    }
+}
+
+impl<V> Default for IpNetworkMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> IpNetworkMap<V> {
+    /// Creates a new, empty map.
+    pub fn new() -> Self {
+        Self { trie: Trie::new() }
+    }
+
+    /// The number of networks within this map.
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    /// Whether this map contains no networks at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Associates the value with the network, returning the value previously associated with it.
+    ///
+    /// The host bits of the network are ignored, so `10.1.2.3/8` and `10.0.0.0/8` are the same
+    /// key.
+    pub fn insert(&mut self, net: IpNetwork, value: V) -> Option<V> {
+        self.trie.insert(net, value)
+    }
+
+    /// Removes the network from this map, returning the value that was associated with it.
+    pub fn remove(&mut self, net: &IpNetwork) -> Option<V> {
+        self.trie.remove(*net)
+    }
+
+    /// Gets the value associated with exactly this network.
+    pub fn get(&self, net: &IpNetwork) -> Option<&V> {
+        self.trie.get(*net)
+    }
+
+    /// Gets a mutable reference to the value associated with exactly this network.
+    pub fn get_mut(&mut self, net: &IpNetwork) -> Option<&mut V> {
+        self.trie.get_mut(*net)
+    }
+
+    /// Finds the most specific network which contains the address, along with its value.
+    pub fn longest_match(&self, addr: IpAddress) -> Option<(IpNetwork, &V)> {
+        self.matches(addr).last()
+    }
+
+    /// Finds every network which contains the address, from the least to the most specific.
+    pub fn matches(&self, addr: IpAddress) -> impl Iterator<Item = (IpNetwork, &V)> + '_ {
+        self.trie.matches(addr.value())
+    }
+
+    /// Iterates over every network and its value, ordered by address and then by prefix length,
+    /// so that every network is immediately followed by the networks it contains.
+    pub fn iter(&self) -> impl Iterator<Item = (IpNetwork, &V)> + '_ {
+        self.trie.iter()
+    }
+}
+
+impl<V> FromIterator<(IpNetwork, V)> for IpNetworkMap<V> {
+    fn from_iter<I: IntoIterator<Item = (IpNetwork, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<V> Extend<(IpNetwork, V)> for IpNetworkMap<V> {
+    fn extend<I: IntoIterator<Item = (IpNetwork, V)>>(&mut self, iter: I) {
+        for (net, value) in iter {
+            self.insert(net, value);
+        }
+    }
+}
+
+/// Combines networks and their values into as few as possible, without changing which value the
+/// longest match of any address finds. Where a network is given more than once, the last value
+/// wins, just as for [`IpNetworkMap::insert`].
+///
+/// Sibling networks are merged into their supernet only where their values are equal, a network
+/// is dropped where its value is the same as that of the network containing it, and a network is
+/// dropped where its two halves both have values of their own. The result is sorted as
+/// [`IpNetworkMap::iter`] sorts it.
+pub fn aggregate_values<V, I>(pairs: I) -> Vec<(IpNetwork, V)>
+where
+    V: Eq,
+    I: IntoIterator<Item = (IpNetwork, V)>,
+{
+    let mut map: IpNetworkMap<V> = pairs.into_iter().collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        // Merging from the longest prefixes up lets each merged pair merge again straight away.
+        for prefix_len in (1..=32).rev() {
+            let lower_halves: Vec<_> = map
+                .iter()
+                .map(|(net, _)| net)
+                .filter(|net| net.num_network_bits() == prefix_len)
+                .filter(|net| !net.network_address().value().bit(prefix_len - 1))
+                .collect();
+            for lower in lower_halves {
+                let parent = lower.supernet().unwrap();
+                let (upper, _) = parent.subnets().unwrap();
+                let equal = match (map.get(&lower), map.get(&upper)) {
+                    (Some(a), Some(b)) => a == b,
+                    _ => continue,
+                };
+                if equal {
+                    map.remove(&upper);
+                    let value = map.remove(&lower).unwrap();
+                    map.insert(parent, value);
+                } else if map.remove(&parent).is_none() {
+                    continue;
+                }
+                changed = true;
+            }
+        }
+        let redundant: Vec<_> = map
+            .iter()
+            .filter(|&(net, value)| {
+                let cover = map
+                    .matches(net.network_address())
+                    .take_while(|(other, _)| other.num_network_bits() < net.num_network_bits())
+                    .last();
+                cover.is_some_and(|(_, other)| other == value)
+            })
+            .map(|(net, _)| net)
+            .collect();
+        for net in redundant {
+            map.remove(&net);
+            changed = true;
+        }
+    }
+    let nets: Vec<_> = map.iter().map(|(net, _)| net).collect();
+    nets.into_iter()
+        .map(|net| (net, map.remove(&net).unwrap()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    fn map() -> IpNetworkMap<&'static str> {
+        vec![
+            (net("10.0.0.0/8"), "a"),
+            (net("10.1.0.0/16"), "b"),
+            (net("10.1.2.0/24"), "c"),
+            (net("192.168.0.0/16"), "d"),
+            (net("0.0.0.0/0"), "default"),
+            (net("255.255.255.255/32"), "broadcast"),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn exact() {
+        let mut map = map();
+        assert_eq!(6, map.len());
+        assert_eq!(Some(&"b"), map.get(&net("10.1.0.0/16")));
+        assert_eq!(Some(&"b"), map.get(&net("10.1.99.99/16")));
+        assert_eq!(None, map.get(&net("10.1.0.0/17")));
+        assert_eq!(None, map.get(&net("10.1.0.0/15")));
+
+        assert_eq!(Some("b"), map.insert(net("10.1.0.0/16"), "B"));
+        assert_eq!(6, map.len());
+        *map.get_mut(&net("10.1.0.0/16")).unwrap() = "bb";
+        assert_eq!(Some(&"bb"), map.get(&net("10.1.0.0/16")));
+
+        assert_eq!(Some("bb"), map.remove(&net("10.1.0.0/16")));
+        assert_eq!(None, map.remove(&net("10.1.0.0/16")));
+        assert_eq!(5, map.len());
+        assert_eq!(None, map.get(&net("10.1.0.0/16")));
+        assert_eq!(Some(&"c"), map.get(&net("10.1.2.0/24")));
+    }
+
+    #[test]
+    fn longest_match() {
+        let mut map = map();
+        let lookup = |map: &IpNetworkMap<_>, s| map.longest_match(addr(s)).map(|(_, v)| *v);
+        assert_eq!(Some("c"), lookup(&map, "10.1.2.3"));
+        assert_eq!(Some("b"), lookup(&map, "10.1.3.3"));
+        assert_eq!(Some("a"), lookup(&map, "10.2.3.3"));
+        assert_eq!(Some("default"), lookup(&map, "11.0.0.0"));
+        assert_eq!(Some("broadcast"), lookup(&map, "255.255.255.255"));
+        assert_eq!(
+            Some(net("10.1.0.0/16")),
+            map.longest_match(addr("10.1.3.3")).map(|(net, _)| net)
+        );
+        assert_eq!(
+            vec!["default", "a", "b", "c"],
+            map.matches(addr("10.1.2.3"))
+                .map(|(_, v)| *v)
+                .collect::<Vec<_>>()
+        );
+
+        map.remove(&net("0.0.0.0/0"));
+        assert_eq!(None, lookup(&map, "11.0.0.0"));
+        assert_eq!(
+            None,
+            IpNetworkMap::<()>::new().longest_match(addr("1.1.1.1"))
+        );
+    }
+
+    #[test]
+    fn iter() {
+        let order: Vec<_> = map().iter().map(|(net, _)| net.to_string()).collect();
+        assert_eq!(
+            vec![
+                "0.0.0.0/0",
+                "10.0.0.0/8",
+                "10.1.0.0/16",
+                "10.1.2.0/24",
+                "192.168.0.0/16",
+                "255.255.255.255/32",
+            ],
+            order
+        );
+    }
+
+    #[test]
+    fn aggregate_values() {
+        let pairs: Vec<_> = vec![
+            ("10.0.0.0/25", "us"),
+            ("10.0.0.128/25", "us"),
+            ("10.0.1.0/24", "us"),
+            ("10.0.2.0/24", "ca"),
+            ("10.0.3.0/24", "us"),
+            // Already covered by an equal value.
+            ("10.0.0.64/26", "us"),
+            // Both halves have values of their own, so this is never matched.
+            ("192.168.0.0/24", "gb"),
+            ("192.168.0.0/25", "fr"),
+            ("192.168.0.128/25", "de"),
+            ("172.16.0.0/12", "x"),
+            ("172.16.0.0/12", "y"),
+        ]
+        .into_iter()
+        .map(|(s, value)| (net(s), value))
+        .collect();
+        let aggregated: Vec<_> = super::aggregate_values(pairs.clone())
+            .into_iter()
+            .map(|(net, value)| format!("{} {}", net, value))
+            .collect();
+        assert_eq!(
+            vec![
+                "10.0.0.0/23 us",
+                "10.0.2.0/24 ca",
+                "10.0.3.0/24 us",
+                "172.16.0.0/12 y",
+                "192.168.0.0/25 fr",
+                "192.168.0.128/25 de",
+            ],
+            aggregated
+        );
+
+        // Every address still finds the same value.
+        let before: IpNetworkMap<_> = pairs.into_iter().collect();
+        let after: IpNetworkMap<_> = super::aggregate_values(before.iter())
+            .into_iter()
+            .map(|(net, value)| (net, *value))
+            .collect();
+        for s in &[
+            "10.0.0.1",
+            "10.0.0.200",
+            "10.0.2.1",
+            "10.0.3.255",
+            "192.168.0.1",
+            "192.168.0.255",
+            "11.0.0.0",
+        ] {
+            let lookup =
+                |map: &IpNetworkMap<&'static str>| map.longest_match(addr(s)).map(|(_, v)| *v);
+            assert_eq!(lookup(&before), lookup(&after), "{}", s);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! # The IPv6-Network-Map Module
+//!
+//! An IPv6 Network Map associates values with IPv6 networks, and finds the most specific network
+//! containing an address, just as an [`IpNetworkMap`] does for IPv4. Both are the same binary
+//! trie underneath, only 128 levels deep rather than 32.
+//!
+//! [`IpNetworkMap`]: crate::map::IpNetworkMap
+use super::{
+    addr6::Ipv6Address,
+    map::{Trie, TrieKey},
+    net6::Ipv6Network,
+};
+use std::iter::FromIterator;
+
+#[derive(Debug, Clone)]
+pub struct Ipv6NetworkMap<V> {
+    trie: Trie<Ipv6Network, V>,
+}
+
+impl TrieKey for Ipv6Network {
+    type Bits = u128;
+
+    fn bits(&self) -> u128 {
+        self.network_address().value()
+    }
+
+    fn prefix_len(&self) -> u8 {
+        self.num_network_bits()
+    }
+
+    fn canonical(self) -> Self {
+        Ipv6Network::new(self.network_address(), self.num_network_bits()).unwrap()
+    }
+}
+
+impl<V> Default for Ipv6NetworkMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Ipv6NetworkMap<V> {
+    /// Creates a new, empty map.
+    pub fn new() -> Self {
+        Self { trie: Trie::new() }
+    }
+
+    /// The number of networks within this map.
+    pub fn len(&self) -> usize {
+        self.trie.len()
+    }
+
+    /// Whether this map contains no networks at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Associates the value with the network, returning the value previously associated with it.
+    /// The host bits of the network are ignored.
+    pub fn insert(&mut self, net: Ipv6Network, value: V) -> Option<V> {
+        self.trie.insert(net, value)
+    }
+
+    /// Removes the network from this map, returning the value that was associated with it.
+    pub fn remove(&mut self, net: &Ipv6Network) -> Option<V> {
+        self.trie.remove(*net)
+    }
+
+    /// Gets the value associated with exactly this network.
+    pub fn get(&self, net: &Ipv6Network) -> Option<&V> {
+        self.trie.get(*net)
+    }
+
+    /// Gets a mutable reference to the value associated with exactly this network.
+    pub fn get_mut(&mut self, net: &Ipv6Network) -> Option<&mut V> {
+        self.trie.get_mut(*net)
+    }
+
+    /// Finds the most specific network which contains the address, along with its value.
+    pub fn longest_match(&self, addr: Ipv6Address) -> Option<(Ipv6Network, &V)> {
+        self.matches(addr).last()
+    }
+
+    /// Finds every network which contains the address, from the least to the most specific.
+    pub fn matches(&self, addr: Ipv6Address) -> impl Iterator<Item = (Ipv6Network, &V)> + '_ {
+        self.trie.matches(addr.value())
+    }
+
+    /// Iterates over every network and its value, ordered by address and then by prefix length,
+    /// so that every network is immediately followed by the networks it contains.
+    pub fn iter(&self) -> impl Iterator<Item = (Ipv6Network, &V)> + '_ {
+        self.trie.iter()
+    }
+}
+
+impl<V> FromIterator<(Ipv6Network, V)> for Ipv6NetworkMap<V> {
+    fn from_iter<I: IntoIterator<Item = (Ipv6Network, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<V> Extend<(Ipv6Network, V)> for Ipv6NetworkMap<V> {
+    fn extend<I: IntoIterator<Item = (Ipv6Network, V)>>(&mut self, iter: I) {
+        for (net, value) in iter {
+            self.insert(net, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> Ipv6Network {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> Ipv6Address {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn longest_match() {
+        let mut map: Ipv6NetworkMap<&str> = vec![
+            (net("::/0"), "default"),
+            (net("2001:db8::/32"), "documentation"),
+            (net("2001:db8:1::/48"), "site"),
+            (net("2001:db8:1:2::/64"), "lan"),
+            (net("2001:db8:1:2::1/128"), "router"),
+        ]
+        .into_iter()
+        .collect();
+        let lookup = |map: &Ipv6NetworkMap<_>, s| map.longest_match(addr(s)).map(|(_, v)| *v);
+        assert_eq!(Some("router"), lookup(&map, "2001:db8:1:2::1"));
+        assert_eq!(Some("lan"), lookup(&map, "2001:db8:1:2::2"));
+        assert_eq!(Some("site"), lookup(&map, "2001:db8:1:3::"));
+        assert_eq!(Some("default"), lookup(&map, "fe80::1"));
+        assert_eq!(5, map.matches(addr("2001:db8:1:2::1")).count());
+
+        assert_eq!(
+            Some("lan"),
+            map.insert(net("2001:db8:1:2:ffff::/64"), "LAN")
+        );
+        assert_eq!(Some(&"LAN"), map.get(&net("2001:db8:1:2::/64")));
+        assert_eq!(Some("default"), map.remove(&net("::/0")));
+        assert_eq!(None, lookup(&map, "fe80::1"));
+        assert_eq!(4, map.len());
+        let order: Vec<_> = map.iter().map(|(net, _)| net.to_string()).collect();
+        assert_eq!(
+            vec![
+                "2001:db8::/32",
+                "2001:db8:1::/48",
+                "2001:db8:1:2::/64",
+                "2001:db8:1:2::1/128"
+            ],
+            order
+        );
+    }
+}
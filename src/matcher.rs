@@ -0,0 +1,229 @@
+//! # The Matcher Module
+//!
+//! Matching logic is rarely a single network. Real policies read like "inside the office, but not
+//! the guest VLAN, or any documentation address", and are clearest when written just like that.
+//! This module has a trait for anything which matches addresses, implemented by the crate's
+//! networks, ranges, sets, and filters, and by plain predicates, along with combinators which
+//! build larger matchers out of smaller ones.
+use super::{addr::IpAddress, filter::IpFilter, net::IpNetwork, range::IpRange, set::IpSet};
+use std::fmt;
+
+/// Something which either matches an address, or does not.
+pub trait Matcher {
+    /// Whether the address matches.
+    fn matches(&self, addr: IpAddress) -> bool;
+
+    /// Matches the addresses which both matchers match.
+    fn and<M: Matcher>(self, other: M) -> And<Self, M>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    /// Matches the addresses which either matcher matches.
+    fn or<M: Matcher>(self, other: M) -> Or<Self, M>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    /// Matches the addresses which this matcher does not.
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+impl Matcher for IpAddress {
+    fn matches(&self, addr: IpAddress) -> bool {
+        *self == addr
+    }
+}
+
+impl Matcher for IpNetwork {
+    fn matches(&self, addr: IpAddress) -> bool {
+        self.contains(addr)
+    }
+}
+
+impl Matcher for IpRange {
+    fn matches(&self, addr: IpAddress) -> bool {
+        self.contains(addr)
+    }
+}
+
+impl Matcher for IpSet {
+    fn matches(&self, addr: IpAddress) -> bool {
+        self.contains(addr)
+    }
+}
+
+/// A filter matches the addresses it allows.
+impl Matcher for IpFilter {
+    fn matches(&self, addr: IpAddress) -> bool {
+        self.is_allowed(addr)
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for &M {
+    fn matches(&self, addr: IpAddress) -> bool {
+        (**self).matches(addr)
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for Box<M> {
+    fn matches(&self, addr: IpAddress) -> bool {
+        (**self).matches(addr)
+    }
+}
+
+/// Matches the addresses for which a function returns `true`.
+#[derive(Debug, Copy, Clone)]
+pub struct Predicate<F>(F);
+
+/// Creates a matcher out of a function, as in `predicate(|addr| addr.is_private())`.
+pub fn predicate<F: Fn(IpAddress) -> bool>(f: F) -> Predicate<F> {
+    Predicate(f)
+}
+
+impl<F: Fn(IpAddress) -> bool> Matcher for Predicate<F> {
+    fn matches(&self, addr: IpAddress) -> bool {
+        (self.0)(addr)
+    }
+}
+
+/// Matches the addresses which both matchers match. See [`Matcher::and`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct And<A, B>(A, B);
+
+impl<A: Matcher, B: Matcher> Matcher for And<A, B> {
+    fn matches(&self, addr: IpAddress) -> bool {
+        self.0.matches(addr) && self.1.matches(addr)
+    }
+}
+
+/// Matches the addresses which either matcher matches. See [`Matcher::or`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Or<A, B>(A, B);
+
+impl<A: Matcher, B: Matcher> Matcher for Or<A, B> {
+    fn matches(&self, addr: IpAddress) -> bool {
+        self.0.matches(addr) || self.1.matches(addr)
+    }
+}
+
+/// Matches the addresses which the matcher does not. See [`Matcher::not`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Not<M>(M);
+
+impl<M: Matcher> Matcher for Not<M> {
+    fn matches(&self, addr: IpAddress) -> bool {
+        !self.0.matches(addr)
+    }
+}
+
+/// An ordered list of matchers, each with a value, which classifies an address by the first
+/// matcher to match it.
+///
+/// As a matcher itself, it matches the addresses which any of its matchers match.
+pub struct Priority<'a, T> {
+    arms: Vec<(Box<dyn Matcher + 'a>, T)>,
+}
+
+impl<'a, T> Default for Priority<'a, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T> Priority<'a, T> {
+    /// Creates a new, empty list, which matches nothing.
+    pub fn new() -> Self {
+        Self { arms: Vec::new() }
+    }
+
+    /// Adds a matcher after every existing matcher.
+    pub fn then<M: Matcher + 'a>(mut self, matcher: M, value: T) -> Self {
+        self.arms.push((Box::new(matcher), value));
+        self
+    }
+
+    /// Finds the value of the first matcher to match the address.
+    pub fn classify(&self, addr: IpAddress) -> Option<&T> {
+        self.arms
+            .iter()
+            .find(|(matcher, _)| matcher.matches(addr))
+            .map(|(_, value)| value)
+    }
+}
+
+impl<'a, T> Matcher for Priority<'a, T> {
+    fn matches(&self, addr: IpAddress) -> bool {
+        self.classify(addr).is_some()
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Priority<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<&T> = self.arms.iter().map(|(_, value)| value).collect();
+        f.debug_struct("Priority").field("values", &values).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Action;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn combinators() {
+        let office = net("10.0.0.0/16")
+            .and(net("10.0.99.0/24").not())
+            .or(predicate(|a| a.is_documentation()));
+        assert!(office.matches(addr("10.0.1.1")));
+        assert!(!office.matches(addr("10.0.99.1")));
+        assert!(office.matches(addr("192.0.2.1")));
+        assert!(!office.matches(addr("8.8.8.8")));
+
+        let range = IpRange::new(addr("1.0.0.0"), addr("1.0.0.9")).unwrap();
+        let set: IpSet = vec![net("2.0.0.0/8")].into_iter().collect();
+        let boxed: Vec<Box<dyn Matcher>> = vec![Box::new(range), Box::new(set)];
+        assert!(boxed.iter().any(|m| m.matches(addr("1.0.0.5"))));
+        assert!(boxed.iter().any(|m| m.matches(addr("2.9.9.9"))));
+        assert!(!boxed.iter().any(|m| m.matches(addr("1.0.0.10"))));
+
+        let filter = IpFilter::new(Action::Allow).deny(net("10.0.0.0/8"));
+        assert!(filter.matches(addr("11.0.0.0")));
+        assert!((&filter).not().matches(addr("10.0.0.0")));
+        let initial: Box<dyn Matcher> = Box::new(filter.not());
+        let any = boxed.into_iter().fold(initial, |m, b| Box::new(m.or(b)));
+        assert!(any.matches(addr("10.0.0.0")) && any.matches(addr("2.0.0.0")));
+        assert!(!any.matches(addr("3.0.0.0")));
+    }
+
+    #[test]
+    fn priority() {
+        let tiers = Priority::new()
+            .then(addr("10.0.0.1"), "admin")
+            .then(net("10.0.0.0/8"), "internal")
+            .then(predicate(|a| a.is_private()), "private");
+        assert_eq!(Some(&"admin"), tiers.classify(addr("10.0.0.1")));
+        assert_eq!(Some(&"internal"), tiers.classify(addr("10.0.0.2")));
+        assert_eq!(Some(&"private"), tiers.classify(addr("192.168.0.1")));
+        assert_eq!(None, tiers.classify(addr("8.8.8.8")));
+        assert!(tiers.matches(addr("172.16.0.1")));
+        assert!(!Priority::<()>::new().matches(addr("0.0.0.0")));
+    }
+}
@@ -0,0 +1,207 @@
+//! # The Merge Module
+//!
+//! Feeds are usually published already sorted, and there are usually a lot of them. Collecting
+//! every feed into memory only to sort it all again wastes both, so this module merges any number
+//! of sorted streams of networks into a single sorted stream, a network at a time, optionally
+//! aggregating it along the way.
+use super::{
+    addr::IpAddress,
+    net::IpNetwork,
+    range::{IpRange, Networks},
+};
+use std::{cmp::Reverse, collections::BinaryHeap, iter::Fuse};
+
+/// Merges streams of networks, each sorted as [`prefix_order`](crate::set::prefix_order) sorts
+/// them, into one sorted stream without any duplicates.
+///
+/// Only the next network of each stream is held at any time. Host bits are cleared, so networks
+/// which differ only in their host bits are duplicates. If a stream is not sorted, neither is the
+/// merged stream, although every network is still produced.
+pub fn merge_sorted<I, S>(streams: I) -> MergeSorted<S::IntoIter>
+where
+    I: IntoIterator<Item = S>,
+    S: IntoIterator<Item = IpNetwork>,
+{
+    let mut merged = MergeSorted {
+        streams: streams
+            .into_iter()
+            .map(|stream| stream.into_iter().fuse())
+            .collect(),
+        heads: BinaryHeap::new(),
+        last: None,
+    };
+    for i in 0..merged.streams.len() {
+        merged.advance(i);
+    }
+    merged
+}
+
+/// The iterator returned by [`merge_sorted`].
+#[derive(Debug, Clone)]
+pub struct MergeSorted<S> {
+    streams: Vec<Fuse<S>>,
+    /// The next network of each stream which has one, smallest first, with the stream it came from.
+    heads: BinaryHeap<Reverse<(IpAddress, u8, usize)>>,
+    last: Option<IpNetwork>,
+}
+
+impl<S: Iterator<Item = IpNetwork>> MergeSorted<S> {
+    /// Combines the merged networks into the fewest networks which cover the same addresses, as
+    /// [`aggregate`](crate::set::aggregate) does, still a network at a time.
+    pub fn aggregated(self) -> Aggregated<Self> {
+        aggregate_sorted(self)
+    }
+
+    /// Takes the next network of a stream, if it has one.
+    fn advance(&mut self, i: usize) {
+        if let Some(net) = self.streams[i].next() {
+            let head = (net.network_address(), net.num_network_bits(), i);
+            self.heads.push(Reverse(head));
+        }
+    }
+}
+
+impl<S: Iterator<Item = IpNetwork>> Iterator for MergeSorted<S> {
+    type Item = IpNetwork;
+
+    fn next(&mut self) -> Option<IpNetwork> {
+        loop {
+            let Reverse((base, prefix_len, i)) = self.heads.pop()?;
+            self.advance(i);
+            let net = IpNetwork::new(base, prefix_len).unwrap();
+            if self.last != Some(net) {
+                self.last = Some(net);
+                return Some(net);
+            }
+        }
+    }
+}
+
+/// Combines a stream of networks, sorted as [`prefix_order`](crate::set::prefix_order) sorts
+/// them, into the fewest networks which cover the same addresses, without collecting the stream.
+///
+/// Only networks which overlap or touch are held at any time.
+pub fn aggregate_sorted<I: IntoIterator<Item = IpNetwork>>(nets: I) -> Aggregated<I::IntoIter> {
+    Aggregated {
+        nets: nets.into_iter().fuse(),
+        run: None,
+        out: None,
+    }
+}
+
+/// The iterator returned by [`aggregate_sorted`] and [`MergeSorted::aggregated`].
+#[derive(Debug, Clone)]
+pub struct Aggregated<I> {
+    nets: Fuse<I>,
+    /// The first and last addresses of the networks which have been merged so far.
+    run: Option<(u64, u64)>,
+    /// The networks covering the last run to have been finished.
+    out: Option<Networks>,
+}
+
+impl<I: Iterator<Item = IpNetwork>> Iterator for Aggregated<I> {
+    type Item = IpNetwork;
+
+    fn next(&mut self) -> Option<IpNetwork> {
+        loop {
+            if let Some(net) = self.out.as_mut().and_then(Iterator::next) {
+                return Some(net);
+            }
+            let (first, last) = match self.nets.next() {
+                Some(net) => {
+                    let start = u64::from(net.network_address().value());
+                    let end = u64::from(net.broadcast_address().value());
+                    match self.run.replace((start, end)) {
+                        Some((first, last)) if start <= last + 1 => {
+                            self.run = Some((first, last.max(end)));
+                            continue;
+                        }
+                        Some(finished) => finished,
+                        None => continue,
+                    }
+                }
+                None => self.run.take()?,
+            };
+            let range = IpRange::new(IpAddress::from(first as u32), IpAddress::from(last as u32));
+            self.out = range.map(|range| range.networks());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNetwork> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    fn strings<I: IntoIterator<Item = IpNetwork>>(nets: I) -> Vec<String> {
+        nets.into_iter().map(|net| net.to_string()).collect()
+    }
+
+    #[test]
+    fn merge() {
+        let feeds = vec![
+            nets(&["10.0.0.0/24", "10.0.1.0/24", "192.168.0.0/16"]),
+            nets(&["10.0.0.0/8", "10.0.0.7/24", "172.16.0.0/12"]),
+            nets(&[]),
+            nets(&["1.1.1.1/32", "10.0.1.0/24", "255.255.255.255/32"]),
+        ];
+        assert_eq!(
+            vec![
+                "1.1.1.1/32",
+                "10.0.0.0/8",
+                "10.0.0.0/24",
+                "10.0.1.0/24",
+                "172.16.0.0/12",
+                "192.168.0.0/16",
+                "255.255.255.255/32",
+            ],
+            strings(merge_sorted(feeds.clone()))
+        );
+        assert_eq!(
+            vec![
+                "1.1.1.1/32",
+                "10.0.0.0/8",
+                "172.16.0.0/12",
+                "192.168.0.0/16",
+                "255.255.255.255/32",
+            ],
+            strings(merge_sorted(feeds).aggregated())
+        );
+        assert_eq!(0, merge_sorted(Vec::<Vec<IpNetwork>>::new()).count());
+    }
+
+    #[test]
+    fn streaming() {
+        // Neither stream ends, so nothing could be produced if either were collected.
+        let evens = (0..).map(|i| IpNetwork::new(IpAddress::from(i * 2), 32).unwrap());
+        let odds = (0..).map(|i| IpNetwork::new(IpAddress::from(i * 2 + 1), 32).unwrap());
+        let merged: Vec<IpNetwork> = merge_sorted(vec![
+            Box::new(evens) as Box<dyn Iterator<Item = IpNetwork>>,
+            Box::new(odds),
+        ])
+        .take(5)
+        .collect();
+        assert_eq!(
+            vec![
+                "0.0.0.0/32",
+                "0.0.0.1/32",
+                "0.0.0.2/32",
+                "0.0.0.3/32",
+                "0.0.0.4/32"
+            ],
+            strings(merged)
+        );
+
+        let aggregated = aggregate_sorted(nets(&[
+            "10.0.0.0/25",
+            "10.0.0.128/25",
+            "10.0.1.0/24",
+            "10.0.3.0/24",
+            "10.0.3.0/25",
+        ]));
+        assert_eq!(vec!["10.0.0.0/23", "10.0.3.0/24"], strings(aggregated));
+    }
+}
@@ -0,0 +1,386 @@
+//! # The MRT Module
+//!
+//! Route collectors such as RouteViews and RIPE RIS publish snapshots of their routing tables in
+//! the MRT format (RFC 6396). This module streams the announced prefixes out of a `TABLE_DUMP_V2`
+//! file, optionally along with the ASNs which originate each, so that a snapshot can be loaded
+//! straight into the crate's own types.
+//!
+//! Snapshots are usually published compressed; they must be decompressed before being read here.
+//! Records for other address families, and other record types, are skipped.
+use super::{addr::IpAddress, map::IpNetworkMap, net::IpNetwork};
+use std::{
+    error, fmt,
+    io::{self, Read},
+};
+
+/// The MRT type of every record in a routing table dump.
+const TABLE_DUMP_V2: u16 = 13;
+/// The `TABLE_DUMP_V2` subtypes holding IPv4 routes, without and with path identifiers
+/// (RFC 8050).
+const RIB_IPV4_UNICAST: u16 = 2;
+const RIB_IPV4_MULTICAST: u16 = 3;
+const RIB_IPV4_UNICAST_ADDPATH: u16 = 8;
+const RIB_IPV4_MULTICAST_ADDPATH: u16 = 9;
+/// The BGP path attribute listing the ASes a route has passed through.
+const AS_PATH: u8 = 2;
+/// The segment type of an ordered run of ASes within an AS path.
+const AS_SEQUENCE: u8 = 2;
+
+/// A prefix announced in a routing table dump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RibRecord {
+    pub network: IpNetwork,
+    /// The distinct ASNs which originate the prefix, as seen by any peer, in ascending order.
+    /// This is empty unless origins were asked for.
+    pub origins: Vec<u32>,
+}
+
+/// A single MRT record, of any type.
+struct Record {
+    kind: u16,
+    subtype: u16,
+    body: Vec<u8>,
+}
+
+/// A streaming reader of the IPv4 prefixes in an MRT `TABLE_DUMP_V2` file.
+pub struct MrtReader<R> {
+    reader: R,
+    origins: bool,
+}
+
+impl<R: io::Read> MrtReader<R> {
+    /// Creates a reader of the prefixes only.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            origins: false,
+        }
+    }
+
+    /// Sets whether the origin ASNs of each prefix are read too. This means decoding the path
+    /// attributes of every route, so is slower.
+    pub fn with_origins(mut self, origins: bool) -> Self {
+        self.origins = origins;
+        self
+    }
+
+    /// Reads the next record, whatever its type. `None` is a clean end of the file.
+    fn next_record(&mut self) -> Option<Result<Record, MrtError>> {
+        let mut header = [0; 12];
+        match read_fully(&mut self.reader, &mut header) {
+            Ok(0) => return None,
+            Ok(12) => {}
+            Ok(_) => return Some(Err(MrtError::Truncated)),
+            Err(err) => return Some(Err(err.into())),
+        }
+        let kind = u16::from_be_bytes([header[4], header[5]]);
+        let subtype = u16::from_be_bytes([header[6], header[7]]);
+        let len = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+        // The length comes from the file, so the body grows as it is read rather than being
+        // allocated up front.
+        let mut body = Vec::new();
+        let read = (&mut self.reader)
+            .take(u64::from(len))
+            .read_to_end(&mut body);
+        Some(match read {
+            Ok(n) if n as u64 == u64::from(len) => Ok(Record {
+                kind,
+                subtype,
+                body,
+            }),
+            Ok(_) => Err(MrtError::Truncated),
+            Err(err) => Err(err.into()),
+        })
+    }
+
+    /// Decodes the body of an IPv4 RIB record.
+    fn parse_rib(&self, body: &[u8], add_path: bool) -> Result<RibRecord, MrtError> {
+        let mut cursor = Cursor(body);
+        cursor.take(4)?; // The sequence number.
+        let prefix_len = cursor.take(1)?[0];
+        if prefix_len > 32 {
+            return Err(MrtError::Malformed("prefix length"));
+        }
+        let mut octets = [0; 4];
+        let prefix = cursor.take(usize::from(prefix_len).div_ceil(8))?;
+        octets[..prefix.len()].copy_from_slice(prefix);
+        let network = IpNetwork::new(IpAddress::from(octets), prefix_len).unwrap();
+
+        let mut origins = Vec::new();
+        if self.origins {
+            for _ in 0..cursor.u16()? {
+                cursor.take(2 + 4)?; // The peer index and originated time.
+                if add_path {
+                    cursor.take(4)?;
+                }
+                let len = cursor.u16()?;
+                origins.extend(origin(cursor.take(usize::from(len))?)?);
+            }
+            origins.sort_unstable();
+            origins.dedup();
+        }
+        Ok(RibRecord { network, origins })
+    }
+}
+
+impl<R: io::Read> Iterator for MrtReader<R> {
+    type Item = Result<RibRecord, MrtError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let record = match self.next_record()? {
+                Ok(record) => record,
+                Err(err) => return Some(Err(err)),
+            };
+            let add_path = match (record.kind, record.subtype) {
+                (TABLE_DUMP_V2, RIB_IPV4_UNICAST) | (TABLE_DUMP_V2, RIB_IPV4_MULTICAST) => false,
+                (TABLE_DUMP_V2, RIB_IPV4_UNICAST_ADDPATH)
+                | (TABLE_DUMP_V2, RIB_IPV4_MULTICAST_ADDPATH) => true,
+                _ => continue,
+            };
+            return Some(self.parse_rib(&record.body, add_path));
+        }
+    }
+}
+
+/// Reads every prefix in a dump into a map from each prefix to its origin ASNs.
+pub fn load_origins<R: io::Read>(reader: R) -> Result<IpNetworkMap<Vec<u32>>, MrtError> {
+    let mut map: IpNetworkMap<Vec<u32>> = IpNetworkMap::new();
+    for record in MrtReader::new(reader).with_origins(true) {
+        let record = record?;
+        match map.get_mut(&record.network) {
+            // The same prefix may appear in both unicast and multicast tables.
+            Some(origins) => {
+                origins.extend(record.origins);
+                origins.sort_unstable();
+                origins.dedup();
+            }
+            None => {
+                map.insert(record.network, record.origins);
+            }
+        }
+    }
+    Ok(map)
+}
+
+/// Finds the origin ASN within some BGP path attributes: the last AS of the path, provided that
+/// it ends in an ordered segment. A path ending in an unordered set has no single origin.
+fn origin(attributes: &[u8]) -> Result<Option<u32>, MrtError> {
+    let mut cursor = Cursor(attributes);
+    while !cursor.0.is_empty() {
+        let flags = cursor.take(1)?[0];
+        let code = cursor.take(1)?[0];
+        // The extended-length flag widens the length field to two octets.
+        let len = match flags & 0x10 {
+            0 => usize::from(cursor.take(1)?[0]),
+            _ => usize::from(cursor.u16()?),
+        };
+        let value = cursor.take(len)?;
+        if code != AS_PATH {
+            continue;
+        }
+        // Within MRT dumps, AS paths always use four-octet ASNs.
+        let mut path = Cursor(value);
+        let mut origin = None;
+        while !path.0.is_empty() {
+            let segment_type = path.take(1)?[0];
+            let count = usize::from(path.take(1)?[0]);
+            let asns = path.take(4 * count)?;
+            origin = match (segment_type, asns.chunks(4).last()) {
+                (AS_SEQUENCE, Some(last)) => {
+                    Some(u32::from_be_bytes([last[0], last[1], last[2], last[3]]))
+                }
+                (AS_SEQUENCE, None) => origin,
+                _ => None,
+            };
+        }
+        return Ok(origin);
+    }
+    Ok(None)
+}
+
+/// Reads until the buffer is full or the reader is exhausted, returning how much was read.
+fn read_fully<R: io::Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
+
+/// The unread remainder of a record.
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], MrtError> {
+        if n > self.0.len() {
+            return Err(MrtError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, MrtError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+/// The error returned when an MRT file could not be read.
+#[derive(Debug)]
+pub enum MrtError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// A record, or a field within it, ended early.
+    Truncated,
+    /// A field held an impossible value.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for MrtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read MRT data: {}", err),
+            Self::Truncated => f.write_str("truncated MRT record"),
+            Self::Malformed(field) => write!(f, "malformed MRT record: invalid {}", field),
+        }
+    }
+}
+
+impl error::Error for MrtError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MrtError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an MRT record of the specified type.
+    fn record(kind: u16, subtype: u16, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![0, 0, 0, 0];
+        out.extend_from_slice(&kind.to_be_bytes());
+        out.extend_from_slice(&subtype.to_be_bytes());
+        out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    /// Builds a RIB entry whose AS path is a single sequence.
+    fn rib_entry(path: &[u32]) -> Vec<u8> {
+        let mut as_path = vec![AS_SEQUENCE, path.len() as u8];
+        for asn in path {
+            as_path.extend_from_slice(&asn.to_be_bytes());
+        }
+        // An ORIGIN attribute, then the AS path.
+        let mut attributes = vec![0x40, 1, 1, 0, 0x40, AS_PATH, as_path.len() as u8];
+        attributes.extend(as_path);
+        let mut out = vec![0, 0, 0, 0, 0, 0];
+        out.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+        out.extend(attributes);
+        out
+    }
+
+    fn rib(prefix: &[u8], prefix_len: u8, paths: &[&[u32]]) -> Vec<u8> {
+        let mut body = vec![0, 0, 0, 1, prefix_len];
+        body.extend_from_slice(prefix);
+        body.extend_from_slice(&(paths.len() as u16).to_be_bytes());
+        for path in paths {
+            body.extend(rib_entry(path));
+        }
+        record(TABLE_DUMP_V2, RIB_IPV4_UNICAST, &body)
+    }
+
+    fn dump() -> Vec<u8> {
+        let mut data = record(TABLE_DUMP_V2, 1, &[0; 10]); // A peer index table.
+        data.extend(rib(&[10], 8, &[&[65001, 64512], &[65002, 64513, 64512]]));
+        data.extend(record(TABLE_DUMP_V2, 4, &[0; 5])); // An IPv6 route.
+        data.extend(rib(&[192, 0, 2], 24, &[&[65001, 64496]]));
+        data.extend(rib(&[172, 16], 12, &[&[65001, 64497], &[65002, 64498]]));
+        data
+    }
+
+    #[test]
+    fn prefixes() {
+        let nets: Vec<String> = MrtReader::new(dump().as_slice())
+            .map(|record| record.unwrap())
+            .inspect(|record| assert!(record.origins.is_empty()))
+            .map(|record| record.network.to_string())
+            .collect();
+        assert_eq!(vec!["10.0.0.0/8", "192.0.2.0/24", "172.16.0.0/12"], nets);
+    }
+
+    #[test]
+    fn origins() {
+        let map = load_origins(dump().as_slice()).unwrap();
+        assert_eq!(3, map.len());
+        let origins = |s: &str| map.get(&s.parse().unwrap()).cloned();
+        assert_eq!(Some(vec![64512]), origins("10.0.0.0/8"));
+        assert_eq!(Some(vec![64496]), origins("192.0.2.0/24"));
+        assert_eq!(Some(vec![64497, 64498]), origins("172.16.0.0/12"));
+    }
+
+    #[test]
+    fn as_set_origin() {
+        // A path ending in an AS_SET has no single origin.
+        let attributes = [
+            0x40,
+            AS_PATH,
+            12,
+            AS_SEQUENCE,
+            1,
+            0,
+            0,
+            0,
+            1,
+            1,
+            1,
+            0,
+            0,
+            0,
+            2,
+        ];
+        assert_eq!(None, origin(&attributes).unwrap());
+        // Extended-length attributes are read too.
+        let attributes = [0x50, AS_PATH, 0, 6, AS_SEQUENCE, 1, 0, 0, 0, 7];
+        assert_eq!(Some(7), origin(&attributes).unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let mut data = dump();
+        data.truncate(data.len() - 3);
+        let results: Vec<_> = MrtReader::new(data.as_slice()).collect();
+        assert!(matches!(results.last(), Some(Err(MrtError::Truncated))));
+
+        let data = rib(&[10], 33, &[]);
+        let result = MrtReader::new(data.as_slice()).next().unwrap();
+        assert_eq!(
+            "malformed MRT record: invalid prefix length",
+            result.unwrap_err().to_string()
+        );
+        assert!(MrtReader::new(&[][..]).next().is_none());
+
+        // A header claiming a 4 GiB body, with nothing behind it.
+        let mut data = record(TABLE_DUMP_V2, RIB_IPV4_UNICAST, &[]);
+        data[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        let result = MrtReader::new(data.as_slice()).next().unwrap();
+        assert!(matches!(result, Err(MrtError::Truncated)));
+    }
+}
@@ -0,0 +1,223 @@
+//! # The IPv6 Multicast Module
+//!
+//! An IPv6 multicast address is more than a group number: after its leading `ff` come four flag
+//! bits and a four-bit scope, which bounds how far the group reaches. Some multicast addresses go
+//! further, and embed the unicast prefix of the network which allocated the group (RFC 3306), or
+//! even the address of the group's rendezvous point (RFC 3956). This module breaks a multicast
+//! address down into those parts.
+use super::{addr6::Ipv6Address, bits::AddressBits, net6::Ipv6Network};
+
+/// The flags of a multicast address, the four bits after its leading `ff`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Flags {
+    /// The `R` bit, set when the address embeds the address of its rendezvous point.
+    pub rendezvous_point: bool,
+    /// The `P` bit, set when the address embeds a unicast prefix.
+    pub prefix: bool,
+    /// The `T` bit, set when the address was not permanently assigned by IANA.
+    pub transient: bool,
+}
+
+/// How far a multicast group reaches, as assigned by RFC 7346.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Scope {
+    InterfaceLocal,
+    LinkLocal,
+    RealmLocal,
+    AdminLocal,
+    SiteLocal,
+    OrganizationLocal,
+    Global,
+    /// Scope `0` or `f`, which may never be used.
+    Reserved(u8),
+    /// Any other scope, which administrators may define for themselves.
+    Unassigned(u8),
+}
+
+impl From<u8> for Scope {
+    /// Interprets the low four bits as a scope.
+    fn from(value: u8) -> Self {
+        match value & 0xf {
+            0x1 => Scope::InterfaceLocal,
+            0x2 => Scope::LinkLocal,
+            0x3 => Scope::RealmLocal,
+            0x4 => Scope::AdminLocal,
+            0x5 => Scope::SiteLocal,
+            0x8 => Scope::OrganizationLocal,
+            0xe => Scope::Global,
+            value @ (0x0 | 0xf) => Scope::Reserved(value),
+            value => Scope::Unassigned(value),
+        }
+    }
+}
+
+impl From<Scope> for u8 {
+    fn from(scope: Scope) -> Self {
+        match scope {
+            Scope::InterfaceLocal => 0x1,
+            Scope::LinkLocal => 0x2,
+            Scope::RealmLocal => 0x3,
+            Scope::AdminLocal => 0x4,
+            Scope::SiteLocal => 0x5,
+            Scope::OrganizationLocal => 0x8,
+            Scope::Global => 0xe,
+            Scope::Reserved(value) | Scope::Unassigned(value) => value,
+        }
+    }
+}
+
+/// What the bits between the scope and the group ID of a multicast address hold.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// Nothing more than a larger group ID.
+    Plain,
+    /// The unicast prefix of the network which allocated the group, as with RFC 3306.
+    UnicastPrefixBased { prefix: Ipv6Network },
+    /// A source-specific group, within `ff3x::/32`, which has a unicast-prefix-based layout but
+    /// an empty prefix.
+    SourceSpecific,
+    /// The unicast prefix of the group's rendezvous point, along with the rendezvous point itself,
+    /// as with RFC 3956.
+    EmbeddedRp {
+        prefix: Ipv6Network,
+        rendezvous_point: Ipv6Address,
+    },
+}
+
+/// The parts of a multicast address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Multicast {
+    pub flags: Flags,
+    pub scope: Scope,
+    pub kind: Kind,
+    /// The group ID, which is the last 112 bits of a plain address, or the last 32 bits of one
+    /// with an embedded prefix.
+    pub group_id: u128,
+}
+
+impl Multicast {
+    /// Breaks a multicast address down into its parts, or gives `None` if the address is not
+    /// multicast.
+    pub fn parse(addr: Ipv6Address) -> Option<Self> {
+        if !addr.is_multicast() {
+            return None;
+        }
+        let [_, flags_and_scope, reserved, prefix_len, ..] = addr.octets();
+        let flags = Flags {
+            rendezvous_point: flags_and_scope & 0x40 != 0,
+            prefix: flags_and_scope & 0x20 != 0,
+            transient: flags_and_scope & 0x10 != 0,
+        };
+        let scope = Scope::from(flags_and_scope);
+        let value = addr.value();
+        let plain = Self {
+            flags,
+            scope,
+            kind: Kind::Plain,
+            group_id: value & u128::wildcard(16),
+        };
+        // Only a transient address may embed a prefix, and a prefix may be at most 64 bits long.
+        if !(flags.prefix && flags.transient) || prefix_len > 64 {
+            return Some(plain);
+        }
+        let embedded = (value >> 32) & u64::MAX as u128;
+        let prefix = Ipv6Network::new(
+            Ipv6Address::from((embedded << 64) & u128::mask(prefix_len)),
+            prefix_len,
+        )?;
+        let kind = match (flags.rendezvous_point, reserved, prefix_len) {
+            // The low four bits of the reserved byte are the interface ID of the rendezvous point.
+            (true, riid, 1..=64) if riid >> 4 == 0 => Kind::EmbeddedRp {
+                prefix,
+                rendezvous_point: Ipv6Address::from(
+                    prefix.network_address().value() | u128::from(riid),
+                ),
+            },
+            (false, 0, 0) if embedded == 0 => Kind::SourceSpecific,
+            (false, 0, 1..=64) => Kind::UnicastPrefixBased { prefix },
+            _ => return Some(plain),
+        };
+        Some(Self {
+            flags,
+            scope,
+            kind,
+            group_id: value & u128::from(u32::MAX),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Option<Multicast> {
+        Multicast::parse(s.parse().unwrap())
+    }
+
+    #[test]
+    fn plain() {
+        assert!(parse("2001:db8::1").is_none());
+        let all_nodes = parse("ff02::1").unwrap();
+        assert_eq!(Scope::LinkLocal, all_nodes.scope);
+        assert_eq!(Kind::Plain, all_nodes.kind);
+        assert_eq!(1, all_nodes.group_id);
+        assert!(!all_nodes.flags.transient);
+
+        let transient = parse("ff15::1:2").unwrap();
+        assert!(transient.flags.transient && !transient.flags.prefix);
+        assert_eq!(Scope::SiteLocal, transient.scope);
+        assert_eq!(0x1_0002, transient.group_id);
+
+        assert_eq!(Scope::Reserved(0), parse("ff00::").unwrap().scope);
+        assert_eq!(Scope::Unassigned(6), parse("ff06::").unwrap().scope);
+        for value in 0..16 {
+            assert_eq!(value, u8::from(Scope::from(value)));
+        }
+    }
+
+    #[test]
+    fn unicast_prefix_based() {
+        let group = parse("ff3e:30:2001:db8::1234").unwrap();
+        assert!(group.flags.prefix && group.flags.transient);
+        assert_eq!(Scope::Global, group.scope);
+        assert_eq!(
+            Kind::UnicastPrefixBased {
+                prefix: "2001:db8::/48".parse().unwrap()
+            },
+            group.kind
+        );
+        assert_eq!(0x1234, group.group_id);
+
+        assert_eq!(Kind::SourceSpecific, parse("ff3e::8000:1").unwrap().kind);
+        // A prefix longer than 64 bits, or a set reserved byte, leaves the address plain.
+        assert_eq!(Kind::Plain, parse("ff3e:41:2001:db8::1").unwrap().kind);
+        assert_eq!(Kind::Plain, parse("ff3e:130:2001:db8::1").unwrap().kind);
+        // The prefix flag means nothing without the transient flag.
+        assert_eq!(Kind::Plain, parse("ff2e:30:2001:db8::1").unwrap().kind);
+    }
+
+    #[test]
+    fn embedded_rp() {
+        let group = parse("ff7e:140:2001:db8:be:ef00::1").unwrap();
+        assert!(group.flags.rendezvous_point);
+        assert_eq!(
+            Kind::EmbeddedRp {
+                prefix: "2001:db8:be:ef00::/64".parse().unwrap(),
+                rendezvous_point: "2001:db8:be:ef00::1".parse().unwrap(),
+            },
+            group.kind
+        );
+        assert_eq!(1, group.group_id);
+
+        let short = parse("ff7e:320:2001:db8::42").unwrap();
+        assert_eq!(
+            Kind::EmbeddedRp {
+                prefix: "2001:db8::/32".parse().unwrap(),
+                rendezvous_point: "2001:db8::3".parse().unwrap(),
+            },
+            short.kind
+        );
+        // An embedded rendezvous point needs a prefix.
+        assert_eq!(Kind::Plain, parse("ff7e:100::1").unwrap().kind);
+    }
+}
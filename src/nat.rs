@@ -0,0 +1,133 @@
+//! # The NAT Module
+//!
+//! Static NAT translates every address of one network to the address at the same position within
+//! another, and back again. The two networks must be the same size, and the arithmetic is simple,
+//! but fiddly enough to be worth getting right once. This is also how lab environments rewrite a
+//! copied address plan onto new space.
+use super::{addr::IpAddress, net::IpNetwork};
+use std::fmt;
+
+/// A one-to-one translation between the addresses of two networks of the same size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct NatMap {
+    inside: IpNetwork,
+    outside: IpNetwork,
+}
+
+impl NatMap {
+    /// Creates a translation from the inside network to the outside network.
+    ///
+    /// The networks **must** have the same prefix length, or their addresses could not be paired
+    /// off one-to-one. Any host bits of their base addresses are ignored.
+    pub fn new(inside: IpNetwork, outside: IpNetwork) -> Option<Self> {
+        let prefix_len = inside.num_network_bits();
+        if prefix_len != outside.num_network_bits() {
+            return None;
+        }
+        Some(Self {
+            inside: IpNetwork::new(inside.network_address(), prefix_len)?,
+            outside: IpNetwork::new(outside.network_address(), prefix_len)?,
+        })
+    }
+
+    /// The network whose addresses are translated.
+    pub fn inside(&self) -> IpNetwork {
+        self.inside
+    }
+
+    /// The network the addresses are translated into.
+    pub fn outside(&self) -> IpNetwork {
+        self.outside
+    }
+
+    /// The same translation, in the opposite direction.
+    pub fn reversed(&self) -> Self {
+        Self {
+            inside: self.outside,
+            outside: self.inside,
+        }
+    }
+
+    /// Translates an inside address to its outside address, keeping its host bits.
+    pub fn translate(&self, addr: IpAddress) -> Option<IpAddress> {
+        rebase(addr, self.inside, self.outside)
+    }
+
+    /// Translates an outside address back to its inside address.
+    pub fn untranslate(&self, addr: IpAddress) -> Option<IpAddress> {
+        rebase(addr, self.outside, self.inside)
+    }
+
+    /// Translates an inside subnet to its outside subnet.
+    pub fn translate_network(&self, net: IpNetwork) -> Option<IpNetwork> {
+        if !net.is_subnet_of(&self.inside) {
+            return None;
+        }
+        let base = self.translate(net.network_address())?;
+        IpNetwork::new(base, net.num_network_bits())
+    }
+}
+
+/// Moves an address from one network to the same position within another of the same size.
+fn rebase(addr: IpAddress, from: IpNetwork, to: IpNetwork) -> Option<IpAddress> {
+    if !from.contains(addr) {
+        return None;
+    }
+    let host = addr & from.get_wildcard();
+    Some(to.network_address() | host)
+}
+
+impl fmt::Display for NatMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} <-> {}", self.inside, self.outside)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn validation() {
+        assert!(NatMap::new(net("10.0.0.0/24"), net("203.0.113.0/25")).is_none());
+        let map = NatMap::new(net("10.0.0.77/24"), net("203.0.113.9/24")).unwrap();
+        assert_eq!("10.0.0.0/24", map.inside().to_string());
+        assert_eq!("203.0.113.0/24", map.outside().to_string());
+        assert_eq!("10.0.0.0/24 <-> 203.0.113.0/24", map.to_string());
+    }
+
+    #[test]
+    fn translation() {
+        let map = NatMap::new(net("10.1.0.0/16"), net("198.18.0.0/16")).unwrap();
+        assert_eq!(Some(addr("198.18.4.5")), map.translate(addr("10.1.4.5")));
+        assert_eq!(Some(addr("10.1.4.5")), map.untranslate(addr("198.18.4.5")));
+        assert_eq!(
+            Some(addr("198.18.255.255")),
+            map.translate(addr("10.1.255.255"))
+        );
+        assert_eq!(None, map.translate(addr("10.2.0.0")));
+        assert_eq!(None, map.untranslate(addr("10.1.4.5")));
+        assert_eq!(
+            Some(addr("10.1.4.5")),
+            map.reversed().translate(addr("198.18.4.5"))
+        );
+        assert_eq!(
+            Some(net("198.18.128.0/20")),
+            map.translate_network(net("10.1.128.0/20"))
+        );
+        assert_eq!(None, map.translate_network(net("10.0.0.0/8")));
+
+        let all = NatMap::new(net("0.0.0.0/0"), net("0.0.0.0/0")).unwrap();
+        assert_eq!(Some(addr("1.2.3.4")), all.translate(addr("1.2.3.4")));
+        let host = NatMap::new(net("10.0.0.1/32"), net("192.0.2.1/32")).unwrap();
+        assert_eq!(Some(addr("192.0.2.1")), host.translate(addr("10.0.0.1")));
+    }
+}
@@ -3,13 +3,29 @@
 //! An IP Network is a grouping of hosts, which create a communication mesh. Depending
 //! on the context, the hosts within a network may have a special relationship. Just as the
 //! address is only an identifier of a host, a network is only an identifier of a set of hosts.
-use super::addr::IpAddress;
-use std::fmt;
+use super::{
+    addr::{AddrParseError, AddrParseErrorKind, IpAddress},
+    bits::AddressBits,
+    random::Rng,
+    range::IpRange,
+};
+use std::{cmp::Ordering, error, fmt, str};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The number of bits in an address, and so the longest prefix.
+const BITS: u8 = <u32 as AddressBits>::BITS;
+
+/// A network, identified by a base address and the length of its prefix.
+///
+/// Networks are ordered by their network address, then by their prefix length, so a supernet
+/// sorts before the subnets which share its network address. Networks which differ only in the
+/// host bits of their base addresses are ordered by those last.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct IpNetwork {
-    base: IpAddress,
+    /// The base address with its host bits cleared, kept so that comparisons need no masking.
+    network: IpAddress,
     prefix_len: u8,
+    /// The base address as it was given, host bits and all.
+    base: IpAddress,
 }
 
 impl IpNetwork {
@@ -19,13 +35,26 @@ impl IpNetwork {
     /// The prefix length **must** be between 0 and 32, inclusive. If it were not, then we would be
     /// left with a prefix length longer than the address. This is undefined behavior.
     pub fn new(base: IpAddress, prefix_len: u8) -> Option<Self> {
-        if (0..=32).contains(&prefix_len) {
-            Some(Self { base, prefix_len })
+        if prefix_len <= BITS {
+            Some(Self {
+                network: base & IpAddress::from(u32::mask(prefix_len)),
+                prefix_len,
+                base,
+            })
         } else {
             None
         }
     }
 
+    /// Creates a new IP Network from a base address and a netmask, such as `192.168.1.5` and
+    /// `255.255.255.0`, as interfaces are usually configured.
+    ///
+    /// The base address keeps any host bits, but the netmask **must** be a run of set bits
+    /// followed by a run of clear bits.
+    pub fn with_netmask(base: IpAddress, mask: IpAddress) -> Result<Self, BuildError> {
+        IpNetworkBuilder::new().base(base).mask(mask).build()
+    }
+
     /// The number of bits that compose the network prefix.
     ///
     /// This will be the number of leading bits that are required to be **identical** to the
@@ -38,14 +67,63 @@ impl IpNetwork {
     ///
     /// This will be the number of bits that differentiate each host within the network.
     pub fn num_host_bits(&self) -> u8 {
-        32 - self.num_network_bits()
+        BITS - self.num_network_bits()
     }
 
-    /// Gets the number of individual hosts that reside within this network.
-    pub fn num_hosts(&self) -> u64 {
+    /// Gets the number of addresses within this network, including its network and broadcast
+    /// addresses.
+    pub fn num_addresses(&self) -> u64 {
         1 << self.num_host_bits()
     }
 
+    /// Gets the number of addresses within this network which can be assigned to hosts.
+    ///
+    /// The network and broadcast addresses cannot be, except within a `/31`, which is a
+    /// point-to-point link of two hosts (RFC 3021), and a `/32`, which is a single host.
+    pub fn num_usable_hosts(&self) -> u64 {
+        match self.num_host_bits() {
+            0 => 1,
+            1 => 2,
+            bits => (1 << bits) - 2,
+        }
+    }
+
+    /// The first address which can be assigned to a host; in a `/31` or a `/32`, this is the
+    /// network address itself.
+    pub fn first_usable(&self) -> IpAddress {
+        match self.num_host_bits() {
+            0 | 1 => self.network,
+            _ => IpAddress::from(self.network.value() + 1),
+        }
+    }
+
+    /// The last address which can be assigned to a host; in a `/31` or a `/32`, this is the
+    /// broadcast address itself.
+    pub fn last_usable(&self) -> IpAddress {
+        match self.num_host_bits() {
+            0 | 1 => self.broadcast_address(),
+            _ => IpAddress::from(self.broadcast_address().value() - 1),
+        }
+    }
+
+    /// Every address which can be assigned to a host, from [`first_usable`](Self::first_usable)
+    /// to [`last_usable`](Self::last_usable), yielded lazily.
+    pub fn usable_hosts(&self) -> Addresses {
+        Addresses {
+            next: u64::from(self.first_usable().value()),
+            end: u64::from(self.last_usable().value()) + 1,
+        }
+    }
+
+    /// Gets the number of addresses within this network.
+    #[deprecated(
+        note = "use `num_addresses`, or `num_usable_hosts` to leave out the network and \
+                         broadcast addresses"
+    )]
+    pub fn num_hosts(&self) -> u64 {
+        self.num_addresses()
+    }
+
     /// The supernet of some IP network is one bit less-specific than its subnets. This means that
     /// the address space is one bit more ambiguous, and offers a power of two more addresses
     /// within the network set.
@@ -61,22 +139,572 @@ impl IpNetwork {
     /// number was irrelevant. Each of these children networks will contain exactly half of the
     /// supernet.
     pub fn subnets(self) -> Option<(Self, Self)> {
-        if let Some(lower_net) = Self::new(self.base, self.num_network_bits() + 1) {
-            let mut upper_net = lower_net.clone();
-            upper_net.base = (*lower_net.base | (1 << lower_net.num_host_bits())).into();
-            Some((upper_net, lower_net))
+        let bit = u32::next_bit(self.num_network_bits())?;
+        let lower_net = Self::new(self.network, self.num_network_bits() + 1)?;
+        let upper_net = Self::new(
+            IpAddress::from(self.network.value() | bit),
+            self.num_network_bits() + 1,
+        )?;
+        Some((upper_net, lower_net))
+    }
+
+    /// Shifts this network by whole networks of its own size: forward for a positive count, and
+    /// backward for a negative one, so that `offset(1)` is the next network of the same size.
+    /// The result has no host bits, and must lie within the address space.
+    pub fn offset(&self, k: i64) -> Option<Self> {
+        let start =
+            i128::from(self.network.value()) + i128::from(k) * i128::from(self.num_addresses());
+        if start < 0 || start + i128::from(self.num_addresses()) > 1 << BITS {
+            return None;
+        }
+        Self::new(IpAddress::from(start as u32), self.num_network_bits())
+    }
+
+    /// Divides this network into every one of its subnets with the specified prefix length, in
+    /// ascending order.
+    ///
+    /// The prefix length may not be shorter than this network's own, nor longer than 32.
+    pub fn subnets_with_prefix(&self, prefix_len: u8) -> Option<Subnets> {
+        if prefix_len < self.num_network_bits() || prefix_len > BITS {
+            return None;
+        }
+        let next = u64::from(self.network_address().value());
+        Some(Subnets {
+            next,
+            end: next + self.num_addresses(),
+            prefix_len,
+        })
+    }
+
+    /// Walks the tree of this network's subnets depth-first, each network before its subnets and
+    /// the lower half before the upper, yielding those with prefix lengths from `min_prefix` to
+    /// `max_prefix`, inclusive. The walk goes no deeper than `max_prefix`.
+    ///
+    /// The minimum may not be shorter than this network's own prefix, nor longer than the
+    /// maximum, which may not be longer than 32.
+    pub fn walk(&self, min_prefix: u8, max_prefix: u8) -> Option<Walk> {
+        if min_prefix < self.num_network_bits() || min_prefix > max_prefix || max_prefix > BITS {
+            return None;
+        }
+        let root = Self::new(self.network, self.num_network_bits()).unwrap();
+        Some(Walk {
+            stack: vec![root],
+            min_prefix,
+            max_prefix,
+            keep: |_| true,
+            last: None,
+        })
+    }
+
+    /// Reshapes this network to the specified prefix length: a shorter prefix gives the supernet
+    /// which encloses this network, and a longer prefix gives every subnet of this network.
+    ///
+    /// Either way, the result can be iterated over, so that a mix of networks can be brought to
+    /// the same granularity with one call, as in `nets.flat_map(|net| net.resize(24).unwrap())`.
+    /// The prefix length may not be longer than 32.
+    pub fn resize(&self, prefix_len: u8) -> Option<Resized> {
+        if prefix_len <= self.num_network_bits() {
+            let supernet = Self::new(self.base, prefix_len)?;
+            Self::new(supernet.network_address(), prefix_len).map(Resized::Supernet)
         } else {
-            None
+            self.subnets_with_prefix(prefix_len).map(Resized::Subnets)
         }
     }
 
     /// Creates the mask associated with this network, in IP Address form.
     pub fn get_mask(&self) -> IpAddress {
-        match self.num_network_bits() {
-            // Overflow check
-            32 => IpAddress::from(u32::MAX),
-            n => IpAddress::from(!(u32::MAX >> n)),
+        IpAddress::from(u32::mask(self.num_network_bits()))
+    }
+
+    /// Creates the wildcard (or host mask) associated with this network, in IP Address form.
+    ///
+    /// This is the inverse of the network mask, and is the form expected by many router ACLs.
+    pub fn get_wildcard(&self) -> IpAddress {
+        IpAddress::from(u32::wildcard(self.num_network_bits()))
+    }
+
+    /// Gets the first address within this network, which has all host bits set to `0`.
+    pub fn network_address(&self) -> IpAddress {
+        self.network
+    }
+
+    /// Gets the last address within this network, which has all host bits set to `1`.
+    pub fn broadcast_address(&self) -> IpAddress {
+        self.network | self.get_wildcard()
+    }
+
+    /// Gets the address at the specified offset from the start of this network, unless the offset
+    /// runs past the end of it.
+    pub fn nth(&self, n: u64) -> Option<IpAddress> {
+        if n >= self.num_addresses() {
+            return None;
         }
+        self.network_address().checked_add(n as u32)
+    }
+
+    /// Every address within this network, from the network address to the broadcast address,
+    /// yielded lazily.
+    pub fn addresses(&self) -> Addresses {
+        let next = u64::from(self.network.value());
+        Addresses {
+            next,
+            end: next + self.num_addresses(),
+        }
+    }
+
+    /// Whether the specified address lies within this network.
+    ///
+    /// Only the network bits are compared; the host bits of both the candidate and this
+    /// network's base address are irrelevant.
+    pub fn contains(&self, addr: IpAddress) -> bool {
+        addr & self.get_mask() == self.network
+    }
+
+    /// Whether this network is a subnet of the other; that is, whether every single address in
+    /// this network is also in the other. Every network is a subnet of itself.
+    pub fn is_subnet_of(&self, other: &Self) -> bool {
+        self.num_network_bits() >= other.num_network_bits() && other.contains(self.network)
+    }
+
+    /// Whether this network is a supernet of the other; that is, whether every single address in
+    /// the other network is also in this one. Every network is a supernet of itself.
+    pub fn is_supernet_of(&self, other: &Self) -> bool {
+        other.is_subnet_of(self)
+    }
+
+    /// Whether this network and the other describe the same network, with the same prefix
+    /// length and the same network bits. Unlike `==`, this ignores any host bits of their base
+    /// addresses, so `192.168.1.5/24` is the same network as `192.168.1.0/24`.
+    pub fn same_network(&self, other: &Self) -> bool {
+        self.prefix_len == other.prefix_len && self.network == other.network
+    }
+
+    /// Compares this network with the other by containment: `Less` if it is a subnet of the
+    /// other, `Greater` if it is a supernet, and `Equal` if they cover the same addresses.
+    ///
+    /// Two networks either nest or are disjoint, and disjoint networks are incomparable, so this
+    /// is `None` for them.
+    pub fn compare_containment(&self, other: &Self) -> Option<Ordering> {
+        match (self.is_subnet_of(other), self.is_supernet_of(other)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => None,
+        }
+    }
+
+    /// Whether every address of the range lies within this network. The range need not be
+    /// aligned to any network.
+    pub fn contains_range(&self, range: &IpRange) -> bool {
+        IpRange::from(*self).contains_range(range)
+    }
+
+    /// The addresses of the range which lie within this network, unless there are none.
+    pub fn intersection(&self, range: &IpRange) -> Option<IpRange> {
+        IpRange::from(*self).intersection(range)
+    }
+
+    /// The fraction of this network's addresses which lie within the range.
+    pub fn overlap_fraction(&self, range: &IpRange) -> f64 {
+        IpRange::from(*self).overlap_fraction(range)
+    }
+
+    /// Picks an address within this network uniformly at random.
+    pub fn random_address(&self, rng: &mut Rng) -> IpAddress {
+        let offset = rng.below(self.num_addresses()) as u32;
+        IpAddress::from(self.network_address().value() | offset)
+    }
+
+    /// Picks a subnet of this network with the specified prefix length uniformly at random.
+    ///
+    /// The prefix length may not be shorter than this network's own, nor longer than 32.
+    pub fn random_subnet(&self, prefix_len: u8, rng: &mut Rng) -> Option<IpNetwork> {
+        let count = self.subnets_with_prefix(prefix_len)?.len() as u64;
+        let offset = rng.below(count) << (32 - prefix_len);
+        IpNetwork::new(
+            IpAddress::from(self.network_address().value() | offset as u32),
+            prefix_len,
+        )
+    }
+}
+
+/// An iterator over the equally-sized subnets of a network.
+#[derive(Debug, Clone)]
+pub struct Subnets {
+    next: u64,
+    /// One past the last address of the parent network.
+    end: u64,
+    prefix_len: u8,
+}
+
+impl Iterator for Subnets {
+    type Item = IpNetwork;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        let net = IpNetwork::new(IpAddress::from(self.next as u32), self.prefix_len);
+        self.next += 1 << (32 - self.prefix_len);
+        net
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = ((self.end - self.next) >> (32 - self.prefix_len)) as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Subnets {}
+
+/// An iterator over every address of a network. See [`IpNetwork::addresses`].
+#[derive(Debug, Clone)]
+pub struct Addresses {
+    next: u64,
+    /// One past the last address.
+    end: u64,
+}
+
+impl Iterator for Addresses {
+    type Item = IpAddress;
+
+    fn next(&mut self) -> Option<IpAddress> {
+        self.nth(0)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<IpAddress> {
+        let addr = self.next.saturating_add(n as u64);
+        if addr >= self.end {
+            self.next = self.end;
+            return None;
+        }
+        self.next = addr + 1;
+        Some(IpAddress::from(addr as u32))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.next) as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Addresses {}
+
+impl IntoIterator for IpNetwork {
+    type Item = IpAddress;
+    type IntoIter = Addresses;
+
+    fn into_iter(self) -> Addresses {
+        self.addresses()
+    }
+}
+
+/// A preorder walk of the tree of a network's subnets. See [`IpNetwork::walk`].
+#[derive(Debug, Clone)]
+pub struct Walk<F = fn(&IpNetwork) -> bool> {
+    /// The networks still to be visited, with the next on top.
+    stack: Vec<IpNetwork>,
+    min_prefix: u8,
+    max_prefix: u8,
+    keep: F,
+    /// The network yielded last, whose subnets are yet to be pushed.
+    last: Option<IpNetwork>,
+}
+
+impl<F: FnMut(&IpNetwork) -> bool> Walk<F> {
+    /// Prunes the walk, visiting only the networks for which the predicate holds, and none of the
+    /// subnets of those for which it does not. The predicate sees every network the walk
+    /// reaches, including those shorter than the minimum prefix length.
+    pub fn prune<G: FnMut(&IpNetwork) -> bool>(self, keep: G) -> Walk<G> {
+        Walk {
+            stack: self.stack,
+            min_prefix: self.min_prefix,
+            max_prefix: self.max_prefix,
+            keep,
+            last: self.last,
+        }
+    }
+
+    /// Skips the subnets of the network yielded last.
+    pub fn skip_subtree(&mut self) {
+        self.last = None;
+    }
+
+    /// Pushes the subnets of the network, upper half first, so that the lower is visited first.
+    fn descend(&mut self, net: IpNetwork) {
+        if net.num_network_bits() < self.max_prefix {
+            let (upper, lower) = net.subnets().unwrap();
+            self.stack.push(upper);
+            self.stack.push(lower);
+        }
+    }
+}
+
+impl<F: FnMut(&IpNetwork) -> bool> Iterator for Walk<F> {
+    type Item = IpNetwork;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(last) = self.last.take() {
+            self.descend(last);
+        }
+        while let Some(net) = self.stack.pop() {
+            if !(self.keep)(&net) {
+                continue;
+            }
+            if net.num_network_bits() >= self.min_prefix {
+                self.last = Some(net);
+                return Some(net);
+            }
+            self.descend(net);
+        }
+        None
+    }
+}
+
+/// A network reshaped to a new prefix length. See [`IpNetwork::resize`].
+#[derive(Debug, Clone)]
+pub enum Resized {
+    /// The network which encloses the original, which may be the original itself, without any
+    /// host bits.
+    Supernet(IpNetwork),
+    /// The subnets which divide up the original, in ascending order.
+    Subnets(Subnets),
+}
+
+impl IntoIterator for Resized {
+    type Item = IpNetwork;
+    type IntoIter = Subnets;
+
+    fn into_iter(self) -> Subnets {
+        match self {
+            // The one subnet of a network, with its own prefix length, is itself.
+            Self::Supernet(net) => net.subnets_with_prefix(net.num_network_bits()).unwrap(),
+            Self::Subnets(subnets) => subnets,
+        }
+    }
+}
+
+/// Builds a network out of whichever of its descriptions are at hand: a base address, a prefix
+/// length, a netmask, a wildcard mask, or the first and last addresses. Each description which is
+/// given must agree with every other.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpNetworkBuilder {
+    base: Option<IpAddress>,
+    prefix_len: Option<u8>,
+    mask: Option<IpAddress>,
+    wildcard: Option<IpAddress>,
+    endpoints: Option<(IpAddress, IpAddress)>,
+}
+
+impl IpNetworkBuilder {
+    /// Creates a builder with nothing described yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The base address of the network, which may have host bits set.
+    pub fn base(mut self, base: IpAddress) -> Self {
+        self.base = Some(base);
+        self
+    }
+
+    /// The length of the network's prefix.
+    pub fn prefix_len(mut self, prefix_len: u8) -> Self {
+        self.prefix_len = Some(prefix_len);
+        self
+    }
+
+    /// The netmask of the network, as in `255.255.255.0`.
+    pub fn mask(mut self, mask: IpAddress) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// The wildcard mask of the network, as in `0.0.0.255`.
+    pub fn wildcard(mut self, wildcard: IpAddress) -> Self {
+        self.wildcard = Some(wildcard);
+        self
+    }
+
+    /// The first and last addresses of the network.
+    pub fn endpoints(mut self, first: IpAddress, last: IpAddress) -> Self {
+        self.endpoints = Some((first, last));
+        self
+    }
+
+    /// Builds the network, making sure that every description agrees.
+    pub fn build(self) -> Result<IpNetwork, BuildError> {
+        let mut lengths = Vec::new();
+        if let Some(prefix_len) = self.prefix_len {
+            if prefix_len > BITS {
+                return Err(BuildError::InvalidPrefixLen(prefix_len));
+            }
+            lengths.push((Constraint::PrefixLen, prefix_len));
+        }
+        if let Some(mask) = self.mask {
+            let prefix_len = mask.value().prefix_len_of_mask();
+            lengths.push((
+                Constraint::Mask,
+                prefix_len.ok_or(BuildError::InvalidMask(mask))?,
+            ));
+        }
+        if let Some(wildcard) = self.wildcard {
+            let prefix_len = (!wildcard).value().prefix_len_of_mask();
+            let prefix_len = prefix_len.ok_or(BuildError::InvalidWildcard(wildcard))?;
+            lengths.push((Constraint::Wildcard, prefix_len));
+        }
+        if let Some((first, last)) = self.endpoints {
+            if first > last {
+                return Err(BuildError::InvalidEndpoints(first, last));
+            }
+            let prefix_len = u32::largest_block(first.value(), last.value());
+            if first.value() | u32::wildcard(prefix_len) != last.value() {
+                return Err(BuildError::InvalidEndpoints(first, last));
+            }
+            lengths.push((Constraint::Endpoints, prefix_len));
+        }
+
+        let (from, prefix_len) = *lengths.first().ok_or(BuildError::MissingPrefixLen)?;
+        if let Some(&(other, _)) = lengths.iter().find(|&&(_, len)| len != prefix_len) {
+            return Err(BuildError::Conflict(from, other));
+        }
+        let base = match (self.base, self.endpoints) {
+            (Some(base), Some((first, _))) => {
+                if base & IpAddress::from(u32::mask(prefix_len)) != first {
+                    return Err(BuildError::Conflict(
+                        Constraint::Base,
+                        Constraint::Endpoints,
+                    ));
+                }
+                base
+            }
+            (Some(base), None) => base,
+            (None, Some((first, _))) => first,
+            (None, None) => return Err(BuildError::MissingBase),
+        };
+        Ok(IpNetwork::new(base, prefix_len).unwrap())
+    }
+}
+
+/// One of the descriptions given to an [`IpNetworkBuilder`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Constraint {
+    Base,
+    PrefixLen,
+    Mask,
+    Wildcard,
+    Endpoints,
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Constraint::Base => "base address",
+            Constraint::PrefixLen => "prefix length",
+            Constraint::Mask => "netmask",
+            Constraint::Wildcard => "wildcard mask",
+            Constraint::Endpoints => "endpoints",
+        })
+    }
+}
+
+/// The error returned when an [`IpNetworkBuilder`] does not describe a network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// Neither a base address nor the endpoints were given.
+    MissingBase,
+    /// None of a prefix length, a netmask, a wildcard mask, or the endpoints were given.
+    MissingPrefixLen,
+    /// The prefix length is longer than 32.
+    InvalidPrefixLen(u8),
+    /// The netmask is not a run of set bits followed by a run of clear bits.
+    InvalidMask(IpAddress),
+    /// The wildcard mask is not a run of clear bits followed by a run of set bits.
+    InvalidWildcard(IpAddress),
+    /// The first and last addresses do not begin and end a single network.
+    InvalidEndpoints(IpAddress, IpAddress),
+    /// Two descriptions describe different networks.
+    Conflict(Constraint, Constraint),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingBase => f.write_str("no base address or endpoints"),
+            BuildError::MissingPrefixLen => {
+                f.write_str("no prefix length, netmask, wildcard mask, or endpoints")
+            }
+            BuildError::InvalidPrefixLen(len) => write!(f, "invalid prefix length `{}`", len),
+            BuildError::InvalidMask(mask) => write!(f, "invalid netmask `{}`", mask),
+            BuildError::InvalidWildcard(mask) => write!(f, "invalid wildcard mask `{}`", mask),
+            BuildError::InvalidEndpoints(first, last) => {
+                write!(f, "`{}` to `{}` is not a single network", first, last)
+            }
+            BuildError::Conflict(first, second) => {
+                write!(f, "the {} and the {} disagree", first, second)
+            }
+        }
+    }
+}
+
+impl error::Error for BuildError {}
+
+/// The error returned when a string could not be parsed as an IP Network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetParseError(pub(crate) NetParseErrorKind);
+
+impl NetParseError {
+    /// Which part of the string was wrong.
+    pub fn kind(&self) -> NetParseErrorKind {
+        self.0
+    }
+}
+
+/// The ways in which a string may fail to be a network.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NetParseErrorKind {
+    /// The base address could not be parsed, for the reason given.
+    Address(AddrParseErrorKind),
+    /// The prefix length was missing, was not a number, or was too long for the address.
+    PrefixLen,
+    /// The network was rejected in some other way, such as by stricter parsing options.
+    Syntax,
+}
+
+impl fmt::Display for NetParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            NetParseErrorKind::Address(kind) => {
+                write!(f, "invalid base address: {}", AddrParseError(kind))
+            }
+            NetParseErrorKind::PrefixLen => f.write_str("invalid prefix length"),
+            NetParseErrorKind::Syntax => f.write_str("invalid IP network syntax"),
+        }
+    }
+}
+
+impl error::Error for NetParseError {}
+
+impl From<AddrParseError> for NetParseError {
+    fn from(err: AddrParseError) -> Self {
+        Self(NetParseErrorKind::Address(err.kind()))
+    }
+}
+
+impl str::FromStr for IpNetwork {
+    type Err = NetParseError;
+
+    /// Parses a network from CIDR notation, such as `10.0.0.0/8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '/');
+        let base = parts.next().unwrap_or_default().parse()?;
+        const INVALID: NetParseError = NetParseError(NetParseErrorKind::PrefixLen);
+        let prefix = parts.next().ok_or(INVALID)?;
+        if prefix.is_empty() || prefix.len() > 2 || !prefix.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(INVALID);
+        }
+        let prefix_len = prefix.parse().map_err(|_| INVALID)?;
+        Self::new(base, prefix_len).ok_or(INVALID)
     }
 }
 
@@ -86,6 +714,22 @@ impl fmt::Display for IpNetwork {
     }
 }
 
+/// Shows the network in CIDR notation, as in `IpNetwork(10.0.0.0/8)`, or field by field under
+/// `{:#?}`.
+impl fmt::Debug for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.debug_struct("IpNetwork")
+                .field("network", &self.network)
+                .field("prefix_len", &self.prefix_len)
+                .field("base", &self.base)
+                .finish()
+        } else {
+            write!(f, "IpNetwork({})", self)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -104,6 +748,69 @@ mod tests {
         );
     }
 
+    #[test]
+    fn debug() {
+        let net = IpNetwork::new(IpAddress::from([10, 0, 0, 0]), 8).unwrap();
+        assert_eq!("IpNetwork(10.0.0.0/8)", format!("{:?}", net));
+        assert_eq!(
+            "[IpNetwork(10.0.0.0/8), IpNetwork(10.0.0.0/8)]",
+            format!("{:?}", [net; 2])
+        );
+        assert_eq!(
+            "IpNetwork {\n    network: IpAddress(\n        167772160,\n    ),\n    prefix_len: 8,\n    \
+             base: IpAddress(\n        167772160,\n    ),\n}",
+            format!("{:#?}", net)
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let net = |s: &str| s.parse::<IpNetwork>().unwrap();
+        let mut nets = vec![
+            net("10.0.1.0/24"),
+            net("10.0.0.0/24"),
+            net("10.0.0.0/8"),
+            net("9.255.0.0/16"),
+            net("10.0.0.0/8"),
+        ];
+        nets.sort();
+        nets.dedup();
+        assert_eq!(
+            vec![
+                net("9.255.0.0/16"),
+                net("10.0.0.0/8"),
+                net("10.0.0.0/24"),
+                net("10.0.1.0/24"),
+            ],
+            nets
+        );
+
+        let unique: std::collections::HashSet<_> = nets.iter().chain(&nets).collect();
+        assert_eq!(4, unique.len());
+    }
+
+    #[test]
+    fn containment() {
+        let net = |s: &str| s.parse::<IpNetwork>().unwrap();
+        let compare = |a: &str, b: &str| net(a).compare_containment(&net(b));
+        assert_eq!(Some(Ordering::Less), compare("10.1.0.0/16", "10.0.0.0/8"));
+        assert_eq!(Some(Ordering::Greater), compare("0.0.0.0/0", "10.0.0.0/8"));
+        assert_eq!(Some(Ordering::Equal), compare("10.0.0.0/8", "10.0.0.0/8"));
+        assert_eq!(Some(Ordering::Equal), compare("10.9.9.9/8", "10.0.0.0/8"));
+        assert_eq!(None, compare("10.0.0.0/8", "11.0.0.0/8"));
+        assert_eq!(None, compare("10.0.0.0/24", "10.0.1.0/24"));
+    }
+
+    #[test]
+    fn same_network() {
+        let net = |s: &str| s.parse::<IpNetwork>().unwrap();
+        assert!(net("192.168.1.5/24").same_network(&net("192.168.1.0/24")));
+        assert_ne!(net("192.168.1.5/24"), net("192.168.1.0/24"));
+        assert!(!net("192.168.1.0/24").same_network(&net("192.168.1.0/25")));
+        assert!(!net("192.168.1.0/24").same_network(&net("192.168.2.0/24")));
+        assert!(net("1.2.3.4/0").same_network(&net("0.0.0.0/0")));
+    }
+
     #[test]
     fn bad_prefix_len() {
         for i in 0..=32 {
@@ -202,30 +909,493 @@ mod tests {
     }
 
     #[test]
-    fn num_hosts() {
+    fn parse() {
+        assert_eq!(
+            Ok(IpNetwork::new(IpAddress::from([10, 0, 0, 0]), 8).unwrap()),
+            "10.0.0.0/8".parse()
+        );
+        assert_eq!(
+            Ok(IpNetwork::new(IpAddress::from([192, 168, 1, 5]), 24).unwrap()),
+            "192.168.1.5/24".parse()
+        );
+        assert_eq!(
+            Ok(IpNetwork::new(IpAddress::from(0), 0).unwrap()),
+            "0.0.0.0/0".parse()
+        );
+        for bad in &[
+            "",
+            "10.0.0.0",
+            "10.0.0.0/",
+            "10.0.0.0/33",
+            "10.0.0.0/+8",
+            "10.0.0/8",
+            "/8",
+            "10.0.0.0/8/8",
+            "10.0.0.0/008",
+        ] {
+            assert!(bad.parse::<IpNetwork>().is_err(), "{}", bad);
+        }
+        let kind = |s: &str| s.parse::<IpNetwork>().unwrap_err().kind();
+        assert_eq!(
+            NetParseErrorKind::Address(AddrParseErrorKind::TooFewOctets),
+            kind("10.0.0/8")
+        );
+        assert_eq!(
+            NetParseErrorKind::Address(AddrParseErrorKind::OctetOutOfRange),
+            kind("10.0.0.256/8")
+        );
+        for bad in &["10.0.0.0", "10.0.0.0/", "10.0.0.0/33", "10.0.0.0/8/8"] {
+            assert_eq!(NetParseErrorKind::PrefixLen, kind(bad), "{}", bad);
+        }
+        assert_eq!(
+            "invalid base address: empty octet in IP address",
+            "/8".parse::<IpNetwork>().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn boundaries() {
+        let net: IpNetwork = "192.168.1.5/24".parse().unwrap();
+        assert_eq!(IpAddress::from([192, 168, 1, 0]), net.network_address());
+        assert_eq!(IpAddress::from([192, 168, 1, 255]), net.broadcast_address());
+        assert_eq!(IpAddress::from([0, 0, 0, 255]), net.get_wildcard());
+
+        let net: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert_eq!(IpAddress::from(0), net.network_address());
+        assert_eq!(IpAddress::from(u32::MAX), net.broadcast_address());
+
+        let net: IpNetwork = "10.1.2.3/32".parse().unwrap();
+        assert_eq!(IpAddress::from([10, 1, 2, 3]), net.network_address());
+        assert_eq!(IpAddress::from([10, 1, 2, 3]), net.broadcast_address());
+        assert_eq!(IpAddress::from(0), net.get_wildcard());
+    }
+
+    #[test]
+    fn contains() {
+        let net: IpNetwork = "192.168.1.5/24".parse().unwrap();
+        assert!(net.contains(IpAddress::from([192, 168, 1, 0])));
+        assert!(net.contains(IpAddress::from([192, 168, 1, 255])));
+        assert!(!net.contains(IpAddress::from([192, 168, 2, 0])));
+        assert!(!net.contains(IpAddress::from([192, 168, 0, 255])));
+
+        let net: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert!(net.contains(IpAddress::from(0)));
+        assert!(net.contains(IpAddress::from(u32::MAX)));
+
+        let net: IpNetwork = "10.1.2.3/32".parse().unwrap();
+        assert!(net.contains(IpAddress::from([10, 1, 2, 3])));
+        assert!(!net.contains(IpAddress::from([10, 1, 2, 4])));
+    }
+
+    #[test]
+    fn subnet_of() {
+        let net = |s: &str| s.parse::<IpNetwork>().unwrap();
+        assert!(net("10.1.0.0/16").is_subnet_of(&net("10.0.0.0/8")));
+        assert!(net("10.0.0.0/8").is_subnet_of(&net("10.0.0.0/8")));
+        assert!(net("10.0.0.0/8").is_subnet_of(&net("0.0.0.0/0")));
+        assert!(!net("10.0.0.0/8").is_subnet_of(&net("10.1.0.0/16")));
+        assert!(!net("11.0.0.0/16").is_subnet_of(&net("10.0.0.0/8")));
+        assert!(net("10.0.0.0/8").is_supernet_of(&net("10.1.2.3/32")));
+        assert!(!net("10.0.0.0/8").is_supernet_of(&net("0.0.0.0/0")));
+    }
+
+    #[test]
+    fn subnets_with_prefix() {
+        let net: IpNetwork = "10.0.0.77/22".parse().unwrap();
+        let subnets: Vec<_> = net
+            .subnets_with_prefix(24)
+            .unwrap()
+            .map(|net| net.to_string())
+            .collect();
+        assert_eq!(
+            vec!["10.0.0.0/24", "10.0.1.0/24", "10.0.2.0/24", "10.0.3.0/24"],
+            subnets
+        );
+        assert_eq!(1, net.subnets_with_prefix(22).unwrap().len());
+        assert_eq!(1024, net.subnets_with_prefix(32).unwrap().len());
+        assert!(net.subnets_with_prefix(21).is_none());
+        assert!(net.subnets_with_prefix(33).is_none());
+
+        let everything: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        let mut halves = everything.subnets_with_prefix(1).unwrap();
+        assert_eq!(Some("0.0.0.0/1".parse().unwrap()), halves.next());
+        assert_eq!(Some("128.0.0.0/1".parse().unwrap()), halves.next());
+        assert_eq!(None, halves.next());
+        assert_eq!(1 << 32, everything.subnets_with_prefix(32).unwrap().len());
+    }
+
+    #[test]
+    fn offset() {
+        let net = |s: &str| s.parse::<IpNetwork>().unwrap();
+        let site = |n| net("10.8.0.0/22").offset(n).map(|net| net.to_string());
+        assert_eq!(Some("10.8.0.0/22".to_string()), site(0));
+        assert_eq!(Some("10.8.4.0/22".to_string()), site(1));
+        assert_eq!(Some("10.8.20.0/22".to_string()), site(5));
+        assert_eq!(Some("10.7.252.0/22".to_string()), site(-1));
+        assert_eq!(
+            Some("10.8.4.0/22".to_string()),
+            net("10.8.3.7/22").offset(1).map(|net| net.to_string())
+        );
+
+        assert_eq!(
+            Some(net("255.255.255.0/24")),
+            net("255.255.254.0/24").offset(1)
+        );
+        assert_eq!(None, net("255.255.255.0/24").offset(1));
+        assert_eq!(None, net("0.0.0.0/24").offset(-1));
+        assert_eq!(None, net("0.0.0.0/0").offset(1));
+        assert_eq!(None, net("10.0.0.0/8").offset(i64::MIN));
+        assert_eq!(Some(net("0.0.0.0/8")), net("10.0.0.0/8").offset(-10));
+    }
+
+    #[test]
+    fn walk() {
+        let net: IpNetwork = "10.0.0.77/24".parse().unwrap();
+        let walked: Vec<_> = net
+            .walk(25, 26)
+            .unwrap()
+            .map(|net| net.to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "10.0.0.0/25",
+                "10.0.0.0/26",
+                "10.0.0.64/26",
+                "10.0.0.128/25",
+                "10.0.0.128/26",
+                "10.0.0.192/26"
+            ],
+            walked
+        );
+        assert_eq!(1, net.walk(24, 24).unwrap().count());
+        assert_eq!(511, net.walk(24, 32).unwrap().count());
+        assert!(net.walk(23, 24).is_none());
+        assert!(net.walk(26, 25).is_none());
+        assert!(net.walk(24, 33).is_none());
+
+        // Find the largest blocks wholly within a range, without visiting any outside it.
+        let (first, last) = (0x0a00_0014, 0x0a00_0063);
+        let mut visited = 0;
+        let mut walk = net.walk(24, 32).unwrap().prune(|net| {
+            visited += 1;
+            let (start, end) = (
+                net.network_address().value(),
+                net.broadcast_address().value(),
+            );
+            start <= last && end >= first
+        });
+        let mut covered = Vec::new();
+        while let Some(net) = walk.next() {
+            let (start, end) = (
+                net.network_address().value(),
+                net.broadcast_address().value(),
+            );
+            if start >= first && end <= last {
+                covered.push(net.to_string());
+                walk.skip_subtree();
+            }
+        }
+        assert_eq!(
+            vec![
+                "10.0.0.20/30",
+                "10.0.0.24/29",
+                "10.0.0.32/27",
+                "10.0.0.64/27",
+                "10.0.0.96/30"
+            ],
+            covered
+        );
+        assert!(visited < 40, "{}", visited);
+    }
+
+    #[test]
+    fn resize() {
+        let net = |s: &str| s.parse::<IpNetwork>().unwrap();
+        let strings = |resized: Resized| -> Vec<String> {
+            resized.into_iter().map(|n| n.to_string()).collect()
+        };
+        match net("10.1.2.3/24").resize(8) {
+            Some(Resized::Supernet(supernet)) => assert_eq!(net("10.0.0.0/8"), supernet),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(
+            vec!["10.1.2.0/24"],
+            strings(net("10.1.2.3/24").resize(24).unwrap())
+        );
+        assert_eq!(
+            vec![
+                "10.1.2.0/26",
+                "10.1.2.64/26",
+                "10.1.2.128/26",
+                "10.1.2.192/26"
+            ],
+            strings(net("10.1.2.0/24").resize(26).unwrap())
+        );
+        assert_eq!(
+            vec!["0.0.0.0/0"],
+            strings(net("1.2.3.4/32").resize(0).unwrap())
+        );
+        assert!(net("10.0.0.0/8").resize(33).is_none());
+
+        let uniform: Vec<String> = vec![net("10.0.0.0/23"), net("10.0.5.7/24"), net("10.0.9.0/25")]
+            .into_iter()
+            .flat_map(|n| n.resize(24).unwrap())
+            .map(|n| n.to_string())
+            .collect();
+        assert_eq!(
+            vec!["10.0.0.0/24", "10.0.1.0/24", "10.0.5.0/24", "10.0.9.0/24"],
+            uniform
+        );
+    }
+
+    #[test]
+    fn nth() {
+        let net: IpNetwork = "10.1.2.3/24".parse().unwrap();
+        assert_eq!(Some(IpAddress::from([10, 1, 2, 0])), net.nth(0));
+        assert_eq!(Some(IpAddress::from([10, 1, 2, 255])), net.nth(255));
+        assert_eq!(None, net.nth(256));
+        let everything: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert_eq!(
+            Some(IpAddress::from(u32::MAX)),
+            everything.nth(u64::from(u32::MAX))
+        );
+        assert_eq!(None, everything.nth(1 << 32));
+    }
+
+    #[test]
+    fn addresses() {
+        let net: IpNetwork = "10.1.2.3/30".parse().unwrap();
+        let addrs: Vec<_> = net.into_iter().map(|addr| addr.to_string()).collect();
+        assert_eq!(vec!["10.1.2.0", "10.1.2.1", "10.1.2.2", "10.1.2.3"], addrs);
+
+        let mut big = "10.0.0.0/16".parse::<IpNetwork>().unwrap().addresses();
+        assert_eq!(65536, big.len());
+        assert_eq!(Some(IpAddress::from([10, 0, 1, 0])), big.nth(256));
+        assert_eq!(65279, big.len());
+        assert_eq!(Some(IpAddress::from([10, 0, 255, 255])), big.last());
+
+        let everything: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        let mut all = everything.addresses();
+        assert_eq!(Some(IpAddress::from(u32::MAX)), all.nth(u32::MAX as usize));
+        assert_eq!(None, all.next());
+        let one: IpNetwork = "255.255.255.255/32".parse().unwrap();
+        assert_eq!(1, one.addresses().count());
+    }
+
+    /// Every operation at every prefix length, on the addresses at the edges of the address space,
+    /// gives an answer rather than panicking.
+    #[test]
+    fn edges() {
+        for &base in &[0, 1, 0x7fff_ffff, 0x8000_0000, u32::MAX - 1, u32::MAX] {
+            for prefix_len in 0..=BITS {
+                let net = IpNetwork::new(IpAddress::from(base), prefix_len).unwrap();
+                net.supernet();
+                net.subnets();
+                net.nth(net.num_addresses() - 1).unwrap();
+                assert!(net.nth(net.num_addresses()).is_none());
+                assert!(net.contains(net.broadcast_address()));
+                for len in 0..=BITS + 1 {
+                    net.resize(len).into_iter().flatten().take(2).count();
+                    assert_eq!(
+                        len >= prefix_len && len <= BITS,
+                        net.subnets_with_prefix(len).is_some()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn with_netmask() {
+        let addr = |s: &str| s.parse::<IpAddress>().unwrap();
+        let net = IpNetwork::with_netmask(addr("192.168.1.5"), addr("255.255.255.0")).unwrap();
+        assert_eq!("192.168.1.5/24", net.to_string());
+        let host = IpNetwork::with_netmask(addr("192.168.1.5"), addr("255.255.255.255"));
+        assert_eq!(Ok(32), host.map(|net| net.num_network_bits()));
+        let everything = IpNetwork::with_netmask(addr("192.168.1.5"), addr("0.0.0.0"));
+        assert_eq!(Ok(0), everything.map(|net| net.num_network_bits()));
+        assert_eq!(
+            Err(BuildError::InvalidMask(addr("255.255.0.255"))),
+            IpNetwork::with_netmask(addr("192.168.1.5"), addr("255.255.0.255"))
+        );
+    }
+
+    #[test]
+    fn builder() {
+        let addr = |s: &str| s.parse::<IpAddress>().unwrap();
+        let expected: IpNetwork = "10.1.2.0/24".parse().unwrap();
+        let built = IpNetworkBuilder::new()
+            .base(addr("10.1.2.0"))
+            .mask(addr("255.255.255.0"))
+            .wildcard(addr("0.0.0.255"))
+            .prefix_len(24)
+            .build();
+        assert_eq!(Ok(expected), built);
+        let built = IpNetworkBuilder::new()
+            .endpoints(addr("10.1.2.0"), addr("10.1.2.255"))
+            .build();
+        assert_eq!(Ok(expected), built);
+        let built = IpNetworkBuilder::new()
+            .base(addr("10.1.2.3"))
+            .prefix_len(24)
+            .build();
+        assert_eq!("10.1.2.3/24", built.unwrap().to_string());
+
+        let conflict = IpNetworkBuilder::new()
+            .base(addr("10.1.2.0"))
+            .prefix_len(24)
+            .mask(addr("255.255.0.0"))
+            .build();
+        assert_eq!(
+            Err(BuildError::Conflict(
+                Constraint::PrefixLen,
+                Constraint::Mask
+            )),
+            conflict
+        );
+        let conflict = IpNetworkBuilder::new()
+            .base(addr("10.1.3.0"))
+            .endpoints(addr("10.1.2.0"), addr("10.1.2.255"))
+            .build();
+        assert_eq!(
+            "the base address and the endpoints disagree",
+            conflict.unwrap_err().to_string()
+        );
+        for (builder, err) in &[
+            (
+                IpNetworkBuilder::new().prefix_len(8),
+                BuildError::MissingBase,
+            ),
+            (
+                IpNetworkBuilder::new().base(addr("10.0.0.0")),
+                BuildError::MissingPrefixLen,
+            ),
+            (
+                IpNetworkBuilder::new().prefix_len(33),
+                BuildError::InvalidPrefixLen(33),
+            ),
+            (
+                IpNetworkBuilder::new().mask(addr("255.0.255.0")),
+                BuildError::InvalidMask(addr("255.0.255.0")),
+            ),
+            (
+                IpNetworkBuilder::new().wildcard(addr("255.0.0.0")),
+                BuildError::InvalidWildcard(addr("255.0.0.0")),
+            ),
+            (
+                IpNetworkBuilder::new().endpoints(addr("10.0.0.1"), addr("10.0.0.2")),
+                BuildError::InvalidEndpoints(addr("10.0.0.1"), addr("10.0.0.2")),
+            ),
+            (
+                IpNetworkBuilder::new().endpoints(addr("10.0.0.2"), addr("10.0.0.1")),
+                BuildError::InvalidEndpoints(addr("10.0.0.2"), addr("10.0.0.1")),
+            ),
+        ] {
+            assert_eq!(Err(err.clone()), builder.clone().build());
+        }
+    }
+
+    #[test]
+    fn host_bits() {
+        let net: IpNetwork = "10.0.0.200/24".parse().unwrap();
+        assert_eq!("10.0.0.200/24", net.to_string());
+        assert_eq!(IpAddress::from([10, 0, 0, 0]), net.network_address());
+        assert_eq!(
+            Some((
+                "10.0.0.128/25".parse().unwrap(),
+                "10.0.0.0/25".parse().unwrap()
+            )),
+            net.subnets()
+        );
+        let canonical: IpNetwork = "10.0.0.0/24".parse().unwrap();
+        assert_ne!(canonical, net);
+        assert!(canonical < net && net < "10.0.0.0/25".parse().unwrap());
+        assert!(canonical.same_network(&net));
+    }
+
+    #[test]
+    fn num_usable_hosts() {
+        let usable = |s: &str| s.parse::<IpNetwork>().unwrap().num_usable_hosts();
+        assert_eq!(1, usable("10.0.0.1/32"));
+        assert_eq!(2, usable("10.0.0.0/31"));
+        assert_eq!(2, usable("10.0.0.0/30"));
+        assert_eq!(254, usable("10.0.0.0/24"));
+        assert_eq!(4294967294, usable("0.0.0.0/0"));
+
+        let hosts = |s: &str| {
+            let net = s.parse::<IpNetwork>().unwrap();
+            let addrs: Vec<_> = net.usable_hosts().map(|a| a.to_string()).collect();
+            assert_eq!(net.num_usable_hosts(), addrs.len() as u64);
+            assert_eq!(net.first_usable().to_string(), addrs[0]);
+            assert_eq!(net.last_usable().to_string(), addrs[addrs.len() - 1]);
+            addrs
+        };
+        assert_eq!(vec!["10.0.0.1"], hosts("10.0.0.1/32"));
+        assert_eq!(vec!["10.0.0.0", "10.0.0.1"], hosts("10.0.0.0/31"));
+        assert_eq!(vec!["10.0.0.1", "10.0.0.2"], hosts("10.0.0.3/30"));
+        assert_eq!(254, hosts("192.168.7.0/24").len());
+        let everything: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        assert_eq!(IpAddress::from(1), everything.first_usable());
+        assert_eq!(IpAddress::from(u32::MAX - 1), everything.last_usable());
+        assert_eq!(4294967294, everything.usable_hosts().len());
+    }
+
+    #[test]
+    fn num_addresses() {
         assert_eq!(
             1,
-            IpNetwork::new(IpAddress::from(0), 32).unwrap().num_hosts()
+            IpNetwork::new(IpAddress::from(0), 32)
+                .unwrap()
+                .num_addresses()
         );
         assert_eq!(
             2,
-            IpNetwork::new(IpAddress::from(0), 31).unwrap().num_hosts()
+            IpNetwork::new(IpAddress::from(0), 31)
+                .unwrap()
+                .num_addresses()
         );
         assert_eq!(
             4096,
-            IpNetwork::new(IpAddress::from(0), 20).unwrap().num_hosts()
+            IpNetwork::new(IpAddress::from(0), 20)
+                .unwrap()
+                .num_addresses()
         );
         assert_eq!(
             4194304,
-            IpNetwork::new(IpAddress::from(0), 10).unwrap().num_hosts()
+            IpNetwork::new(IpAddress::from(0), 10)
+                .unwrap()
+                .num_addresses()
         );
         assert_eq!(
             2147483648,
-            IpNetwork::new(IpAddress::from(0), 1).unwrap().num_hosts()
+            IpNetwork::new(IpAddress::from(0), 1)
+                .unwrap()
+                .num_addresses()
         );
         assert_eq!(
             4294967296,
-            IpNetwork::new(IpAddress::from(0), 0).unwrap().num_hosts()
+            IpNetwork::new(IpAddress::from(0), 0)
+                .unwrap()
+                .num_addresses()
         );
     }
+
+    #[test]
+    fn within_network() {
+        let mut rng = Rng::seed_from_u64(0);
+        let net: IpNetwork = "10.20.0.0/14".parse().unwrap();
+        for _ in 0..100 {
+            assert!(net.contains(net.random_address(&mut rng)));
+            let subnet = net.random_subnet(24, &mut rng).unwrap();
+            assert!(subnet.is_subnet_of(&net));
+            assert_eq!(24, subnet.num_network_bits());
+        }
+        assert!(net.random_subnet(13, &mut rng).is_none());
+
+        let host: IpNetwork = "1.2.3.4/32".parse().unwrap();
+        assert_eq!(IpAddress::from([1, 2, 3, 4]), host.random_address(&mut rng));
+        let everything: IpNetwork = "0.0.0.0/0".parse().unwrap();
+        everything.random_address(&mut rng);
+        everything.random_subnet(32, &mut rng).unwrap();
+    }
 }
@@ -0,0 +1,454 @@
+//! # The IPv6-Network Module
+//!
+//! An IPv6 Network is a prefix of up to 128 bits, and otherwise behaves exactly like its IPv4
+//! counterpart. The address space is far too large to ever enumerate, so the aggregation,
+//! exclusion, and spanning algorithms here all work on the boundaries of networks, never on their
+//! individual addresses.
+use super::{
+    addr6::Ipv6Address,
+    bits::{self, AddressBits},
+    net::{NetParseError, NetParseErrorKind},
+    random::{PermutedSubnets, Rng},
+};
+use std::{fmt, str};
+
+/// The number of bits in an address, and so the longest prefix.
+const BITS: u8 = <u128 as AddressBits>::BITS;
+
+/// An IPv6 network, identified by a base address and the length of its prefix.
+///
+/// Networks are ordered by their network address, then by their prefix length, so a supernet
+/// sorts before the subnets which share its network address. Networks which differ only in the
+/// host bits of their base addresses are ordered by those last.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ipv6Network {
+    /// The base address with its host bits cleared, kept so that comparisons need no masking.
+    network: Ipv6Address,
+    prefix_len: u8,
+    /// The base address as it was given, host bits and all.
+    base: Ipv6Address,
+}
+
+impl Ipv6Network {
+    /// Creates a new network with the specified base address and prefix length (in *bits*).
+    ///
+    /// The prefix length **must** be between 0 and 128, inclusive.
+    pub fn new(base: Ipv6Address, prefix_len: u8) -> Option<Self> {
+        if prefix_len <= BITS {
+            Some(Self {
+                network: Ipv6Address::from(base.value() & u128::mask(prefix_len)),
+                prefix_len,
+                base,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The number of bits that compose the network prefix.
+    pub fn num_network_bits(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// The number of bits that compose the network suffix, which differentiate each host.
+    pub fn num_host_bits(&self) -> u8 {
+        BITS - self.num_network_bits()
+    }
+
+    /// The network one bit less specific than this one, which contains it.
+    pub fn supernet(self) -> Option<Self> {
+        match self.num_network_bits() {
+            0 => None,
+            n => Self::new(self.base, n - 1),
+        }
+    }
+
+    /// Gets the two children of this network, each of which contains exactly half of it.
+    pub fn subnets(self) -> Option<(Self, Self)> {
+        let bit = u128::next_bit(self.num_network_bits())?;
+        let network = self.network_address();
+        let lower_net = Self::new(network, self.num_network_bits() + 1)?;
+        let upper_net = Self::new(
+            Ipv6Address::from(network.value() | bit),
+            self.num_network_bits() + 1,
+        )?;
+        Some((upper_net, lower_net))
+    }
+
+    /// Creates the mask associated with this network, in address form.
+    pub fn get_mask(&self) -> Ipv6Address {
+        Ipv6Address::from(u128::mask(self.num_network_bits()))
+    }
+
+    /// Creates the wildcard (or host mask) associated with this network, in address form.
+    pub fn get_wildcard(&self) -> Ipv6Address {
+        Ipv6Address::from(u128::wildcard(self.num_network_bits()))
+    }
+
+    /// Gets the first address within this network, which has all host bits set to `0`.
+    pub fn network_address(&self) -> Ipv6Address {
+        self.network
+    }
+
+    /// Gets the last address within this network, which has all host bits set to `1`.
+    ///
+    /// IPv6 has no broadcast, so unlike in IPv4 this is an ordinary address.
+    pub fn last_address(&self) -> Ipv6Address {
+        Ipv6Address::from(self.network.value() | u128::wildcard(self.prefix_len))
+    }
+
+    /// Gets the address at the specified offset from the start of this network, unless the offset
+    /// runs past the end of it.
+    pub fn nth(&self, n: u128) -> Option<Ipv6Address> {
+        if n & self.get_mask().value() != 0 {
+            return None;
+        }
+        self.network_address().checked_add(n)
+    }
+
+    /// Whether the specified address lies within this network. Host bits are irrelevant.
+    pub fn contains(&self, addr: Ipv6Address) -> bool {
+        addr.value() & u128::mask(self.prefix_len) == self.network.value()
+    }
+
+    /// Whether every address in this network is also in the other. Every network is a subnet of
+    /// itself.
+    pub fn is_subnet_of(&self, other: &Self) -> bool {
+        self.num_network_bits() >= other.num_network_bits() && other.contains(self.network)
+    }
+
+    /// Whether every address in the other network is also in this one. Every network is a
+    /// supernet of itself.
+    pub fn is_supernet_of(&self, other: &Self) -> bool {
+        other.is_subnet_of(self)
+    }
+
+    /// Whether this network and the other have the same prefix length and the same network bits,
+    /// ignoring any host bits of their base addresses.
+    pub fn same_network(&self, other: &Self) -> bool {
+        self.prefix_len == other.prefix_len && self.network == other.network
+    }
+
+    /// The fewest networks which cover every address of this network which is not within any of
+    /// the excluded networks, in ascending order.
+    pub fn exclude<I: IntoIterator<Item = Self>>(&self, excluded: I) -> Vec<Self> {
+        let mut holes: Vec<_> = excluded.into_iter().map(|net| net.bounds()).collect();
+        bits::merge_ranges(&mut holes);
+        networks(bits::subtract_ranges(&[self.bounds()], &holes))
+    }
+
+    /// Picks an address within this network uniformly at random.
+    pub fn random_address(&self, rng: &mut Rng) -> Ipv6Address {
+        let offset = rng.next_u128() & self.get_wildcard().value();
+        Ipv6Address::from(self.network_address().value() | offset)
+    }
+
+    /// Picks a subnet of this network with the specified prefix length uniformly at random, such
+    /// as a `/64` out of a delegated `/48`.
+    ///
+    /// The prefix length may not be shorter than this network's own, nor longer than 128.
+    pub fn random_subnet(&self, prefix_len: u8, rng: &mut Rng) -> Option<Ipv6Network> {
+        if prefix_len < self.num_network_bits() {
+            return None;
+        }
+        let subnet = Ipv6Network::new(self.random_address(rng), prefix_len)?;
+        Ipv6Network::new(subnet.network_address(), prefix_len)
+    }
+
+    /// Visits every subnet of this network with the specified prefix length exactly once, in a
+    /// random order.
+    ///
+    /// An IPv6 network is usually far too large to visit every address of, so this is for bounded
+    /// subspaces: the prefix length may be at most 64 bits longer than this network's own, as
+    /// with the `/64`s of a `/48`, or the addresses of a `/112`.
+    pub fn permuted_subnets(&self, prefix_len: u8, rng: &mut Rng) -> Option<PermutedSubnets> {
+        let bits = prefix_len.checked_sub(self.num_network_bits())?;
+        if bits > 64 || prefix_len > BITS {
+            return None;
+        }
+        Some(PermutedSubnets::new(
+            self.network_address().value(),
+            prefix_len,
+            bits,
+            rng,
+        ))
+    }
+
+    /// The network address and prefix length of this network, as the `bits` helpers take them.
+    fn prefix(&self) -> (u128, u8) {
+        (self.network.value(), self.prefix_len)
+    }
+
+    fn from_prefix((base, prefix_len): (u128, u8)) -> Self {
+        Self::new(Ipv6Address::from(base), prefix_len).unwrap()
+    }
+
+    /// The first and last addresses of this network, as integers.
+    fn bounds(&self) -> (u128, u128) {
+        (self.network_address().value(), self.last_address().value())
+    }
+}
+
+/// The fewest networks which exactly cover the ranges, in ascending order.
+fn networks(ranges: Vec<(u128, u128)>) -> Vec<Ipv6Network> {
+    ranges
+        .into_iter()
+        .flat_map(|(start, end)| bits::blocks(start, end))
+        .filter_map(|(base, prefix_len)| Ipv6Network::new(Ipv6Address::from(base), prefix_len))
+        .collect()
+}
+
+/// Combines the networks into the fewest networks which cover exactly the same addresses.
+///
+/// Duplicates and networks which lie within another are dropped, and adjacent networks are merged
+/// into their supernet wherever possible.
+pub fn aggregate<I: IntoIterator<Item = Ipv6Network>>(nets: I) -> Vec<Ipv6Network> {
+    let mut ranges: Vec<_> = nets.into_iter().map(|net| net.bounds()).collect();
+    bits::merge_ranges(&mut ranges);
+    networks(ranges)
+}
+
+/// Drops every network which lies within another of the networks, without merging anything.
+///
+/// Unlike [`aggregate`], the surviving networks are exactly those which were given (with their
+/// host bits cleared).
+pub fn remove_shadowed<I: IntoIterator<Item = Ipv6Network>>(nets: I) -> Vec<Ipv6Network> {
+    let mut prefixes: Vec<_> = nets.into_iter().map(|net| net.prefix()).collect();
+    bits::remove_shadowed(&mut prefixes);
+    prefixes.into_iter().map(Ipv6Network::from_prefix).collect()
+}
+
+/// The smallest single network which contains every one of the networks, if there are any.
+pub fn spanning<I: IntoIterator<Item = Ipv6Network>>(nets: I) -> Option<Ipv6Network> {
+    bits::spanning(nets.into_iter().map(|net| net.prefix())).map(Ipv6Network::from_prefix)
+}
+
+impl str::FromStr for Ipv6Network {
+    type Err = NetParseError;
+
+    /// Parses a network from CIDR notation, such as `2001:db8::/32`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const INVALID: NetParseError = NetParseError(NetParseErrorKind::PrefixLen);
+        let (base, prefix) = s.split_once('/').ok_or(INVALID)?;
+        if prefix.is_empty() || prefix.len() > 3 || !prefix.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(INVALID);
+        }
+        let prefix_len = prefix.parse().map_err(|_| INVALID)?;
+        Self::new(base.parse()?, prefix_len).ok_or(INVALID)
+    }
+}
+
+impl fmt::Display for Ipv6Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.base, self.prefix_len)
+    }
+}
+
+/// Shows the network in CIDR notation, as in `Ipv6Network(2001:db8::/32)`, or field by field
+/// under `{:#?}`.
+impl fmt::Debug for Ipv6Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.debug_struct("Ipv6Network")
+                .field("network", &self.network)
+                .field("prefix_len", &self.prefix_len)
+                .field("base", &self.base)
+                .finish()
+        } else {
+            write!(f, "Ipv6Network({})", self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn net(s: &str) -> Ipv6Network {
+        s.parse().unwrap()
+    }
+
+    fn nets(list: &[&str]) -> Vec<Ipv6Network> {
+        list.iter().map(|s| net(s)).collect()
+    }
+
+    fn strings(nets: &[Ipv6Network]) -> Vec<String> {
+        nets.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn basics() {
+        let n = net("2001:db8::1234/64");
+        assert_eq!("2001:db8::1234/64", n.to_string());
+        assert_eq!("2001:db8::", n.network_address().to_string());
+        assert_eq!(
+            "2001:db8::ffff:ffff:ffff:ffff",
+            n.last_address().to_string()
+        );
+        assert_eq!("ffff:ffff:ffff:ffff::", n.get_mask().to_string());
+        assert_eq!("::ffff:ffff:ffff:ffff", n.get_wildcard().to_string());
+        assert!(n.contains("2001:db8::ffff".parse().unwrap()));
+        assert!(!n.contains("2001:db8:0:1::".parse().unwrap()));
+        assert!(n.supernet().unwrap().same_network(&net("2001:db8::/63")));
+        assert_eq!(
+            Some((net("2001:db8:0:1::/64"), net("2001:db8::/64"))),
+            net("2001:db8::/63").subnets()
+        );
+        assert!(net("2001:db8::/127").subnets().is_some());
+        // Host bits never leak into the halves, which must differ.
+        assert_eq!(
+            Some((net("2001:db8::fffe/127"), net("2001:db8::fffc/127"))),
+            net("2001:db8::ffff/126").subnets()
+        );
+        assert_eq!(
+            Some((net("2001:db8::3/128"), net("2001:db8::2/128"))),
+            net("2001:db8::3/127").subnets()
+        );
+        assert!(net("2001:db8::/128").subnets().is_none());
+        assert!(net("::/0").supernet().is_none());
+        assert_eq!(Some(n.last_address()), n.nth(u128::from(u64::MAX)));
+        assert_eq!(None, n.nth(1 << 64));
+        assert_eq!(Some(n.network_address()), net("2001:db8::/128").nth(0));
+        assert_eq!(None, net("2001:db8::/128").nth(1));
+        assert_eq!(
+            Some(Ipv6Address::from(u128::MAX)),
+            net("::/0").nth(u128::MAX)
+        );
+        for bad in &[
+            "2001:db8::/129",
+            "2001:db8::",
+            "2001:db8::/",
+            "2001:db8::/+1",
+            "1.2.3.4/8",
+        ] {
+            assert!(bad.parse::<Ipv6Network>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn host_bits() {
+        let n = net("2001:db8::1234/64");
+        let canonical = net("2001:db8::/64");
+        assert_ne!(canonical, n);
+        assert!(canonical.same_network(&n));
+        // Ordering goes by network address first, so host bits cannot put a network after a
+        // longer prefix which shares its network address.
+        assert!(canonical < n && n < net("2001:db8::/65"));
+        assert!(n < net("2001:db8:0:1::/64"));
+        assert!(net("2001:db8::1/128").is_subnet_of(&n));
+    }
+
+    #[test]
+    fn aggregation() {
+        assert_eq!(
+            vec!["2001:db8::/64"],
+            strings(&aggregate(nets(&[
+                "2001:db8::8000:0:0:0/65",
+                "2001:db8::/65"
+            ])))
+        );
+        assert_eq!(
+            vec!["2001:db8::/126"],
+            strings(&aggregate(nets(&[
+                "2001:db8::/127",
+                "2001:db8::2/127",
+                "2001:db8::3/128"
+            ])))
+        );
+        assert_eq!(
+            vec!["2001:db8::/127", "2001:db8::3/128"],
+            strings(&aggregate(nets(&[
+                "2001:db8::1/128",
+                "2001:db8::/128",
+                "2001:db8::3/128"
+            ])))
+        );
+        assert_eq!(
+            vec!["::/0"],
+            strings(&aggregate(nets(&["::/1", "8000::/1", "2001:db8::/64"])))
+        );
+        assert_eq!(
+            vec!["2001:db8::/48", "2001:db9::/64"],
+            strings(&remove_shadowed(nets(&[
+                "2001:db9::/64",
+                "2001:db8:0:1::/64",
+                "2001:db8::/48",
+                "2001:db8:0:1::1/64",
+                "2001:db8::/127",
+            ])))
+        );
+    }
+
+    #[test]
+    fn exclusion() {
+        assert_eq!(
+            vec!["2001:db8::1/128"],
+            strings(&net("2001:db8::/127").exclude(nets(&["2001:db8::/128"])))
+        );
+        assert_eq!(
+            vec!["2001:db8:0:1::/64"],
+            strings(&net("2001:db8::/63").exclude(nets(&["2001:db8::/64"])))
+        );
+        assert!(net("2001:db8::/64")
+            .exclude(nets(&["2001:db8::/48"]))
+            .is_empty());
+        assert_eq!(
+            vec!["2001:db8::/64"],
+            strings(&net("2001:db8::/64").exclude(nets(&["2001:db9::/64"])))
+        );
+        let rest = net("::/0").exclude(nets(&["2001:db8::/127"]));
+        assert_eq!(127, rest.len());
+        assert!(rest.contains(&net("2001:db8::2/127")));
+        assert!(rest
+            .iter()
+            .all(|n| !n.contains("2001:db8::1".parse().unwrap())));
+    }
+
+    #[test]
+    fn span() {
+        assert_eq!(
+            Some(net("2001:db8::/63")),
+            spanning(nets(&["2001:db8::/64", "2001:db8:0:1::/64"]))
+        );
+        assert_eq!(
+            Some(net("2001:db8::/127")),
+            spanning(nets(&["2001:db8::1/128", "2001:db8::/128"]))
+        );
+        assert_eq!(
+            Some(net("2001:db8::/32")),
+            spanning(nets(&["2001:db8::/32", "2001:db8:ffff::/48"]))
+        );
+        assert_eq!(Some(net("::/0")), spanning(nets(&["::/128", "8000::/1"])));
+        assert_eq!(
+            Some(net("2001:db8::/64")),
+            spanning(nets(&["2001:db8::5/64"]))
+        );
+        assert_eq!(None, spanning(Vec::new()));
+    }
+
+    #[test]
+    fn within_ipv6_network() {
+        let mut rng = Rng::seed_from_u64(0);
+        let net: Ipv6Network = "2001:db8:1200::/40".parse().unwrap();
+        let mut seen = HashSet::new();
+        for _ in 0..100 {
+            assert!(net.contains(net.random_address(&mut rng)));
+            let subnet = net.random_subnet(64, &mut rng).unwrap();
+            assert!(subnet.is_subnet_of(&net));
+            assert_eq!(
+                subnet.network_address(),
+                subnet
+                    .random_subnet(64, &mut rng)
+                    .unwrap()
+                    .network_address()
+            );
+            seen.insert(subnet);
+        }
+        assert!(seen.len() > 90);
+        assert!(net.random_subnet(39, &mut rng).is_none());
+        assert!(net.random_subnet(129, &mut rng).is_none());
+        let everything: Ipv6Network = "::/0".parse().unwrap();
+        everything.random_subnet(128, &mut rng).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+//! # The Parse-Options Module
+//!
+//! The parsers behind `str::parse` are forgiving: a network may carry host bits, as in
+//! `10.0.0.5/8`, an octet may carry leading zeros, and an IPv6 address may be written any way at
+//! all. Where the text comes from an untrusted source, such as a firewall rule or an access list,
+//! that leniency hides mistakes, and leading zeros are read as octal by some other parsers. These
+//! options make the same parsers strict, in one place.
+use super::{
+    addr::{AddrParseError, AddrParseErrorKind, IpAddress},
+    addr6::Ipv6Address,
+    net::{IpNetwork, NetParseError, NetParseErrorKind},
+    net6::Ipv6Network,
+};
+
+/// Options controlling how strictly addresses and networks are parsed.
+///
+/// The default accepts everything that `str::parse` does.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ParseOptions {
+    reject_host_bits: bool,
+    reject_leading_zeros: bool,
+    reject_non_canonical: bool,
+}
+
+impl ParseOptions {
+    /// Accepts everything that `str::parse` does.
+    pub fn lenient() -> Self {
+        Self::default()
+    }
+
+    /// Accepts only the strict RFC form of each address and network: no host bits, no leading
+    /// zeros, and IPv6 only as RFC 5952 writes it, so that everything accepted displays exactly
+    /// as it was written.
+    pub fn strict() -> Self {
+        Self {
+            reject_host_bits: true,
+            reject_leading_zeros: true,
+            reject_non_canonical: true,
+        }
+    }
+
+    /// Whether a network with any host bits set, as in `10.0.0.5/8`, is an error.
+    pub fn reject_host_bits(mut self, reject: bool) -> Self {
+        self.reject_host_bits = reject;
+        self
+    }
+
+    /// Whether leading zeros in an IPv4 octet or a prefix length, as in `010.0.0.1` or `/08`, are
+    /// an error.
+    pub fn reject_leading_zeros(mut self, reject: bool) -> Self {
+        self.reject_leading_zeros = reject;
+        self
+    }
+
+    /// Whether an IPv6 address not written in its RFC 5952 form, as in `2001:DB8:0::1`, is an
+    /// error.
+    pub fn reject_non_canonical(mut self, reject: bool) -> Self {
+        self.reject_non_canonical = reject;
+        self
+    }
+
+    /// Parses an IPv4 address from its dotted-quad form.
+    pub fn parse_address(&self, s: &str) -> Result<IpAddress, AddrParseError> {
+        if self.reject_leading_zeros && s.split('.').any(has_leading_zero) {
+            return Err(AddrParseError(AddrParseErrorKind::Syntax));
+        }
+        s.parse()
+    }
+
+    /// Parses an IPv4 network from CIDR notation.
+    pub fn parse_network(&self, s: &str) -> Result<IpNetwork, NetParseError> {
+        let (base, prefix) = s
+            .split_once('/')
+            .ok_or(NetParseError(NetParseErrorKind::PrefixLen))?;
+        let base = self.parse_address(base)?;
+        if self.reject_leading_zeros && has_leading_zero(prefix) {
+            return Err(NetParseError(NetParseErrorKind::PrefixLen));
+        }
+        let net: IpNetwork = s.parse()?;
+        if self.reject_host_bits && net.network_address() != base {
+            return Err(NetParseError(NetParseErrorKind::Syntax));
+        }
+        Ok(net)
+    }
+
+    /// Parses an IPv6 address from its colon-hex form.
+    pub fn parse_ipv6_address(&self, s: &str) -> Result<Ipv6Address, AddrParseError> {
+        let addr: Ipv6Address = s.parse()?;
+        if self.reject_non_canonical && addr.to_string() != s {
+            return Err(AddrParseError(AddrParseErrorKind::Syntax));
+        }
+        Ok(addr)
+    }
+
+    /// Parses an IPv6 network from CIDR notation.
+    pub fn parse_ipv6_network(&self, s: &str) -> Result<Ipv6Network, NetParseError> {
+        let (base, prefix) = s
+            .split_once('/')
+            .ok_or(NetParseError(NetParseErrorKind::PrefixLen))?;
+        let base = self.parse_ipv6_address(base)?;
+        if self.reject_leading_zeros && has_leading_zero(prefix) {
+            return Err(NetParseError(NetParseErrorKind::PrefixLen));
+        }
+        let net: Ipv6Network = s.parse()?;
+        if self.reject_host_bits && net.network_address() != base {
+            return Err(NetParseError(NetParseErrorKind::Syntax));
+        }
+        Ok(net)
+    }
+}
+
+/// Whether a number has a zero before its first significant digit.
+fn has_leading_zero(digits: &str) -> bool {
+    digits.len() > 1 && digits.starts_with('0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lenient() {
+        let options = ParseOptions::lenient();
+        assert!(options.parse_address("010.0.0.1").is_ok());
+        assert_eq!(
+            "10.0.0.5/8",
+            options.parse_network("10.0.0.5/08").unwrap().to_string()
+        );
+        assert!(options.parse_ipv6_address("2001:DB8:0::1").is_ok());
+        assert!(options.parse_ipv6_network("2001:db8::1/32").is_ok());
+    }
+
+    #[test]
+    fn strict() {
+        let options = ParseOptions::strict();
+        assert!(options.parse_address("10.0.0.1").is_ok());
+        assert!(options.parse_address("10.0.0.0").is_ok());
+        assert!(options.parse_address("010.0.0.1").is_err());
+        assert!(options.parse_network("10.0.0.0/8").is_ok());
+        assert!(options.parse_network("0.0.0.0/0").is_ok());
+        assert!(options.parse_network("10.0.0.5/8").is_err());
+        assert!(options.parse_network("10.0.0.0/08").is_err());
+        assert!(options.parse_network("10.00.0.0/8").is_err());
+
+        assert!(options.parse_ipv6_address("2001:db8::1").is_ok());
+        assert!(options.parse_ipv6_address("2001:DB8::1").is_err());
+        assert!(options.parse_ipv6_address("2001:db8:0::1").is_err());
+        assert!(options.parse_ipv6_address("2001:db8:0:0:1:0:0:1").is_err());
+        assert!(options.parse_ipv6_network("2001:db8::/32").is_ok());
+        assert!(options.parse_ipv6_network("2001:db8::1/32").is_err());
+        assert!(options.parse_ipv6_network("2001:db8::/032").is_err());
+
+        let some = ParseOptions::lenient().reject_host_bits(true);
+        assert!(some.parse_network("010.0.0.0/8").is_ok());
+        assert!(some.parse_network("10.0.0.5/8").is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! # The Packet Module
+//!
+//! Capture analysis usually comes down to asking which addresses were talking. This module pulls
+//! the source and destination addresses out of raw IPv4 packets and Ethernet frames, and streams
+//! them out of classic `pcap` capture files, so that captures can be fed straight into sets and
+//! maps.
+//!
+//! Libraries such as `pnet` hand out addresses as [`std::net::Ipv4Addr`], which converts to and
+//! from [`IpAddress`] directly. Packets of other protocols are skipped.
+use super::addr::IpAddress;
+use std::{error, fmt, io};
+
+/// The EtherType of an IPv4 payload.
+const ETHERTYPE_IPV4: u16 = 0x0800;
+/// The EtherTypes of VLAN tags, which may be stacked before the payload's own.
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+/// The link types of capture files which this module can read.
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_IPV4: u32 = 228;
+
+/// Reads the source and destination addresses of a raw IPv4 packet.
+pub fn ipv4_endpoints(packet: &[u8]) -> Option<(IpAddress, IpAddress)> {
+    let header_len = usize::from(packet.first()? & 0x0f) * 4;
+    if packet[0] >> 4 != 4 || header_len < 20 || packet.len() < header_len {
+        return None;
+    }
+    let address =
+        |at: usize| IpAddress::from([packet[at], packet[at + 1], packet[at + 2], packet[at + 3]]);
+    Some((address(12), address(16)))
+}
+
+/// Reads the source and destination addresses of the IPv4 packet an Ethernet frame carries, if it
+/// carries one, looking past any VLAN tags.
+pub fn ethernet_endpoints(frame: &[u8]) -> Option<(IpAddress, IpAddress)> {
+    let mut offset = 12;
+    loop {
+        let ethertype = u16::from_be_bytes([*frame.get(offset)?, *frame.get(offset + 1)?]);
+        match ethertype {
+            ETHERTYPE_VLAN | ETHERTYPE_QINQ => offset += 4,
+            ETHERTYPE_IPV4 => return ipv4_endpoints(&frame[offset + 2..]),
+            _ => return None,
+        }
+    }
+}
+
+/// Reads the source and destination addresses of every IPv4 packet in a classic `pcap` capture
+/// file, in the order they were captured.
+///
+/// Captures of Ethernet, of raw IP, and of the BSD loopback are understood. The newer `pcapng`
+/// format is not.
+#[derive(Debug)]
+pub struct PcapReader<R> {
+    reader: R,
+    /// Whether the fields of the file are big-endian.
+    big_endian: bool,
+    link_type: u32,
+}
+
+impl<R: io::Read> PcapReader<R> {
+    /// Reads the file's header, checking that its packets can be understood.
+    pub fn new(mut reader: R) -> Result<Self, PcapError> {
+        let mut header = [0; 24];
+        read_exact(&mut reader, &mut header)?;
+        let magic = [header[0], header[1], header[2], header[3]];
+        // Microsecond and nanosecond timestamps differ only in their magic numbers.
+        let big_endian = match u32::from_le_bytes(magic) {
+            0xa1b2_c3d4 | 0xa1b2_3c4d => false,
+            0xd4c3_b2a1 | 0x4d3c_b2a1 => true,
+            _ => return Err(PcapError::NotPcap),
+        };
+        let mut reader = Self {
+            reader,
+            big_endian,
+            link_type: 0,
+        };
+        reader.link_type = reader.field(&header[20..24]) & 0x0fff_ffff;
+        match reader.link_type {
+            LINKTYPE_NULL | LINKTYPE_ETHERNET | LINKTYPE_RAW | LINKTYPE_IPV4 => Ok(reader),
+            other => Err(PcapError::UnsupportedLinkType(other)),
+        }
+    }
+
+    fn field(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Reads the next packet, returning `None` at the end of the file.
+    fn next_packet(&mut self) -> Result<Option<Vec<u8>>, PcapError> {
+        let mut header = [0; 16];
+        match self.reader.read(&mut header[..1])? {
+            0 => return Ok(None),
+            _ => read_exact(&mut self.reader, &mut header[1..])?,
+        }
+        let captured = self.field(&header[8..12]) as usize;
+        // No link allows packets anywhere near this large; a bigger length is corruption.
+        if captured > 0x0400_0000 {
+            return Err(PcapError::Malformed);
+        }
+        let mut packet = vec![0; captured];
+        read_exact(&mut self.reader, &mut packet)?;
+        Ok(Some(packet))
+    }
+
+    /// Reads the addresses of a packet of the file's link type.
+    fn endpoints(&self, packet: &[u8]) -> Option<(IpAddress, IpAddress)> {
+        match self.link_type {
+            LINKTYPE_ETHERNET => ethernet_endpoints(packet),
+            // The loopback's header is the address family, in the byte order of the capturing
+            // host, which may not be that of the file.
+            LINKTYPE_NULL => match packet.get(..4)? {
+                [2, 0, 0, 0] | [0, 0, 0, 2] => ipv4_endpoints(&packet[4..]),
+                _ => None,
+            },
+            _ => ipv4_endpoints(packet),
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for PcapReader<R> {
+    type Item = Result<(IpAddress, IpAddress), PcapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.next_packet() {
+                Ok(Some(packet)) => {
+                    if let Some(endpoints) = self.endpoints(&packet) {
+                        return Some(Ok(endpoints));
+                    }
+                }
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Fills the buffer, treating an early end of the file as truncation.
+fn read_exact<R: io::Read>(reader: &mut R, buf: &mut [u8]) -> Result<(), PcapError> {
+    reader.read_exact(buf).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => PcapError::Truncated,
+        _ => PcapError::Io(err),
+    })
+}
+
+/// The error returned when a capture file could not be read.
+#[derive(Debug)]
+pub enum PcapError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The file does not begin with a `pcap` magic number.
+    NotPcap,
+    /// The file captured a link whose packets are not understood.
+    UnsupportedLinkType(u32),
+    /// The file ended part of the way through a header or packet.
+    Truncated,
+    /// A packet claims an impossible length.
+    Malformed,
+}
+
+impl fmt::Display for PcapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read capture: {}", err),
+            Self::NotPcap => f.write_str("not a pcap capture file"),
+            Self::UnsupportedLinkType(link) => write!(f, "unsupported capture link type {}", link),
+            Self::Truncated => f.write_str("truncated capture file"),
+            Self::Malformed => f.write_str("malformed packet record"),
+        }
+    }
+}
+
+impl error::Error for PcapError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PcapError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An IPv4 header, without any payload.
+    fn ipv4(src: [u8; 4], dst: [u8; 4]) -> Vec<u8> {
+        let mut packet = vec![0x45, 0, 0, 20, 0, 0, 0, 0, 64, 6, 0, 0];
+        packet.extend_from_slice(&src);
+        packet.extend_from_slice(&dst);
+        packet
+    }
+
+    fn ethernet(tags: usize, ethertype: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        for _ in 0..tags {
+            frame.extend_from_slice(&[0x81, 0x00, 0, 7]);
+        }
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn pcap(big_endian: bool, link_type: u32, packets: &[Vec<u8>]) -> Vec<u8> {
+        let field = |n: u32| {
+            if big_endian {
+                n.to_be_bytes()
+            } else {
+                n.to_le_bytes()
+            }
+        };
+        let mut file = field(0xa1b2_c3d4).to_vec();
+        file.extend_from_slice(&[0, 2, 0, 4][..]);
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&field(65535));
+        file.extend_from_slice(&field(link_type));
+        for packet in packets {
+            file.extend_from_slice(&[0; 8]);
+            file.extend_from_slice(&field(packet.len() as u32));
+            file.extend_from_slice(&field(packet.len() as u32));
+            file.extend_from_slice(packet);
+        }
+        file
+    }
+
+    #[test]
+    fn frames() {
+        let packet = ipv4([192, 0, 2, 1], [198, 51, 100, 2]);
+        let endpoints = Some((
+            IpAddress::from([192, 0, 2, 1]),
+            IpAddress::from([198, 51, 100, 2]),
+        ));
+        assert_eq!(endpoints, ipv4_endpoints(&packet));
+        assert_eq!(None, ipv4_endpoints(&packet[..19]));
+        assert_eq!(None, ipv4_endpoints(&[0x60; 40]));
+        assert_eq!(endpoints, ethernet_endpoints(&ethernet(0, 0x0800, &packet)));
+        assert_eq!(endpoints, ethernet_endpoints(&ethernet(2, 0x0800, &packet)));
+        assert_eq!(None, ethernet_endpoints(&ethernet(0, 0x86dd, &packet)));
+        assert_eq!(None, ethernet_endpoints(&[0; 13]));
+    }
+
+    #[test]
+    fn capture() {
+        let first = ipv4([10, 0, 0, 1], [10, 0, 0, 2]);
+        let second = ipv4([10, 0, 0, 2], [10, 0, 0, 1]);
+        let expected = vec![
+            (
+                IpAddress::from([10, 0, 0, 1]),
+                IpAddress::from([10, 0, 0, 2]),
+            ),
+            (
+                IpAddress::from([10, 0, 0, 2]),
+                IpAddress::from([10, 0, 0, 1]),
+            ),
+        ];
+        let packets = vec![
+            ethernet(0, 0x0800, &first),
+            ethernet(0, 0x0806, &[0; 28]),
+            ethernet(1, 0x0800, &second),
+        ];
+        for &big_endian in &[false, true] {
+            let file = pcap(big_endian, 1, &packets);
+            let endpoints: Vec<_> = PcapReader::new(&file[..])
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap();
+            assert_eq!(expected, endpoints);
+        }
+
+        let file = pcap(false, 101, &[first, second]);
+        assert_eq!(2, PcapReader::new(&file[..]).unwrap().count());
+    }
+
+    #[test]
+    fn errors() {
+        let err = |file: &[u8]| PcapReader::new(file).unwrap_err().to_string();
+        assert_eq!("not a pcap capture file", err(&[0; 24]));
+        assert_eq!("truncated capture file", err(&[0xd4, 0xc3, 0xb2, 0xa1]));
+        assert_eq!(
+            "unsupported capture link type 105",
+            err(&pcap(false, 105, &[]))
+        );
+        let file = pcap(false, 1, &[ethernet(0, 0x0800, &ipv4([1; 4], [2; 4]))]);
+        let mut reader = PcapReader::new(&file[..file.len() - 1]).unwrap();
+        assert_eq!(
+            "truncated capture file",
+            reader.next().unwrap().unwrap_err().to_string()
+        );
+    }
+}
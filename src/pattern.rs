@@ -0,0 +1,408 @@
+//! # The Pattern Module
+//!
+//! Scan targets and ACL entries are often written the way nmap reads them, with a range or list
+//! in place of any octet: `10.0.1-5.0/24`, `192.168.{1,3,7}.1`, or `172.16.*.1`. Expanding these
+//! by hand is a reliable source of off-by-one errors. This module parses such patterns, and walks
+//! the addresses or networks they describe in ascending order, one at a time, so that even a
+//! pattern covering the whole address space costs nothing until it is walked.
+use super::{addr::IpAddress, net::IpNetwork, set::IpSet};
+use std::{error, fmt, str};
+
+/// An address pattern, with a set of values for each octet, and optionally a prefix length.
+///
+/// Each octet is written as a number, a range `a-b`, `*` for every value, or a comma-separated
+/// list of those, optionally wrapped in braces.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OctetPattern {
+    /// The values of each octet, in ascending order, without duplicates.
+    octets: [Vec<u8>; 4],
+    prefix_len: Option<u8>,
+}
+
+impl OctetPattern {
+    /// The prefix length written after the pattern, if any.
+    pub fn prefix_len(&self) -> Option<u8> {
+        self.prefix_len
+    }
+
+    /// The number of addresses the pattern matches.
+    pub fn num_addresses(&self) -> u64 {
+        self.octets
+            .iter()
+            .map(|values| values.len() as u64)
+            .product()
+    }
+
+    /// Whether the pattern matches the address.
+    pub fn contains(&self, addr: IpAddress) -> bool {
+        self.octets
+            .iter()
+            .zip(addr.octets().iter())
+            .all(|(values, octet)| values.binary_search(octet).is_ok())
+    }
+
+    /// Walks every address the pattern matches, in ascending order.
+    pub fn addresses(&self) -> Addresses {
+        Addresses {
+            octets: self.octets.clone(),
+            indices: Some([0; 4]),
+        }
+    }
+
+    /// Walks the networks of the pattern's prefix length which contain the addresses it matches,
+    /// in ascending order, and each only once; without a prefix length, each address is a `/32`.
+    pub fn networks(&self) -> Networks {
+        let prefix_len = self.prefix_len.unwrap_or(32);
+        let mut octets = self.octets.clone();
+        // Octets wholly within the host bits make no difference to the network.
+        for values in octets.iter_mut().skip(usize::from(prefix_len).div_ceil(8)) {
+            values.truncate(1);
+        }
+        Networks {
+            addresses: Addresses {
+                octets,
+                indices: Some([0; 4]),
+            },
+            prefix_len,
+            last: None,
+        }
+    }
+
+    /// Every address the pattern matches, or with a prefix length, every address of the networks
+    /// it covers.
+    ///
+    /// Trailing octets which match every value are taken as a shorter prefix, rather than walked,
+    /// so that `10.*.*.*` costs no more than `10.0.0.0/8`.
+    pub fn to_set(&self) -> IpSet {
+        let full = self
+            .octets
+            .iter()
+            .rev()
+            .take_while(|values| values.len() == 256)
+            .count() as u8;
+        let prefix_len = (32 - 8 * full).min(self.prefix_len.unwrap_or(32));
+        let pattern = Self {
+            octets: self.octets.clone(),
+            prefix_len: Some(prefix_len),
+        };
+        pattern.networks().collect()
+    }
+}
+
+impl str::FromStr for OctetPattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, prefix_len) = match s.split_once('/') {
+            Some((pattern, len)) => {
+                let len = len
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|&len| len <= 32)
+                    .ok_or_else(|| PatternError::InvalidPrefixLen(len.to_string()))?;
+                (pattern, Some(len))
+            }
+            None => (s, None),
+        };
+        let fields = split_octets(pattern);
+        if fields.len() != 4 {
+            return Err(PatternError::OctetCount(fields.len()));
+        }
+        let mut octets: [Vec<u8>; 4] = Default::default();
+        for (values, field) in octets.iter_mut().zip(fields) {
+            *values = parse_octet(field)?;
+        }
+        Ok(Self { octets, prefix_len })
+    }
+}
+
+/// Splits a pattern on the dots between octets, but not on anything inside braces.
+fn split_octets(pattern: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in pattern.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '.' if depth == 0 => {
+                fields.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&pattern[start..]);
+    fields
+}
+
+/// Reads the values of a single octet, sorted, without duplicates.
+fn parse_octet(field: &str) -> Result<Vec<u8>, PatternError> {
+    let invalid = || PatternError::InvalidOctet(field.to_string());
+    let list = match field.strip_prefix('{') {
+        Some(rest) => rest.strip_suffix('}').ok_or_else(invalid)?,
+        None => field,
+    };
+    let mut values = Vec::new();
+    for item in list.split(',') {
+        let (first, last) = match item {
+            "*" => (0, 255),
+            _ => match item.split_once('-') {
+                Some((first, last)) => (
+                    first.parse::<u8>().map_err(|_| invalid())?,
+                    last.parse::<u8>().map_err(|_| invalid())?,
+                ),
+                None => {
+                    let value = item.parse::<u8>().map_err(|_| invalid())?;
+                    (value, value)
+                }
+            },
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        values.extend(first..=last);
+    }
+    values.sort_unstable();
+    values.dedup();
+    Ok(values)
+}
+
+/// Writes each octet as compactly as it can be, with runs as ranges, and every value as `*`.
+impl fmt::Display for OctetPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, values) in self.octets.iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            if values.len() == 256 {
+                f.write_str("*")?;
+                continue;
+            }
+            let mut runs = Vec::new();
+            for &value in values {
+                match runs.last_mut() {
+                    Some((_, last)) if *last + 1 == value => *last = value,
+                    _ => runs.push((value, value)),
+                }
+            }
+            let runs: Vec<_> = runs
+                .into_iter()
+                .map(|(first, last)| {
+                    if first == last {
+                        first.to_string()
+                    } else {
+                        format!("{}-{}", first, last)
+                    }
+                })
+                .collect();
+            f.write_str(&runs.join(","))?;
+        }
+        if let Some(prefix_len) = self.prefix_len {
+            write!(f, "/{}", prefix_len)?;
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over the addresses a pattern matches. See [`OctetPattern::addresses`].
+#[derive(Debug, Clone)]
+pub struct Addresses {
+    octets: [Vec<u8>; 4],
+    /// The index into each octet's values of the next address, or `None` once every address has
+    /// been walked.
+    indices: Option<[usize; 4]>,
+}
+
+impl Iterator for Addresses {
+    type Item = IpAddress;
+
+    fn next(&mut self) -> Option<IpAddress> {
+        let indices = self.indices.as_mut()?;
+        let mut octets = [0; 4];
+        for (octet, (values, &i)) in octets
+            .iter_mut()
+            .zip(self.octets.iter().zip(indices.iter()))
+        {
+            *octet = values[i];
+        }
+        // Counts up like an odometer, from the last octet.
+        let mut carried = true;
+        for (i, values) in indices.iter_mut().zip(&self.octets).rev() {
+            *i += 1;
+            if *i < values.len() {
+                carried = false;
+                break;
+            }
+            *i = 0;
+        }
+        if carried {
+            self.indices = None;
+        }
+        Some(IpAddress::from(octets))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<IpAddress> {
+        // Adds to the odometer directly, rather than walking every address skipped.
+        let indices = self.indices.as_mut()?;
+        let mut carry = n;
+        for (i, values) in indices.iter_mut().zip(&self.octets).rev() {
+            let total = *i + carry;
+            *i = total % values.len();
+            carry = total / values.len();
+        }
+        if carry > 0 {
+            self.indices = None;
+        }
+        self.next()
+    }
+}
+
+/// An iterator over the networks a pattern covers. See [`OctetPattern::networks`].
+#[derive(Debug, Clone)]
+pub struct Networks {
+    addresses: Addresses,
+    prefix_len: u8,
+    last: Option<IpNetwork>,
+}
+
+impl Iterator for Networks {
+    type Item = IpNetwork;
+
+    fn next(&mut self) -> Option<IpNetwork> {
+        for addr in &mut self.addresses {
+            let net = IpNetwork::new(addr, self.prefix_len).unwrap();
+            let net = IpNetwork::new(net.network_address(), self.prefix_len).unwrap();
+            if self.last != Some(net) {
+                self.last = Some(net);
+                return Some(net);
+            }
+        }
+        None
+    }
+}
+
+/// The error returned when a pattern cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    /// The pattern had some number of octets other than four.
+    OctetCount(usize),
+    /// An octet was not a number, range, or list of them, from `0` to `255`.
+    InvalidOctet(String),
+    /// The prefix length was not a number from `0` to `32`.
+    InvalidPrefixLen(String),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OctetCount(count) => write!(f, "expected 4 octets in pattern, found {}", count),
+            Self::InvalidOctet(octet) => write!(f, "invalid octet pattern {:?}", octet),
+            Self::InvalidPrefixLen(len) => write!(f, "invalid prefix length {:?}", len),
+        }
+    }
+}
+
+impl error::Error for PatternError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(s: &str) -> OctetPattern {
+        s.parse().unwrap()
+    }
+
+    fn strings<T: ToString>(iter: impl Iterator<Item = T>) -> Vec<String> {
+        iter.map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn addresses() {
+        let list = pattern("192.168.{7,1,3}.1");
+        assert_eq!(
+            vec!["192.168.1.1", "192.168.3.1", "192.168.7.1"],
+            strings(list.addresses())
+        );
+        assert!(list.contains("192.168.3.1".parse().unwrap()));
+        assert!(!list.contains("192.168.2.1".parse().unwrap()));
+
+        let mixed = pattern("10.0-1.5,9-10.254-255");
+        assert_eq!(12, mixed.num_addresses());
+        let all = strings(mixed.addresses());
+        assert_eq!(12, all.len());
+        assert_eq!("10.0.5.254", all[0]);
+        assert_eq!("10.0.9.254", all[2]);
+        assert_eq!("10.1.10.255", all[11]);
+
+        let everything = pattern("*.*.*.*");
+        assert_eq!(1 << 32, everything.num_addresses());
+        assert_eq!(
+            Some("255.255.255.255".parse().unwrap()),
+            everything.addresses().nth(0xffff_ffff)
+        );
+        assert_eq!(
+            Some("0.0.1.0".parse().unwrap()),
+            everything.addresses().nth(256)
+        );
+    }
+
+    #[test]
+    fn networks() {
+        let nets = pattern("10.0.1-5.0/24");
+        assert_eq!(Some(24), nets.prefix_len());
+        assert_eq!(
+            vec![
+                "10.0.1.0/24",
+                "10.0.2.0/24",
+                "10.0.3.0/24",
+                "10.0.4.0/24",
+                "10.0.5.0/24"
+            ],
+            strings(nets.networks())
+        );
+        // Host octets, and host bits within an octet, collapse together.
+        assert_eq!(
+            vec!["172.16.0.0/12", "172.32.0.0/12"],
+            strings(pattern("172.16-47.*.*/12").networks())
+        );
+        assert_eq!(
+            vec!["192.0.2.1/32"],
+            strings(pattern("192.0.2.1").networks())
+        );
+
+        assert_eq!(
+            vec!["10.0.0.0/8"],
+            strings(pattern("10.*.*.*").to_set().iter())
+        );
+        assert_eq!(
+            vec!["10.0.0.0/23", "10.0.3.0/24"],
+            strings(pattern("10.0.0,1,3.*").to_set().iter())
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("10.0.1-5.0/24", pattern("10.0.{5,1-4}.0/24").to_string());
+        assert_eq!("*.1,3,5-7.0.0", pattern("0-255.1,3,5-7,6.0.0").to_string());
+        assert_eq!(
+            Err(PatternError::OctetCount(3)),
+            "10.0.1".parse::<OctetPattern>()
+        );
+        assert_eq!(
+            Err(PatternError::InvalidOctet("5-1".into())),
+            "10.0.5-1.0".parse::<OctetPattern>()
+        );
+        assert_eq!(
+            Err(PatternError::InvalidOctet("256".into())),
+            "10.0.256.0".parse::<OctetPattern>()
+        );
+        assert_eq!(
+            Err(PatternError::InvalidOctet("{1,2}x".into())),
+            "10.0.{1,2}x.0".parse::<OctetPattern>()
+        );
+        assert_eq!(
+            Err(PatternError::InvalidPrefixLen("33".into())),
+            "10.0.0.0/33".parse::<OctetPattern>()
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! # The Persistent-Set Module
+//!
+//! An [`IpSet`] is changed in place, so anything reading it must wait for every update to finish.
+//! A persistent set is never changed at all: inserting or removing gives a new set, which shares
+//! every part of the old set that the update did not touch. Keeping an old set around is then as
+//! cheap as cloning a pointer, so a reader can hold a stable snapshot while updates stream in.
+use super::{addr::IpAddress, bits::AddressBits, net::IpNetwork, set::IpSet};
+use std::{iter::FromIterator, sync::Arc};
+
+/// A node of the trie, covering the addresses of some prefix.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Node {
+    /// Every address of the prefix is in the set.
+    Full,
+    /// Some addresses of the prefix are in the set, split between the prefix's two halves.
+    Branch(Child, Child),
+}
+
+/// A half of a prefix, which is `None` where none of its addresses are in the set.
+type Child = Option<Arc<Node>>;
+
+/// A set of addresses which is never changed, only replaced. Cloning it is cheap.
+///
+/// Internally this is a binary trie of prefixes, copied along the path to each update. A prefix
+/// whose halves are both full is itself full, so the trie is always as small as it can be.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PersistentIpSet {
+    root: Child,
+}
+
+impl PersistentIpSet {
+    /// Creates a new, empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether this set contains no addresses at all.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Gets the number of addresses within this set.
+    pub fn num_addresses(&self) -> u64 {
+        self.iter().map(|net| net.num_addresses()).sum()
+    }
+
+    /// Whether the specified address lies within this set.
+    pub fn contains(&self, addr: IpAddress) -> bool {
+        let mut node = &self.root;
+        let mut depth = 0;
+        loop {
+            match node.as_deref() {
+                None => return false,
+                Some(Node::Full) => return true,
+                Some(Node::Branch(lower, upper)) => {
+                    node = if addr.value().bit(depth) {
+                        upper
+                    } else {
+                        lower
+                    };
+                    depth += 1;
+                }
+            }
+        }
+    }
+
+    /// Creates a set with every address of this set, and of the network.
+    pub fn insert(&self, net: IpNetwork) -> Self {
+        let bits = net.network_address().value();
+        Self {
+            root: insert(&self.root, bits, 0, net.num_network_bits()),
+        }
+    }
+
+    /// Creates a set with every address of this set, but none of the network.
+    pub fn remove(&self, net: IpNetwork) -> Self {
+        let bits = net.network_address().value();
+        Self {
+            root: remove(&self.root, bits, 0, net.num_network_bits()),
+        }
+    }
+
+    /// The fewest networks which exactly cover this set, in ascending order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            stack: self
+                .root
+                .as_deref()
+                .map(|node| (node, 0, 0))
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// Builds a prefix out of its two halves, merging them where both are full, and pruning it where
+/// both are empty.
+fn branch(lower: Child, upper: Child) -> Child {
+    match (lower, upper) {
+        (None, None) => None,
+        (Some(lower), Some(upper))
+            if matches!(*lower, Node::Full) && matches!(*upper, Node::Full) =>
+        {
+            Some(Arc::new(Node::Full))
+        }
+        (lower, upper) => Some(Arc::new(Node::Branch(lower, upper))),
+    }
+}
+
+/// The halves of a prefix, sharing them with the original node.
+fn halves(node: &Child) -> (Child, Child) {
+    match node.as_deref() {
+        None => (None, None),
+        Some(Node::Full) => (Some(Arc::new(Node::Full)), Some(Arc::new(Node::Full))),
+        Some(Node::Branch(lower, upper)) => (lower.clone(), upper.clone()),
+    }
+}
+
+fn insert(node: &Child, bits: u32, depth: u8, prefix_len: u8) -> Child {
+    if matches!(node.as_deref(), Some(Node::Full)) {
+        return node.clone();
+    }
+    if depth == prefix_len {
+        return Some(Arc::new(Node::Full));
+    }
+    let (lower, upper) = halves(node);
+    if bits.bit(depth) {
+        branch(lower, insert(&upper, bits, depth + 1, prefix_len))
+    } else {
+        branch(insert(&lower, bits, depth + 1, prefix_len), upper)
+    }
+}
+
+fn remove(node: &Child, bits: u32, depth: u8, prefix_len: u8) -> Child {
+    if node.is_none() || depth == prefix_len {
+        return None;
+    }
+    let (lower, upper) = halves(node);
+    if bits.bit(depth) {
+        branch(lower, remove(&upper, bits, depth + 1, prefix_len))
+    } else {
+        branch(remove(&lower, bits, depth + 1, prefix_len), upper)
+    }
+}
+
+/// An iterator over the networks of a persistent set. See [`PersistentIpSet::iter`].
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    /// The nodes still to be visited, with the prefix each covers; the next is on top.
+    stack: Vec<(&'a Node, u32, u8)>,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = IpNetwork;
+
+    fn next(&mut self) -> Option<IpNetwork> {
+        loop {
+            let (node, bits, depth) = self.stack.pop()?;
+            match node {
+                Node::Full => return IpNetwork::new(IpAddress::from(bits), depth),
+                Node::Branch(lower, upper) => {
+                    let bit = u32::next_bit(depth).unwrap_or(0);
+                    if let Some(upper) = upper {
+                        self.stack.push((upper, bits | bit, depth + 1));
+                    }
+                    if let Some(lower) = lower {
+                        self.stack.push((lower, bits, depth + 1));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FromIterator<IpNetwork> for PersistentIpSet {
+    fn from_iter<I: IntoIterator<Item = IpNetwork>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Self::new(), |set, net| set.insert(net))
+    }
+}
+
+impl From<&PersistentIpSet> for IpSet {
+    fn from(set: &PersistentIpSet) -> Self {
+        set.iter().collect()
+    }
+}
+
+impl From<&IpSet> for PersistentIpSet {
+    fn from(set: &IpSet) -> Self {
+        set.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn strings(set: &PersistentIpSet) -> Vec<String> {
+        set.iter().map(|net| net.to_string()).collect()
+    }
+
+    #[test]
+    fn snapshots() {
+        let empty = PersistentIpSet::new();
+        let one = empty.insert(net("10.0.0.0/24"));
+        let two = one.insert(net("10.0.1.0/24"));
+        let holed = two.remove(net("10.0.0.128/25"));
+        assert!(empty.is_empty());
+        assert_eq!(vec!["10.0.0.0/24"], strings(&one));
+        assert_eq!(vec!["10.0.0.0/23"], strings(&two));
+        assert_eq!(vec!["10.0.0.0/25", "10.0.1.0/24"], strings(&holed));
+        assert!(two.contains(IpAddress::from([10, 0, 0, 200])));
+        assert!(!holed.contains(IpAddress::from([10, 0, 0, 200])));
+        assert!(holed.contains(IpAddress::from([10, 0, 1, 200])));
+        assert_eq!(384, holed.num_addresses());
+        assert_eq!(two, holed.insert(net("10.0.0.128/25")));
+        assert!(holed.remove(net("0.0.0.0/0")).is_empty());
+
+        let everything = empty.insert(net("0.0.0.0/0"));
+        assert!(everything.contains(IpAddress::from(u32::MAX)));
+        assert_eq!(1 << 32, everything.num_addresses());
+        let all_but_one = everything.remove(net("255.255.255.255/32"));
+        assert_eq!(32, all_but_one.iter().count());
+        assert!(!all_but_one.contains(IpAddress::from(u32::MAX)));
+    }
+
+    #[test]
+    fn sharing() {
+        let base: PersistentIpSet = vec![net("10.0.0.0/8"), net("192.168.0.0/16")]
+            .into_iter()
+            .collect();
+        let updated = base.insert(net("192.168.0.0/24"));
+        let updated = updated.insert(net("172.16.0.0/12"));
+        // Every address below 128.0.0.0 was untouched by the update, so it is shared outright.
+        let lower = |set: &PersistentIpSet| match set.root.as_deref() {
+            Some(Node::Branch(lower, _)) => lower.clone().unwrap(),
+            _ => unreachable!(),
+        };
+        assert!(Arc::ptr_eq(&lower(&base), &lower(&updated)));
+    }
+
+    #[test]
+    fn matches_ip_set() {
+        let nets = [
+            "10.0.0.0/8",
+            "10.1.0.0/16",
+            "11.0.0.0/8",
+            "192.168.1.0/24",
+            "192.168.0.0/24",
+            "0.0.0.0/32",
+            "255.255.255.255/32",
+        ];
+        let holes = ["10.0.0.0/9", "10.200.0.1/32", "192.168.0.128/25"];
+        let mut set = IpSet::new();
+        let mut persistent = PersistentIpSet::new();
+        for s in &nets {
+            set.insert(net(s));
+            persistent = persistent.insert(net(s));
+        }
+        for s in &holes {
+            set.remove(net(s));
+            persistent = persistent.remove(net(s));
+        }
+        assert_eq!(set, IpSet::from(&persistent));
+        assert_eq!(persistent, PersistentIpSet::from(&set));
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            persistent.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(set.num_addresses(), persistent.num_addresses());
+    }
+}
@@ -0,0 +1,351 @@
+//! # The Plan Module
+//!
+//! Carving subnets out of address space is easy; carving them out of *usable* address space is
+//! where naive automation goes wrong, handing out loopback, multicast, or documentation networks
+//! which will never route. This module plans subnets within some candidate space while steering
+//! clear of the special-purpose ranges of RFC 6890, unless they are explicitly allowed.
+//!
+//! The same ranges are what an internet-wide measurement leaves out of its outer loop, which
+//! walks every network of some size across the whole address space; see [`address_space`].
+use super::{addr::IpAddress, net::IpNetwork, set::IpSet};
+use std::convert::TryFrom;
+
+/// The special-purpose ranges of the address space.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SpecialRange {
+    /// `0.0.0.0/8`, which names hosts on "this" network.
+    ThisNetwork,
+    /// The private ranges of RFC 1918: `10.0.0.0/8`, `172.16.0.0/12`, and `192.168.0.0/16`.
+    Private,
+    /// The shared address space used by carrier-grade NAT, `100.64.0.0/10`.
+    Shared,
+    /// `127.0.0.0/8`.
+    Loopback,
+    /// `169.254.0.0/16`.
+    LinkLocal,
+    /// The IETF protocol assignments, `192.0.0.0/24`.
+    ProtocolAssignments,
+    /// `192.0.2.0/24`, `198.51.100.0/24`, and `203.0.113.0/24`.
+    Documentation,
+    /// `198.18.0.0/15`.
+    Benchmarking,
+    /// `224.0.0.0/4`.
+    Multicast,
+    /// `240.0.0.0/4`, including the limited broadcast address.
+    Reserved,
+}
+
+impl SpecialRange {
+    /// Every special-purpose range.
+    pub const ALL: [Self; 10] = [
+        Self::ThisNetwork,
+        Self::Private,
+        Self::Shared,
+        Self::Loopback,
+        Self::LinkLocal,
+        Self::ProtocolAssignments,
+        Self::Documentation,
+        Self::Benchmarking,
+        Self::Multicast,
+        Self::Reserved,
+    ];
+
+    /// The networks which make up the range.
+    pub fn networks(&self) -> Vec<IpNetwork> {
+        let networks: &[&str] = match self {
+            Self::ThisNetwork => &["0.0.0.0/8"],
+            Self::Private => &["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"],
+            Self::Shared => &["100.64.0.0/10"],
+            Self::Loopback => &["127.0.0.0/8"],
+            Self::LinkLocal => &["169.254.0.0/16"],
+            Self::ProtocolAssignments => &["192.0.0.0/24"],
+            Self::Documentation => &["192.0.2.0/24", "198.51.100.0/24", "203.0.113.0/24"],
+            Self::Benchmarking => &["198.18.0.0/15"],
+            Self::Multicast => &["224.0.0.0/4"],
+            Self::Reserved => &["240.0.0.0/4"],
+        };
+        networks.iter().map(|net| net.parse().unwrap()).collect()
+    }
+}
+
+/// Plans subnets within candidate space, avoiding special-purpose ranges.
+///
+/// Only the private ranges are allowed at first, as they are what most plans are carved from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Planner {
+    candidates: IpSet,
+    allowed: Vec<SpecialRange>,
+}
+
+impl Planner {
+    /// Creates a planner which carves subnets out of the candidate space.
+    pub fn new(candidates: IpSet) -> Self {
+        Self {
+            candidates,
+            allowed: vec![SpecialRange::Private],
+        }
+    }
+
+    /// Allows subnets to be planned within the special-purpose range.
+    pub fn allow(mut self, range: SpecialRange) -> Self {
+        if !self.allowed.contains(&range) {
+            self.allowed.push(range);
+        }
+        self
+    }
+
+    /// Forbids subnets from being planned within the special-purpose range.
+    pub fn forbid(mut self, range: SpecialRange) -> Self {
+        self.allowed.retain(|&r| r != range);
+        self
+    }
+
+    /// The candidate space, less every special-purpose range which is not allowed.
+    pub fn usable(&self) -> IpSet {
+        let avoided: IpSet = SpecialRange::ALL
+            .iter()
+            .filter(|range| !self.allowed.contains(range))
+            .flat_map(SpecialRange::networks)
+            .collect();
+        self.candidates.difference(&avoided)
+    }
+
+    /// Plans the requested number of subnets with the prefix length, taking the lowest usable
+    /// space first. There may not be enough usable space for them all.
+    pub fn plan(&self, count: usize, prefix_len: u8) -> Option<Vec<IpNetwork>> {
+        if prefix_len > 32 {
+            return None;
+        }
+        let usable = self.usable();
+        let subnets: Vec<IpNetwork> = usable
+            .ranges()
+            .flat_map(|range| range.networks())
+            .filter_map(|net| net.subnets_with_prefix(prefix_len))
+            .flatten()
+            .take(count)
+            .collect();
+        if subnets.len() == count {
+            Some(subnets)
+        } else {
+            None
+        }
+    }
+}
+
+/// Walks every network of the prefix length across the whole address space, such as every
+/// `/16`, in ascending order. The prefix length may be at most 32.
+pub fn address_space(prefix_len: u8) -> Option<AddressSpace> {
+    if prefix_len > 32 {
+        return None;
+    }
+    Some(AddressSpace {
+        next: 0,
+        prefix_len,
+        excluded: IpSet::new(),
+        bounds: Vec::new(),
+    })
+}
+
+/// An iterator over every network of a prefix length across the address space. See
+/// [`address_space`].
+#[derive(Debug, Clone)]
+pub struct AddressSpace {
+    next: u64,
+    prefix_len: u8,
+    excluded: IpSet,
+    /// The first address of each excluded range, and one past its last.
+    bounds: Vec<(u64, u64)>,
+}
+
+impl AddressSpace {
+    /// Skips every network which overlaps the set, even in part.
+    pub fn excluding_set(mut self, set: &IpSet) -> Self {
+        self.excluded = self.excluded.union(set);
+        self.bounds = self
+            .excluded
+            .ranges()
+            .map(|range| {
+                let start = u64::from(range.start().value());
+                (start, start + range.num_addresses())
+            })
+            .collect();
+        self
+    }
+
+    /// Skips every network which overlaps the special-purpose range, even in part.
+    pub fn excluding(self, range: SpecialRange) -> Self {
+        let set: IpSet = range.networks().into_iter().collect();
+        self.excluding_set(&set)
+    }
+
+    /// Skips every network which overlaps any special-purpose range, as a measurement of the
+    /// public internet should.
+    pub fn excluding_special(self) -> Self {
+        let set: IpSet = SpecialRange::ALL
+            .iter()
+            .flat_map(SpecialRange::networks)
+            .collect();
+        self.excluding_set(&set)
+    }
+
+    /// Moves straight to the network which contains the address, without walking the networks
+    /// before it, so that a job can be split up or resumed.
+    pub fn starting_at(mut self, addr: IpAddress) -> Self {
+        self.next = u64::from(addr.value()) & !(self.size() - 1);
+        self
+    }
+
+    fn size(&self) -> u64 {
+        1 << (32 - self.prefix_len)
+    }
+}
+
+impl Iterator for AddressSpace {
+    type Item = IpNetwork;
+
+    fn next(&mut self) -> Option<IpNetwork> {
+        let size = self.size();
+        while self.next < 1 << 32 {
+            let (start, end) = (self.next, self.next + size);
+            // The first excluded range which ends after this network starts.
+            let i = self.bounds.partition_point(|&(_, last)| last <= start);
+            match self.bounds.get(i) {
+                // Jump to the first network past the excluded range.
+                Some(&(first, last)) if first < end => {
+                    self.next = (last + size - 1) & !(size - 1);
+                }
+                _ => {
+                    self.next = end;
+                    return IpNetwork::new(IpAddress::from(start as u32), self.prefix_len);
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = ((1 << 32) - self.next.min(1 << 32)) / self.size();
+        let upper = usize::try_from(remaining).ok();
+        if self.bounds.is_empty() {
+            (upper.unwrap_or(usize::MAX), upper)
+        } else {
+            (0, upper)
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<IpNetwork> {
+        if self.bounds.is_empty() {
+            // Without exclusions, every network is the same distance from the next.
+            let skip = (n as u64).saturating_mul(self.size());
+            self.next = self.next.saturating_add(skip).min(1 << 32);
+            return self.next();
+        }
+        for _ in 0..n {
+            self.next()?;
+        }
+        self.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(nets: &[&str]) -> IpSet {
+        nets.iter()
+            .map(|net| net.parse::<IpNetwork>().unwrap())
+            .collect()
+    }
+
+    fn strings(nets: &[IpNetwork]) -> Vec<String> {
+        nets.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn avoids_special_ranges() {
+        let planner = Planner::new(set(&["0.0.0.0/0"]));
+        let usable = planner.usable();
+        for &special in &[
+            "0.1.2.3",
+            "100.64.0.1",
+            "127.0.0.1",
+            "192.0.2.1",
+            "224.0.0.1",
+        ] {
+            assert!(!usable.contains(special.parse().unwrap()), "{}", special);
+        }
+        assert!(usable.contains("10.0.0.1".parse().unwrap()));
+        assert!(usable.contains("8.8.8.8".parse().unwrap()));
+        assert_eq!(
+            vec!["1.0.0.0/8", "2.0.0.0/8"],
+            strings(&planner.plan(2, 8).unwrap())
+        );
+
+        let planner = Planner::new(set(&["100.0.0.0/8"]));
+        assert_eq!(
+            vec!["100.0.0.0/10", "100.128.0.0/10", "100.192.0.0/10"],
+            strings(&planner.plan(3, 10).unwrap())
+        );
+        let planner = planner.allow(SpecialRange::Shared);
+        assert_eq!(
+            vec!["100.0.0.0/10", "100.64.0.0/10"],
+            strings(&planner.plan(2, 10).unwrap())
+        );
+    }
+
+    #[test]
+    fn plans_around_holes() {
+        let planner = Planner::new(set(&["192.0.0.0/22"]));
+        // Only 192.0.1.0/24 and 192.0.3.0/24 remain once the holes are taken out.
+        assert_eq!(
+            vec!["192.0.1.0/24", "192.0.3.0/24"],
+            strings(&planner.plan(2, 24).unwrap())
+        );
+        assert!(planner.plan(3, 24).is_none());
+        assert!(planner.plan(1, 23).is_none());
+        assert_eq!(Some(vec![]), planner.plan(0, 24));
+        assert!(planner.plan(1, 33).is_none());
+
+        let planner = Planner::new(set(&["10.0.0.0/8"])).forbid(SpecialRange::Private);
+        assert!(planner.usable().is_empty());
+    }
+
+    #[test]
+    fn address_space() {
+        let all = super::address_space(16).unwrap();
+        assert_eq!((65536, Some(65536)), all.size_hint());
+        let first: Vec<_> = super::address_space(16).unwrap().take(2).collect();
+        assert_eq!(vec!["0.0.0.0/16", "0.1.0.0/16"], strings(&first));
+        assert_eq!(
+            Some("10.20.0.0/16".parse().unwrap()),
+            super::address_space(16).unwrap().nth(10 * 256 + 20)
+        );
+        let mut resumed = super::address_space(24)
+            .unwrap()
+            .starting_at("255.255.254.77".parse().unwrap());
+        assert_eq!("255.255.254.0/24", resumed.next().unwrap().to_string());
+        assert_eq!("255.255.255.0/24", resumed.next().unwrap().to_string());
+        assert_eq!(None, resumed.next());
+        assert_eq!(1, super::address_space(0).unwrap().count());
+        assert!(super::address_space(33).is_none());
+
+        let public: Vec<_> = super::address_space(8)
+            .unwrap()
+            .excluding_special()
+            .collect();
+        // 0, 10, 100, 127, 169, 172, 192, 198, and 203 each overlap some special range, as does
+        // everything from 224 up.
+        assert_eq!(256 - 9 - 32, public.len());
+        assert_eq!("1.0.0.0/8", public[0].to_string());
+        assert!(!public.contains(&"172.0.0.0/8".parse().unwrap()));
+        assert_eq!("223.0.0.0/8", public[public.len() - 1].to_string());
+
+        let mut no_private = super::address_space(16)
+            .unwrap()
+            .excluding(SpecialRange::Private)
+            .starting_at("9.255.0.0".parse().unwrap());
+        assert_eq!("9.255.0.0/16", no_private.next().unwrap().to_string());
+        assert_eq!("11.0.0.0/16", no_private.next().unwrap().to_string());
+        assert_eq!(Some("11.2.0.0/16".parse().unwrap()), no_private.nth(1));
+    }
+}
@@ -0,0 +1,170 @@
+//! # The Pool Module
+//!
+//! An ISP which hands each customer a `/29`, or a platform which gives each tenant a `/24`, can
+//! avoid keeping an assignment table at all by deriving the subnet from the customer's number:
+//! customer `i` gets the `i`th subnet of the pool, and the subnet leads straight back to the
+//! customer. A pool index is that mapping, in both directions. Keyed with a secret, it scrambles
+//! the order, so that neighbouring customers do not get neighbouring subnets and one customer's
+//! subnet says little about the next one's.
+use super::{
+    addr::IpAddress,
+    net::IpNetwork,
+    random::{Permutation, Rng},
+};
+
+/// A one-to-one mapping between the numbers `0..num_subnets` and the subnets of a pool.
+#[derive(Debug, Clone)]
+pub struct PoolIndex {
+    pool: IpNetwork,
+    prefix_len: u8,
+    permutation: Option<Permutation>,
+}
+
+impl PoolIndex {
+    /// Creates an index of the subnets of the pool with the prefix length, in ascending order.
+    ///
+    /// The prefix length **must** be at least as long as the pool's own, and at most 32, where
+    /// each subnet is a single address.
+    pub fn new(pool: IpNetwork, prefix_len: u8) -> Option<Self> {
+        if prefix_len < pool.num_network_bits() || prefix_len > 32 {
+            return None;
+        }
+        let pool = IpNetwork::new(pool.network_address(), pool.num_network_bits()).unwrap();
+        Some(Self {
+            pool,
+            prefix_len,
+            permutation: None,
+        })
+    }
+
+    /// Scrambles the order of the subnets with the key. The same key always gives the same
+    /// order; the scrambling makes assignments hard to guess, but is **not** cryptographic.
+    pub fn keyed(mut self, key: u64) -> Self {
+        let bits = self.prefix_len - self.pool.num_network_bits();
+        self.permutation = Some(Permutation::new(bits, &mut Rng::seed_from_u64(key)));
+        self
+    }
+
+    /// The pool the subnets are drawn from.
+    pub fn pool(&self) -> IpNetwork {
+        self.pool
+    }
+
+    /// The prefix length of each subnet.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// The number of subnets, and so of indices.
+    pub fn num_subnets(&self) -> u64 {
+        1 << (self.prefix_len - self.pool.num_network_bits())
+    }
+
+    /// The subnet with the index, if the index is within the pool.
+    pub fn index_to_subnet(&self, index: u64) -> Option<IpNetwork> {
+        if index >= self.num_subnets() {
+            return None;
+        }
+        let slot = match &self.permutation {
+            Some(permutation) => permutation.apply(index),
+            None => index,
+        };
+        let offset = (slot << (32 - self.prefix_len)) as u32;
+        let base = IpAddress::from(self.pool.network_address().value() + offset);
+        IpNetwork::new(base, self.prefix_len)
+    }
+
+    /// The index of the subnet, if it is one of the pool's subnets, with no host bits.
+    pub fn subnet_to_index(&self, net: IpNetwork) -> Option<u64> {
+        if net.num_network_bits() != self.prefix_len
+            || net != IpNetwork::new(net.network_address(), self.prefix_len).unwrap()
+        {
+            return None;
+        }
+        self.address_to_index(net.network_address())
+    }
+
+    /// The index of the subnet which contains the address, if the pool does.
+    pub fn address_to_index(&self, addr: IpAddress) -> Option<u64> {
+        if !self.pool.contains(addr) {
+            return None;
+        }
+        let offset = addr.value() - self.pool.network_address().value();
+        let slot = u64::from(offset)
+            .checked_shr(u32::from(32 - self.prefix_len))
+            .unwrap_or(0);
+        Some(match &self.permutation {
+            Some(permutation) => permutation.invert(slot),
+            None => slot,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn ordered() {
+        let index = PoolIndex::new(net("100.64.0.0/16"), 29).unwrap();
+        assert_eq!(8192, index.num_subnets());
+        assert_eq!(Some(net("100.64.0.0/29")), index.index_to_subnet(0));
+        assert_eq!(Some(net("100.64.0.40/29")), index.index_to_subnet(5));
+        assert_eq!(Some(net("100.64.255.248/29")), index.index_to_subnet(8191));
+        assert_eq!(None, index.index_to_subnet(8192));
+
+        assert_eq!(Some(5), index.subnet_to_index(net("100.64.0.40/29")));
+        assert_eq!(
+            Some(5),
+            index.address_to_index("100.64.0.47".parse().unwrap())
+        );
+        // Not one of the pool's subnets: the wrong size, host bits, or outside the pool.
+        assert_eq!(None, index.subnet_to_index(net("100.64.0.40/28")));
+        assert_eq!(None, index.subnet_to_index(net("100.64.0.41/29")));
+        assert_eq!(None, index.subnet_to_index(net("100.65.0.0/29")));
+
+        assert!(PoolIndex::new(net("10.0.0.0/8"), 7).is_none());
+        assert!(PoolIndex::new(net("10.0.0.0/8"), 33).is_none());
+    }
+
+    #[test]
+    fn keyed() {
+        let index = PoolIndex::new(net("10.0.0.0/22"), 26)
+            .unwrap()
+            .keyed(0xc0ffee);
+        let subnets: Vec<_> = (0..index.num_subnets())
+            .map(|i| index.index_to_subnet(i).unwrap())
+            .collect();
+        let mut sorted = subnets.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(16, sorted.len());
+        assert_ne!(sorted, subnets);
+        for (i, &subnet) in subnets.iter().enumerate() {
+            assert_eq!(Some(i as u64), index.subnet_to_index(subnet));
+            assert_eq!(
+                Some(i as u64),
+                index.address_to_index(subnet.broadcast_address())
+            );
+        }
+        let again = PoolIndex::new(net("10.0.0.0/22"), 26)
+            .unwrap()
+            .keyed(0xc0ffee);
+        assert_eq!(subnets[3], again.index_to_subnet(3).unwrap());
+
+        // A single address per index, and a single index per pool.
+        let hosts = PoolIndex::new(net("192.0.2.0/24"), 32).unwrap().keyed(1);
+        let addr = hosts.index_to_subnet(200).unwrap().network_address();
+        assert_eq!(Some(200), hosts.address_to_index(addr));
+        let whole = PoolIndex::new(net("192.0.2.0/24"), 24).unwrap().keyed(1);
+        assert_eq!(Some(net("192.0.2.0/24")), whole.index_to_subnet(0));
+        assert_eq!(
+            Some(0),
+            whole.address_to_index("192.0.2.9".parse().unwrap())
+        );
+    }
+}
@@ -0,0 +1,541 @@
+//! # The Prefix-List Module
+//!
+//! Routers filter the routes they accept and announce with prefix lists: ordered rules which
+//! permit or deny any route within a network, whose prefix length falls within a range. This
+//! module reads and writes the two common configuration dialects, so that filters can be audited
+//! or migrated from one vendor to another:
+//!
+//! - Cisco-style, as in `ip prefix-list NAME seq 10 permit 10.0.0.0/8 le 24`; and
+//! - Juniper-style set commands, as in
+//!   `set policy-options policy-statement NAME term 10 from route-filter 10.0.0.0/8 upto /24`.
+use super::net::IpNetwork;
+use std::{error, fmt};
+
+/// A network, along with a range of prefix lengths: it matches any network within it whose prefix
+/// length falls within that range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PrefixRange {
+    network: IpNetwork,
+    min_len: u8,
+    max_len: u8,
+}
+
+impl PrefixRange {
+    /// Creates a range with the Cisco semantics of `ge` and `le`: with neither, it matches only
+    /// the network itself; `ge` alone extends the range up to `/32`; and `le` alone extends it
+    /// from the network's own prefix length.
+    pub fn new(network: IpNetwork, ge: Option<u8>, le: Option<u8>) -> Option<Self> {
+        let len = network.num_network_bits();
+        match (ge, le) {
+            (None, None) => Self::with_lengths(network, len, len),
+            (Some(ge), None) => Self::with_lengths(network, ge, 32),
+            (None, Some(le)) => Self::with_lengths(network, len, le),
+            (Some(ge), Some(le)) => Self::with_lengths(network, ge, le),
+        }
+    }
+
+    /// Creates a range matching networks within this one, of prefix lengths from `min_len` to
+    /// `max_len` inclusive. These must not be shorter than the network's own prefix length.
+    pub fn with_lengths(network: IpNetwork, min_len: u8, max_len: u8) -> Option<Self> {
+        let len = network.num_network_bits();
+        if len > min_len || min_len > max_len || max_len > 32 {
+            return None;
+        }
+        Some(Self {
+            network: IpNetwork::new(network.network_address(), len)?,
+            min_len,
+            max_len,
+        })
+    }
+
+    /// The network within which every match lies.
+    pub fn network(&self) -> IpNetwork {
+        self.network
+    }
+
+    /// The shortest prefix length matched.
+    pub fn min_len(&self) -> u8 {
+        self.min_len
+    }
+
+    /// The longest prefix length matched.
+    pub fn max_len(&self) -> u8 {
+        self.max_len
+    }
+
+    /// Whether the network lies within this range's network, with a prefix length in range.
+    pub fn matches(&self, net: &IpNetwork) -> bool {
+        let len = net.num_network_bits();
+        self.min_len <= len && len <= self.max_len && net.is_subnet_of(&self.network)
+    }
+
+    /// The Juniper `route-filter` match type equivalent to this range.
+    fn route_filter_type(&self) -> String {
+        let len = self.network.num_network_bits();
+        match (self.min_len, self.max_len) {
+            (min, max) if min == len && max == len => "exact".into(),
+            (min, 32) if min == len => "orlonger".into(),
+            (min, 32) if min == len + 1 => "longer".into(),
+            (min, max) if min == len => format!("upto /{}", max),
+            (min, max) => format!("prefix-length-range /{}-/{}", min, max),
+        }
+    }
+}
+
+impl fmt::Display for PrefixRange {
+    /// Formats the range as it would appear in a Cisco prefix list, such as `10.0.0.0/8 le 24`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.network)?;
+        let len = self.network.num_network_bits();
+        if self.min_len != len {
+            write!(f, " ge {}", self.min_len)?;
+        }
+        if self.max_len != len && !(self.min_len != len && self.max_len == 32) {
+            write!(f, " le {}", self.max_len)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a prefix list does with a matching route.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    Permit,
+    Deny,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Permit => "permit",
+            Self::Deny => "deny",
+        })
+    }
+}
+
+/// A single numbered rule of a prefix list.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PrefixListEntry {
+    pub seq: u32,
+    pub action: Action,
+    pub range: PrefixRange,
+}
+
+/// A named, ordered list of rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixList {
+    pub name: String,
+    pub description: Option<String>,
+    pub entries: Vec<PrefixListEntry>,
+}
+
+impl PrefixList {
+    /// Creates an empty list, which denies everything.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Appends a rule, numbered five past the last, as routers do when no number is given.
+    pub fn push(&mut self, action: Action, range: PrefixRange) {
+        let seq = self.entries.last().map_or(5, |entry| entry.seq + 5);
+        self.entries.push(PrefixListEntry { seq, action, range });
+    }
+
+    /// Decides the fate of a route: the action of the first rule, by number, which matches it;
+    /// or, as on a router, an implicit deny if none do.
+    pub fn evaluate(&self, net: &IpNetwork) -> Action {
+        self.entries
+            .iter()
+            .filter(|entry| entry.range.matches(net))
+            .min_by_key(|entry| entry.seq)
+            .map_or(Action::Deny, |entry| entry.action)
+    }
+
+    /// Writes the list as Cisco configuration.
+    pub fn to_cisco(&self) -> String {
+        let mut out = String::new();
+        if let Some(description) = &self.description {
+            out += &format!("ip prefix-list {} description {}\n", self.name, description);
+        }
+        for entry in &self.entries {
+            out += &format!(
+                "ip prefix-list {} seq {} {} {}\n",
+                self.name, entry.seq, entry.action, entry.range
+            );
+        }
+        out
+    }
+
+    /// Writes the list as Juniper set commands, defining a policy statement with one term per
+    /// rule. Juniper's own `prefix-list` cannot express prefix-length ranges, so route filters
+    /// are used instead.
+    pub fn to_juniper(&self) -> String {
+        let mut out = String::new();
+        let statement = format!("set policy-options policy-statement {}", self.name);
+        if let Some(description) = &self.description {
+            out += &format!("{} description \"{}\"\n", statement, description);
+        }
+        for entry in &self.entries {
+            let term = format!("{} term {}", statement, entry.seq);
+            out += &format!(
+                "{} from route-filter {} {}\n",
+                term,
+                entry.range.network,
+                entry.range.route_filter_type()
+            );
+            let then = match entry.action {
+                Action::Permit => "accept",
+                Action::Deny => "reject",
+            };
+            out += &format!("{} then {}\n", term, then);
+        }
+        out
+    }
+}
+
+/// Parses every prefix list defined in some configuration text, in the order each is first
+/// named. Both dialects may be mixed; blank lines and `!` or `#` comments are skipped.
+pub fn parse(text: &str) -> Result<Vec<PrefixList>, PrefixListError> {
+    let mut lists: Vec<PrefixList> = Vec::new();
+    // Juniper terms are split across two commands, so a term's rule is only known once both its
+    // route filter and its action have been seen.
+    let mut pending: Vec<(String, u32, Option<PrefixRange>, Option<Action>)> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let error = |kind| PrefixListError { line: i + 1, kind };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            [] => {}
+            [first, ..] if first.starts_with('!') || first.starts_with('#') => {}
+            ["ip", "prefix-list", name, rest @ ..] => {
+                let list = list_named(&mut lists, name);
+                match parse_cisco(list, rest) {
+                    Ok(()) => {}
+                    Err(kind) => return Err(error(kind)),
+                }
+            }
+            ["set", "policy-options", "policy-statement", name, "description", words @ ..] => {
+                let description = words.join(" ").trim_matches('"').to_string();
+                list_named(&mut lists, name).description = Some(description);
+            }
+            ["set", "policy-options", "policy-statement", name, "term", term, rest @ ..] => {
+                let seq = term
+                    .parse()
+                    .map_err(|_| error(ErrorKind::InvalidSeq(term.to_string())))?;
+                list_named(&mut lists, name);
+                let index = match pending.iter().position(|p| p.0 == *name && p.1 == seq) {
+                    Some(index) => index,
+                    None => {
+                        pending.push((name.to_string(), seq, None, None));
+                        pending.len() - 1
+                    }
+                };
+                match rest {
+                    ["from", "route-filter", net, match_type @ ..] => {
+                        pending[index].2 = Some(parse_route_filter(net, match_type).map_err(error)?)
+                    }
+                    ["then", "accept"] => pending[index].3 = Some(Action::Permit),
+                    ["then", "reject"] => pending[index].3 = Some(Action::Deny),
+                    _ => return Err(error(ErrorKind::Syntax(rest.join(" ")))),
+                }
+                if let (name, seq, Some(range), Some(action)) = &pending[index] {
+                    let entry = PrefixListEntry {
+                        seq: *seq,
+                        action: *action,
+                        range: *range,
+                    };
+                    list_named(&mut lists, name).entries.push(entry);
+                }
+            }
+            _ => return Err(error(ErrorKind::Syntax(line.trim().to_string()))),
+        }
+    }
+    Ok(lists)
+}
+
+/// Finds the list with the specified name, creating it if this is the first mention of it.
+fn list_named<'a>(lists: &'a mut Vec<PrefixList>, name: &str) -> &'a mut PrefixList {
+    match lists.iter().position(|list| list.name == name) {
+        Some(index) => &mut lists[index],
+        None => {
+            lists.push(PrefixList::new(name));
+            lists.last_mut().unwrap()
+        }
+    }
+}
+
+/// Parses what follows `ip prefix-list NAME`: either a description, or a rule.
+fn parse_cisco(list: &mut PrefixList, tokens: &[&str]) -> Result<(), ErrorKind> {
+    if let ["description", words @ ..] = tokens {
+        list.description = Some(words.join(" "));
+        return Ok(());
+    }
+    let (seq, tokens) = match tokens {
+        ["seq", seq, rest @ ..] => (
+            Some(
+                seq.parse()
+                    .map_err(|_| ErrorKind::InvalidSeq(seq.to_string()))?,
+            ),
+            rest,
+        ),
+        _ => (None, tokens),
+    };
+    let (action, net, mut tokens) = match tokens {
+        ["permit", net, rest @ ..] => (Action::Permit, net, rest),
+        ["deny", net, rest @ ..] => (Action::Deny, net, rest),
+        _ => return Err(ErrorKind::Syntax(tokens.join(" "))),
+    };
+    let net: IpNetwork = net
+        .parse()
+        .map_err(|_| ErrorKind::InvalidNetwork(net.to_string()))?;
+    let (mut ge, mut le) = (None, None);
+    while let [keyword, value, rest @ ..] = tokens {
+        let slot = match *keyword {
+            "ge" if ge.is_none() => &mut ge,
+            "le" if le.is_none() => &mut le,
+            _ => return Err(ErrorKind::Syntax(tokens.join(" "))),
+        };
+        *slot = Some(
+            value
+                .parse()
+                .map_err(|_| ErrorKind::InvalidRange(tokens.join(" ")))?,
+        );
+        tokens = rest;
+    }
+    if !tokens.is_empty() {
+        return Err(ErrorKind::Syntax(tokens.join(" ")));
+    }
+    let range =
+        PrefixRange::new(net, ge, le).ok_or_else(|| ErrorKind::InvalidRange(net.to_string()))?;
+    match seq {
+        Some(seq) => list.entries.push(PrefixListEntry { seq, action, range }),
+        None => list.push(action, range),
+    }
+    Ok(())
+}
+
+/// Parses a Juniper route filter, such as `10.0.0.0/8 upto /24`.
+fn parse_route_filter(net: &str, match_type: &[&str]) -> Result<PrefixRange, ErrorKind> {
+    let net: IpNetwork = net
+        .parse()
+        .map_err(|_| ErrorKind::InvalidNetwork(net.to_string()))?;
+    let len = net.num_network_bits();
+    let length = |s: &str| s.strip_prefix('/').and_then(|n| n.parse::<u8>().ok());
+    let lengths = match match_type {
+        ["exact"] => Some((len, len)),
+        ["orlonger"] => Some((len, 32)),
+        ["longer"] => Some((len + 1, 32)),
+        ["upto", max] => length(max).map(|max| (len, max)),
+        ["prefix-length-range", range] => range
+            .split_once('-')
+            .and_then(|(min, max)| Some((length(min)?, length(max)?))),
+        _ => return Err(ErrorKind::Syntax(match_type.join(" "))),
+    };
+    lengths
+        .and_then(|(min, max)| PrefixRange::with_lengths(net, min, max))
+        .ok_or_else(|| ErrorKind::InvalidRange(match_type.join(" ")))
+}
+
+/// The error returned when configuration text could not be parsed as prefix lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixListError {
+    line: usize,
+    kind: ErrorKind,
+}
+
+/// What was wrong with a line of configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The line is not a prefix-list statement, or has unexpected words in it.
+    Syntax(String),
+    /// The sequence number or term name is not a number.
+    InvalidSeq(String),
+    /// The network is not in CIDR notation.
+    InvalidNetwork(String),
+    /// The prefix lengths are not numbers, or do not make sense for the network.
+    InvalidRange(String),
+}
+
+impl PrefixListError {
+    /// The line on which the error was found, counting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// What was wrong with the line.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for PrefixListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::Syntax(s) => write!(f, "unexpected `{}`", s),
+            ErrorKind::InvalidSeq(s) => write!(f, "invalid sequence number `{}`", s),
+            ErrorKind::InvalidNetwork(s) => write!(f, "invalid network `{}`", s),
+            ErrorKind::InvalidRange(s) => write!(f, "invalid prefix-length range `{}`", s),
+        }
+    }
+}
+
+impl error::Error for PrefixListError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn range(s: &str, ge: Option<u8>, le: Option<u8>) -> PrefixRange {
+        PrefixRange::new(net(s), ge, le).unwrap()
+    }
+
+    #[test]
+    fn prefix_range() {
+        let exact = range("10.0.0.0/8", None, None);
+        assert!(exact.matches(&net("10.0.0.0/8")));
+        assert!(!exact.matches(&net("10.1.0.0/16")));
+        let le = range("10.0.0.0/8", None, Some(24));
+        assert!(le.matches(&net("10.0.0.0/8")));
+        assert!(le.matches(&net("10.1.2.0/24")));
+        assert!(!le.matches(&net("10.1.2.0/25")));
+        assert!(!le.matches(&net("11.0.0.0/16")));
+        let ge = range("10.0.0.0/8", Some(16), None);
+        assert_eq!((16, 32), (ge.min_len(), ge.max_len()));
+        assert!(!ge.matches(&net("10.0.0.0/8")));
+        assert!(ge.matches(&net("10.1.2.3/32")));
+
+        assert_eq!(None, PrefixRange::new(net("10.0.0.0/8"), Some(4), None));
+        assert_eq!(
+            None,
+            PrefixRange::new(net("10.0.0.0/8"), Some(24), Some(16))
+        );
+        assert_eq!(None, PrefixRange::new(net("10.0.0.0/8"), None, Some(33)));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!("10.0.0.0/8", range("10.0.0.0/8", None, None).to_string());
+        assert_eq!(
+            "10.0.0.0/8 le 24",
+            range("10.0.0.0/8", None, Some(24)).to_string()
+        );
+        assert_eq!(
+            "10.0.0.0/8 ge 16",
+            range("10.0.0.0/8", Some(16), None).to_string()
+        );
+        assert_eq!(
+            "10.0.0.0/8 ge 16 le 24",
+            range("10.0.0.0/8", Some(16), Some(24)).to_string()
+        );
+        assert_eq!(
+            "0.0.0.0/1 le 32",
+            range("0.0.0.0/1", None, Some(32)).to_string()
+        );
+        assert_eq!("10.0.0.0/8", range("10.9.9.9/8", None, None).to_string());
+    }
+
+    #[test]
+    fn cisco() {
+        let text = "\
+! bogons
+ip prefix-list BOGONS description Never accept these
+ip prefix-list BOGONS seq 10 deny 10.0.0.0/8 le 32
+ip prefix-list BOGONS seq 20 deny 192.168.0.0/16 ge 16
+ip prefix-list BOGONS permit 0.0.0.0/0 le 24
+
+ip prefix-list DEFAULT permit 0.0.0.0/1 ge 1 le 1
+";
+        let lists = parse(text).unwrap();
+        assert_eq!(2, lists.len());
+        let bogons = &lists[0];
+        assert_eq!(Some("Never accept these"), bogons.description.as_deref());
+        assert_eq!(
+            vec![10, 20, 25],
+            bogons.entries.iter().map(|e| e.seq).collect::<Vec<_>>()
+        );
+        assert_eq!(Action::Deny, bogons.evaluate(&net("10.1.0.0/16")));
+        assert_eq!(Action::Permit, bogons.evaluate(&net("8.8.8.0/24")));
+        assert_eq!(Action::Deny, bogons.evaluate(&net("8.8.8.0/25")));
+        assert_eq!(
+            "\
+ip prefix-list BOGONS description Never accept these
+ip prefix-list BOGONS seq 10 deny 10.0.0.0/8 le 32
+ip prefix-list BOGONS seq 20 deny 192.168.0.0/16 le 32
+ip prefix-list BOGONS seq 25 permit 0.0.0.0/0 le 24
+",
+            bogons.to_cisco()
+        );
+        assert_eq!(
+            lists,
+            parse(&(bogons.to_cisco() + &lists[1].to_cisco())).unwrap()
+        );
+    }
+
+    #[test]
+    fn juniper() {
+        let mut list = PrefixList::new("EDGE");
+        list.push(Action::Deny, range("10.0.0.0/8", None, None));
+        list.push(Action::Deny, range("172.16.0.0/12", None, Some(32)));
+        list.push(Action::Deny, range("192.168.0.0/16", Some(17), None));
+        list.push(Action::Permit, range("0.0.0.0/0", None, Some(24)));
+        list.push(Action::Permit, range("100.64.0.0/10", Some(16), Some(20)));
+        let text = list.to_juniper();
+        let statement = "set policy-options policy-statement EDGE";
+        for line in &[
+            "term 5 from route-filter 10.0.0.0/8 exact",
+            "term 5 then reject",
+            "term 10 from route-filter 172.16.0.0/12 orlonger",
+            "term 15 from route-filter 192.168.0.0/16 longer",
+            "term 20 from route-filter 0.0.0.0/0 upto /24",
+            "term 20 then accept",
+            "term 25 from route-filter 100.64.0.0/10 prefix-length-range /16-/20",
+        ] {
+            assert!(
+                text.contains(&format!("{} {}\n", statement, line)),
+                "{}",
+                line
+            );
+        }
+        assert_eq!(vec![list], parse(&text).unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let err = |text: &str| parse(text).unwrap_err().to_string();
+        assert_eq!(
+            "line 2: unexpected `route-map X`",
+            err("ip prefix-list A permit 10.0.0.0/8\nroute-map X")
+        );
+        assert_eq!(
+            "line 1: invalid network `10.0.0.0`",
+            err("ip prefix-list A permit 10.0.0.0")
+        );
+        assert_eq!(
+            "line 1: invalid prefix-length range `10.0.0.0/8`",
+            err("ip prefix-list A permit 10.0.0.0/8 ge 4")
+        );
+        assert_eq!(
+            "line 1: unexpected `le 10`",
+            err("ip prefix-list A permit 10.0.0.0/8 le 9 le 10")
+        );
+        assert_eq!(
+            "line 1: invalid sequence number `x`",
+            err("ip prefix-list A seq x permit 10.0.0.0/8")
+        );
+        assert_eq!(
+            "line 1: invalid prefix-length range `upto /4`",
+            err(
+                "set policy-options policy-statement A term 1 from route-filter 10.0.0.0/8 upto /4"
+            )
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! # The Random Module
+//!
+//! Random generation of addresses and networks, for test data and load generation. The generator
+//! here is small and fast, and can be seeded so that a run may be repeated exactly; it is **not**
+//! suitable for anything that needs to be unpredictable to an adversary.
+use super::{addr6::Ipv6Address, bits::AddressBits, net6::Ipv6Network};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::SystemTime,
+};
+
+/// A xoshiro256** pseudo-random number generator.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    /// Creates a generator which will always produce the same sequence for the same seed.
+    pub fn seed_from_u64(seed: u64) -> Self {
+        // The state is expanded from the seed with SplitMix64, as recommended by the authors of
+        // xoshiro, so that similar seeds still produce unrelated sequences.
+        let mut seed = seed;
+        let mut state = [0; 4];
+        for word in state.iter_mut() {
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            *word = z ^ (z >> 31);
+        }
+        Self { state }
+    }
+
+    /// Creates a generator seeded from the process's hash randomization and the current time.
+    pub fn from_entropy() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        if let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            hasher.write_u128(elapsed.as_nanos());
+        }
+        Self::seed_from_u64(hasher.finish())
+    }
+
+    /// Generates the next random number, uniformly distributed across all `u64` values.
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.state[1] << 17;
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= t;
+        self.state[3] = self.state[3].rotate_left(45);
+        result
+    }
+
+    /// Generates a random number uniformly distributed across all `u128` values.
+    pub fn next_u128(&mut self) -> u128 {
+        u128::from(self.next_u64()) << 64 | u128::from(self.next_u64())
+    }
+
+    /// Generates a random number uniformly distributed below the bound, which must not be zero.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound > 0, "cannot generate a number below zero");
+        // Lemire's nearly-divisionless method, rejecting the few results which would bias the
+        // distribution towards low numbers.
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let product = u128::from(self.next_u64()) * u128::from(bound);
+            if product as u64 >= threshold {
+                return (product >> 64) as u64;
+            }
+        }
+    }
+}
+
+/// A random one-to-one mapping of the numbers `0..2^bits` onto themselves.
+///
+/// Walking the indices in order through the permutation visits every number exactly once, in a
+/// scrambled order, without having to remember which numbers have already been seen.
+#[derive(Debug, Clone)]
+pub struct Permutation {
+    bits: u8,
+    keys: [(u64, u64); 4],
+}
+
+impl Permutation {
+    /// Creates a random permutation of the numbers which fit within the number of bits, which may
+    /// be at most 64.
+    pub fn new(bits: u8, rng: &mut Rng) -> Self {
+        assert!(bits <= 64, "cannot permute more than 64 bits");
+        let mut keys = [(0, 0); 4];
+        for key in keys.iter_mut() {
+            // Multiplying by an odd number is invertible modulo a power of two.
+            *key = (rng.next_u64(), rng.next_u64() | 1);
+        }
+        Self { bits, keys }
+    }
+
+    /// The number of bits which are permuted.
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    fn mask(&self) -> u64 {
+        match self.bits {
+            64 => u64::MAX,
+            n => (1 << n) - 1,
+        }
+    }
+
+    /// Maps the index to its place in the permutation. Only the low bits of the index are used.
+    pub fn apply(&self, index: u64) -> u64 {
+        let mask = self.mask();
+        let shift = (u32::from(self.bits) / 2).max(1);
+        let mut x = index & mask;
+        // Each step of each round is a bijection on numbers of the permuted width, so the whole
+        // is too: adding a constant, xor-ing in the high half, and multiplying by an odd number.
+        for &(add, mul) in &self.keys {
+            x = x.wrapping_add(add) & mask;
+            x ^= x.checked_shr(shift).unwrap_or(0);
+            x = x.wrapping_mul(mul) & mask;
+        }
+        x
+    }
+
+    /// Maps a place in the permutation back to its index, undoing [`Permutation::apply`].
+    pub fn invert(&self, value: u64) -> u64 {
+        let mask = self.mask();
+        let shift = (u32::from(self.bits) / 2).max(1);
+        let mut x = value & mask;
+        for &(add, mul) in self.keys.iter().rev() {
+            // Newton's iteration doubles the correct low bits of the inverse each time.
+            let inverse = (0..5).fold(mul, |inv, _| {
+                inv.wrapping_mul(2u64.wrapping_sub(mul.wrapping_mul(inv)))
+            });
+            x = x.wrapping_mul(inverse) & mask;
+            let y = x;
+            for _ in 0..=u32::from(self.bits) / shift {
+                x = y ^ x.checked_shr(shift).unwrap_or(0);
+            }
+            x = x.wrapping_sub(add) & mask;
+        }
+        x
+    }
+}
+
+/// An iterator over the subnets of an IPv6 network, in a random order. See
+/// [`Ipv6Network::permuted_subnets`].
+#[derive(Debug, Clone)]
+pub struct PermutedSubnets {
+    base: u128,
+    prefix_len: u8,
+    permutation: Permutation,
+    next: u128,
+    count: u128,
+}
+
+impl PermutedSubnets {
+    /// Visits the subnets of the network with the base address, `bits` bits longer than its own.
+    pub(crate) fn new(base: u128, prefix_len: u8, bits: u8, rng: &mut Rng) -> Self {
+        Self {
+            base,
+            prefix_len,
+            permutation: Permutation::new(bits, rng),
+            next: 0,
+            count: 1 << bits,
+        }
+    }
+}
+
+impl Iterator for PermutedSubnets {
+    type Item = Ipv6Network;
+
+    fn next(&mut self) -> Option<Ipv6Network> {
+        if self.next >= self.count {
+            return None;
+        }
+        let index = self.permutation.apply(self.next as u64);
+        self.next += 1;
+        let offset = u128::from(index) << (<u128 as AddressBits>::BITS - self.prefix_len);
+        Ipv6Network::new(Ipv6Address::from(self.base | offset), self.prefix_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn seeded() {
+        let a: Vec<_> = {
+            let mut rng = Rng::seed_from_u64(42);
+            (0..8).map(|_| rng.next_u64()).collect()
+        };
+        let mut rng = Rng::seed_from_u64(42);
+        assert_eq!(a, (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>());
+        let mut rng = Rng::seed_from_u64(43);
+        assert_ne!(a, (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn below() {
+        let mut rng = Rng::seed_from_u64(1);
+        let mut seen = [false; 10];
+        for _ in 0..1000 {
+            seen[rng.below(10) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+        assert_eq!(0, rng.below(1));
+        assert!(rng.below(u64::MAX) < u64::MAX);
+    }
+
+    #[test]
+    fn permutation() {
+        let mut rng = Rng::seed_from_u64(7);
+        for bits in 0..=12 {
+            let perm = Permutation::new(bits, &mut rng);
+            let image: HashSet<_> = (0..1u64 << bits).map(|i| perm.apply(i)).collect();
+            assert_eq!(1 << bits, image.len());
+            assert!(image.iter().all(|&x| x < 1 << bits));
+        }
+        let perm = Permutation::new(16, &mut rng);
+        let first: Vec<_> = (0..16).map(|i| perm.apply(i)).collect();
+        assert_ne!((0..16).collect::<Vec<_>>(), first);
+        let perm = Permutation::new(64, &mut rng);
+        assert_ne!(perm.apply(0), perm.apply(1));
+        for bits in &[0, 1, 7, 16, 33, 64] {
+            let perm = Permutation::new(*bits, &mut rng);
+            for _ in 0..100 {
+                let index = rng.next_u64() & perm.mask();
+                assert_eq!(index, perm.invert(perm.apply(index)));
+            }
+        }
+    }
+
+    #[test]
+    fn permuted_subnets() {
+        let mut rng = Rng::seed_from_u64(3);
+        let net: Ipv6Network = "2001:db8::/48".parse().unwrap();
+        let subnets: Vec<_> = net.permuted_subnets(56, &mut rng).unwrap().collect();
+        assert_eq!(256, subnets.len());
+        assert_eq!(256, subnets.iter().collect::<HashSet<_>>().len());
+        assert!(subnets
+            .iter()
+            .all(|s| s.is_subnet_of(&net) && s.num_network_bits() == 56));
+        assert_ne!("2001:db8::/56", subnets[0].to_string());
+
+        let host: Ipv6Network = "2001:db8::/126".parse().unwrap();
+        let mut addresses: Vec<_> = host
+            .permuted_subnets(128, &mut rng)
+            .unwrap()
+            .map(|net| net.to_string())
+            .collect();
+        addresses.sort();
+        assert_eq!(
+            vec![
+                "2001:db8::/128",
+                "2001:db8::1/128",
+                "2001:db8::2/128",
+                "2001:db8::3/128"
+            ],
+            addresses
+        );
+        assert!(net.permuted_subnets(113, &mut rng).is_none());
+        assert!(net.permuted_subnets(47, &mut rng).is_none());
+        assert_eq!(
+            2,
+            net.permuted_subnets(112, &mut rng).unwrap().take(2).count()
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! # The IP-Range Module
+//!
+//! An IP Range is the set of every address between two endpoints. Unlike a network, a range need
+//! not be aligned to any power of two, so it is the natural form for the results of set
+//! operations. Any range can be broken back down into a minimal list of networks.
+use super::{
+    addr::IpAddress,
+    bits::{self, Blocks},
+    net::IpNetwork,
+};
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IpRange {
+    start: IpAddress,
+    end: IpAddress,
+}
+
+impl IpRange {
+    /// Creates a new range spanning from the first address to the last address, inclusive.
+    ///
+    /// The range may not be empty, so the last address **must not** precede the first.
+    pub fn new(start: IpAddress, end: IpAddress) -> Option<Self> {
+        if start <= end {
+            Some(Self { start, end })
+        } else {
+            None
+        }
+    }
+
+    /// The first address within this range.
+    pub fn start(&self) -> IpAddress {
+        self.start
+    }
+
+    /// The last address within this range.
+    pub fn end(&self) -> IpAddress {
+        self.end
+    }
+
+    /// Gets the number of addresses within this range.
+    pub fn num_addresses(&self) -> u64 {
+        u64::from(self.end.value() - self.start.value()) + 1
+    }
+
+    /// Gets the number of addresses within this range.
+    #[deprecated(note = "use `num_addresses`")]
+    pub fn num_hosts(&self) -> u64 {
+        self.num_addresses()
+    }
+
+    /// Gets the address at the specified offset from the start of this range, unless the offset
+    /// runs past the end of it.
+    pub fn nth(&self, n: u64) -> Option<IpAddress> {
+        if n >= self.num_addresses() {
+            return None;
+        }
+        self.start.checked_add(n as u32)
+    }
+
+    /// Whether the specified address lies within this range.
+    pub fn contains(&self, addr: IpAddress) -> bool {
+        self.start <= addr && addr <= self.end
+    }
+
+    /// Whether every address of the other range lies within this one.
+    pub fn contains_range(&self, other: &IpRange) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    /// The addresses which lie within both ranges, unless they are disjoint.
+    pub fn intersection(&self, other: &IpRange) -> Option<IpRange> {
+        IpRange::new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    /// The number of addresses which lie within both ranges.
+    pub fn overlap(&self, other: &IpRange) -> u64 {
+        self.intersection(other)
+            .map_or(0, |range| range.num_addresses())
+    }
+
+    /// The fraction of this range's addresses which also lie within the other, from `0.0` when
+    /// they are disjoint to `1.0` when the other covers it.
+    pub fn overlap_fraction(&self, other: &IpRange) -> f64 {
+        self.overlap(other) as f64 / self.num_addresses() as f64
+    }
+
+    /// Breaks this range down into the fewest networks which exactly cover it, in ascending
+    /// order.
+    pub fn networks(&self) -> Networks {
+        Networks {
+            blocks: bits::blocks(self.start.value(), self.end.value()),
+        }
+    }
+}
+
+impl From<IpNetwork> for IpRange {
+    fn from(net: IpNetwork) -> Self {
+        Self {
+            start: net.network_address(),
+            end: net.broadcast_address(),
+        }
+    }
+}
+
+impl fmt::Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// An iterator over the minimal networks covering a range.
+#[derive(Debug, Clone)]
+pub struct Networks {
+    blocks: Blocks<u32>,
+}
+
+impl Iterator for Networks {
+    type Item = IpNetwork;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (base, prefix_len) = self.blocks.next()?;
+        IpNetwork::new(IpAddress::from(base), prefix_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: [u8; 4], end: [u8; 4]) -> IpRange {
+        IpRange::new(IpAddress::from(start), IpAddress::from(end)).unwrap()
+    }
+
+    fn networks(range: IpRange) -> Vec<String> {
+        range.networks().map(|net| net.to_string()).collect()
+    }
+
+    #[test]
+    fn init() {
+        assert!(IpRange::new(IpAddress::from(1), IpAddress::from(0)).is_none());
+        assert_eq!(1, range([1, 1, 1, 1], [1, 1, 1, 1]).num_addresses());
+        assert_eq!(
+            4294967296,
+            range([0, 0, 0, 0], [255, 255, 255, 255]).num_addresses()
+        );
+        assert_eq!(
+            range([10, 0, 0, 0], [10, 255, 255, 255]),
+            IpRange::from("10.1.2.3/8".parse::<IpNetwork>().unwrap())
+        );
+    }
+
+    #[test]
+    fn contains() {
+        let r = range([10, 0, 0, 5], [10, 0, 1, 2]);
+        assert!(r.contains(IpAddress::from([10, 0, 0, 5])));
+        assert!(r.contains(IpAddress::from([10, 0, 0, 255])));
+        assert!(r.contains(IpAddress::from([10, 0, 1, 2])));
+        assert!(!r.contains(IpAddress::from([10, 0, 0, 4])));
+        assert!(!r.contains(IpAddress::from([10, 0, 1, 3])));
+        assert_eq!(Some(IpAddress::from([10, 0, 1, 2])), r.nth(253));
+        assert_eq!(None, r.nth(254));
+    }
+
+    #[test]
+    fn overlap() {
+        let r = range([10, 0, 0, 200], [10, 0, 1, 99]);
+        let net: IpNetwork = "10.0.0.0/24".parse().unwrap();
+        assert_eq!(
+            Some(range([10, 0, 0, 200], [10, 0, 0, 255])),
+            net.intersection(&r)
+        );
+        assert_eq!(56.0 / 256.0, net.overlap_fraction(&r));
+        assert_eq!(56.0 / 156.0, r.overlap_fraction(&IpRange::from(net)));
+        assert_eq!(56, r.overlap(&IpRange::from(net)));
+        assert!(!net.contains_range(&r));
+        assert!(net.contains_range(&range([10, 0, 0, 7], [10, 0, 0, 9])));
+        assert!(r.contains_range(&r));
+
+        let other: IpNetwork = "10.0.2.0/24".parse().unwrap();
+        assert_eq!(None, other.intersection(&r));
+        assert_eq!(0.0, other.overlap_fraction(&r));
+        assert_eq!(0, r.overlap(&IpRange::from(other)));
+        let all = range([0, 0, 0, 0], [255, 255, 255, 255]);
+        assert_eq!(1.0, net.overlap_fraction(&all));
+        assert_eq!(
+            1.0 / 4294967296.0,
+            all.overlap_fraction(&range([1, 2, 3, 4], [1, 2, 3, 4]))
+        );
+    }
+
+    #[test]
+    fn to_networks() {
+        assert_eq!(
+            vec!["0.0.0.0/0"],
+            networks(range([0, 0, 0, 0], [255, 255, 255, 255]))
+        );
+        assert_eq!(
+            vec!["255.255.255.255/32"],
+            networks(range([255, 255, 255, 255], [255, 255, 255, 255]))
+        );
+        assert_eq!(
+            vec!["10.0.0.0/24"],
+            networks(range([10, 0, 0, 0], [10, 0, 0, 255]))
+        );
+        assert_eq!(
+            vec![
+                "10.0.0.5/32",
+                "10.0.0.6/31",
+                "10.0.0.8/29",
+                "10.0.0.16/28",
+                "10.0.0.32/27",
+                "10.0.0.64/26",
+                "10.0.0.128/25",
+                "10.0.1.0/31",
+                "10.0.1.2/32",
+            ],
+            networks(range([10, 0, 0, 5], [10, 0, 1, 2]))
+        );
+        assert_eq!(
+            vec!["0.0.0.0/32"],
+            networks(range([0, 0, 0, 0], [0, 0, 0, 0]))
+        );
+        assert_eq!(
+            vec!["255.255.255.254/31"],
+            networks(range([255, 255, 255, 254], [255, 255, 255, 255]))
+        );
+    }
+}
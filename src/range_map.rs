@@ -0,0 +1,232 @@
+//! # The IP-Range-Map Module
+//!
+//! An IP Range Map associates values with ranges of addresses. Where a network map answers which
+//! of many nested networks is the most specific, the ranges of a range map never overlap, so each
+//! address has at most one value. This is the shape of most databases keyed by address, such as
+//! geolocation or ASN tables, whose ranges rarely fall on network boundaries.
+use super::{addr::IpAddress, range::IpRange};
+use std::iter::FromIterator;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpRangeMap<V> {
+    /// The ranges and their values, sorted by address, and never overlapping.
+    entries: Vec<(IpRange, V)>,
+}
+
+impl<V> Default for IpRangeMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> IpRangeMap<V> {
+    /// Creates a new, empty map.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// The number of ranges in this map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether this map contains no ranges at all.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Associates the value with every address of the range.
+    ///
+    /// Any addresses of the range which already had a value are given this one instead, so an
+    /// existing range may be trimmed, or split in two.
+    pub fn insert(&mut self, range: IpRange, value: V)
+    where
+        V: Clone,
+    {
+        let (start, end) = (
+            u64::from(range.start().value()),
+            u64::from(range.end().value()),
+        );
+        // The entries from `first` up to `last` are the ones overlapping the new range.
+        let first = self
+            .entries
+            .partition_point(|(r, _)| u64::from(r.end().value()) < start);
+        let last = self
+            .entries
+            .partition_point(|(r, _)| u64::from(r.start().value()) <= end);
+        let mut replacement = Vec::with_capacity(3);
+        if first < last {
+            let (before, value) = &self.entries[first];
+            if before.start() < range.start() {
+                let trimmed = span(u64::from(before.start().value()), start - 1);
+                replacement.push((trimmed, value.clone()));
+            }
+        }
+        replacement.push((range, value));
+        if first < last {
+            let (after, value) = &self.entries[last - 1];
+            if after.end() > range.end() {
+                let trimmed = span(end + 1, u64::from(after.end().value()));
+                replacement.push((trimmed, value.clone()));
+            }
+        }
+        self.entries.splice(first..last, replacement);
+    }
+
+    /// Finds the value associated with the address.
+    pub fn lookup(&self, addr: IpAddress) -> Option<&V> {
+        self.lookup_entry(addr).map(|(_, value)| value)
+    }
+
+    /// Finds the range containing the address, along with its value.
+    pub fn lookup_entry(&self, addr: IpAddress) -> Option<(IpRange, &V)> {
+        let index = self.entries.partition_point(|(r, _)| r.end() < addr);
+        self.entries
+            .get(index)
+            .filter(|(r, _)| r.contains(addr))
+            .map(|(r, value)| (*r, value))
+    }
+
+    /// Finds the range closest to the address, along with its value: the range containing it, if
+    /// there is one, or else whichever of the ranges either side of it is fewer addresses away.
+    /// Where both are just as far, the lower range is chosen.
+    pub fn nearest(&self, addr: IpAddress) -> Option<(IpRange, &V)> {
+        let index = self.entries.partition_point(|(r, _)| r.end() < addr);
+        let below = index.checked_sub(1).and_then(|i| self.entries.get(i));
+        let above = self.entries.get(index);
+        let closest = match (below, above) {
+            (Some(below), Some(above)) if !above.0.contains(addr) => {
+                let to_below = addr.value() - below.0.end().value();
+                let to_above = above.0.start().value() - addr.value();
+                if to_below <= to_above {
+                    below
+                } else {
+                    above
+                }
+            }
+            (below, above) => above.or(below)?,
+        };
+        Some((closest.0, &closest.1))
+    }
+
+    /// Iterates over every range and its value, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = (IpRange, &V)> + '_ {
+        self.entries.iter().map(|(r, value)| (*r, value))
+    }
+}
+
+/// Creates the range between two addresses given as wide integers.
+fn span(start: u64, end: u64) -> IpRange {
+    IpRange::new(IpAddress::from(start as u32), IpAddress::from(end as u32)).unwrap()
+}
+
+impl<V: Clone> FromIterator<(IpRange, V)> for IpRangeMap<V> {
+    fn from_iter<I: IntoIterator<Item = (IpRange, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<V: Clone> Extend<(IpRange, V)> for IpRangeMap<V> {
+    fn extend<I: IntoIterator<Item = (IpRange, V)>>(&mut self, iter: I) {
+        for (range, value) in iter {
+            self.insert(range, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: &str, end: &str) -> IpRange {
+        IpRange::new(start.parse().unwrap(), end.parse().unwrap()).unwrap()
+    }
+
+    fn entries(map: &IpRangeMap<&'static str>) -> Vec<String> {
+        map.iter()
+            .map(|(r, value)| format!("{} {}", r, value))
+            .collect()
+    }
+
+    #[test]
+    fn lookup() {
+        let map: IpRangeMap<_> = vec![
+            (range("1.0.0.0", "1.0.0.255"), "AU"),
+            (range("1.0.1.0", "1.0.3.255"), "CN"),
+            (range("255.255.255.0", "255.255.255.255"), "ZZ"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(3, map.len());
+        let lookup = |s: &str| map.lookup(s.parse().unwrap()).copied();
+        assert_eq!(Some("AU"), lookup("1.0.0.0"));
+        assert_eq!(Some("AU"), lookup("1.0.0.255"));
+        assert_eq!(Some("CN"), lookup("1.0.2.7"));
+        assert_eq!(None, lookup("1.0.4.0"));
+        assert_eq!(None, lookup("0.255.255.255"));
+        assert_eq!(Some("ZZ"), lookup("255.255.255.255"));
+        assert_eq!(
+            Some((range("1.0.1.0", "1.0.3.255"), &"CN")),
+            map.lookup_entry("1.0.1.1".parse().unwrap())
+        );
+        assert!(IpRangeMap::<()>::new().lookup(0.into()).is_none());
+    }
+
+    #[test]
+    fn nearest() {
+        let map: IpRangeMap<_> = vec![
+            (range("10.0.0.0", "10.0.0.9"), "a"),
+            (range("10.0.0.19", "10.0.0.29"), "b"),
+        ]
+        .into_iter()
+        .collect();
+        let nearest = |s: &str| map.nearest(s.parse().unwrap()).map(|(_, v)| *v);
+        assert_eq!(Some("a"), nearest("0.0.0.0"));
+        assert_eq!(Some("a"), nearest("10.0.0.5"));
+        assert_eq!(Some("a"), nearest("10.0.0.13"));
+        // Five addresses from either range.
+        assert_eq!(Some("a"), nearest("10.0.0.14"));
+        assert_eq!(Some("b"), nearest("10.0.0.15"));
+        assert_eq!(Some("b"), nearest("10.0.0.20"));
+        assert_eq!(Some("b"), nearest("255.255.255.255"));
+        assert_eq!(
+            Some((range("10.0.0.19", "10.0.0.29"), &"b")),
+            map.nearest("10.0.0.25".parse().unwrap())
+        );
+        assert!(IpRangeMap::<()>::new().nearest(0.into()).is_none());
+    }
+
+    #[test]
+    fn overlapping_insert() {
+        let mut map = IpRangeMap::new();
+        map.insert(range("10.0.0.0", "10.0.0.255"), "a");
+        map.insert(range("10.0.2.0", "10.0.2.255"), "b");
+        // Splits the first range.
+        map.insert(range("10.0.0.16", "10.0.0.31"), "c");
+        assert_eq!(
+            vec![
+                "10.0.0.0-10.0.0.15 a",
+                "10.0.0.16-10.0.0.31 c",
+                "10.0.0.32-10.0.0.255 a",
+                "10.0.2.0-10.0.2.255 b",
+            ],
+            entries(&map)
+        );
+        // Swallows the middle ranges, and trims either end.
+        map.insert(range("10.0.0.8", "10.0.2.127"), "d");
+        assert_eq!(
+            vec![
+                "10.0.0.0-10.0.0.7 a",
+                "10.0.0.8-10.0.2.127 d",
+                "10.0.2.128-10.0.2.255 b",
+            ],
+            entries(&map)
+        );
+        map.insert(range("0.0.0.0", "255.255.255.255"), "all");
+        assert_eq!(vec!["0.0.0.0-255.255.255.255 all"], entries(&map));
+    }
+}
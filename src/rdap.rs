@@ -0,0 +1,484 @@
+//! # The RDAP Module
+//!
+//! Handling abuse starts by finding out who an address was allocated to. The Registration Data
+//! Access Protocol (RFC 9082, RFC 9083) answers that question in JSON, over HTTPS, from whichever
+//! regional internet registry made the allocation. This module asks, and reads the answer into
+//! the crate's own types.
+//!
+//! The standard library speaks neither HTTPS nor JSON. Fetching is left to a [`Transport`], of
+//! which the default runs `curl`; the little JSON that is needed is read here. Lookups block.
+use super::{addr::IpAddress, net::IpNetwork, range::IpRange};
+use std::{error, fmt, io, process::Command, str::Chars};
+
+/// The regional internet registries, which allocate addresses.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Rir {
+    Afrinic,
+    Apnic,
+    Arin,
+    Lacnic,
+    RipeNcc,
+}
+
+impl Rir {
+    /// Recognises a registry by its WHOIS server, which RDAP responses name.
+    fn from_whois(server: &str) -> Option<Self> {
+        match server.trim().to_ascii_lowercase().as_str() {
+            "whois.afrinic.net" => Some(Self::Afrinic),
+            "whois.apnic.net" => Some(Self::Apnic),
+            "whois.arin.net" => Some(Self::Arin),
+            "whois.lacnic.net" => Some(Self::Lacnic),
+            "whois.ripe.net" => Some(Self::RipeNcc),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Rir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Afrinic => "AFRINIC",
+            Self::Apnic => "APNIC",
+            Self::Arin => "ARIN",
+            Self::Lacnic => "LACNIC",
+            Self::RipeNcc => "RIPE NCC",
+        })
+    }
+}
+
+/// The allocation an address or network belongs to, as a registry describes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    /// The registry which made the allocation, if it could be recognised.
+    pub rir: Option<Rir>,
+    pub range: IpRange,
+    /// The registry's identifier for the allocation, such as `NET-8-8-8-0-2`.
+    pub handle: Option<String>,
+    /// The name the holder gave the allocation, such as `GOGL`.
+    pub name: Option<String>,
+    /// The name of the organization the allocation is registered to.
+    pub organization: Option<String>,
+    pub country: Option<String>,
+}
+
+/// Fetches documents over HTTP.
+pub trait Transport {
+    /// Fetches the document at the URL, following any redirects.
+    fn get(&self, url: &str) -> io::Result<String>;
+}
+
+/// A transport which runs `curl`, which must be installed.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Curl;
+
+impl Transport for Curl {
+    fn get(&self, url: &str) -> io::Result<String> {
+        let output = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location"])
+            .args(["--header", "Accept: application/rdap+json"])
+            .arg(url)
+            .output()?;
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr);
+            return Err(io::Error::other(message.trim()));
+        }
+        String::from_utf8(output.stdout)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/// A client which asks an RDAP service about addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdapClient<T = Curl> {
+    base: String,
+    transport: T,
+}
+
+impl Default for RdapClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RdapClient {
+    /// Creates a client which asks `rdap.org`, which redirects each query to the registry
+    /// responsible for it.
+    pub fn new() -> Self {
+        Self::with_transport(Curl)
+    }
+}
+
+impl<T: Transport> RdapClient<T> {
+    /// Creates a client which fetches through the transport.
+    pub fn with_transport(transport: T) -> Self {
+        Self {
+            base: "https://rdap.org".to_string(),
+            transport,
+        }
+    }
+
+    /// Sets the service to ask, such as a registry's own `https://rdap.arin.net/registry`.
+    pub fn with_base(mut self, base: &str) -> Self {
+        self.base = base.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Finds the allocation the address belongs to.
+    pub fn lookup(&self, addr: IpAddress) -> Result<Allocation, RdapError> {
+        self.fetch(&addr.to_string())
+    }
+
+    /// Finds the allocation the network belongs to, which is the smallest one containing it.
+    pub fn lookup_network(&self, net: IpNetwork) -> Result<Allocation, RdapError> {
+        self.fetch(&net.to_string())
+    }
+
+    fn fetch(&self, target: &str) -> Result<Allocation, RdapError> {
+        let url = format!("{}/ip/{}", self.base, target);
+        parse(&self.transport.get(&url).map_err(RdapError::Io)?)
+    }
+}
+
+/// Reads an RDAP IP network object.
+pub fn parse(text: &str) -> Result<Allocation, RdapError> {
+    let doc = Json::parse(text).ok_or(RdapError::InvalidJson)?;
+    let string = |key| doc.get(key).and_then(Json::as_str).map(str::to_string);
+    let address = |key| {
+        let s = doc
+            .get(key)
+            .and_then(Json::as_str)
+            .ok_or(RdapError::MissingField(key))?;
+        s.parse::<IpAddress>()
+            .map_err(|_| RdapError::InvalidAddress(s.to_string()))
+    };
+    let (start, end) = (address("startAddress")?, address("endAddress")?);
+    let range = IpRange::new(start, end)
+        .ok_or_else(|| RdapError::InvalidAddress(format!("{}-{}", start, end)))?;
+
+    // The holder is the registrant entity, whose name is the `fn` property of its vCard.
+    let organization = doc
+        .get("entities")
+        .and_then(Json::as_array)
+        .unwrap_or_default()
+        .iter()
+        .find(|entity| {
+            let roles = entity.get("roles").and_then(Json::as_array);
+            roles.is_some_and(|roles| roles.iter().any(|r| r.as_str() == Some("registrant")))
+        })
+        .and_then(|entity| entity.get("vcardArray")?.as_array()?.get(1)?.as_array())
+        .and_then(|properties| {
+            properties
+                .iter()
+                .find_map(|property| match property.as_array()? {
+                    [name, _, _, value, ..] if name.as_str() == Some("fn") => value.as_str(),
+                    _ => None,
+                })
+        })
+        .map(str::to_string);
+
+    Ok(Allocation {
+        rir: doc
+            .get("port43")
+            .and_then(Json::as_str)
+            .and_then(Rir::from_whois),
+        range,
+        handle: string("handle"),
+        name: string("name"),
+        organization,
+        country: string("country"),
+    })
+}
+
+/// How deeply arrays and objects may nest. Real RDAP responses stay well under this.
+const MAX_DEPTH: usize = 64;
+
+/// A JSON value. Numbers are never needed, so they are kept as written.
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a whole document.
+    fn parse(text: &str) -> Option<Self> {
+        let mut chars = text.chars();
+        let value = Self::parse_value(&mut chars, 0)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Some(value),
+            Some(_) => None,
+        }
+    }
+
+    /// Parses one value, `depth` arrays and objects deep. Nesting past `MAX_DEPTH` is rejected
+    /// rather than risking the stack on a hostile response.
+    fn parse_value(chars: &mut Chars<'_>, depth: usize) -> Option<Self> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        skip_whitespace(chars);
+        let rest = chars.as_str();
+        match chars.next()? {
+            'n' => keyword(chars, "ull", Self::Null),
+            't' => keyword(chars, "rue", Self::Bool(true)),
+            'f' => keyword(chars, "alse", Self::Bool(false)),
+            '"' => parse_string(chars).map(Self::String),
+            '[' => {
+                let mut items = Vec::new();
+                skip_whitespace(chars);
+                if chars.as_str().starts_with(']') {
+                    chars.next();
+                    return Some(Self::Array(items));
+                }
+                loop {
+                    items.push(Self::parse_value(chars, depth + 1)?);
+                    skip_whitespace(chars);
+                    match chars.next()? {
+                        ',' => continue,
+                        ']' => return Some(Self::Array(items)),
+                        _ => return None,
+                    }
+                }
+            }
+            '{' => {
+                let mut members = Vec::new();
+                skip_whitespace(chars);
+                if chars.as_str().starts_with('}') {
+                    chars.next();
+                    return Some(Self::Object(members));
+                }
+                loop {
+                    skip_whitespace(chars);
+                    if chars.next()? != '"' {
+                        return None;
+                    }
+                    let key = parse_string(chars)?;
+                    skip_whitespace(chars);
+                    if chars.next()? != ':' {
+                        return None;
+                    }
+                    members.push((key, Self::parse_value(chars, depth + 1)?));
+                    skip_whitespace(chars);
+                    match chars.next()? {
+                        ',' => continue,
+                        '}' => return Some(Self::Object(members)),
+                        _ => return None,
+                    }
+                }
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let len = rest
+                    .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                    .unwrap_or(rest.len());
+                *chars = rest[len..].chars();
+                Some(Self::Number(rest[..len].to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value of the member of an object.
+    fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Chars<'_>) {
+    *chars = chars.as_str().trim_start().chars();
+}
+
+/// Parses the rest of a keyword whose first letter has been read.
+fn keyword(chars: &mut Chars<'_>, rest: &str, value: Json) -> Option<Json> {
+    let s = chars.as_str().strip_prefix(rest)?;
+    *chars = s.chars();
+    Some(value)
+}
+
+/// Parses the rest of a string whose opening quote has been read.
+fn parse_string(chars: &mut Chars<'_>) -> Option<String> {
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(s),
+            '\\' => match chars.next()? {
+                'b' => s.push('\u{8}'),
+                'f' => s.push('\u{c}'),
+                'n' => s.push('\n'),
+                'r' => s.push('\r'),
+                't' => s.push('\t'),
+                'u' => {
+                    let high = parse_unit(chars)?;
+                    let code = if (0xd800..0xdc00).contains(&high) {
+                        // A surrogate pair, which must be followed by its other half.
+                        let low = chars.as_str().strip_prefix("\\u")?;
+                        *chars = low.chars();
+                        let low = parse_unit(chars)?;
+                        0x10000 + ((high - 0xd800) << 10) + low.checked_sub(0xdc00)?
+                    } else {
+                        high
+                    };
+                    s.push(char::from_u32(code)?);
+                }
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+}
+
+/// Parses the four hex digits of a `\u` escape.
+fn parse_unit(chars: &mut Chars<'_>) -> Option<u32> {
+    let hex: String = chars.by_ref().take(4).collect();
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+/// The error returned when an allocation could not be found.
+#[derive(Debug)]
+pub enum RdapError {
+    /// The service could not be reached, or refused the query.
+    Io(io::Error),
+    /// The response is not JSON.
+    InvalidJson,
+    /// The response lacks a field every network object has.
+    MissingField(&'static str),
+    /// An address in the response is not a valid address.
+    InvalidAddress(String),
+}
+
+impl fmt::Display for RdapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "RDAP query failed: {}", err),
+            Self::InvalidJson => f.write_str("RDAP response is not valid JSON"),
+            Self::MissingField(key) => write!(f, "RDAP response has no `{}`", key),
+            Self::InvalidAddress(s) => write!(f, "RDAP response has invalid address `{}`", s),
+        }
+    }
+}
+
+impl error::Error for RdapError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    const RESPONSE: &str = r#"{
+  "rdapConformance": ["rdap_level_0", "cidr0"],
+  "objectClassName": "ip network",
+  "handle": "NET-8-8-8-0-2",
+  "startAddress": "8.8.8.0",
+  "endAddress": "8.8.8.255",
+  "ipVersion": "v4",
+  "name": "GOGL",
+  "type": "DIRECT ALLOCATION",
+  "port43": "whois.arin.net",
+  "events": [{"eventAction": "last changed", "eventDate": "2014-03-14T16:52:05-04:00"}],
+  "entities": [
+    {"handle": "ABUSE5250-ARIN", "roles": ["abuse"],
+     "vcardArray": ["vcard", [["fn", {}, "text", "Abuse"]]]},
+    {"handle": "GOGL", "roles": ["registrant"], "port43": "whois.arin.net",
+     "vcardArray": ["vcard", [
+       ["version", {}, "text", "4.0"],
+       ["fn", {}, "text", "Google LLC é😀"],
+       ["adr", {"label": "1600 Amphitheatre Parkway\nMountain View"}, "text", ["", "", "", "", "", "", ""]],
+       ["kind", {}, "text", "org"]
+     ]]}
+  ],
+  "cidr0_cidrs": [{"v4prefix": "8.8.8.0", "length": 24}],
+  "remarks": null, "status": ["active"], "ok": true, "weight": -1.5e3
+}"#;
+
+    /// A transport which serves the same response to every request, remembering the URLs.
+    struct Canned(RefCell<Vec<String>>);
+
+    impl Transport for Canned {
+        fn get(&self, url: &str) -> io::Result<String> {
+            self.0.borrow_mut().push(url.to_string());
+            Ok(RESPONSE.to_string())
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        let client = RdapClient::with_transport(Canned(RefCell::new(Vec::new())));
+        let allocation = client.lookup("8.8.8.8".parse().unwrap()).unwrap();
+        assert_eq!(Some(Rir::Arin), allocation.rir);
+        assert_eq!("8.8.8.0-8.8.8.255", allocation.range.to_string());
+        assert_eq!(Some("NET-8-8-8-0-2"), allocation.handle.as_deref());
+        assert_eq!(Some("GOGL"), allocation.name.as_deref());
+        assert_eq!(Some("Google LLC é😀"), allocation.organization.as_deref());
+        assert_eq!(None, allocation.country);
+
+        let client = client.with_base("https://rdap.arin.net/registry/");
+        client
+            .lookup_network("8.8.8.0/24".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            vec![
+                "https://rdap.org/ip/8.8.8.8",
+                "https://rdap.arin.net/registry/ip/8.8.8.0/24",
+            ],
+            *client.transport.0.borrow()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let err = |text: &str| parse(text).unwrap_err().to_string();
+        assert_eq!("RDAP response is not valid JSON", err("{\"a\": }"));
+        assert_eq!("RDAP response is not valid JSON", err("{} {}"));
+        assert_eq!(
+            "RDAP response has no `startAddress`",
+            err(r#"{"errorCode": 404}"#)
+        );
+        assert_eq!(
+            "RDAP response has invalid address `2001:db8::`",
+            err(r#"{"startAddress": "2001:db8::", "endAddress": "2001:db8::ff"}"#)
+        );
+        let allocation =
+            parse(r#"{"startAddress":"1.0.0.0","endAddress":"1.0.0.255","country":"AU"}"#).unwrap();
+        assert_eq!(Some("AU".to_string()), allocation.country);
+        assert_eq!(None, allocation.rir);
+    }
+
+    #[test]
+    fn nesting() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH + 1)).is_some());
+        assert!(Json::parse(&nested(MAX_DEPTH + 2)).is_none());
+        assert!(matches!(
+            parse(&"[".repeat(200_000)),
+            Err(RdapError::InvalidJson)
+        ));
+    }
+}
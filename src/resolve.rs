@@ -0,0 +1,513 @@
+//! # The Resolver Module
+//!
+//! Log enrichment wants the names behind addresses, and allowlists are usually written with a
+//! mix of names and networks. This module is a small DNS stub resolver which asks a recursive
+//! server, such as the ones listed in `/etc/resolv.conf`, and hands back the crate's own types.
+//!
+//! Queries are sent over UDP, and retried over TCP if the answer does not fit. Lookups block; a
+//! batch of them is spread over a bounded number of threads.
+use super::{
+    addr::IpAddress, addr6::Ipv6Address, net::IpNetwork, net6::Ipv6Network, random::Rng, set::IpSet,
+};
+use std::{
+    convert::TryInto,
+    error, fmt, fs,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, UdpSocket},
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+/// The record types this resolver asks for.
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+/// The Internet class, which is the only one in use.
+const CLASS_IN: u16 = 1;
+/// The response code for a name which does not exist.
+const NXDOMAIN: u8 = 3;
+
+/// A stub resolver, which forwards every query to a recursive server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolver {
+    servers: Vec<SocketAddr>,
+    timeout: Duration,
+}
+
+impl Resolver {
+    /// Creates a resolver which asks the servers in turn, until one of them answers.
+    pub fn new(servers: Vec<SocketAddr>) -> Self {
+        Self {
+            servers,
+            timeout: Duration::from_secs(2),
+        }
+    }
+
+    /// Creates a resolver which asks the servers listed in `/etc/resolv.conf`.
+    pub fn from_system() -> io::Result<Self> {
+        let conf = fs::read_to_string("/etc/resolv.conf")?;
+        let servers: Vec<SocketAddr> = conf
+            .lines()
+            .filter_map(
+                |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["nameserver", server, ..] => server.parse().ok(),
+                    _ => None,
+                },
+            )
+            .map(|ip| SocketAddr::new(ip, 53))
+            .collect();
+        if servers.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no nameservers in /etc/resolv.conf",
+            ));
+        }
+        Ok(Self::new(servers))
+    }
+
+    /// Sets how long to wait for each server to answer.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Finds the names which the address's `PTR` records point to. An address without any is
+    /// not an error, but has no names.
+    pub fn resolve_ptr(&self, addr: IpAddress) -> Result<Vec<String>, ResolveError> {
+        let response = self.query(&addr.reverse_pointer(), TYPE_PTR)?;
+        response
+            .answers
+            .iter()
+            .filter(|answer| answer.kind == TYPE_PTR)
+            .map(|answer| read_name(&response.message, answer.rdata).map(|(name, _)| name))
+            .collect()
+    }
+
+    /// Finds the names of every address, asking about up to `concurrency` of them at once. The
+    /// results are in the same order as the addresses.
+    pub fn resolve_ptr_batch<I>(
+        &self,
+        addrs: I,
+        concurrency: usize,
+    ) -> Vec<(IpAddress, Result<Vec<String>, ResolveError>)>
+    where
+        I: IntoIterator<Item = IpAddress>,
+    {
+        let addrs: Vec<IpAddress> = addrs.into_iter().collect();
+        let results: Vec<_> = addrs.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..concurrency.clamp(1, addrs.len().max(1)) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    match addrs.get(i) {
+                        Some(&addr) => *results[i].lock().unwrap() = Some(self.resolve_ptr(addr)),
+                        None => break,
+                    }
+                });
+            }
+        });
+        addrs
+            .into_iter()
+            .zip(results)
+            .map(|(addr, result)| (addr, result.into_inner().unwrap().unwrap()))
+            .collect()
+    }
+
+    /// Finds the IPv4 addresses of the host. A host without any is not an error, but has no
+    /// addresses. Only `A` records are asked for, so any IPv6 addresses of the host are not
+    /// included.
+    pub fn resolve_a(&self, host: &str) -> Result<Vec<IpAddress>, ResolveError> {
+        let response = self.query(host, TYPE_A)?;
+        let mut addrs = Vec::new();
+        // Any aliases along the way are answers too, but only the addresses are wanted.
+        for answer in response.answers.iter().filter(|a| a.kind == TYPE_A) {
+            let rdata = &response.message[answer.rdata..answer.rdata + answer.rdlength];
+            let octets: [u8; 4] = rdata.try_into().map_err(|_| ResolveError::Malformed)?;
+            if !addrs.contains(&IpAddress::from(octets)) {
+                addrs.push(IpAddress::from(octets));
+            }
+        }
+        Ok(addrs)
+    }
+
+    /// Gathers a mix of networks, addresses, and host names into a single set, resolving each
+    /// host name to its addresses. A host name without any addresses is an error, as it is almost
+    /// certainly a mistake.
+    ///
+    /// An [`IpSet`] only holds IPv4 addresses, so IPv6 addresses and networks are rejected rather
+    /// than being mistaken for host names.
+    pub fn resolve_set<I, S>(&self, entries: I) -> Result<IpSet, ResolveError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut set = IpSet::new();
+        for entry in entries {
+            let entry = entry.as_ref().trim();
+            if let Ok(addr) = entry.parse::<IpAddress>() {
+                set.insert(IpNetwork::new(addr, 32).unwrap());
+            } else if entry.parse::<Ipv6Address>().is_ok() || entry.parse::<Ipv6Network>().is_ok() {
+                return Err(ResolveError::Ipv6(entry.to_string()));
+            } else if entry.contains('/') {
+                let net = entry
+                    .parse()
+                    .map_err(|_| ResolveError::InvalidName(entry.to_string()))?;
+                set.insert(net);
+            } else {
+                let addrs = self.resolve_a(entry)?;
+                if addrs.is_empty() {
+                    return Err(ResolveError::NoAddresses(entry.to_string()));
+                }
+                for addr in addrs {
+                    set.insert(IpNetwork::new(addr, 32).unwrap());
+                }
+            }
+        }
+        Ok(set)
+    }
+
+    /// Asks each server in turn, returning the answers of the first to respond.
+    fn query(&self, name: &str, kind: u16) -> Result<Response, ResolveError> {
+        let id = Rng::from_entropy().next_u64() as u16;
+        let query = encode_query(id, name, kind)?;
+        let mut last_error = None;
+        for server in &self.servers {
+            let response =
+                self.exchange_udp(*server, &query)
+                    .and_then(|response| match response.get(2) {
+                        // The truncation bit: the answer only fits over TCP.
+                        Some(flags) if flags & 0x02 != 0 => self.exchange_tcp(*server, &query),
+                        _ => Ok(response),
+                    });
+            match response {
+                Ok(response) => return decode_response(id, response),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.map_or(ResolveError::NoServers, ResolveError::Io))
+    }
+
+    fn exchange_udp(&self, server: SocketAddr, query: &[u8]) -> io::Result<Vec<u8>> {
+        let local: SocketAddr = match server {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_read_timeout(Some(self.timeout))?;
+        socket.connect(server)?;
+        socket.send(query)?;
+        let mut buf = vec![0; 512];
+        let len = socket.recv(&mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    fn exchange_tcp(&self, server: SocketAddr, query: &[u8]) -> io::Result<Vec<u8>> {
+        let mut stream = TcpStream::connect_timeout(&server, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        // Over TCP, every message is preceded by its length.
+        let mut framed = (query.len() as u16).to_be_bytes().to_vec();
+        framed.extend_from_slice(query);
+        stream.write_all(&framed)?;
+        let mut len = [0; 2];
+        stream.read_exact(&mut len)?;
+        let mut buf = vec![0; usize::from(u16::from_be_bytes(len))];
+        stream.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// A response to a query. The whole message is kept, as names within the answers may point
+/// anywhere within it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Response {
+    message: Vec<u8>,
+    answers: Vec<Answer>,
+}
+
+/// A resource record from the answer section of a response.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Answer {
+    kind: u16,
+    /// The offset of the record's data within the response.
+    rdata: usize,
+    rdlength: usize,
+}
+
+/// Builds a query for the records of the specified type, asking for recursion.
+fn encode_query(id: u16, name: &str, kind: u16) -> Result<Vec<u8>, ResolveError> {
+    let mut query = id.to_be_bytes().to_vec();
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(ResolveError::InvalidName(name.to_string()));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&kind.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Picks the answers out of a response to the query with the specified identifier.
+fn decode_response(id: u16, message: Vec<u8>) -> Result<Response, ResolveError> {
+    let field = |at: usize| -> Result<u16, ResolveError> {
+        match message.get(at..at + 2) {
+            Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+            None => Err(ResolveError::Malformed),
+        }
+    };
+    if field(0)? != id || field(2)? & 0x8000 == 0 {
+        return Err(ResolveError::Malformed);
+    }
+    match (field(2)? & 0x000f) as u8 {
+        0 => {}
+        NXDOMAIN => {
+            let answers = Vec::new();
+            return Ok(Response { message, answers });
+        }
+        rcode => return Err(ResolveError::ServerFailure(rcode)),
+    }
+    let (questions, answers) = (field(4)?, field(6)?);
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = read_name(&message, offset)?.1 + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        offset = read_name(&message, offset)?.1;
+        let kind = field(offset)?;
+        let rdlength = usize::from(field(offset + 8)?);
+        let rdata = offset + 10;
+        if rdata + rdlength > message.len() {
+            return Err(ResolveError::Malformed);
+        }
+        records.push(Answer {
+            kind,
+            rdata,
+            rdlength,
+        });
+        offset = rdata + rdlength;
+    }
+    Ok(Response {
+        message,
+        answers: records,
+    })
+}
+
+/// Reads a possibly-compressed name from a message, returning it along with the offset just past
+/// it.
+fn read_name(message: &[u8], mut offset: usize) -> Result<(String, usize), ResolveError> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Compression pointers may only point backwards, but a malicious message could still loop.
+    for _ in 0..128 {
+        let len = *message.get(offset).ok_or(ResolveError::Malformed)?;
+        match len {
+            0 => {
+                let name = labels.join(".");
+                return Ok((name, end.unwrap_or(offset + 1)));
+            }
+            len if len & 0xc0 == 0xc0 => {
+                let low = *message.get(offset + 1).ok_or(ResolveError::Malformed)?;
+                end.get_or_insert(offset + 2);
+                offset = usize::from(u16::from_be_bytes([len & 0x3f, low]));
+            }
+            len if len & 0xc0 == 0 => {
+                let label = message
+                    .get(offset + 1..offset + 1 + usize::from(len))
+                    .ok_or(ResolveError::Malformed)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                offset += 1 + usize::from(len);
+            }
+            _ => return Err(ResolveError::Malformed),
+        }
+    }
+    Err(ResolveError::Malformed)
+}
+
+/// The error returned when a name could not be resolved.
+#[derive(Debug)]
+pub enum ResolveError {
+    /// There were no servers to ask.
+    NoServers,
+    /// The name cannot be put into a query.
+    InvalidName(String),
+    /// No server answered, or the last one to be asked failed in transit.
+    Io(io::Error),
+    /// The server answered with something other than a DNS response to the query.
+    Malformed,
+    /// The server could not answer, giving this response code.
+    ServerFailure(u8),
+    /// The host name has no addresses.
+    NoAddresses(String),
+    /// The entry is an IPv6 address or network, which cannot go into an IPv4 set.
+    Ipv6(String),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoServers => f.write_str("no DNS servers to ask"),
+            Self::InvalidName(name) => write!(f, "invalid DNS name `{}`", name),
+            Self::Io(err) => write!(f, "DNS query failed: {}", err),
+            Self::Malformed => f.write_str("malformed DNS response"),
+            Self::ServerFailure(rcode) => write!(f, "DNS server failed with code {}", rcode),
+            Self::NoAddresses(host) => write!(f, "`{}` has no addresses", host),
+            Self::Ipv6(entry) => write!(f, "`{}` is IPv6, but only IPv4 sets are supported", entry),
+        }
+    }
+}
+
+impl error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Starts a server which answers the `PTR` query for `1.2.0.192.in-addr.arpa` with two
+    /// names, the `A` query for `host.example` with an alias and two addresses, and every other
+    /// query with `NXDOMAIN`, returning its address.
+    fn serve() -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || loop {
+            let mut buf = [0; 512];
+            let (len, peer) = socket.recv_from(&mut buf).unwrap();
+            let query = &buf[..len];
+            let mut response = query.to_vec();
+            response[2] |= 0x80;
+            if query[12..].starts_with(b"\x011\x012\x010\x03192") {
+                response[7] = 2;
+                for name in &[&b"\x03one\x07example\x00"[..], b"\x03two\xc0\x00"] {
+                    // Each answer's name points back at the question.
+                    response.extend_from_slice(&[0xc0, 12, 0, 12, 0, 1, 0, 0, 0, 60, 0]);
+                    response.push(name.len() as u8);
+                    response.extend_from_slice(name);
+                }
+                // The second name points at the first answer's `example`.
+                let second = response.len() - 1;
+                let example = len + 12 + 4;
+                response[second] = example as u8;
+            } else if query[12..].starts_with(b"\x04host\x07example\x00\x00\x01") {
+                response[7] = 3;
+                response.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xc0, 12]);
+                for addr in &[[192, 0, 2, 1], [192, 0, 2, 7]] {
+                    response.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+                    response.extend_from_slice(addr);
+                }
+            } else {
+                response[3] |= NXDOMAIN;
+            }
+            socket.send_to(&response, peer).unwrap();
+        });
+        addr
+    }
+
+    #[test]
+    fn ptr() {
+        let resolver = Resolver::new(vec![serve()]);
+        assert_eq!(
+            vec!["one.example", "two.example"],
+            resolver.resolve_ptr("192.0.2.1".parse().unwrap()).unwrap()
+        );
+        assert!(resolver
+            .resolve_ptr("192.0.2.2".parse().unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn a() {
+        let resolver = Resolver::new(vec![serve()]);
+        assert_eq!(
+            vec![
+                IpAddress::from([192, 0, 2, 1]),
+                IpAddress::from([192, 0, 2, 7])
+            ],
+            resolver.resolve_a("host.example").unwrap()
+        );
+        assert!(resolver.resolve_a("missing.example").unwrap().is_empty());
+
+        let set = resolver
+            .resolve_set(["10.0.0.0/8", "host.example", "192.0.2.2"])
+            .unwrap();
+        let nets: Vec<String> = set.iter().map(|net| net.to_string()).collect();
+        assert_eq!(
+            vec!["10.0.0.0/8", "192.0.2.1/32", "192.0.2.2/32", "192.0.2.7/32"],
+            nets
+        );
+        assert_eq!(
+            "`missing.example` has no addresses",
+            resolver
+                .resolve_set(["missing.example"])
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(matches!(
+            resolver.resolve_set(["10.0.0.0/33"]),
+            Err(ResolveError::InvalidName(_))
+        ));
+        for entry in &["2001:db8::/32", "2001:db8::1", "::ffff:192.0.2.1"] {
+            assert_eq!(
+                format!("`{}` is IPv6, but only IPv4 sets are supported", entry),
+                resolver.resolve_set([entry]).unwrap_err().to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn batch() {
+        let resolver = Resolver::new(vec![serve()]);
+        let addrs: Vec<IpAddress> = (0..10).map(|i| IpAddress::from([192, 0, 2, i])).collect();
+        let results = resolver.resolve_ptr_batch(addrs.clone(), 4);
+        assert_eq!(
+            addrs,
+            results.iter().map(|(addr, _)| *addr).collect::<Vec<_>>()
+        );
+        for (addr, names) in results {
+            let names = names.unwrap();
+            assert_eq!(addr.octets()[3] == 1, !names.is_empty(), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        // Nothing answers on a socket which is bound but never read.
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let resolver = Resolver::new(vec![silent.local_addr().unwrap()])
+            .with_timeout(Duration::from_millis(50));
+        assert!(matches!(
+            resolver.resolve_ptr(IpAddress::from(0)),
+            Err(ResolveError::Io(_))
+        ));
+        assert!(matches!(
+            Resolver::new(Vec::new()).resolve_ptr(IpAddress::from(0)),
+            Err(ResolveError::NoServers)
+        ));
+    }
+
+    #[test]
+    fn names() {
+        let query = encode_query(7, "example.com.", TYPE_PTR).unwrap();
+        assert_eq!(
+            ("example.com".to_string(), query.len() - 4),
+            read_name(&query, 12).unwrap()
+        );
+        assert!(encode_query(7, "a..b", TYPE_PTR).is_err());
+        // A pointer to itself never ends.
+        assert!(read_name(&[0xc0, 0], 0).is_err());
+    }
+}
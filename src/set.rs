@@ -0,0 +1,739 @@
+//! # The IP-Set Module
+//!
+//! An IP Set is an arbitrary collection of addresses. It is kept internally as a sorted list of
+//! disjoint ranges, which makes membership tests a binary search, and means the set can always
+//! be reported back as the smallest possible list of networks.
+use super::{addr::IpAddress, bits, net::IpNetwork, range::IpRange};
+use std::{cmp::Ordering, iter::FromIterator};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct IpSet {
+    /// Sorted, and never overlapping or even adjacent; adjacent ranges are always merged.
+    ranges: Vec<IpRange>,
+}
+
+impl IpSet {
+    /// Creates a new, empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether this set contains no addresses at all.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Gets the number of addresses within this set.
+    pub fn num_addresses(&self) -> u64 {
+        self.ranges.iter().map(IpRange::num_addresses).sum()
+    }
+
+    /// Gets the number of addresses within this set.
+    #[deprecated(note = "use `num_addresses`")]
+    pub fn num_hosts(&self) -> u64 {
+        self.num_addresses()
+    }
+
+    /// Whether the specified address lies within this set.
+    pub fn contains(&self, addr: IpAddress) -> bool {
+        let i = self.ranges.partition_point(|range| range.end() < addr);
+        self.ranges.get(i).is_some_and(|range| range.contains(addr))
+    }
+
+    /// Adds every address within the network to this set.
+    pub fn insert(&mut self, net: IpNetwork) {
+        self.insert_range(net.into());
+    }
+
+    /// Adds every address within the range to this set.
+    pub fn insert_range(&mut self, range: IpRange) {
+        self.ranges.push(range);
+        self.normalize();
+    }
+
+    /// Removes every address within the network from this set.
+    pub fn remove(&mut self, net: IpNetwork) {
+        self.remove_range(net.into());
+    }
+
+    /// Removes every address within the range from this set.
+    pub fn remove_range(&mut self, range: IpRange) {
+        *self = self.difference(&Self::from(range));
+    }
+
+    /// Adds every network of one batch, then removes every network of another, renormalizing the
+    /// set only once for the whole update. An address in both batches ends up removed.
+    ///
+    /// Reports the net effect of the update, which may be much less than the batches suggest:
+    /// adding what was already there, or removing what never was, changes nothing.
+    pub fn apply_batch<A, R>(&mut self, adds: A, removes: R) -> ChangeSummary
+    where
+        A: IntoIterator<Item = IpNetwork>,
+        R: IntoIterator<Item = IpNetwork>,
+    {
+        let old = self.bounds();
+        let mut bounds = old.clone();
+        bounds.extend(adds.into_iter().map(net_bounds));
+        bits::merge_ranges(&mut bounds);
+        let mut holes: Vec<_> = removes.into_iter().map(net_bounds).collect();
+        bits::merge_ranges(&mut holes);
+        let new = bits::subtract_ranges(&bounds, &holes);
+        let summary = ChangeSummary {
+            added: Self::from_bounds(bits::subtract_ranges(&new, &old)),
+            removed: Self::from_bounds(bits::subtract_ranges(&old, &new)),
+        };
+        *self = Self::from_bounds(new);
+        summary
+    }
+
+    /// Creates the set of addresses which are in either this set, or the other, or both.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut ranges = Vec::with_capacity(self.ranges.len() + other.ranges.len());
+        ranges.extend_from_slice(&self.ranges);
+        ranges.extend_from_slice(&other.ranges);
+        let mut set = Self { ranges };
+        set.normalize();
+        set
+    }
+
+    /// Creates the set of addresses which are in both this set and the other.
+    pub fn intersection(&self, other: &Self) -> Self {
+        let ranges = bits::intersect_ranges(&self.bounds(), &other.bounds());
+        Self::from_bounds(ranges)
+    }
+
+    /// Creates the set of addresses which are in this set, but not in the other.
+    pub fn difference(&self, other: &Self) -> Self {
+        let ranges = bits::subtract_ranges(&self.bounds(), &other.bounds());
+        Self::from_bounds(ranges)
+    }
+
+    /// The fewest networks which cover the addresses in either this set, or the other, or both,
+    /// in ascending order, found as they are needed rather than collected into a set.
+    pub fn union_iter<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = IpNetwork> + 'a {
+        let union = Union {
+            a: &self.ranges,
+            b: &other.ranges,
+        };
+        union.flat_map(|range| range.networks())
+    }
+
+    /// The fewest networks which cover the addresses in both this set and the other, in
+    /// ascending order, found as they are needed rather than collected into a set.
+    pub fn intersection_iter<'a>(
+        &'a self,
+        other: &'a Self,
+    ) -> impl Iterator<Item = IpNetwork> + 'a {
+        let intersection = Intersection {
+            a: &self.ranges,
+            b: &other.ranges,
+        };
+        intersection.flat_map(|range| range.networks())
+    }
+
+    /// The fewest networks which cover the addresses in this set but not in the other, in
+    /// ascending order, found as they are needed rather than collected into a set.
+    pub fn difference_iter<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = IpNetwork> + 'a {
+        let difference = Difference {
+            ranges: &self.ranges,
+            holes: &other.ranges,
+            rest: None,
+        };
+        difference.flat_map(|range| range.networks())
+    }
+
+    /// The fewest networks which cover the addresses of the universe that are not in this set, in
+    /// ascending order: whatever is still free within it.
+    pub fn gaps(&self, universe: IpNetwork) -> impl Iterator<Item = IpNetwork> + '_ {
+        let (start, end) = wide(&universe.into());
+        let first = self
+            .ranges
+            .partition_point(|range| range.end() < universe.network_address());
+        let mut ranges = self.ranges[first..].iter().map(wide);
+        // The first address of the universe which has not been reported or skipped yet.
+        let mut cursor = start;
+        let gaps = std::iter::from_fn(move || loop {
+            if cursor > end {
+                return None;
+            }
+            match ranges.next() {
+                Some((range_start, range_end)) if range_start <= end => {
+                    let gap = (cursor, range_start.saturating_sub(1));
+                    cursor = cursor.max(range_end + 1);
+                    if gap.0 < range_start {
+                        return Some(narrow(gap));
+                    }
+                }
+                _ => {
+                    let gap = (cursor, end);
+                    cursor = end + 1;
+                    return Some(narrow(gap));
+                }
+            }
+        });
+        gaps.flat_map(|range| range.networks())
+    }
+
+    /// The disjoint ranges which make up this set, in ascending order.
+    pub fn ranges(&self) -> impl Iterator<Item = IpRange> + '_ {
+        self.ranges.iter().copied()
+    }
+
+    /// The fewest networks which exactly cover this set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = IpNetwork> + '_ {
+        self.ranges.iter().flat_map(IpRange::networks)
+    }
+
+    /// Restores the invariant that ranges are sorted, and neither overlap nor touch.
+    fn normalize(&mut self) {
+        let mut bounds = self.bounds();
+        bits::merge_ranges(&mut bounds);
+        *self = Self::from_bounds(bounds);
+    }
+
+    /// The first and last addresses of each range, as integers.
+    fn bounds(&self) -> Vec<(u32, u32)> {
+        let bounds = |range: &IpRange| (range.start().value(), range.end().value());
+        self.ranges.iter().map(bounds).collect()
+    }
+
+    fn from_bounds(bounds: Vec<(u32, u32)>) -> Self {
+        let range = |(start, end)| IpRange::new(IpAddress::from(start), IpAddress::from(end));
+        Self {
+            ranges: bounds.into_iter().filter_map(range).collect(),
+        }
+    }
+}
+
+/// The first and last addresses of a network, as integers.
+fn net_bounds(net: IpNetwork) -> (u32, u32) {
+    (
+        net.network_address().value(),
+        net.broadcast_address().value(),
+    )
+}
+
+/// The net effect of [`IpSet::apply_batch`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ChangeSummary {
+    /// The addresses which are in the set now, but were not before.
+    pub added: IpSet,
+    /// The addresses which were in the set before, but are not now.
+    pub removed: IpSet,
+}
+
+impl ChangeSummary {
+    /// Whether the update left the set exactly as it was.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The first and last addresses of a range, widened so that one past the last address is never
+/// out of bounds.
+fn wide(range: &IpRange) -> (u64, u64) {
+    (
+        u64::from(range.start().value()),
+        u64::from(range.end().value()),
+    )
+}
+
+fn narrow((start, end): (u64, u64)) -> IpRange {
+    IpRange::new(IpAddress::from(start as u32), IpAddress::from(end as u32)).unwrap()
+}
+
+/// The ranges of the union of two sets, merging those which overlap or touch.
+struct Union<'a> {
+    a: &'a [IpRange],
+    b: &'a [IpRange],
+}
+
+impl Union<'_> {
+    /// Takes whichever next range of either set begins first.
+    fn pop(&mut self) -> Option<(u64, u64)> {
+        let list = match (self.a.first(), self.b.first()) {
+            (Some(a), Some(b)) if b.start() < a.start() => &mut self.b,
+            (Some(_), _) => &mut self.a,
+            (None, _) => &mut self.b,
+        };
+        let (first, rest) = list.split_first()?;
+        *list = rest;
+        Some(wide(first))
+    }
+
+    fn peek_start(&self) -> Option<u64> {
+        let starts = self.a.first().into_iter().chain(self.b.first());
+        starts.map(|range| wide(range).0).min()
+    }
+}
+
+impl Iterator for Union<'_> {
+    type Item = IpRange;
+
+    fn next(&mut self) -> Option<IpRange> {
+        let (start, mut end) = self.pop()?;
+        while self.peek_start().is_some_and(|next| next <= end + 1) {
+            end = end.max(self.pop()?.1);
+        }
+        Some(narrow((start, end)))
+    }
+}
+
+/// The ranges of the intersection of two sets.
+struct Intersection<'a> {
+    a: &'a [IpRange],
+    b: &'a [IpRange],
+}
+
+impl Iterator for Intersection<'_> {
+    type Item = IpRange;
+
+    fn next(&mut self) -> Option<IpRange> {
+        loop {
+            let ((a_start, a_end), (b_start, b_end)) =
+                (wide(self.a.first()?), wide(self.b.first()?));
+            // Whichever range ends first cannot overlap anything further along the other set.
+            if a_end < b_end {
+                self.a = &self.a[1..];
+            } else {
+                self.b = &self.b[1..];
+            }
+            let (start, end) = (a_start.max(b_start), a_end.min(b_end));
+            if start <= end {
+                return Some(narrow((start, end)));
+            }
+        }
+    }
+}
+
+/// The ranges of one set, less the holes of another.
+struct Difference<'a> {
+    ranges: &'a [IpRange],
+    holes: &'a [IpRange],
+    /// What remains of the range being cut, if any.
+    rest: Option<(u64, u64)>,
+}
+
+impl Iterator for Difference<'_> {
+    type Item = IpRange;
+
+    fn next(&mut self) -> Option<IpRange> {
+        loop {
+            let (start, end) = match self.rest.take() {
+                Some(rest) => rest,
+                None => {
+                    let (first, rest) = self.ranges.split_first()?;
+                    self.ranges = rest;
+                    wide(first)
+                }
+            };
+            // Every hole which ends before this range begins is behind every later range too.
+            while self.holes.first().is_some_and(|hole| wide(hole).1 < start) {
+                self.holes = &self.holes[1..];
+            }
+            match self.holes.first().map(wide) {
+                Some((hole_start, hole_end)) if hole_start <= end => {
+                    if hole_end < end {
+                        self.rest = Some((hole_end + 1, end));
+                    }
+                    if start < hole_start {
+                        return Some(narrow((start, hole_start - 1)));
+                    }
+                }
+                _ => return Some(narrow((start, end))),
+            }
+        }
+    }
+}
+
+impl From<IpNetwork> for IpSet {
+    fn from(net: IpNetwork) -> Self {
+        Self::from(IpRange::from(net))
+    }
+}
+
+impl From<IpRange> for IpSet {
+    fn from(range: IpRange) -> Self {
+        Self {
+            ranges: vec![range],
+        }
+    }
+}
+
+impl FromIterator<IpNetwork> for IpSet {
+    fn from_iter<I: IntoIterator<Item = IpNetwork>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<IpNetwork> for IpSet {
+    fn extend<I: IntoIterator<Item = IpNetwork>>(&mut self, iter: I) {
+        self.ranges.extend(iter.into_iter().map(IpRange::from));
+        self.normalize();
+    }
+}
+
+/// Combines the networks into the fewest networks which cover exactly the same addresses.
+///
+/// Duplicates and networks which lie within another are dropped, and adjacent networks are merged
+/// into their supernet wherever possible.
+pub fn aggregate<I: IntoIterator<Item = IpNetwork>>(nets: I) -> Vec<IpNetwork> {
+    nets.into_iter().collect::<IpSet>().iter().collect()
+}
+
+/// Drops every network which lies within another of the networks, without merging anything.
+///
+/// Unlike [`aggregate`], the surviving networks are exactly those which were given (with their
+/// host bits cleared), so this is safe to use where each entry carries its own meaning.
+pub fn remove_shadowed<I: IntoIterator<Item = IpNetwork>>(nets: I) -> Vec<IpNetwork> {
+    let mut prefixes: Vec<_> = nets.into_iter().map(prefix).collect();
+    bits::remove_shadowed(&mut prefixes);
+    prefixes.into_iter().map(from_prefix).collect()
+}
+
+/// The smallest single network which contains every one of the networks, if there are any.
+pub fn spanning<I: IntoIterator<Item = IpNetwork>>(nets: I) -> Option<IpNetwork> {
+    bits::spanning(nets.into_iter().map(prefix)).map(from_prefix)
+}
+
+/// The network address and prefix length of a network, as the `bits` helpers take them.
+fn prefix(net: IpNetwork) -> (u32, u8) {
+    (net.network_address().value(), net.num_network_bits())
+}
+
+fn from_prefix((base, prefix_len): (u32, u8)) -> IpNetwork {
+    IpNetwork::new(IpAddress::from(base), prefix_len).unwrap()
+}
+
+/// Compares networks in the order routing tables list them: by network address, with a shorter
+/// prefix before the more-specific prefixes which share its address. Host bits are ignored.
+pub fn prefix_order(a: &IpNetwork, b: &IpNetwork) -> Ordering {
+    (a.network_address(), a.num_network_bits()).cmp(&(b.network_address(), b.num_network_bits()))
+}
+
+/// Sorts networks into the order routing tables list them, as [`prefix_order`] compares them.
+pub fn sort_prefix_order(nets: &mut [IpNetwork]) {
+    nets.sort_by(prefix_order);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(list: &[&str]) -> Vec<IpNetwork> {
+        list.iter().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn contains() {
+        let set: IpSet = nets(&["10.0.0.0/8", "192.168.1.0/24", "192.168.3.7/32"])
+            .into_iter()
+            .collect();
+        assert!(set.contains(IpAddress::from([10, 255, 0, 1])));
+        assert!(set.contains(IpAddress::from([192, 168, 1, 0])));
+        assert!(set.contains(IpAddress::from([192, 168, 3, 7])));
+        assert!(!set.contains(IpAddress::from([192, 168, 2, 0])));
+        assert!(!set.contains(IpAddress::from([11, 0, 0, 0])));
+        assert!(!set.contains(IpAddress::from(0)));
+        assert_eq!(16777216 + 256 + 1, set.num_addresses());
+        assert!(IpSet::new().is_empty());
+        assert!(!IpSet::new().contains(IpAddress::from(0)));
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let set = |list: &[&str]| nets(list).into_iter().collect::<IpSet>();
+        let a = set(&["10.0.0.0/24", "10.0.2.0/24", "10.0.8.0/21"]);
+        let b = set(&["10.0.1.0/24", "10.0.2.128/25", "10.0.12.0/22", "11.0.0.0/8"]);
+
+        assert_eq!(
+            set(&["10.0.0.0/23", "10.0.2.0/24", "10.0.8.0/21", "11.0.0.0/8"]),
+            a.union(&b)
+        );
+        assert_eq!(a.union(&b), b.union(&a));
+        assert_eq!(a, a.union(&IpSet::new()));
+
+        assert_eq!(set(&["10.0.2.128/25", "10.0.12.0/22"]), a.intersection(&b));
+        assert_eq!(a.intersection(&b), b.intersection(&a));
+        assert!(a.intersection(&IpSet::new()).is_empty());
+        assert_eq!(a, a.intersection(&set(&["0.0.0.0/0"])));
+    }
+
+    #[test]
+    fn lazy() {
+        let set = |list: &[&str]| nets(list).into_iter().collect::<IpSet>();
+        let sets = [
+            set(&["10.0.0.0/24", "10.0.2.0/24", "10.0.8.0/21"]),
+            set(&["10.0.1.0/24", "10.0.2.128/25", "10.0.12.0/22", "11.0.0.0/8"]),
+            set(&["10.0.0.0/8", "255.255.255.255/32"]),
+            set(&["0.0.0.0/0"]),
+            set(&[
+                "0.0.0.0/32",
+                "10.0.2.64/26",
+                "10.0.9.0/24",
+                "10.0.15.255/32",
+            ]),
+            IpSet::new(),
+        ];
+        for a in &sets {
+            for b in &sets {
+                let union: Vec<_> = a.union_iter(b).collect();
+                assert_eq!(a.union(b).iter().collect::<Vec<_>>(), union);
+                let intersection: Vec<_> = a.intersection_iter(b).collect();
+                assert_eq!(a.intersection(b).iter().collect::<Vec<_>>(), intersection);
+                let difference: Vec<_> = a.difference_iter(b).collect();
+                assert_eq!(a.difference(b).iter().collect::<Vec<_>>(), difference);
+            }
+        }
+    }
+
+    #[test]
+    fn difference() {
+        let set = |list: &[&str]| nets(list).into_iter().collect::<IpSet>();
+        let to_strings = |set: IpSet| set.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "10.0.0.0/16",
+                "10.1.0.0/24",
+                "10.1.2.0/23",
+                "10.1.4.0/22",
+                "10.1.8.0/21"
+            ],
+            to_strings(
+                set(&["10.0.0.0/16", "10.1.0.0/20"])
+                    .difference(&set(&["10.1.1.0/24", "10.1.16.0/24"]))
+            )
+        );
+        assert_eq!(
+            vec![
+                "0.0.0.0/1",
+                "128.0.0.0/2",
+                "192.0.0.0/3",
+                "224.0.0.0/4",
+                "240.0.0.0/5",
+                "248.0.0.0/6",
+                "252.0.0.0/7",
+                "254.0.0.0/8",
+                "255.0.0.0/9",
+                "255.128.0.0/10",
+                "255.192.0.0/11",
+                "255.224.0.0/12",
+                "255.240.0.0/13",
+                "255.248.0.0/14",
+                "255.252.0.0/15",
+                "255.254.0.0/16",
+                "255.255.0.0/17",
+                "255.255.128.0/18",
+                "255.255.192.0/19",
+                "255.255.224.0/20",
+                "255.255.240.0/21",
+                "255.255.248.0/22",
+                "255.255.252.0/23",
+                "255.255.254.0/24",
+                "255.255.255.0/25",
+                "255.255.255.128/26",
+                "255.255.255.192/27",
+                "255.255.255.224/28",
+                "255.255.255.240/29",
+                "255.255.255.248/30",
+                "255.255.255.252/31",
+                "255.255.255.254/32"
+            ],
+            to_strings(set(&["0.0.0.0/0"]).difference(&set(&["255.255.255.255/32"])))
+        );
+        // A hole spanning several ranges, and holes outside of every range.
+        assert_eq!(
+            vec!["10.0.0.0/24", "10.0.3.0/24"],
+            to_strings(
+                set(&["10.0.0.0/23", "10.0.2.0/24", "10.0.3.0/24", "10.0.4.0/24"]).difference(
+                    &set(&["9.0.0.0/8", "10.0.1.0/24", "10.0.2.0/24", "10.0.4.0/22"])
+                )
+            )
+        );
+        assert!(set(&["10.0.0.0/8"])
+            .difference(&set(&["0.0.0.0/0"]))
+            .is_empty());
+        assert_eq!(
+            set(&["10.0.0.0/8"]),
+            set(&["10.0.0.0/8"]).difference(&IpSet::new())
+        );
+
+        let mut s = set(&["10.0.0.0/8"]);
+        s.remove("10.128.0.0/9".parse().unwrap());
+        assert_eq!(vec!["10.0.0.0/9"], to_strings(s));
+    }
+
+    #[test]
+    fn batch() {
+        let mut set: IpSet = nets(&["10.0.0.0/24", "10.0.2.0/24"]).into_iter().collect();
+        let summary = set.apply_batch(
+            nets(&[
+                "10.0.0.0/25",
+                "10.0.1.0/24",
+                "10.0.3.0/24",
+                "192.168.0.0/24",
+            ]),
+            nets(&["10.0.3.0/24", "172.16.0.0/12", "10.0.2.128/25"]),
+        );
+        let strings = |set: &IpSet| set.iter().map(|net| net.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            vec!["10.0.0.0/23", "10.0.2.0/25", "192.168.0.0/24"],
+            strings(&set)
+        );
+        assert_eq!(
+            vec!["10.0.1.0/24", "192.168.0.0/24"],
+            strings(&summary.added)
+        );
+        assert_eq!(vec!["10.0.2.128/25"], strings(&summary.removed));
+        assert!(!summary.is_empty());
+
+        let before = set.clone();
+        let summary = set.apply_batch(nets(&["10.0.0.0/24"]), nets(&["11.0.0.0/8"]));
+        assert!(summary.is_empty());
+        assert_eq!(before, set);
+        let summary = set.apply_batch(nets(&["0.0.0.0/0"]), Vec::new());
+        assert_eq!(1 << 32, set.num_addresses());
+        assert_eq!(
+            before.num_addresses() + summary.added.num_addresses(),
+            1 << 32
+        );
+    }
+
+    #[test]
+    fn gaps() {
+        let set: IpSet = nets(&["10.0.0.0/24", "10.0.2.0/23", "9.0.0.0/8", "10.0.5.7/32"])
+            .into_iter()
+            .collect();
+        let gaps = |universe: &str| {
+            set.gaps(universe.parse().unwrap())
+                .map(|net| net.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![
+                "10.0.1.0/24",
+                "10.0.4.0/24",
+                "10.0.5.0/30",
+                "10.0.5.4/31",
+                "10.0.5.6/32",
+                "10.0.5.8/29",
+                "10.0.5.16/28",
+                "10.0.5.32/27",
+                "10.0.5.64/26",
+                "10.0.5.128/25",
+                "10.0.6.0/23",
+            ],
+            gaps("10.0.0.0/21")
+        );
+        assert_eq!(vec!["10.0.1.0/24"], gaps("10.0.0.0/22"));
+        assert!(gaps("9.1.0.0/16").is_empty());
+        assert!(gaps("10.0.3.0/24").is_empty());
+        assert_eq!(vec!["11.0.0.0/8"], gaps("11.0.0.0/8"));
+        assert_eq!(
+            vec!["0.0.0.0/0"],
+            IpSet::new()
+                .gaps("0.0.0.0/0".parse().unwrap())
+                .map(|net| net.to_string())
+                .collect::<Vec<_>>()
+        );
+        let full: IpSet = nets(&["0.0.0.0/0"]).into_iter().collect();
+        assert_eq!(0, full.gaps("255.255.255.255/32".parse().unwrap()).count());
+        assert_eq!(
+            set.gaps("0.0.0.0/0".parse().unwrap()).collect::<IpSet>(),
+            IpSet::from(IpRange::from("0.0.0.0/0".parse::<IpNetwork>().unwrap())).difference(&set)
+        );
+    }
+
+    #[test]
+    fn aggregation() {
+        assert_eq!(
+            nets(&["10.0.0.0/23", "10.0.3.0/24"]),
+            aggregate(nets(&[
+                "10.0.1.0/24",
+                "10.0.0.0/24",
+                "10.0.0.128/25",
+                "10.0.3.0/24",
+                "10.0.3.4/32",
+            ]))
+        );
+        assert_eq!(
+            nets(&["0.0.0.0/0"]),
+            aggregate(nets(&["128.0.0.0/1", "0.0.0.0/1"]))
+        );
+        assert_eq!(
+            nets(&["10.0.1.0/24", "10.0.2.0/24"]),
+            aggregate(nets(&["10.0.1.0/24", "10.0.2.0/24"]))
+        );
+        assert_eq!(
+            nets(&["192.168.0.0/24"]),
+            aggregate(nets(&["192.168.0.77/24"]))
+        );
+        assert!(aggregate(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn shadowed() {
+        assert_eq!(
+            nets(&["10.0.0.0/8", "11.0.0.0/24", "11.0.1.0/24"]),
+            remove_shadowed(nets(&[
+                "11.0.1.0/24",
+                "10.20.0.0/16",
+                "10.0.0.0/8",
+                "11.0.0.0/24",
+                "10.0.0.0/8",
+                "10.255.255.255/32",
+            ]))
+        );
+    }
+
+    #[test]
+    fn span() {
+        let span = |list: &[&str]| spanning(nets(list)).map(|net| net.to_string());
+        assert_eq!(
+            Some("10.0.0.0/23".into()),
+            span(&["10.0.0.0/24", "10.0.1.7/24"])
+        );
+        assert_eq!(
+            Some("10.0.0.0/8".into()),
+            span(&["10.0.0.0/8", "10.9.0.0/16"])
+        );
+        assert_eq!(
+            Some("0.0.0.0/0".into()),
+            span(&["1.0.0.0/8", "255.0.0.0/8"])
+        );
+        assert_eq!(
+            Some("10.0.0.4/30".into()),
+            span(&["10.0.0.5/32", "10.0.0.6/32"])
+        );
+        assert_eq!(None, span(&[]));
+    }
+
+    #[test]
+    fn prefix_ordering() {
+        let mut list = nets(&[
+            "10.0.1.0/24",
+            "9.0.0.0/8",
+            "10.0.0.0/16",
+            "10.0.0.0/8",
+            "10.0.0.0/24",
+            "0.0.0.0/0",
+        ]);
+        sort_prefix_order(&mut list);
+        assert_eq!(
+            nets(&[
+                "0.0.0.0/0",
+                "9.0.0.0/8",
+                "10.0.0.0/8",
+                "10.0.0.0/16",
+                "10.0.0.0/24",
+                "10.0.1.0/24",
+            ]),
+            list
+        );
+        let (a, b) = (nets(&["10.0.0.9/8"])[0], nets(&["10.0.0.0/8"])[0]);
+        assert_eq!(Ordering::Equal, prefix_order(&a, &b));
+    }
+}
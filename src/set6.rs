@@ -0,0 +1,157 @@
+//! # The IPv6-Set Module
+//!
+//! An IPv6 Set is an arbitrary collection of IPv6 addresses, kept just as an [`IpSet`] is: as a
+//! sorted list of disjoint ranges. Blocklists and allowlists are dual-stack, so they are usually
+//! kept as one of each.
+//!
+//! [`IpSet`]: crate::set::IpSet
+use super::{addr6::Ipv6Address, bits, net6::Ipv6Network};
+use std::iter::FromIterator;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Ipv6Set {
+    /// The first and last addresses of each range. Sorted, and never overlapping or even
+    /// adjacent; adjacent ranges are always merged.
+    ranges: Vec<(u128, u128)>,
+}
+
+/// The first and last addresses of a network, as integers.
+fn bounds(net: Ipv6Network) -> (u128, u128) {
+    (net.network_address().value(), net.last_address().value())
+}
+
+impl Ipv6Set {
+    /// Creates a new, empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether this set contains no addresses at all.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Whether the specified address lies within this set.
+    pub fn contains(&self, addr: Ipv6Address) -> bool {
+        let addr = addr.value();
+        let i = self.ranges.partition_point(|&(_, end)| end < addr);
+        self.ranges.get(i).is_some_and(|&(start, _)| start <= addr)
+    }
+
+    /// Adds every address within the network to this set.
+    pub fn insert(&mut self, net: Ipv6Network) {
+        self.ranges.push(bounds(net));
+        bits::merge_ranges(&mut self.ranges);
+    }
+
+    /// Removes every address within the network from this set.
+    pub fn remove(&mut self, net: Ipv6Network) {
+        self.ranges = bits::subtract_ranges(&self.ranges, &[bounds(net)]);
+    }
+
+    /// Creates the set of addresses which are in either this set, or the other, or both.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut ranges = [&self.ranges[..], &other.ranges[..]].concat();
+        bits::merge_ranges(&mut ranges);
+        Self { ranges }
+    }
+
+    /// Creates the set of addresses which are in both this set and the other.
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            ranges: bits::intersect_ranges(&self.ranges, &other.ranges),
+        }
+    }
+
+    /// Creates the set of addresses which are in this set, but not in the other.
+    pub fn difference(&self, other: &Self) -> Self {
+        Self {
+            ranges: bits::subtract_ranges(&self.ranges, &other.ranges),
+        }
+    }
+
+    /// The fewest networks which exactly cover this set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Ipv6Network> + '_ {
+        self.ranges
+            .iter()
+            .flat_map(|&(start, end)| bits::blocks(start, end))
+            .filter_map(|(base, prefix_len)| Ipv6Network::new(Ipv6Address::from(base), prefix_len))
+    }
+}
+
+impl From<Ipv6Network> for Ipv6Set {
+    fn from(net: Ipv6Network) -> Self {
+        Self {
+            ranges: vec![bounds(net)],
+        }
+    }
+}
+
+impl FromIterator<Ipv6Network> for Ipv6Set {
+    fn from_iter<I: IntoIterator<Item = Ipv6Network>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Ipv6Network> for Ipv6Set {
+    fn extend<I: IntoIterator<Item = Ipv6Network>>(&mut self, iter: I) {
+        self.ranges.extend(iter.into_iter().map(bounds));
+        bits::merge_ranges(&mut self.ranges);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(list: &[&str]) -> Ipv6Set {
+        list.iter()
+            .map(|s| s.parse::<Ipv6Network>().unwrap())
+            .collect()
+    }
+
+    fn addr(s: &str) -> Ipv6Address {
+        s.parse().unwrap()
+    }
+
+    fn strings(set: &Ipv6Set) -> Vec<String> {
+        set.iter().map(|net| net.to_string()).collect()
+    }
+
+    #[test]
+    fn contains() {
+        let s = set(&["2001:db8::/32", "fe80::/10", "::1/128"]);
+        assert!(s.contains(addr("2001:db8:ffff::1")));
+        assert!(s.contains(addr("febf:ffff::")));
+        assert!(s.contains(addr("::1")));
+        assert!(!s.contains(addr("::2")));
+        assert!(!s.contains(addr("2001:db9::")));
+        assert!(!Ipv6Set::new().contains(addr("::")));
+        assert!(set(&["::/0"]).contains(addr("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff")));
+    }
+
+    #[test]
+    fn operations() {
+        let a = set(&["2001:db8::/64", "2001:db8:0:2::/64"]);
+        let b = set(&["2001:db8:0:1::/64", "2001:db8:0:2::/65"]);
+        assert_eq!(
+            vec!["2001:db8::/63", "2001:db8:0:2::/64"],
+            strings(&a.union(&b))
+        );
+        assert_eq!(vec!["2001:db8:0:2::/65"], strings(&a.intersection(&b)));
+        assert_eq!(
+            vec!["2001:db8::/64", "2001:db8:0:2:8000::/65"],
+            strings(&a.difference(&b))
+        );
+
+        let mut s = Ipv6Set::from("2001:db8::/127".parse::<Ipv6Network>().unwrap());
+        s.remove("2001:db8::1/128".parse().unwrap());
+        assert_eq!(vec!["2001:db8::/128"], strings(&s));
+        s.insert("2001:db8::1/128".parse().unwrap());
+        assert_eq!(vec!["2001:db8::/127"], strings(&s));
+        s.remove("::/0".parse().unwrap());
+        assert!(s.is_empty());
+    }
+}
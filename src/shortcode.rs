@@ -0,0 +1,312 @@
+//! # The Short-Code Module
+//!
+//! Dotted-quad notation is compact enough for a terminal, but a QR code, a ticket reference, or a
+//! seven-segment display wants something shorter still, and something which survives being read
+//! aloud. A short code packs an address, or a network, into a handful of characters from an
+//! alphabet without look-alike characters, optionally with a check character that catches a
+//! mistyped character or two swapped ones. Every code decodes back to exactly what was encoded.
+use super::{addr::IpAddress, net::IpNetwork};
+use std::{error, fmt};
+
+/// The characters of a short code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Alphabet {
+    /// Crockford's base32, which leaves out `I`, `L`, `O`, and `U`. Decoding ignores case, and
+    /// reads `I` and `L` as `1`, and `O` as `0`.
+    Base32,
+    /// The base58 alphabet of Bitcoin addresses, which leaves out `0`, `O`, `I`, and `l`, and is
+    /// case-sensitive.
+    Base58,
+}
+
+impl Alphabet {
+    fn symbols(&self) -> &'static [u8] {
+        match self {
+            Self::Base32 => b"0123456789ABCDEFGHJKMNPQRSTVWXYZ",
+            Self::Base58 => b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz",
+        }
+    }
+
+    fn radix(&self) -> u64 {
+        self.symbols().len() as u64
+    }
+
+    /// The number of characters needed for a value of the specified number of bits.
+    fn width(&self, bits: u32) -> usize {
+        let mut width = 0;
+        let mut capacity = 1u128;
+        while capacity < 1 << bits {
+            capacity *= u128::from(self.radix());
+            width += 1;
+        }
+        width
+    }
+
+    fn digit(&self, c: char) -> Option<u64> {
+        let c = match self {
+            Self::Base32 => match c.to_ascii_uppercase() {
+                'I' | 'L' => '1',
+                'O' => '0',
+                c => c,
+            },
+            Self::Base58 => c,
+        };
+        let i = self.symbols().iter().position(|&s| char::from(s) == c)?;
+        Some(i as u64)
+    }
+}
+
+/// The bits of an address code.
+const ADDR_BITS: u32 = 32;
+/// The bits of a network code: the network address, followed by six bits of prefix length.
+const NET_BITS: u32 = 38;
+
+/// Encodes addresses and networks as short codes, and decodes them again.
+///
+/// Codes of the same kind are always the same length, so an address code never decodes as a
+/// network, nor the other way around. Hyphens may be added anywhere for readability, and are
+/// ignored when decoding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShortCodec {
+    alphabet: Alphabet,
+    checksum: bool,
+}
+
+impl ShortCodec {
+    /// Creates a codec with the alphabet, without a check character.
+    pub fn new(alphabet: Alphabet) -> Self {
+        Self {
+            alphabet,
+            checksum: false,
+        }
+    }
+
+    /// Appends a check character to every code, computed with the Luhn mod N algorithm, which
+    /// catches any single mistyped character and most swaps of neighbouring ones.
+    pub fn with_checksum(mut self) -> Self {
+        self.checksum = true;
+        self
+    }
+
+    /// Encodes the address.
+    pub fn encode(&self, addr: IpAddress) -> String {
+        self.encode_value(u64::from(addr.value()), ADDR_BITS)
+    }
+
+    /// Decodes an address encoded by [`ShortCodec::encode`].
+    pub fn decode(&self, code: &str) -> Result<IpAddress, ShortCodeError> {
+        let value = self.decode_value(code, ADDR_BITS)?;
+        Ok(IpAddress::from(value as u32))
+    }
+
+    /// Encodes the network, without any host bits of its base address.
+    pub fn encode_network(&self, net: IpNetwork) -> String {
+        let value =
+            u64::from(net.network_address().value()) << 6 | u64::from(net.num_network_bits());
+        self.encode_value(value, NET_BITS)
+    }
+
+    /// Decodes a network encoded by [`ShortCodec::encode_network`].
+    pub fn decode_network(&self, code: &str) -> Result<IpNetwork, ShortCodeError> {
+        let value = self.decode_value(code, NET_BITS)?;
+        let addr = IpAddress::from((value >> 6) as u32);
+        let net = IpNetwork::new(addr, (value & 0x3f) as u8).ok_or(ShortCodeError::OutOfRange)?;
+        // A base address with host bits would never have been encoded.
+        if net.network_address() != addr {
+            return Err(ShortCodeError::OutOfRange);
+        }
+        Ok(net)
+    }
+
+    fn encode_value(&self, mut value: u64, bits: u32) -> String {
+        let radix = self.alphabet.radix();
+        let mut digits = vec![0; self.alphabet.width(bits)];
+        for digit in digits.iter_mut().rev() {
+            *digit = value % radix;
+            value /= radix;
+        }
+        if self.checksum {
+            digits.push(check_digit(&digits, radix));
+        }
+        let symbols = self.alphabet.symbols();
+        digits
+            .into_iter()
+            .map(|d| char::from(symbols[d as usize]))
+            .collect()
+    }
+
+    fn decode_value(&self, code: &str, bits: u32) -> Result<u64, ShortCodeError> {
+        let mut digits = code
+            .chars()
+            .filter(|&c| c != '-')
+            .map(|c| {
+                self.alphabet
+                    .digit(c)
+                    .ok_or(ShortCodeError::InvalidCharacter(c))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let width = self.alphabet.width(bits);
+        if digits.len() != width + self.checksum as usize {
+            return Err(ShortCodeError::InvalidLength);
+        }
+        let radix = self.alphabet.radix();
+        if self.checksum {
+            let check = digits.pop().unwrap();
+            if check != check_digit(&digits, radix) {
+                return Err(ShortCodeError::ChecksumMismatch);
+            }
+        }
+        let value = digits
+            .into_iter()
+            .fold(0u128, |value, d| value * u128::from(radix) + u128::from(d));
+        if value >> bits != 0 {
+            return Err(ShortCodeError::OutOfRange);
+        }
+        Ok(value as u64)
+    }
+}
+
+/// The Luhn mod N check digit of the digits.
+fn check_digit(digits: &[u64], radix: u64) -> u64 {
+    let sum: u64 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            let addend = if i % 2 == 0 { d * 2 } else { d };
+            addend / radix + addend % radix
+        })
+        .sum();
+    (radix - sum % radix) % radix
+}
+
+/// The error returned when a short code cannot be decoded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ShortCodeError {
+    /// The character is not part of the alphabet.
+    InvalidCharacter(char),
+    /// The code has too many or too few characters.
+    InvalidLength,
+    /// The check character does not match the rest of the code.
+    ChecksumMismatch,
+    /// The code holds a value which no address or network encodes to.
+    OutOfRange,
+}
+
+impl fmt::Display for ShortCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCharacter(c) => write!(f, "invalid character {:?} in short code", c),
+            Self::InvalidLength => f.write_str("short code has the wrong length"),
+            Self::ChecksumMismatch => f.write_str("short code checksum does not match"),
+            Self::OutOfRange => f.write_str("short code does not encode an address"),
+        }
+    }
+}
+
+impl error::Error for ShortCodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> IpAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let codecs = [
+            ShortCodec::new(Alphabet::Base32),
+            ShortCodec::new(Alphabet::Base32).with_checksum(),
+            ShortCodec::new(Alphabet::Base58),
+            ShortCodec::new(Alphabet::Base58).with_checksum(),
+        ];
+        let addrs = ["0.0.0.0", "192.0.2.1", "10.20.30.40", "255.255.255.255"];
+        let nets = [
+            "0.0.0.0/0",
+            "10.0.0.0/8",
+            "192.0.2.128/25",
+            "255.255.255.255/32",
+        ];
+        for codec in &codecs {
+            for &s in &addrs {
+                let code = codec.encode(addr(s));
+                assert_eq!(Ok(addr(s)), codec.decode(&code), "{:?} {}", codec, code);
+                assert_eq!(
+                    Err(ShortCodeError::InvalidLength),
+                    codec.decode_network(&code)
+                );
+            }
+            for &s in &nets {
+                let net: IpNetwork = s.parse().unwrap();
+                let code = codec.encode_network(net);
+                assert_eq!(Ok(net), codec.decode_network(&code), "{:?} {}", codec, code);
+            }
+        }
+    }
+
+    #[test]
+    fn lengths() {
+        let base32 = ShortCodec::new(Alphabet::Base32);
+        let base58 = ShortCodec::new(Alphabet::Base58);
+        assert_eq!("0000000", base32.encode(addr("0.0.0.0")));
+        assert_eq!("3ZZZZZZ", base32.encode(addr("255.255.255.255")));
+        assert_eq!(6, base58.encode(addr("255.255.255.255")).len());
+        assert_eq!(
+            8,
+            base32.encode_network("10.0.0.0/8".parse().unwrap()).len()
+        );
+        assert_eq!(
+            7,
+            base58.encode_network("10.0.0.0/8".parse().unwrap()).len()
+        );
+        assert_eq!(8, base32.with_checksum().encode(addr("10.0.0.1")).len());
+    }
+
+    #[test]
+    fn lenient_base32() {
+        let codec = ShortCodec::new(Alphabet::Base32);
+        assert_eq!("00G40R4", codec.encode(addr("1.2.3.4")));
+        assert_eq!(Ok(addr("1.2.3.4")), codec.decode("oog-40r4"));
+        assert_eq!("0000011", codec.encode(addr("0.0.0.33")));
+        assert_eq!(Ok(addr("0.0.0.33")), codec.decode("OOOOOIL"));
+        assert_eq!(
+            Err(ShortCodeError::InvalidCharacter('U')),
+            codec.decode("U0G40R4")
+        );
+        assert_eq!(Err(ShortCodeError::OutOfRange), codec.decode("4000000"));
+        assert_eq!(Err(ShortCodeError::InvalidLength), codec.decode("010406"));
+        // A base address with host bits, or a prefix length past 32.
+        let net = u64::from(addr("10.0.0.1").value()) << 6 | 8;
+        assert_eq!(
+            Err(ShortCodeError::OutOfRange),
+            codec.decode_network(&codec.encode_value(net, NET_BITS))
+        );
+        assert_eq!(
+            Err(ShortCodeError::OutOfRange),
+            codec.decode_network(&codec.encode_value(33, NET_BITS))
+        );
+    }
+
+    #[test]
+    fn checksum() {
+        let codec = ShortCodec::new(Alphabet::Base58).with_checksum();
+        let code = codec.encode(addr("203.0.113.9"));
+        let chars: Vec<char> = code.chars().collect();
+        for i in 0..chars.len() {
+            let mut typo = chars.clone();
+            typo[i] = if typo[i] == 'z' { 'y' } else { 'z' };
+            let typo: String = typo.into_iter().collect();
+            assert_eq!(Err(ShortCodeError::ChecksumMismatch), codec.decode(&typo));
+        }
+        let mut swapped = chars;
+        swapped.swap(1, 2);
+        let swapped: String = swapped.into_iter().collect();
+        assert_ne!(swapped, code);
+        assert_eq!(
+            Err(ShortCodeError::ChecksumMismatch),
+            codec.decode(&swapped)
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! # The 6rd Module
+//!
+//! 6rd (IPv6 Rapid Deployment, RFC 5969) lets an ISP offer IPv6 across an IPv4-only network by
+//! deriving each customer's IPv6 prefix from their IPv4 address. The ISP's 6rd prefix is followed
+//! by whichever bits of the customer's IPv4 address are not common to the whole domain, so the
+//! mapping works in both directions.
+use super::{
+    addr::IpAddress, addr6::Ipv6Address, bits::AddressBits, net::IpNetwork, net6::Ipv6Network,
+};
+
+/// The parameters of a 6rd domain.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SixRd {
+    prefix: Ipv6Network,
+    ipv4_prefix: IpNetwork,
+}
+
+impl SixRd {
+    /// Creates a 6rd domain from the ISP's 6rd prefix, and the IPv4 network its customers are
+    /// addressed from. The length of that IPv4 network is the domain's `IPv4MaskLen`: the number
+    /// of leading bits every customer's address has in common, and which are left out of their
+    /// delegated prefix.
+    ///
+    /// The delegated prefixes **must** fit within an IPv6 address.
+    pub fn new(prefix: Ipv6Network, ipv4_prefix: IpNetwork) -> Option<Self> {
+        if prefix.num_network_bits() + ipv4_prefix.num_host_bits() > <u128 as AddressBits>::BITS {
+            return None;
+        }
+        Some(Self {
+            prefix,
+            ipv4_prefix,
+        })
+    }
+
+    /// The ISP's 6rd prefix.
+    pub fn prefix(&self) -> Ipv6Network {
+        self.prefix
+    }
+
+    /// The IPv4 network every customer of the domain is addressed from.
+    pub fn ipv4_prefix(&self) -> IpNetwork {
+        self.ipv4_prefix
+    }
+
+    /// The length of the prefix delegated to each customer.
+    pub fn delegated_prefix_len(&self) -> u8 {
+        self.prefix.num_network_bits() + self.ipv4_prefix.num_host_bits()
+    }
+
+    /// Derives the prefix delegated to the customer with the specified IPv4 address, if that
+    /// address is within the domain.
+    pub fn delegated_prefix(&self, addr: IpAddress) -> Option<Ipv6Network> {
+        if !self.ipv4_prefix.contains(addr) {
+            return None;
+        }
+        let suffix = u128::from((addr & self.ipv4_prefix.get_wildcard()).value());
+        // The suffix ends where the delegated prefix does; an empty suffix has nowhere to go.
+        let shift = u32::from(<u128 as AddressBits>::BITS - self.delegated_prefix_len());
+        let base = self.prefix.network_address().value() | suffix.checked_shl(shift).unwrap_or(0);
+        Ipv6Network::new(Ipv6Address::from(base), self.delegated_prefix_len())
+    }
+
+    /// Extracts the IPv4 address of the customer an IPv6 address was delegated to, if the IPv6
+    /// address is within the domain.
+    pub fn ipv4_address(&self, addr: Ipv6Address) -> Option<IpAddress> {
+        if !self.prefix.contains(addr) {
+            return None;
+        }
+        let shift = u32::from(<u128 as AddressBits>::BITS - self.delegated_prefix_len());
+        let suffix = addr.value().checked_shr(shift).unwrap_or(0);
+        let suffix = IpAddress::from(suffix as u32) & self.ipv4_prefix.get_wildcard();
+        Some(self.ipv4_prefix.network_address() | suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domain(prefix: &str, ipv4_prefix: &str) -> SixRd {
+        SixRd::new(prefix.parse().unwrap(), ipv4_prefix.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn derive() {
+        // The example of RFC 5969, with no common IPv4 bits.
+        let sixrd = domain("2001:db8::/32", "0.0.0.0/0");
+        assert_eq!(64, sixrd.delegated_prefix_len());
+        let customer = IpAddress::from([192, 0, 2, 1]);
+        let delegated = sixrd.delegated_prefix(customer).unwrap();
+        assert_eq!("2001:db8:c000:201::/64", delegated.to_string());
+        let host: Ipv6Address = "2001:db8:c000:201::1".parse().unwrap();
+        assert_eq!(Some(customer), sixrd.ipv4_address(host));
+
+        // Every customer within 10.0.0.0/8 shares its first octet, so it is left out.
+        let sixrd = domain("2001:db8:ff00::/40", "10.0.0.0/8");
+        assert_eq!(64, sixrd.delegated_prefix_len());
+        let customer = IpAddress::from([10, 1, 2, 3]);
+        let delegated = sixrd.delegated_prefix(customer).unwrap();
+        assert_eq!("2001:db8:ff01:203::/64", delegated.to_string());
+        assert_eq!(
+            Some(customer),
+            sixrd.ipv4_address(delegated.network_address())
+        );
+        assert!(sixrd
+            .delegated_prefix(IpAddress::from([11, 1, 2, 3]))
+            .is_none());
+        assert!(sixrd
+            .ipv4_address("2001:db8:fe00::1".parse().unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn edges() {
+        let sixrd = domain("2001:db8::/32", "192.0.2.1/32");
+        assert_eq!(32, sixrd.delegated_prefix_len());
+        let customer = IpAddress::from([192, 0, 2, 1]);
+        assert_eq!(
+            "2001:db8::/32",
+            sixrd.delegated_prefix(customer).unwrap().to_string()
+        );
+        assert_eq!(
+            Some(customer),
+            sixrd.ipv4_address("2001:db8::1".parse().unwrap())
+        );
+
+        let sixrd = domain("::/0", "0.0.0.0/0");
+        let customer = IpAddress::from([255, 255, 255, 255]);
+        let delegated = sixrd.delegated_prefix(customer).unwrap();
+        assert_eq!("ffff:ffff::/32", delegated.to_string());
+        assert_eq!(
+            Some(customer),
+            sixrd.ipv4_address(delegated.network_address())
+        );
+        assert!(SixRd::new(
+            "2001:db8::/100".parse().unwrap(),
+            "0.0.0.0/0".parse().unwrap()
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,381 @@
+//! # The Snapshot Module
+//!
+//! A routing table of a million prefixes takes a while to rebuild from text, and rebuilding it on
+//! every restart soon adds up. This module saves an [`IpNetworkMap`] or an [`Ipv6NetworkMap`],
+//! values and all, as a compact binary snapshot, which loads back far faster than it parses.
+//!
+//! A snapshot starts with the bytes `IPNM`, a format version, and the address family, followed by
+//! the number of entries. Each entry is the network address, big-endian, its prefix length, and
+//! its value, written as its [`SnapshotValue`] implementation chooses. Snapshots are read and
+//! written unbuffered, so a file should be wrapped in a `BufReader` or a `BufWriter` first.
+use super::{
+    addr::IpAddress, addr6::Ipv6Address, map::IpNetworkMap, map6::Ipv6NetworkMap, net::IpNetwork,
+    net6::Ipv6Network,
+};
+use std::{
+    convert::TryFrom,
+    error, fmt,
+    io::{self, Read},
+};
+
+/// The bytes every snapshot starts with.
+const MAGIC: [u8; 4] = *b"IPNM";
+/// The version of the format written here. Older versions are still read.
+const VERSION: u8 = 1;
+/// The address families a snapshot can hold.
+const IPV4: u8 = 4;
+const IPV6: u8 = 6;
+
+/// A value which can be saved within a snapshot.
+pub trait SnapshotValue: Sized {
+    /// Writes the value in a form which [`read_from`](Self::read_from) reads back.
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    /// Reads a value written by [`write_to`](Self::write_to).
+    fn read_from<R: io::Read>(reader: &mut R) -> Result<Self, SnapshotError>;
+}
+
+impl SnapshotValue for () {
+    fn write_to<W: io::Write>(&self, _: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_from<R: io::Read>(_: &mut R) -> Result<Self, SnapshotError> {
+        Ok(())
+    }
+}
+
+impl SnapshotValue for bool {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[u8::from(*self)])
+    }
+
+    fn read_from<R: io::Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        match read_bytes(reader)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            _ => Err(SnapshotError::Malformed("boolean")),
+        }
+    }
+}
+
+macro_rules! int_values {
+    ($($ty:ty),*) => {$(
+        /// Written big-endian.
+        impl SnapshotValue for $ty {
+            fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.to_be_bytes())
+            }
+
+            fn read_from<R: io::Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+                Ok(Self::from_be_bytes(read_bytes(reader)?))
+            }
+        }
+    )*};
+}
+
+int_values!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// Written as its length in bytes, as a `u32`, followed by its UTF-8.
+impl SnapshotValue for String {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_len(writer, self.len())?;
+        writer.write_all(self.as_bytes())
+    }
+
+    fn read_from<R: io::Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        // The length is not trusted with an allocation up front, in case the snapshot is corrupt.
+        let len = read_len(reader)?;
+        let mut bytes = Vec::new();
+        reader.by_ref().take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(SnapshotError::Truncated);
+        }
+        String::from_utf8(bytes).map_err(|_| SnapshotError::Malformed("string"))
+    }
+}
+
+/// Written as its length, as a `u32`, followed by each of its elements.
+impl<T: SnapshotValue> SnapshotValue for Vec<T> {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_len(writer, self.len())?;
+        self.iter().try_for_each(|value| value.write_to(writer))
+    }
+
+    fn read_from<R: io::Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        let len = read_len(reader)?;
+        (0..len).map(|_| T::read_from(reader)).collect()
+    }
+}
+
+/// Written as a `bool` for whether there is a value, followed by the value itself.
+impl<T: SnapshotValue> SnapshotValue for Option<T> {
+    fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.is_some().write_to(writer)?;
+        self.iter().try_for_each(|value| value.write_to(writer))
+    }
+
+    fn read_from<R: io::Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        if bool::read_from(reader)? {
+            T::read_from(reader).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+macro_rules! tuple_values {
+    ($(($($name:ident),*)),*) => {$(
+        /// Written as each of its elements in turn.
+        impl<$($name: SnapshotValue),*> SnapshotValue for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+                let ($($name,)*) = self;
+                $($name.write_to(writer)?;)*
+                Ok(())
+            }
+
+            fn read_from<R: io::Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+                Ok(($($name::read_from(reader)?,)*))
+            }
+        }
+    )*};
+}
+
+tuple_values!((A, B), (A, B, C), (A, B, C, D));
+
+/// Saves the map as a snapshot.
+pub fn save<V: SnapshotValue, W: io::Write>(
+    map: &IpNetworkMap<V>,
+    mut writer: W,
+) -> io::Result<()> {
+    write_header(&mut writer, IPV4, map.len())?;
+    for (net, value) in map.iter() {
+        writer.write_all(&net.network_address().octets())?;
+        writer.write_all(&[net.num_network_bits()])?;
+        value.write_to(&mut writer)?;
+    }
+    writer.flush()
+}
+
+/// Loads a map from a snapshot written by [`save`].
+pub fn load<V: SnapshotValue, R: io::Read>(
+    mut reader: R,
+) -> Result<IpNetworkMap<V>, SnapshotError> {
+    let len = read_header(&mut reader, IPV4)?;
+    let mut map = IpNetworkMap::new();
+    for _ in 0..len {
+        let addr = IpAddress::from(read_bytes::<_, 4>(&mut reader)?);
+        let [prefix_len] = read_bytes(&mut reader)?;
+        let net =
+            IpNetwork::new(addr, prefix_len).ok_or(SnapshotError::Malformed("prefix length"))?;
+        map.insert(net, V::read_from(&mut reader)?);
+    }
+    Ok(map)
+}
+
+/// Saves the IPv6 map as a snapshot.
+pub fn save6<V: SnapshotValue, W: io::Write>(
+    map: &Ipv6NetworkMap<V>,
+    mut writer: W,
+) -> io::Result<()> {
+    write_header(&mut writer, IPV6, map.len())?;
+    for (net, value) in map.iter() {
+        writer.write_all(&net.network_address().octets())?;
+        writer.write_all(&[net.num_network_bits()])?;
+        value.write_to(&mut writer)?;
+    }
+    writer.flush()
+}
+
+/// Loads an IPv6 map from a snapshot written by [`save6`].
+pub fn load6<V: SnapshotValue, R: io::Read>(
+    mut reader: R,
+) -> Result<Ipv6NetworkMap<V>, SnapshotError> {
+    let len = read_header(&mut reader, IPV6)?;
+    let mut map = Ipv6NetworkMap::new();
+    for _ in 0..len {
+        let addr = Ipv6Address::from(read_bytes::<_, 16>(&mut reader)?);
+        let [prefix_len] = read_bytes(&mut reader)?;
+        let net =
+            Ipv6Network::new(addr, prefix_len).ok_or(SnapshotError::Malformed("prefix length"))?;
+        map.insert(net, V::read_from(&mut reader)?);
+    }
+    Ok(map)
+}
+
+fn write_header<W: io::Write>(writer: &mut W, family: u8, len: usize) -> io::Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&[VERSION, family])?;
+    (len as u64).write_to(writer)
+}
+
+/// Reads the header of a snapshot, returning the number of entries which follow it.
+fn read_header<R: io::Read>(reader: &mut R, family: u8) -> Result<u64, SnapshotError> {
+    if read_bytes(reader)? != MAGIC {
+        return Err(SnapshotError::NotASnapshot);
+    }
+    let [version, actual] = read_bytes(reader)?;
+    if version == 0 || version > VERSION {
+        return Err(SnapshotError::UnsupportedVersion(version));
+    }
+    if actual != family {
+        return Err(SnapshotError::Malformed("address family"));
+    }
+    u64::read_from(reader)
+}
+
+fn write_len<W: io::Write>(writer: &mut W, len: usize) -> io::Result<()> {
+    let len =
+        u32::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too long"))?;
+    len.write_to(writer)
+}
+
+fn read_len<R: io::Read>(reader: &mut R) -> Result<usize, SnapshotError> {
+    u32::read_from(reader).map(|len| len as usize)
+}
+
+fn read_bytes<R: io::Read, const N: usize>(reader: &mut R) -> Result<[u8; N], SnapshotError> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// The error returned when a snapshot could not be read.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The snapshot ended early.
+    Truncated,
+    /// The data did not start like a snapshot at all.
+    NotASnapshot,
+    /// The snapshot was written by a newer version of the format.
+    UnsupportedVersion(u8),
+    /// A field held an impossible value.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read snapshot: {}", err),
+            Self::Truncated => f.write_str("truncated snapshot"),
+            Self::NotASnapshot => f.write_str("not a snapshot"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}", version)
+            }
+            Self::Malformed(field) => write!(f, "malformed snapshot: invalid {}", field),
+        }
+    }
+}
+
+impl error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            Self::Truncated
+        } else {
+            Self::Io(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let map: IpNetworkMap<(String, Vec<u32>, Option<i64>)> = vec![
+            (net("0.0.0.0/0"), ("default".to_string(), vec![], None)),
+            (
+                net("10.0.0.0/8"),
+                ("private".to_string(), vec![64512], Some(-1)),
+            ),
+            (
+                net("10.1.2.0/24"),
+                ("ünïcode".to_string(), vec![1, 2, 3], Some(7)),
+            ),
+            (net("255.255.255.255/32"), (String::new(), vec![], None)),
+        ]
+        .into_iter()
+        .collect();
+        let mut bytes = Vec::new();
+        save(&map, &mut bytes).unwrap();
+        assert_eq!(b"IPNM\x01\x04", &bytes[..6]);
+        let loaded = load(&bytes[..]).unwrap();
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            loaded.iter().collect::<Vec<_>>()
+        );
+
+        let map6: Ipv6NetworkMap<u16> = vec![
+            ("2001:db8::/32".parse().unwrap(), 1),
+            ("::/0".parse().unwrap(), 2),
+        ]
+        .into_iter()
+        .collect();
+        let mut bytes = Vec::new();
+        save6(&map6, &mut bytes).unwrap();
+        let loaded = load6::<u16, _>(&bytes[..]).unwrap();
+        assert_eq!(
+            map6.iter().collect::<Vec<_>>(),
+            loaded.iter().collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            load::<u16, _>(&bytes[..]),
+            Err(SnapshotError::Malformed("address family"))
+        ));
+    }
+
+    #[test]
+    fn invalid() {
+        let map: IpNetworkMap<bool> = vec![(net("10.0.0.0/8"), true)].into_iter().collect();
+        let mut bytes = Vec::new();
+        save(&map, &mut bytes).unwrap();
+        for len in 0..bytes.len() {
+            assert!(matches!(
+                load::<bool, _>(&bytes[..len]),
+                Err(SnapshotError::Truncated)
+            ));
+        }
+
+        let mut corrupt = bytes.clone();
+        corrupt[0] = b'X';
+        assert!(matches!(
+            load::<bool, _>(&corrupt[..]),
+            Err(SnapshotError::NotASnapshot)
+        ));
+        let mut corrupt = bytes.clone();
+        corrupt[4] = 2;
+        assert!(matches!(
+            load::<bool, _>(&corrupt[..]),
+            Err(SnapshotError::UnsupportedVersion(2))
+        ));
+        let mut corrupt = bytes.clone();
+        corrupt[18] = 33;
+        assert!(matches!(
+            load::<bool, _>(&corrupt[..]),
+            Err(SnapshotError::Malformed("prefix length"))
+        ));
+        let mut corrupt = bytes;
+        corrupt[19] = 2;
+        assert!(matches!(
+            load::<bool, _>(&corrupt[..]),
+            Err(SnapshotError::Malformed("boolean"))
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+//! # The Socket-Address Module
+//!
+//! A Socket Address names one endpoint of a conversation: a host, and a port on that host. Many
+//! list formats carry ports alongside their addresses, as in `192.0.2.1:443`, and this type saves
+//! splitting and joining them by hand.
+use super::{
+    addr::{AddrParseError, IpAddress},
+    net::IpNetwork,
+    set::IpSet,
+};
+use std::{
+    collections::BTreeMap,
+    error, fmt,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    str,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SocketAddress {
+    pub addr: IpAddress,
+    pub port: u16,
+}
+
+impl SocketAddress {
+    /// Creates a new Socket Address from an IP Address and a port.
+    pub fn new(addr: IpAddress, port: u16) -> Self {
+        Self { addr, port }
+    }
+}
+
+impl From<(IpAddress, u16)> for SocketAddress {
+    fn from((addr, port): (IpAddress, u16)) -> Self {
+        Self::new(addr, port)
+    }
+}
+
+impl From<SocketAddrV4> for SocketAddress {
+    fn from(socket: SocketAddrV4) -> Self {
+        Self::new(IpAddress::from(*socket.ip()), socket.port())
+    }
+}
+
+/// Drops the port, keeping only the host's address.
+impl From<SocketAddrV4> for IpAddress {
+    fn from(socket: SocketAddrV4) -> Self {
+        Self::from(*socket.ip())
+    }
+}
+
+impl From<SocketAddress> for SocketAddrV4 {
+    fn from(socket: SocketAddress) -> Self {
+        Self::new(Ipv4Addr::from(socket.addr), socket.port)
+    }
+}
+
+impl From<SocketAddress> for SocketAddr {
+    fn from(socket: SocketAddress) -> Self {
+        Self::V4(socket.into())
+    }
+}
+
+/// The error returned when a string could not be parsed as a Socket Address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketAddrParseError(());
+
+impl fmt::Display for SocketAddrParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid socket address syntax")
+    }
+}
+
+impl error::Error for SocketAddrParseError {}
+
+impl From<AddrParseError> for SocketAddrParseError {
+    fn from(_: AddrParseError) -> Self {
+        Self(())
+    }
+}
+
+impl str::FromStr for SocketAddress {
+    type Err = SocketAddrParseError;
+
+    /// Parses a socket address of the form `192.168.1.5:80`. The address may also be bracketed,
+    /// as in `[192.168.1.5]:80`, as addresses with colons of their own must be.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, port) = s.rsplit_once(':').ok_or(SocketAddrParseError(()))?;
+        let addr = match addr.strip_prefix('[') {
+            Some(addr) => addr.strip_suffix(']').ok_or(SocketAddrParseError(()))?,
+            None => addr,
+        };
+        if port.is_empty() || !port.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(SocketAddrParseError(()));
+        }
+        let port = port.parse().map_err(|_| SocketAddrParseError(()))?;
+        Ok(Self::new(addr.parse()?, port))
+    }
+}
+
+impl fmt::Display for SocketAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.addr, self.port)
+    }
+}
+
+/// The distinct hosts of a list of entries such as `192.0.2.1:443`, with their ports stripped,
+/// along with where in the list each host came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostList {
+    set: IpSet,
+    entries: BTreeMap<IpAddress, Vec<usize>>,
+    errors: Vec<usize>,
+}
+
+impl HostList {
+    /// Parses each entry as a socket address, or else as a bare address. Blank entries are
+    /// skipped, and those which cannot be parsed are recorded as errors.
+    pub fn parse<I, S>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut hosts = Self::default();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let entry = entry.as_ref().trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let addr = match entry.parse::<SocketAddress>() {
+                Ok(socket) => Ok(socket.addr),
+                Err(_) => entry.parse::<IpAddress>(),
+            };
+            match addr {
+                Ok(addr) => hosts.entries.entry(addr).or_default().push(i),
+                Err(_) => hosts.errors.push(i),
+            }
+        }
+        hosts.set = hosts
+            .entries
+            .keys()
+            .map(|&addr| IpNetwork::new(addr, 32).unwrap())
+            .collect();
+        hosts
+    }
+
+    /// Every distinct host.
+    pub fn set(&self) -> &IpSet {
+        &self.set
+    }
+
+    /// The distinct hosts, in ascending order, each with the indices of the entries which named
+    /// it.
+    pub fn hosts(&self) -> impl Iterator<Item = (IpAddress, &[usize])> + '_ {
+        self.entries
+            .iter()
+            .map(|(&addr, entries)| (addr, entries.as_slice()))
+    }
+
+    /// The indices of the entries which named the host, in the order they appeared.
+    pub fn entries(&self, addr: IpAddress) -> &[usize] {
+        self.entries.get(&addr).map_or(&[], Vec::as_slice)
+    }
+
+    /// The indices of the entries which could not be parsed.
+    pub fn errors(&self) -> &[usize] {
+        &self.errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let socket = SocketAddress::new(IpAddress::from([192, 0, 2, 1]), 443);
+        assert_eq!(Ok(socket), "192.0.2.1:443".parse());
+        assert_eq!(Ok(socket), "[192.0.2.1]:443".parse());
+        assert_eq!(
+            Ok(SocketAddress::new(IpAddress::from(0), 65535)),
+            "0.0.0.0:65535".parse()
+        );
+        for bad in &[
+            "",
+            "192.0.2.1",
+            "192.0.2.1:",
+            ":80",
+            "192.0.2.1:65536",
+            "192.0.2.1:+80",
+            "192.0.2.1:80:80",
+            "[192.0.2.1:80",
+            "192.0.2.1]:80",
+            "192.0.2.0/24:80",
+        ] {
+            assert!(bad.parse::<SocketAddress>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn display() {
+        let socket = SocketAddress::from((IpAddress::from([10, 0, 0, 1]), 8080));
+        assert_eq!("10.0.0.1:8080", socket.to_string());
+        assert_eq!(Ok(socket), socket.to_string().parse());
+    }
+
+    #[test]
+    fn std() {
+        let socket = SocketAddress::new(IpAddress::from([127, 0, 0, 1]), 53);
+        let v4 = SocketAddrV4::from(socket);
+        assert_eq!("127.0.0.1:53", v4.to_string());
+        assert_eq!(socket, SocketAddress::from(v4));
+        assert_eq!(
+            "127.0.0.1:53".parse::<SocketAddr>().unwrap(),
+            SocketAddr::from(socket)
+        );
+    }
+
+    #[test]
+    fn host_list() {
+        let log = [
+            "192.0.2.1:443",
+            "198.51.100.7:22",
+            "",
+            "[192.0.2.1]:8443",
+            "192.0.2.2",
+            "example.com:80",
+            " 192.0.2.1:443 ",
+        ];
+        let hosts = HostList::parse(log.iter());
+        let addrs: Vec<_> = hosts.hosts().map(|(addr, _)| addr.to_string()).collect();
+        assert_eq!(vec!["192.0.2.1", "192.0.2.2", "198.51.100.7"], addrs);
+        assert_eq!(&[0, 3, 6], hosts.entries("192.0.2.1".parse().unwrap()));
+        assert!(hosts.entries("203.0.113.1".parse().unwrap()).is_empty());
+        assert_eq!(&[5], hosts.errors());
+        let nets: Vec<_> = hosts.set().iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            vec!["192.0.2.1/32", "192.0.2.2/32", "198.51.100.7/32"],
+            nets
+        );
+
+        let v4 = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 80);
+        assert_eq!(IpAddress::from([10, 0, 0, 1]), IpAddress::from(v4));
+    }
+}
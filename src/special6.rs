@@ -0,0 +1,360 @@
+//! # The IPv6 Special-Purpose Module
+//!
+//! IANA keeps a registry of the IPv6 networks set aside for special purposes, along with whether
+//! packets to or from each may be forwarded, and whether each is reachable across the Internet.
+//! This module carries that registry as a table, which can be searched by address, and which
+//! decides whether an address is global.
+use super::{addr6::Ipv6Address, net6::Ipv6Network};
+
+/// An entry of the IANA IPv6 Special-Purpose Address Registry.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SpecialPurpose {
+    base: u128,
+    prefix_len: u8,
+    /// The name the registry gives the network, such as `Unique-Local`.
+    pub name: &'static str,
+    /// The RFC which set the network aside, such as `RFC 4193`.
+    pub rfc: &'static str,
+    /// Whether an address of the network may be the source of a packet.
+    pub source: bool,
+    /// Whether an address of the network may be the destination of a packet.
+    pub destination: bool,
+    /// Whether a router may forward a packet addressed to or from the network.
+    pub forwardable: bool,
+    /// Whether the network is reachable across the Internet, or `None` where the registry says
+    /// this does not apply, and the network it lies within decides.
+    pub globally_reachable: Option<bool>,
+    /// Whether the network is reserved by the protocol itself, rather than by a later
+    /// specification.
+    pub reserved_by_protocol: bool,
+}
+
+impl SpecialPurpose {
+    /// The network the entry describes.
+    pub fn network(&self) -> Ipv6Network {
+        Ipv6Network::new(Ipv6Address::from(self.base), self.prefix_len).unwrap()
+    }
+}
+
+/// Builds a registry entry; the flags are in the registry's column order.
+const fn entry(
+    base: u128,
+    prefix_len: u8,
+    name: &'static str,
+    rfc: &'static str,
+    (source, destination, forwardable): (bool, bool, bool),
+    globally_reachable: Option<bool>,
+    reserved_by_protocol: bool,
+) -> SpecialPurpose {
+    SpecialPurpose {
+        base,
+        prefix_len,
+        name,
+        rfc,
+        source,
+        destination,
+        forwardable,
+        globally_reachable,
+        reserved_by_protocol,
+    }
+}
+
+const ALL: (bool, bool, bool) = (true, true, true);
+const NONE: (bool, bool, bool) = (false, false, false);
+
+/// The registry, in ascending order of network.
+pub const REGISTRY: [SpecialPurpose; 23] = [
+    entry(
+        0,
+        128,
+        "Unspecified Address",
+        "RFC 4291",
+        (true, false, false),
+        Some(false),
+        true,
+    ),
+    entry(
+        1,
+        128,
+        "Loopback Address",
+        "RFC 4291",
+        NONE,
+        Some(false),
+        true,
+    ),
+    entry(
+        0xffff << 32,
+        96,
+        "IPv4-mapped Address",
+        "RFC 4291",
+        NONE,
+        Some(false),
+        true,
+    ),
+    entry(
+        0x64_ff9b << 96,
+        96,
+        "IPv4-IPv6 Translat.",
+        "RFC 6052",
+        ALL,
+        Some(true),
+        false,
+    ),
+    entry(
+        0x64_ff9b_0001 << 80,
+        48,
+        "IPv4-IPv6 Translat.",
+        "RFC 8215",
+        ALL,
+        Some(false),
+        false,
+    ),
+    entry(
+        0x100 << 112,
+        64,
+        "Discard-Only Address Block",
+        "RFC 6666",
+        ALL,
+        Some(false),
+        false,
+    ),
+    entry(
+        0x2001 << 112,
+        23,
+        "IETF Protocol Assignments",
+        "RFC 2928",
+        NONE,
+        Some(false),
+        false,
+    ),
+    entry(0x2001 << 112, 32, "TEREDO", "RFC 4380", ALL, None, false),
+    entry(
+        0x2001_0001 << 96 | 1,
+        128,
+        "Port Control Protocol Anycast",
+        "RFC 7723",
+        ALL,
+        Some(true),
+        false,
+    ),
+    entry(
+        0x2001_0001 << 96 | 2,
+        128,
+        "Traversal Using Relays around NAT Anycast",
+        "RFC 8155",
+        ALL,
+        Some(true),
+        false,
+    ),
+    entry(
+        0x2001_0001 << 96 | 3,
+        128,
+        "DNS-SD Service Registration Protocol Anycast",
+        "RFC 9665",
+        ALL,
+        Some(true),
+        false,
+    ),
+    entry(
+        0x2001_0002 << 96,
+        48,
+        "Benchmarking",
+        "RFC 5180",
+        ALL,
+        Some(false),
+        false,
+    ),
+    entry(
+        0x2001_0003 << 96,
+        32,
+        "AMT",
+        "RFC 7450",
+        ALL,
+        Some(true),
+        false,
+    ),
+    entry(
+        0x2001_0004_0112 << 80,
+        48,
+        "AS112-v6",
+        "RFC 7535",
+        ALL,
+        Some(true),
+        false,
+    ),
+    entry(
+        0x2001_0020 << 96,
+        28,
+        "ORCHIDv2",
+        "RFC 7343",
+        ALL,
+        Some(true),
+        false,
+    ),
+    entry(
+        0x2001_0030 << 96,
+        28,
+        "Drone Remote ID Protocol Entity Tags (DETs) Prefix",
+        "RFC 9374",
+        ALL,
+        Some(true),
+        false,
+    ),
+    entry(
+        0x2001_0db8 << 96,
+        32,
+        "Documentation",
+        "RFC 3849",
+        NONE,
+        Some(false),
+        false,
+    ),
+    entry(0x2002 << 112, 16, "6to4", "RFC 3056", ALL, None, false),
+    entry(
+        0x2620_004f_8000 << 80,
+        48,
+        "Direct Delegation AS112 Service",
+        "RFC 7534",
+        ALL,
+        Some(true),
+        false,
+    ),
+    entry(
+        0x3fff << 112,
+        20,
+        "Documentation",
+        "RFC 9637",
+        NONE,
+        Some(false),
+        false,
+    ),
+    entry(
+        0x5f00 << 112,
+        16,
+        "Segment Routing (SRv6) SIDs",
+        "RFC 9602",
+        ALL,
+        Some(false),
+        false,
+    ),
+    entry(
+        0xfc00 << 112,
+        7,
+        "Unique-Local",
+        "RFC 4193",
+        ALL,
+        Some(false),
+        false,
+    ),
+    entry(
+        0xfe80 << 112,
+        10,
+        "Link-Local Unicast",
+        "RFC 4291",
+        (true, true, false),
+        Some(false),
+        true,
+    ),
+];
+
+/// Finds every entry whose network contains the address, from the most to the least specific.
+pub fn lookup_all(addr: Ipv6Address) -> impl Iterator<Item = &'static SpecialPurpose> {
+    let mut matches: Vec<_> = REGISTRY
+        .iter()
+        .filter(|entry| entry.network().contains(addr))
+        .collect();
+    matches.sort_by_key(|entry| std::cmp::Reverse(entry.prefix_len));
+    matches.into_iter()
+}
+
+/// Finds the most specific entry whose network contains the address.
+pub fn lookup(addr: Ipv6Address) -> Option<&'static SpecialPurpose> {
+    lookup_all(addr).next()
+}
+
+/// Whether the address is reachable across the Internet, according to the most specific entry
+/// which says either way. Addresses outside the registry are.
+pub(crate) fn is_globally_reachable(addr: Ipv6Address) -> bool {
+    lookup_all(addr)
+        .find_map(|entry| entry.globally_reachable)
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Ipv6Address {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn registry() {
+        let networks: Vec<_> = REGISTRY
+            .iter()
+            .map(|entry| entry.network().to_string())
+            .collect();
+        assert_eq!(
+            vec![
+                "::/128",
+                "::1/128",
+                "::ffff:0.0.0.0/96",
+                "64:ff9b::/96",
+                "64:ff9b:1::/48",
+                "100::/64",
+                "2001::/23",
+                "2001::/32",
+                "2001:1::1/128",
+                "2001:1::2/128",
+                "2001:1::3/128",
+                "2001:2::/48",
+                "2001:3::/32",
+                "2001:4:112::/48",
+                "2001:20::/28",
+                "2001:30::/28",
+                "2001:db8::/32",
+                "2002::/16",
+                "2620:4f:8000::/48",
+                "3fff::/20",
+                "5f00::/16",
+                "fc00::/7",
+                "fe80::/10",
+            ],
+            networks
+        );
+        assert!(REGISTRY
+            .windows(2)
+            .all(|w| w[0].network() <= w[1].network()));
+    }
+
+    #[test]
+    fn lookup() {
+        assert_eq!("Unique-Local", super::lookup(addr("fd12::1")).unwrap().name);
+        assert_eq!("RFC 7723", super::lookup(addr("2001:1::1")).unwrap().rfc);
+        assert_eq!(
+            "TEREDO",
+            super::lookup(addr("2001:0:4136:e378::1")).unwrap().name
+        );
+        assert_eq!(
+            vec!["Port Control Protocol Anycast", "IETF Protocol Assignments"],
+            lookup_all(addr("2001:1::1"))
+                .map(|entry| entry.name)
+                .collect::<Vec<_>>()
+        );
+        assert!(super::lookup(addr("2606:4700::1111")).is_none());
+        let link_local = super::lookup(addr("fe80::1")).unwrap();
+        assert!(link_local.source && !link_local.forwardable && link_local.reserved_by_protocol);
+    }
+
+    #[test]
+    fn globally_reachable() {
+        assert!(is_globally_reachable(addr("2606:4700::1111")));
+        assert!(is_globally_reachable(addr("2001:1::1")));
+        assert!(is_globally_reachable(addr("2002:c000:201::1")));
+        // TEREDO does not say, and the IETF block it lies within is not reachable.
+        assert!(!is_globally_reachable(addr("2001::1")));
+        assert!(!is_globally_reachable(addr("2001:db8::1")));
+        assert!(!is_globally_reachable(addr("::1")));
+        assert!(!is_globally_reachable(addr("fe80::1")));
+    }
+}
@@ -0,0 +1,216 @@
+//! # The Sweep Module
+//!
+//! A sweep probes every address of a network, or of any other collection, to find out which of
+//! them answer. This module runs the probes a bounded number at a time, and gathers the results
+//! by address.
+//!
+//! Sending ICMP echo requests takes privileges the standard library has no way to ask for, so
+//! [`Ping`] runs the system's own `ping`, which has them. Where ICMP is filtered, [`TcpConnect`]
+//! knocks on a port instead. Probes block; each runs on one of the sweep's threads.
+use super::addr::IpAddress;
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{SocketAddr, TcpStream},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A way of finding out whether an address answers.
+pub trait Prober: Sync {
+    /// Probes the address, giving the round-trip time if it answered, or `None` if it did not.
+    /// An error means the probe itself could not be made.
+    fn probe(&self, addr: IpAddress) -> io::Result<Option<Duration>>;
+}
+
+/// A prober which sends a single ICMP echo request, by running `ping`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ping {
+    timeout: Duration,
+}
+
+impl Default for Ping {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ping {
+    /// Creates a prober which waits a second for each reply.
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets how long to wait for each reply. Linux's `ping` only waits whole seconds, so the
+    /// timeout is rounded up.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Prober for Ping {
+    fn probe(&self, addr: IpAddress) -> io::Result<Option<Duration>> {
+        // The BSDs take the wait in milliseconds, and Linux in seconds.
+        let wait = if cfg!(any(target_os = "macos", target_os = "freebsd")) {
+            self.timeout.as_millis().max(1)
+        } else {
+            u128::from(self.timeout.as_secs() + u64::from(self.timeout.subsec_nanos() > 0)).max(1)
+        };
+        let output = Command::new("ping")
+            .args(["-n", "-c", "1", "-W", &wait.to_string()])
+            .arg(addr.to_string())
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+        // `ping` exits with 1 when nothing answered, and 2 when it could not send at all.
+        match output.status.code() {
+            Some(0) => Ok(Some(
+                round_trip(&String::from_utf8_lossy(&output.stdout)).unwrap_or_default(),
+            )),
+            Some(1) => Ok(None),
+            _ => Err(io::Error::other(format!("`ping {}` failed", addr))),
+        }
+    }
+}
+
+/// Reads the round-trip time from `ping`'s report of a reply, as in `time=0.045 ms`.
+fn round_trip(output: &str) -> Option<Duration> {
+    let (_, rest) = output.split_once("time=")?;
+    let millis: f64 = rest.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs_f64(millis / 1000.0))
+}
+
+/// A prober which opens a TCP connection to a port. An address answers if it accepts the
+/// connection, or refuses it; either way, something is there.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TcpConnect {
+    port: u16,
+    timeout: Duration,
+}
+
+impl TcpConnect {
+    /// Creates a prober which connects to the port, waiting a second for each answer.
+    pub fn new(port: u16) -> Self {
+        Self {
+            port,
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets how long to wait for each answer.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Prober for TcpConnect {
+    fn probe(&self, addr: IpAddress) -> io::Result<Option<Duration>> {
+        let start = Instant::now();
+        let socket = SocketAddr::from((addr.octets(), self.port));
+        match TcpStream::connect_timeout(&socket, self.timeout) {
+            Ok(_) => Ok(Some(start.elapsed())),
+            Err(err) => match err.kind() {
+                io::ErrorKind::ConnectionRefused => Ok(Some(start.elapsed())),
+                io::ErrorKind::TimedOut
+                | io::ErrorKind::HostUnreachable
+                | io::ErrorKind::NetworkUnreachable => Ok(None),
+                _ => Err(err),
+            },
+        }
+    }
+}
+
+/// Probes every address, up to `concurrency` of them at once, gathering the results by address.
+pub fn sweep<I, P>(
+    addrs: I,
+    prober: &P,
+    concurrency: usize,
+) -> BTreeMap<IpAddress, io::Result<Option<Duration>>>
+where
+    I: IntoIterator<Item = IpAddress>,
+    P: Prober + ?Sized,
+{
+    let addrs: Vec<IpAddress> = addrs.into_iter().collect();
+    let results = Mutex::new(BTreeMap::new());
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..concurrency.clamp(1, addrs.len().max(1)) {
+            scope.spawn(|| {
+                while let Some(&addr) = addrs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = prober.probe(addr);
+                    results.lock().unwrap().insert(addr, result);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// A prober which finds every even address, and fails on the broadcast address.
+    struct Evens {
+        running: AtomicUsize,
+        most: AtomicUsize,
+    }
+
+    impl Prober for Evens {
+        fn probe(&self, addr: IpAddress) -> io::Result<Option<Duration>> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most.fetch_max(running, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(5));
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            match addr.octets()[3] {
+                255 => Err(io::Error::other("broadcast")),
+                n if n % 2 == 0 => Ok(Some(Duration::from_millis(u64::from(n)))),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    #[test]
+    fn bounded() {
+        let prober = Evens {
+            running: AtomicUsize::new(0),
+            most: AtomicUsize::new(0),
+        };
+        let addrs = (0..256).map(|i| IpAddress::from([10, 0, 0, i as u8]));
+        let results = sweep(addrs.rev(), &prober, 8);
+        assert_eq!(256, results.len());
+        assert!(prober.most.load(Ordering::SeqCst) <= 8);
+        let up: Vec<_> = results
+            .iter()
+            .filter(|(_, r)| matches!(r, Ok(Some(_))))
+            .map(|(addr, _)| addr.octets()[3])
+            .collect();
+        assert_eq!((0..255).step_by(2).collect::<Vec<_>>(), up);
+        assert!(results[&IpAddress::from([10, 0, 0, 255])].is_err());
+        assert!(sweep(Vec::new(), &prober, 0).is_empty());
+    }
+
+    #[test]
+    fn tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let prober = TcpConnect::new(port).with_timeout(Duration::from_millis(500));
+        assert!(prober.probe([127, 0, 0, 1].into()).unwrap().is_some());
+    }
+
+    #[test]
+    fn ping_output() {
+        let output = "PING 127.0.0.1 (127.0.0.1) 56(84) bytes of data.\n\
+            64 bytes from 127.0.0.1: icmp_seq=1 ttl=64 time=0.045 ms\n";
+        assert_eq!(Some(Duration::from_micros(45)), round_trip(output));
+        assert_eq!(None, round_trip("1 packets transmitted, 0 received"));
+    }
+}
@@ -0,0 +1,595 @@
+//! # The Scan-Targets Module
+//!
+//! An internet-wide scan comes down to a list of targets with a few twists: whole networks to
+//! scan, other networks which must never be touched, an order scrambled so that no one network
+//! takes the whole load at once, the work split across several machines, and a way to pick up
+//! where a run left off. This module packages all of that as a [`TargetSpec`], which is then walked
+//! by a single iterator. A run can be checkpointed at any time as a [`ScanCursor`], which is saved
+//! as a line of text and later resumes exactly where the run left off.
+//!
+//! Targets usually arrive as a line of human input, mixing addresses, networks, ranges, and
+//! octet patterns; a [`TargetList`] reads all of them into a single set.
+use super::{
+    addr::IpAddress,
+    blocklist::{self, BlocklistError, BlocklistFormat},
+    bloom::mix,
+    net::IpNetwork,
+    pattern::OctetPattern,
+    random::{Permutation, Rng},
+    range::IpRange,
+    set::IpSet,
+};
+use std::{error, fmt, str};
+
+/// What to scan, what never to scan, in what order, and which share of it this machine takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetSpec {
+    include: IpSet,
+    exclude: IpSet,
+    seed: Option<u64>,
+    /// The number of shards, and the index of the one taken here.
+    shard: (u64, u64),
+}
+
+impl Default for TargetSpec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TargetSpec {
+    /// Creates a spec without any targets, taking the only shard, in ascending order.
+    pub fn new() -> Self {
+        Self {
+            include: IpSet::new(),
+            exclude: IpSet::new(),
+            seed: None,
+            shard: (1, 0),
+        }
+    }
+
+    /// Scans every address of the network, unless it is excluded.
+    pub fn include(mut self, net: IpNetwork) -> Self {
+        self.include.insert(net);
+        self
+    }
+
+    /// Scans every address of the set, unless it is excluded.
+    pub fn include_set(mut self, set: &IpSet) -> Self {
+        self.include = self.include.union(set);
+        self
+    }
+
+    /// Never scans any address of the network, even where it is included.
+    pub fn exclude(mut self, net: IpNetwork) -> Self {
+        self.exclude.insert(net);
+        self
+    }
+
+    /// Never scans any address of the set, even where it is included.
+    pub fn exclude_set(mut self, set: &IpSet) -> Self {
+        self.exclude = self.exclude.union(set);
+        self
+    }
+
+    /// Never scans any address listed in an exclude file, in the
+    /// [`Plain`](BlocklistFormat::Plain) blocklist format that zmap and masscan also read.
+    ///
+    /// Unlike a blocklist, an exclude file which cannot be read in full is an error, since an
+    /// entry which is skipped would be scanned.
+    pub fn exclude_file(self, text: &str) -> Result<Self, Vec<BlocklistError>> {
+        let (set, errors) = blocklist::parse(text, BlocklistFormat::Plain);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(self.exclude_set(&set))
+    }
+
+    /// Scans in an order scrambled by the seed. The same seed always gives the same order, so
+    /// every shard of a scan **must** be given the same one.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Takes only one of a number of equal shares of the targets, counting from `0`. Together,
+    /// the shards cover every target exactly once.
+    pub fn shard(mut self, count: u64, index: u64) -> Option<Self> {
+        if index >= count {
+            return None;
+        }
+        self.shard = (count, index);
+        Some(self)
+    }
+
+    /// Every address to be scanned, across all shards.
+    pub fn to_set(&self) -> IpSet {
+        self.include.difference(&self.exclude)
+    }
+
+    /// Walks the targets of this shard from the start.
+    pub fn targets(&self) -> Targets {
+        self.targets_from(0)
+    }
+
+    /// Walks the targets of this shard from a position reported by [`Targets::position`], so that
+    /// an interrupted run can resume where it left off.
+    pub fn targets_from(&self, position: u64) -> Targets {
+        let mut ranges = Vec::new();
+        let mut len = 0;
+        for range in self.to_set().ranges() {
+            ranges.push((range.start().value(), len));
+            len += range.num_addresses();
+        }
+        let permutation = self.seed.map(|seed| {
+            let bits = (64 - len.saturating_sub(1).leading_zeros()) as u8;
+            Permutation::new(bits, &mut Rng::seed_from_u64(seed))
+        });
+        let end = match &permutation {
+            Some(permutation) => 1 << permutation.bits(),
+            None => len,
+        };
+        // The first position at or after the one given which belongs to this shard.
+        let (count, index) = self.shard;
+        let position = position + (count + index - position % count) % count;
+        Targets {
+            ranges,
+            len,
+            permutation,
+            step: count,
+            position,
+            end,
+            fingerprint: self.fingerprint(),
+        }
+    }
+
+    /// Walks the targets of this shard from where the cursor was taken, only if it was taken from
+    /// an identical spec; a cursor from any other would skip or repeat targets.
+    pub fn resume(&self, cursor: &ScanCursor) -> Result<Targets, ScanCursorError> {
+        if cursor.fingerprint != self.fingerprint() {
+            return Err(ScanCursorError::Mismatch);
+        }
+        Ok(self.targets_from(cursor.position))
+    }
+
+    /// A hash of everything which decides the order of this shard's targets.
+    fn fingerprint(&self) -> u64 {
+        let mut hash = mix(self.shard.0) ^ mix(!self.shard.1);
+        hash = mix(hash ^ self.seed.map_or(0, |seed| mix(seed) | 1));
+        for range in self.to_set().ranges() {
+            let value = u64::from(range.start().value()) << 32 | u64::from(range.end().value());
+            hash = mix(hash ^ value);
+        }
+        hash
+    }
+}
+
+/// An iterator over the targets of a scan. See [`TargetSpec::targets`].
+#[derive(Debug, Clone)]
+pub struct Targets {
+    /// The first address of each range of targets, and the number of targets before it.
+    ranges: Vec<(u32, u64)>,
+    len: u64,
+    permutation: Option<Permutation>,
+    step: u64,
+    position: u64,
+    end: u64,
+    fingerprint: u64,
+}
+
+impl Targets {
+    /// The position of the next target, from which [`TargetSpec::targets_from`] resumes.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// A checkpoint of the run, from which [`TargetSpec::resume`] continues.
+    pub fn cursor(&self) -> ScanCursor {
+        ScanCursor {
+            fingerprint: self.fingerprint,
+            position: self.position,
+            end: self.end,
+        }
+    }
+
+    /// The fraction of this shard's targets which have been walked, from `0.0` to `1.0`.
+    pub fn progress(&self) -> f64 {
+        self.cursor().progress()
+    }
+
+    /// The target with the specified index, in ascending order.
+    fn nth_target(&self, index: u64) -> IpAddress {
+        let i = self.ranges.partition_point(|&(_, before)| before <= index) - 1;
+        let (start, before) = self.ranges[i];
+        IpAddress::from(start + (index - before) as u32)
+    }
+}
+
+impl Iterator for Targets {
+    type Item = IpAddress;
+
+    fn next(&mut self) -> Option<IpAddress> {
+        while self.position < self.end {
+            let position = self.position;
+            self.position += self.step;
+            let index = match &self.permutation {
+                Some(permutation) => permutation.apply(position),
+                None => position,
+            };
+            // The permutation covers the next power of two; anything past the targets is skipped.
+            if index < self.len {
+                return Some(self.nth_target(index));
+            }
+        }
+        None
+    }
+}
+
+/// A checkpoint of a scan, written as a single line such as `scan:0f1e2d3c4b5a6978:1200/65536`,
+/// which holds a fingerprint of the spec, the position reached, and the position it ends at.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScanCursor {
+    fingerprint: u64,
+    position: u64,
+    end: u64,
+}
+
+impl ScanCursor {
+    /// The position of the next target.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// The fraction of the shard's targets which had been walked, from `0.0` to `1.0`.
+    ///
+    /// Positions are walked at a steady pace, so this is exact in ascending order, and very close
+    /// in a scrambled one, where a few positions past the last target are skipped along the way.
+    pub fn progress(&self) -> f64 {
+        if self.position >= self.end {
+            return 1.0;
+        }
+        self.position as f64 / self.end as f64
+    }
+}
+
+impl fmt::Display for ScanCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "scan:{:016x}:{}/{}",
+            self.fingerprint, self.position, self.end
+        )
+    }
+}
+
+impl str::FromStr for ScanCursor {
+    type Err = ScanCursorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s
+            .trim()
+            .strip_prefix("scan:")
+            .ok_or(ScanCursorError::Syntax)?;
+        let (fingerprint, rest) = rest.split_once(':').ok_or(ScanCursorError::Syntax)?;
+        let (position, end) = rest.split_once('/').ok_or(ScanCursorError::Syntax)?;
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if fingerprint.len() != 16
+            || !fingerprint.bytes().all(|b| b.is_ascii_hexdigit())
+            || !digits(position)
+            || !digits(end)
+        {
+            return Err(ScanCursorError::Syntax);
+        }
+        Ok(Self {
+            fingerprint: u64::from_str_radix(fingerprint, 16)
+                .map_err(|_| ScanCursorError::Syntax)?,
+            position: position.parse().map_err(|_| ScanCursorError::Syntax)?,
+            end: end.parse().map_err(|_| ScanCursorError::Syntax)?,
+        })
+    }
+}
+
+/// The error returned when a scan could not be resumed from a cursor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ScanCursorError {
+    /// The cursor was not written as one.
+    Syntax,
+    /// The cursor was taken from a different spec, or another shard of it.
+    Mismatch,
+}
+
+impl fmt::Display for ScanCursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Syntax => "invalid scan cursor syntax",
+            Self::Mismatch => "scan cursor was taken from a different scan",
+        })
+    }
+}
+
+impl error::Error for ScanCursorError {}
+
+/// Targets read from the input scanners commonly accept: addresses, networks, ranges such as
+/// `10.0.0.1-10.0.0.9`, and [octet patterns](OctetPattern) such as `192.168.1-3.*`, separated
+/// by whitespace or commas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetList {
+    set: IpSet,
+    errors: Vec<TargetError>,
+}
+
+impl TargetList {
+    /// Reads every target of the input, skipping and reporting any which cannot be understood.
+    ///
+    /// A comma within an octet pattern, as in `192.168.1,3.1`, lists values of that octet; any
+    /// other comma separates targets.
+    pub fn parse(text: &str) -> Self {
+        let mut list = Self {
+            set: IpSet::new(),
+            errors: Vec::new(),
+        };
+        for token in text.split_whitespace() {
+            if let Some(set) = parse_target(token) {
+                list.set = list.set.union(&set);
+                continue;
+            }
+            for piece in split_commas(token).into_iter().filter(|p| !p.is_empty()) {
+                match parse_target(piece) {
+                    Some(set) => list.set = list.set.union(&set),
+                    None => list.errors.push(TargetError {
+                        token: piece.to_string(),
+                        offset: piece.as_ptr() as usize - text.as_ptr() as usize,
+                    }),
+                }
+            }
+        }
+        list
+    }
+
+    /// Every address of every target which could be read.
+    pub fn set(&self) -> &IpSet {
+        &self.set
+    }
+
+    /// The targets which could not be read, in the order they appeared.
+    pub fn errors(&self) -> &[TargetError] {
+        &self.errors
+    }
+
+    /// The set of targets, only if every one of them could be read.
+    pub fn into_set(self) -> Result<IpSet, Vec<TargetError>> {
+        if self.errors.is_empty() {
+            Ok(self.set)
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+/// Reads a single target of any kind.
+fn parse_target(token: &str) -> Option<IpSet> {
+    if let Some((first, last)) = token.split_once('-') {
+        if let (Ok(first), Ok(last)) = (first.parse(), last.parse()) {
+            let mut set = IpSet::new();
+            set.insert_range(IpRange::new(first, last)?);
+            return Some(set);
+        }
+    }
+    if let Ok(net) = token.parse::<IpNetwork>() {
+        return Some(IpSet::from(net));
+    }
+    if let Ok(addr) = token.parse::<IpAddress>() {
+        return Some(IpSet::from(IpNetwork::new(addr, 32).unwrap()));
+    }
+    token
+        .parse::<OctetPattern>()
+        .ok()
+        .map(|pattern| pattern.to_set())
+}
+
+/// Splits a token on the commas outside braces.
+fn split_commas(token: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in token.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                pieces.push(&token[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pieces.push(&token[start..]);
+    pieces
+}
+
+/// A target which could not be read.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TargetError {
+    token: String,
+    offset: usize,
+}
+
+impl TargetError {
+    /// The target, as written.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// The byte offset of the target within the input.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid target {:?} at offset {}",
+            self.token, self.offset
+        )
+    }
+}
+
+impl error::Error for TargetError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> TargetSpec {
+        TargetSpec::new()
+            .include("10.0.0.0/24".parse().unwrap())
+            .include("192.168.0.0/28".parse().unwrap())
+            .exclude_file("# never\n10.0.0.64/26\n10.0.0.200\n")
+            .unwrap()
+    }
+
+    #[test]
+    fn ordered() {
+        let targets: Vec<_> = spec().targets().map(|addr| addr.to_string()).collect();
+        assert_eq!(256 - 64 - 1 + 16, targets.len());
+        assert_eq!("10.0.0.0", targets[0]);
+        assert_eq!("10.0.0.128", targets[64]);
+        assert_eq!("192.168.0.15", targets[targets.len() - 1]);
+        assert!(!targets.contains(&"10.0.0.200".to_string()));
+        assert!(TargetSpec::new().exclude_file("10.0.0.0/33").is_err());
+        assert_eq!(0, TargetSpec::new().targets().count());
+    }
+
+    #[test]
+    fn permuted_shards() {
+        let spec = spec().seed(42);
+        let all: Vec<_> = spec.targets().collect();
+        let mut sorted = all.clone();
+        sorted.sort();
+        assert_ne!(all, sorted);
+        assert_eq!(
+            spec.to_set(),
+            sorted
+                .iter()
+                .map(|&a| IpNetwork::new(a, 32).unwrap())
+                .collect()
+        );
+        assert_eq!(all, spec.clone().seed(42).targets().collect::<Vec<_>>());
+
+        let mut sharded: Vec<_> = (0..3)
+            .flat_map(|i| spec.clone().shard(3, i).unwrap().targets())
+            .collect();
+        sharded.sort();
+        assert_eq!(sorted, sharded);
+        assert!(spec.clone().shard(3, 3).is_none());
+    }
+
+    #[test]
+    fn resume() {
+        let spec = spec().seed(7).shard(2, 1).unwrap();
+        let all: Vec<_> = spec.targets().collect();
+        let mut targets = spec.targets();
+        let first: Vec<_> = targets.by_ref().take(20).collect();
+        let rest: Vec<_> = spec.targets_from(targets.position()).collect();
+        assert_eq!(all, [first, rest].concat());
+        // A position from another shard moves on to this shard's next one.
+        assert_eq!(spec.targets_from(2).position(), 3);
+    }
+
+    #[test]
+    fn cursor() {
+        let scan = spec().seed(7).shard(2, 1).unwrap();
+        let all: Vec<_> = scan.targets().collect();
+        let mut targets = scan.targets();
+        assert!(targets.progress() < 0.01);
+        let first: Vec<_> = targets.by_ref().take(50).collect();
+        let saved = targets.cursor().to_string();
+        assert!(saved.starts_with("scan:"), "{}", saved);
+        assert!(targets.progress() > 0.2 && targets.progress() < 0.8);
+
+        let cursor: ScanCursor = saved.parse().unwrap();
+        assert_eq!(targets.cursor(), cursor);
+        let rest: Vec<_> = scan.resume(&cursor).unwrap().collect();
+        assert_eq!(all, [first, rest].concat());
+        let mut done = scan.resume(&cursor).unwrap();
+        done.by_ref().count();
+        assert_eq!(1.0, done.progress());
+
+        // A cursor only resumes the spec, and the shard, it was taken from.
+        for other in &[
+            spec().seed(8).shard(2, 1).unwrap(),
+            spec().seed(7).shard(2, 0).unwrap(),
+            spec()
+                .seed(7)
+                .shard(2, 1)
+                .unwrap()
+                .exclude("10.0.0.0/32".parse().unwrap()),
+            spec(),
+        ] {
+            assert_eq!(
+                Err(ScanCursorError::Mismatch),
+                other.resume(&cursor).map(|_| ())
+            );
+        }
+        for bad in &[
+            "",
+            "scan:",
+            "scan:12:3/4",
+            "scan:0123456789abcdef:3",
+            "scan:0123456789abcdef:+3/4",
+            "scan:0123456789abcdeg:3/4",
+            "cursor:0123456789abcdef:3/4",
+        ] {
+            assert_eq!(
+                Err(ScanCursorError::Syntax),
+                bad.parse::<ScanCursor>(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn target_list() {
+        let list = TargetList::parse(
+            "10.0.0.1, 10.0.0.5,10.0.0.6\n192.168.1,3.1 172.16.0.0/30 \
+             10.1.0.0-10.1.0.9 {{ 203.0.113.*/25,bogus,10.0.0.0/33 10.0.0.9-10.0.0.1",
+        );
+        let set: Vec<_> = list.set().iter().map(|net| net.to_string()).collect();
+        assert_eq!(
+            vec![
+                "10.0.0.1/32",
+                "10.0.0.5/32",
+                "10.0.0.6/32",
+                "10.1.0.0/29",
+                "10.1.0.8/31",
+                "172.16.0.0/30",
+                "192.168.1.1/32",
+                "192.168.3.1/32",
+                "203.0.113.0/24"
+            ],
+            set
+        );
+        let errors: Vec<_> = list
+            .errors()
+            .iter()
+            .map(|err| (err.token(), err.offset()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("{{", 74),
+                ("bogus", 92),
+                ("10.0.0.0/33", 98),
+                ("10.0.0.9-10.0.0.1", 110)
+            ],
+            errors
+        );
+        assert!(list.into_set().is_err());
+        assert_eq!(
+            Ok(IpSet::from("10.0.0.0/8".parse::<IpNetwork>().unwrap())),
+            TargetList::parse(" 10.*.*.* ").into_set()
+        );
+        assert_eq!(Ok(IpSet::new()), TargetList::parse(" , ").into_set());
+    }
+}
@@ -0,0 +1,381 @@
+//! # The Network-Tree Module
+//!
+//! A list of networks forms a hierarchy, where each network is a child of the most specific
+//! other network which contains it. This is the natural shape of an address plan: supernets are
+//! carved into allocations, which may be carved further still, and whatever is not allocated is
+//! free.
+use super::{
+    net::IpNetwork,
+    set::{sort_prefix_order, IpSet},
+};
+use std::{collections::BTreeMap, fmt::Write};
+
+/// The containment hierarchy of a list of networks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkTree {
+    roots: Vec<Node>,
+}
+
+/// A single network within a tree, along with every network it contains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    network: IpNetwork,
+    children: Vec<Node>,
+}
+
+impl NetworkTree {
+    /// Builds the hierarchy of the networks. Host bits are ignored, and duplicates are dropped.
+    pub fn new<I: IntoIterator<Item = IpNetwork>>(nets: I) -> Self {
+        let mut nets: Vec<_> = nets
+            .into_iter()
+            .map(|net| IpNetwork::new(net.network_address(), net.num_network_bits()).unwrap())
+            .collect();
+        sort_prefix_order(&mut nets);
+        nets.dedup();
+        let mut i = 0;
+        Self {
+            roots: build(&nets, &mut i, None),
+        }
+    }
+
+    /// The networks which are not contained by any other, in ascending order.
+    pub fn roots(&self) -> &[Node] {
+        &self.roots
+    }
+
+    /// Renders the hierarchy as an indented text tree, in the style of `tree`. Every network which
+    /// has been carved up is annotated with how much of it has been allocated to its children.
+    pub fn to_text(&self) -> String {
+        fn visit(node: &Node, prefix: &str, out: &mut String) {
+            out.push_str(&node.network.to_string());
+            if !node.children.is_empty() {
+                let count = node.children.len();
+                write!(
+                    out,
+                    "  ({} {}, {:.1}% used)",
+                    count,
+                    if count == 1 { "child" } else { "children" },
+                    node.utilization() * 100.0
+                )
+                .unwrap();
+            }
+            out.push('\n');
+            for (i, child) in node.children.iter().enumerate() {
+                let last = i + 1 == node.children.len();
+                out.push_str(prefix);
+                out.push_str(if last { "└── " } else { "├── " });
+                let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                visit(child, &prefix, out);
+            }
+        }
+        let mut out = String::new();
+        for root in &self.roots {
+            visit(root, "", &mut out);
+        }
+        out
+    }
+
+    /// Renders the hierarchy as a Graphviz DOT graph, with an edge from each network to each of
+    /// its children. Networks which have been carved up are drawn as plain boxes, those which
+    /// have not (the allocations) are filled, and the space left free within a carved-up
+    /// network is drawn dashed and highlighted.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph networks {\n");
+        out.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        fn visit(node: &Node, out: &mut String) {
+            let id = node.network.to_string();
+            if node.children.is_empty() {
+                writeln!(out, "    \"{}\" [style=filled, fillcolor=lightblue];", id).unwrap();
+                return;
+            }
+            writeln!(out, "    \"{}\";", id).unwrap();
+            for child in &node.children {
+                writeln!(out, "    \"{}\" -> \"{}\";", id, child.network).unwrap();
+                visit(child, out);
+            }
+            for free in node.free().iter() {
+                writeln!(
+                    out,
+                    "    \"free {free}\" [label=\"{free}\\nfree\", style=\"dashed,filled\", fillcolor=palegreen];",
+                    free = free
+                )
+                .unwrap();
+                writeln!(out, "    \"{}\" -> \"free {}\" [style=dashed];", id, free).unwrap();
+            }
+        }
+        for root in &self.roots {
+            visit(root, &mut out);
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Builds the nodes for every network which follows, up until the first which is not within the
+/// parent. The networks must be sorted so that each is followed directly by those it contains.
+fn build(nets: &[IpNetwork], i: &mut usize, parent: Option<IpNetwork>) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    while let Some(&network) = nets.get(*i) {
+        if parent.is_some_and(|parent| !network.is_subnet_of(&parent)) {
+            break;
+        }
+        *i += 1;
+        let children = build(nets, i, Some(network));
+        nodes.push(Node { network, children });
+    }
+    nodes
+}
+
+impl Node {
+    /// The network at this point in the tree.
+    pub fn network(&self) -> IpNetwork {
+        self.network
+    }
+
+    /// The networks immediately within this one, in ascending order.
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// The fraction of this network's addresses which are within its children, from `0.0` when it
+    /// has not been carved up at all, to `1.0` when it has been completely.
+    pub fn utilization(&self) -> f64 {
+        let free = self.free().num_addresses();
+        let total = self.network.num_addresses();
+        (total - free) as f64 / total as f64
+    }
+
+    /// The addresses within this network which are not within any of its children.
+    pub fn free(&self) -> IpSet {
+        let used: IpSet = self.children.iter().map(|child| child.network).collect();
+        IpSet::from(self.network).difference(&used)
+    }
+}
+
+/// How much of a network is allocated, both at its own level and rolled up from the allocations
+/// at the bottom of the hierarchy beneath it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rollup<L> {
+    network: IpNetwork,
+    label: Option<L>,
+    allocated: u64,
+    used: u64,
+    children: Vec<Rollup<L>>,
+}
+
+/// Rolls up the utilization of the parent network through the hierarchy of labelled allocations
+/// within it. Allocations outside the parent are ignored, and where a network is allocated more
+/// than once, the first label is kept.
+///
+/// An allocation which has not been carved up any further counts as fully used, so a network's
+/// rolled-up usage is that of the allocations at the bottom of the hierarchy beneath it.
+pub fn rollup<L, I>(parent: IpNetwork, allocations: I) -> Rollup<L>
+where
+    I: IntoIterator<Item = (IpNetwork, L)>,
+{
+    let parent = IpNetwork::new(parent.network_address(), parent.num_network_bits()).unwrap();
+    let mut labels = BTreeMap::new();
+    for (net, label) in allocations {
+        if net.is_subnet_of(&parent) {
+            let net = IpNetwork::new(net.network_address(), net.num_network_bits()).unwrap();
+            labels.entry(net).or_insert(label);
+        }
+    }
+    let tree = NetworkTree::new(labels.keys().copied().chain(Some(parent)));
+    fn visit<L>(node: &Node, labels: &mut BTreeMap<IpNetwork, L>) -> Rollup<L> {
+        let label = labels.remove(&node.network);
+        let children: Vec<_> = node
+            .children
+            .iter()
+            .map(|child| visit(child, labels))
+            .collect();
+        let allocated = children.iter().map(|c| c.network.num_addresses()).sum();
+        let used = if children.is_empty() && label.is_some() {
+            node.network.num_addresses()
+        } else {
+            children.iter().map(|c| c.used).sum()
+        };
+        Rollup {
+            network: node.network,
+            label,
+            allocated,
+            used,
+            children,
+        }
+    }
+    visit(&tree.roots[0], &mut labels)
+}
+
+impl<L> Rollup<L> {
+    /// The network at this point in the hierarchy.
+    pub fn network(&self) -> IpNetwork {
+        self.network
+    }
+
+    /// The label of the allocation, unless this is the parent and it was not itself allocated.
+    pub fn label(&self) -> Option<&L> {
+        self.label.as_ref()
+    }
+
+    /// The allocations immediately within this network, in ascending order.
+    pub fn children(&self) -> &[Rollup<L>] {
+        &self.children
+    }
+
+    /// The number of addresses within the allocations immediately within this network.
+    pub fn allocated(&self) -> u64 {
+        self.allocated
+    }
+
+    /// The number of addresses at this level which have not been allocated.
+    pub fn free(&self) -> u64 {
+        self.network.num_addresses() - self.allocated
+    }
+
+    /// The number of addresses within the allocations at the bottom of the hierarchy beneath this
+    /// network.
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    /// The fraction of this network which is allocated at its own level.
+    pub fn utilization(&self) -> f64 {
+        self.allocated as f64 / self.network.num_addresses() as f64
+    }
+
+    /// The fraction of this network which is used, rolled up through the hierarchy.
+    pub fn rolled_up_utilization(&self) -> f64 {
+        self.used as f64 / self.network.num_addresses() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(list: &[&str]) -> NetworkTree {
+        NetworkTree::new(list.iter().map(|s| s.parse().unwrap()))
+    }
+
+    #[test]
+    fn hierarchy() {
+        let t = tree(&[
+            "10.0.1.0/24",
+            "10.0.0.0/16",
+            "192.168.0.0/24",
+            "10.0.1.128/25",
+            "10.0.2.0/24",
+            "10.0.0.0/16",
+        ]);
+        let roots: Vec<_> = t.roots().iter().map(|n| n.network().to_string()).collect();
+        assert_eq!(vec!["10.0.0.0/16", "192.168.0.0/24"], roots);
+        let children: Vec<_> = t.roots()[0]
+            .children()
+            .iter()
+            .map(|n| n.network().to_string())
+            .collect();
+        assert_eq!(vec!["10.0.1.0/24", "10.0.2.0/24"], children);
+        assert_eq!(1, t.roots()[0].children()[0].children().len());
+        assert!(t.roots()[1].children().is_empty());
+        assert!(tree(&[]).roots().is_empty());
+    }
+
+    #[test]
+    fn free() {
+        let t = tree(&["10.0.0.0/22", "10.0.1.0/24"]);
+        let free: Vec<_> = t.roots()[0].free().iter().map(|n| n.to_string()).collect();
+        assert_eq!(vec!["10.0.0.0/24", "10.0.2.0/23"], free);
+        assert_eq!(
+            vec!["10.0.1.0/24"],
+            t.roots()[0].children()[0]
+                .free()
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn text() {
+        let t = tree(&[
+            "10.0.0.0/16",
+            "10.0.1.0/24",
+            "10.0.1.128/25",
+            "10.0.2.0/24",
+            "10.0.2.0/26",
+            "10.0.2.64/26",
+            "192.168.0.0/24",
+        ]);
+        assert_eq!(
+            "10.0.0.0/16  (2 children, 0.8% used)
+├── 10.0.1.0/24  (1 child, 50.0% used)
+│   └── 10.0.1.128/25
+└── 10.0.2.0/24  (2 children, 50.0% used)
+    ├── 10.0.2.0/26
+    └── 10.0.2.64/26
+192.168.0.0/24
+",
+            t.to_text()
+        );
+        assert_eq!(
+            1.0,
+            tree(&["10.0.0.0/23", "10.0.0.0/24", "10.0.1.0/24"]).roots()[0].utilization()
+        );
+        assert_eq!(0.0, tree(&["10.0.0.0/23"]).roots()[0].utilization());
+    }
+
+    #[test]
+    fn dot() {
+        assert_eq!(
+            "digraph networks {
+    node [shape=box, fontname=\"monospace\"];
+    \"10.0.0.0/23\";
+    \"10.0.0.0/23\" -> \"10.0.1.0/24\";
+    \"10.0.1.0/24\" [style=filled, fillcolor=lightblue];
+    \"free 10.0.0.0/24\" [label=\"10.0.0.0/24\\nfree\", style=\"dashed,filled\", fillcolor=palegreen];
+    \"10.0.0.0/23\" -> \"free 10.0.0.0/24\" [style=dashed];
+    \"11.0.0.0/8\" [style=filled, fillcolor=lightblue];
+}
+",
+            tree(&["10.0.0.0/23", "10.0.1.0/24", "11.0.0.0/8"]).to_dot()
+        );
+    }
+
+    #[test]
+    fn rolled_up() {
+        let net = |s: &str| s.parse::<IpNetwork>().unwrap();
+        let r = rollup(
+            net("10.0.0.0/16"),
+            vec![
+                (net("10.0.0.0/20"), "engineering"),
+                (net("10.0.1.0/24"), "build farm"),
+                (net("10.0.2.0/24"), "ci"),
+                (net("10.0.64.0/18"), "storage"),
+                (net("10.0.1.0/24"), "duplicate"),
+                (net("192.168.0.0/24"), "outside"),
+            ],
+        );
+        assert_eq!(None, r.label());
+        assert_eq!(4096 + 16384, r.allocated());
+        assert_eq!(65536 - 4096 - 16384, r.free());
+        assert_eq!(512 + 16384, r.used());
+        assert_eq!(0.3125, r.utilization());
+        assert_eq!(16896.0 / 65536.0, r.rolled_up_utilization());
+
+        let engineering = &r.children()[0];
+        assert_eq!(Some(&"engineering"), engineering.label());
+        assert_eq!(512, engineering.allocated());
+        assert_eq!(512, engineering.used());
+        assert_eq!(Some(&"build farm"), engineering.children()[0].label());
+        assert_eq!(0, engineering.children()[0].allocated());
+        assert_eq!(256, engineering.children()[0].used());
+        assert_eq!(1.0, r.children()[1].rolled_up_utilization());
+
+        let empty = rollup::<&str, _>(net("10.0.0.0/24"), vec![]);
+        assert_eq!((0, 0), (empty.allocated(), empty.used()));
+        assert!(empty.children().is_empty());
+        let whole = rollup(net("10.0.0.0/24"), vec![(net("10.0.0.0/24"), 1)]);
+        assert_eq!(Some(&1), whole.label());
+        assert_eq!(1.0, whole.rolled_up_utilization());
+    }
+}
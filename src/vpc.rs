@@ -0,0 +1,271 @@
+//! # The VPC Module
+//!
+//! A cloud network is usually laid out the same way every time: the VPC's range is split into a
+//! subnet per availability zone for each tier, such as public, private, and database. The
+//! arithmetic is fiddly, because each provider keeps a few addresses of every subnet for itself,
+//! and only allows subnets of certain sizes. This module sizes each tier from the number of hosts
+//! it needs, with the provider's rules factored in, and lays the subnets out without overlap.
+use super::{addr::IpAddress, net::IpNetwork};
+use std::{error, fmt, ops::RangeInclusive};
+
+/// A cloud provider, whose conventions decide which addresses of a subnet are reserved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Provider {
+    /// Amazon Web Services, which reserves the first four addresses of a subnet and the last,
+    /// and allows subnets from `/16` to `/28`.
+    Aws,
+    /// Microsoft Azure, which reserves the first four addresses of a subnet and the last, and
+    /// allows subnets up to `/29`.
+    Azure,
+    /// Google Cloud, which reserves the first two addresses of a subnet and the last two, and
+    /// allows subnets up to `/29`.
+    Gcp,
+    /// No provider at all, reserving only the network and broadcast addresses, as a plain
+    /// network does.
+    Generic,
+}
+
+impl Provider {
+    /// The number of addresses reserved at the start of a subnet, and at the end.
+    pub fn reserved(&self) -> (u64, u64) {
+        match self {
+            Self::Aws | Self::Azure => (4, 1),
+            Self::Gcp => (2, 2),
+            Self::Generic => (1, 1),
+        }
+    }
+
+    /// The prefix lengths a subnet may have.
+    pub fn prefix_lens(&self) -> RangeInclusive<u8> {
+        match self {
+            Self::Aws => 16..=28,
+            Self::Azure | Self::Gcp => 0..=29,
+            Self::Generic => 0..=30,
+        }
+    }
+
+    /// The number of addresses of the subnet which can be given to hosts.
+    pub fn usable_hosts(&self, net: &IpNetwork) -> u64 {
+        let (head, tail) = self.reserved();
+        net.num_addresses().saturating_sub(head + tail)
+    }
+
+    /// The longest prefix length of a subnet with at least the specified number of usable
+    /// hosts, if the provider allows a subnet that large.
+    pub fn prefix_len_for(&self, hosts: u64) -> Option<u8> {
+        let (head, tail) = self.reserved();
+        self.prefix_lens()
+            .rev()
+            .find(|&prefix_len| (1u64 << (32 - prefix_len)) >= hosts.saturating_add(head + tail))
+    }
+}
+
+/// A tier of subnets, which is repeated in every availability zone.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Tier {
+    /// Subnets routed to and from the internet.
+    Public,
+    /// Subnets reaching the internet through NAT, if at all.
+    Private,
+    /// Subnets for databases, which reach nothing outside the VPC.
+    Database,
+}
+
+impl fmt::Display for Tier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Public => "public",
+            Self::Private => "private",
+            Self::Database => "db",
+        })
+    }
+}
+
+/// One subnet of a VPC plan.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VpcSubnet {
+    pub tier: Tier,
+    /// The index of the availability zone, counting from `0`.
+    pub zone: usize,
+    pub network: IpNetwork,
+    /// The number of addresses which can be given to hosts, once the provider's are reserved.
+    pub usable: u64,
+}
+
+/// Splits a VPC's range into a subnet for each tier in each availability zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VpcPlanner {
+    vpc: IpNetwork,
+    provider: Provider,
+    zones: usize,
+    /// Each tier, along with the number of hosts each of its subnets needs.
+    tiers: Vec<(Tier, u64)>,
+}
+
+impl VpcPlanner {
+    /// Creates a planner for the VPC's range, in a single availability zone, without any tiers.
+    pub fn new(vpc: IpNetwork, provider: Provider) -> Self {
+        Self {
+            vpc,
+            provider,
+            zones: 1,
+            tiers: Vec::new(),
+        }
+    }
+
+    /// Spreads every tier across the specified number of availability zones.
+    pub fn zones(mut self, zones: usize) -> Self {
+        self.zones = zones;
+        self
+    }
+
+    /// Adds a tier, whose subnets each need at least the specified number of usable hosts. A
+    /// tier which was already added is resized.
+    pub fn tier(mut self, tier: Tier, hosts: u64) -> Self {
+        match self.tiers.iter_mut().find(|(t, _)| *t == tier) {
+            Some(entry) => entry.1 = hosts,
+            None => self.tiers.push((tier, hosts)),
+        }
+        self
+    }
+
+    /// Lays out the subnets, ordered by tier, in the order the tiers were added, and then by
+    /// zone.
+    ///
+    /// The largest subnets are placed first, from the start of the VPC's range, so that every
+    /// subnet falls on its own boundary without leaving gaps.
+    pub fn plan(&self) -> Result<Vec<VpcSubnet>, VpcError> {
+        let mut requests = Vec::new();
+        for (t, &(tier, hosts)) in self.tiers.iter().enumerate() {
+            let prefix_len = self
+                .provider
+                .prefix_len_for(hosts)
+                .ok_or(VpcError::TooManyHosts(tier))?;
+            requests.extend((0..self.zones).map(|zone| (prefix_len, t, zone)));
+        }
+        requests.sort_by_key(|&(prefix_len, _, _)| prefix_len);
+
+        let mut next = u64::from(self.vpc.network_address().value());
+        let end = next + self.vpc.num_addresses();
+        let mut subnets = Vec::with_capacity(requests.len());
+        for (prefix_len, t, zone) in requests {
+            let size = 1u64 << (32 - prefix_len);
+            if next + size > end {
+                return Err(VpcError::OutOfSpace);
+            }
+            let network = IpNetwork::new(IpAddress::from(next as u32), prefix_len).unwrap();
+            next += size;
+            subnets.push((
+                t,
+                VpcSubnet {
+                    tier: self.tiers[t].0,
+                    zone,
+                    network,
+                    usable: self.provider.usable_hosts(&network),
+                },
+            ));
+        }
+        subnets.sort_by_key(|&(t, subnet)| (t, subnet.zone));
+        Ok(subnets.into_iter().map(|(_, subnet)| subnet).collect())
+    }
+}
+
+/// The error returned when a VPC plan cannot be laid out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VpcError {
+    /// The tier needs more hosts than the largest subnet the provider allows.
+    TooManyHosts(Tier),
+    /// The subnets do not all fit within the VPC's range.
+    OutOfSpace,
+}
+
+impl fmt::Display for VpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyHosts(tier) => write!(
+                f,
+                "the {} tier needs more hosts than a single subnet can hold",
+                tier
+            ),
+            Self::OutOfSpace => f.write_str("the subnets do not fit within the VPC"),
+        }
+    }
+}
+
+impl error::Error for VpcError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn reserved() {
+        let net = net("10.0.0.0/24");
+        assert_eq!(251, Provider::Aws.usable_hosts(&net));
+        assert_eq!(252, Provider::Gcp.usable_hosts(&net));
+        assert_eq!(254, Provider::Generic.usable_hosts(&net));
+        assert_eq!(Some(28), Provider::Aws.prefix_len_for(11));
+        assert_eq!(Some(27), Provider::Aws.prefix_len_for(12));
+        assert_eq!(Some(24), Provider::Gcp.prefix_len_for(252));
+        assert_eq!(Some(23), Provider::Aws.prefix_len_for(252));
+        assert_eq!(Some(29), Provider::Azure.prefix_len_for(0));
+        assert_eq!(None, Provider::Aws.prefix_len_for(65_532));
+    }
+
+    #[test]
+    fn plan() {
+        let subnets = VpcPlanner::new(net("10.0.0.0/16"), Provider::Aws)
+            .zones(3)
+            .tier(Tier::Public, 250)
+            .tier(Tier::Private, 1000)
+            .tier(Tier::Database, 100)
+            .plan()
+            .unwrap();
+        let layout: Vec<_> = subnets
+            .iter()
+            .map(|s| format!("{}-{} {} {}", s.tier, s.zone, s.network, s.usable))
+            .collect();
+        assert_eq!(
+            vec![
+                "public-0 10.0.12.0/24 251",
+                "public-1 10.0.13.0/24 251",
+                "public-2 10.0.14.0/24 251",
+                "private-0 10.0.0.0/22 1019",
+                "private-1 10.0.4.0/22 1019",
+                "private-2 10.0.8.0/22 1019",
+                "db-0 10.0.15.0/25 123",
+                "db-1 10.0.15.128/25 123",
+                "db-2 10.0.16.0/25 123",
+            ],
+            layout
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let planner = VpcPlanner::new(net("10.0.0.0/24"), Provider::Aws).zones(2);
+        assert_eq!(
+            Err(VpcError::OutOfSpace),
+            planner.clone().tier(Tier::Public, 200).plan()
+        );
+        assert_eq!(
+            Err(VpcError::TooManyHosts(Tier::Private)),
+            planner.clone().tier(Tier::Private, 100_000).plan()
+        );
+        // Resizing the tier makes it fit.
+        let subnets = planner
+            .tier(Tier::Public, 200)
+            .tier(Tier::Public, 100)
+            .plan()
+            .unwrap();
+        assert_eq!(net("10.0.0.128/25"), subnets[1].network);
+        assert_eq!(
+            Ok(vec![]),
+            VpcPlanner::new(net("10.0.0.0/24"), Provider::Gcp).plan()
+        );
+    }
+}
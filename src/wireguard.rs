@@ -0,0 +1,139 @@
+//! # The WireGuard Module
+//!
+//! WireGuard routes whatever a peer's `AllowedIPs` lists through the tunnel, and has no way to
+//! list exceptions. A split tunnel, which sends everything except the local network through the
+//! tunnel, must instead list every network which is left once the exceptions are taken out. This
+//! module works that list out, in as few networks as possible.
+use super::{net::IpNetwork, net6::Ipv6Network, set::IpSet, set6::Ipv6Set};
+use std::fmt;
+
+/// The networks a WireGuard peer's `AllowedIPs` should list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AllowedIps {
+    v4: IpSet,
+    v6: Ipv6Set,
+}
+
+impl AllowedIps {
+    /// Allows nothing at all.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows every IPv4 and IPv6 address, as a full tunnel does.
+    pub fn all() -> Self {
+        Self::new()
+            .include("0.0.0.0/0".parse().unwrap())
+            .include6("::/0".parse().unwrap())
+    }
+
+    /// Allows every address of the IPv4 network.
+    pub fn include(mut self, net: IpNetwork) -> Self {
+        self.v4.insert(net);
+        self
+    }
+
+    /// Allows every address of the IPv6 network.
+    pub fn include6(mut self, net: Ipv6Network) -> Self {
+        self.v6.insert(net);
+        self
+    }
+
+    /// Allows none of the addresses of the IPv4 network.
+    pub fn exclude(mut self, net: IpNetwork) -> Self {
+        self.v4.remove(net);
+        self
+    }
+
+    /// Allows none of the addresses of the IPv6 network.
+    pub fn exclude6(mut self, net: Ipv6Network) -> Self {
+        self.v6.remove(net);
+        self
+    }
+
+    /// Allows none of the private IPv4 networks (RFC 1918), nor the IPv6 unique local or
+    /// link-local networks, so that the local network stays reachable outside the tunnel.
+    pub fn exclude_private(self) -> Self {
+        let v4 = [
+            "10.0.0.0/8",
+            "172.16.0.0/12",
+            "192.168.0.0/16",
+            "169.254.0.0/16",
+        ];
+        let v6 = ["fc00::/7", "fe80::/10"];
+        let allowed = v4
+            .iter()
+            .fold(self, |allowed, net| allowed.exclude(net.parse().unwrap()));
+        v6.iter().fold(allowed, |allowed, net| {
+            allowed.exclude6(net.parse().unwrap())
+        })
+    }
+
+    /// The fewest IPv4 networks which cover exactly the allowed addresses, in ascending order.
+    pub fn networks(&self) -> impl Iterator<Item = IpNetwork> + '_ {
+        self.v4.iter()
+    }
+
+    /// The fewest IPv6 networks which cover exactly the allowed addresses, in ascending order.
+    pub fn networks6(&self) -> impl Iterator<Item = Ipv6Network> + '_ {
+        self.v6.iter()
+    }
+
+    /// The `AllowedIPs` line of a WireGuard `[Peer]` section, as in
+    /// `AllowedIPs = 0.0.0.0/5, 8.0.0.0/7`.
+    pub fn config_line(&self) -> String {
+        format!("AllowedIPs = {}", self)
+    }
+}
+
+/// Formats the allowed networks as WireGuard lists them: IPv4 then IPv6, separated by commas.
+impl fmt::Display for AllowedIps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v4 = self.networks().map(|net| net.to_string());
+        let v6 = self.networks6().map(|net| net.to_string());
+        let list: Vec<_> = v4.chain(v6).collect();
+        f.write_str(&list.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_tunnel() {
+        let allowed = AllowedIps::all().exclude("10.0.0.0/8".parse().unwrap());
+        assert_eq!(
+            "AllowedIPs = 0.0.0.0/5, 8.0.0.0/7, 11.0.0.0/8, 12.0.0.0/6, 16.0.0.0/4, 32.0.0.0/3, \
+             64.0.0.0/2, 128.0.0.0/1, ::/0",
+            allowed.config_line()
+        );
+
+        let allowed = AllowedIps::all().exclude_private();
+        assert_eq!(40, allowed.networks().count());
+        assert!(allowed
+            .networks()
+            .all(|net| !net.contains([192, 168, 1, 1].into())));
+        assert!(allowed
+            .networks()
+            .any(|net| net.contains([8, 8, 8, 8].into())));
+        assert_eq!(
+            vec![
+                "::/1",
+                "8000::/2",
+                "c000::/3",
+                "e000::/4",
+                "f000::/5",
+                "f800::/6",
+                "fe00::/9",
+                "fec0::/10",
+                "ff00::/8"
+            ],
+            allowed
+                .networks6()
+                .map(|net| net.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("", AllowedIps::new().to_string());
+    }
+}